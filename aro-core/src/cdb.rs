@@ -0,0 +1,299 @@
+//! Cards from an EDOPro/YGOPro `cards.cdb`, which usually has pre-release (and custom) cards long before the API does.
+//! The simulators store everything as bit flags, so this maps them back to the strings used in `cards.json`.
+use rusqlite::{Connection, OpenFlags};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+};
+
+use crate::data::{Card, Dump, LoadError, MiscInfo};
+
+const TYPE_MONSTER: i64 = 0x1;
+const TYPE_SPELL: i64 = 0x2;
+const TYPE_TRAP: i64 = 0x4;
+const TYPE_NORMAL: i64 = 0x10;
+const TYPE_EFFECT: i64 = 0x20;
+const TYPE_RITUAL: i64 = 0x80;
+const TYPE_TOKEN: i64 = 0x4000;
+const TYPE_PENDULUM: i64 = 0x1000000;
+const TYPE_LINK: i64 = 0x4000000;
+
+/// Frames that replace “Effect” in the API’s type names, e.g. “Synchro Monster”.
+const EXTRA_DECK_TYPES: &[(i64, &str)] = &[(0x40, "Fusion"), (0x2000, "Synchro"), (0x800000, "XYZ"), (TYPE_LINK, "Link")];
+const MONSTER_ABILITIES: &[(i64, &str)] =
+    &[(0x200000, "Flip"), (0x400000, "Toon"), (0x800, "Gemini"), (0x200, "Spirit"), (0x400, "Union"), (0x1000, "Tuner")];
+const SPELL_TRAP_TYPES: &[(i64, &str)] = &[
+    (0x10000, "Quick-Play"),
+    (0x20000, "Continuous"),
+    (0x40000, "Equip"),
+    (0x80000, "Field"),
+    (0x100000, "Counter"),
+    (TYPE_RITUAL, "Ritual"),
+];
+
+const RACES: &[&str] = &[
+    "Warrior",
+    "Spellcaster",
+    "Fairy",
+    "Fiend",
+    "Zombie",
+    "Machine",
+    "Aqua",
+    "Pyro",
+    "Rock",
+    "Winged Beast",
+    "Plant",
+    "Insect",
+    "Thunder",
+    "Dragon",
+    "Beast",
+    "Beast-Warrior",
+    "Dinosaur",
+    "Fish",
+    "Sea Serpent",
+    "Reptile",
+    "Psychic",
+    "Divine-Beast",
+    "Creator-God",
+    "Wyrm",
+    "Cyberse",
+    "Illusion",
+];
+const ATTRIBUTES: &[&str] = &["EARTH", "WATER", "FIRE", "WIND", "LIGHT", "DARK", "DIVINE"];
+/// Link markers are stored in `def`, one bit each, starting at the bottom left.
+const LINK_ARROWS: &[(i64, &str)] = &[
+    (0x1, "Bottom-Left"),
+    (0x2, "Bottom"),
+    (0x4, "Bottom-Right"),
+    (0x8, "Left"),
+    (0x20, "Right"),
+    (0x40, "Top-Left"),
+    (0x80, "Top"),
+    (0x100, "Top-Right"),
+];
+
+const OT_OCG: i64 = 0x1;
+const OT_TCG: i64 = 0x2;
+/// Rush Duel cards share the database but have nothing to do with the rest.
+const OT_RUSH: i64 = 0x200;
+
+/// -2 is how simulators store “?”
+const UNKNOWN_STAT: i64 = -2;
+/// `alias` is also used for cards whose name is treated as another card’s, e.g. Harpie Lady 1.
+/// The simulators only count it as an alternate artwork if the IDs are this close.
+const ARTWORK_ID_OFFSET: i64 = 10;
+
+impl Dump {
+    /// Adds all cards from the cdb that aren’t in the dump yet and returns how many there were.
+    /// Cards the dump already has are left alone because it has more information (prices, printings, etc.).
+    pub fn add_cdb(&mut self, path: impl AsRef<Path>) -> Result<usize, LoadError> {
+        let db = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY).map_err(LoadError::Cdb)?;
+        Ok(self.add_cdb_cards(read_cards(&db).map_err(LoadError::Cdb)?))
+    }
+
+    /// Alternate artworks get the name of their original so they’re grouped with it like the other versions of a card.
+    fn add_cdb_cards(&mut self, cards: Vec<(Card, Option<usize>)>) -> usize {
+        let known: HashSet<_> = self.cards.iter().map(|c| c.id).collect();
+        // The dump’s names win, they’re the ones the original is shown with.
+        let names: HashMap<usize, String> = cards.iter().map(|(c, _)| c).chain(&self.cards).map(|c| (c.id, c.name.clone())).collect();
+        let new: Vec<_> = cards
+            .into_iter()
+            .filter(|(c, _)| !known.contains(&c.id))
+            .map(|(card, artwork_of)| match artwork_of.and_then(|id| names.get(&id)) {
+                Some(name) => Card { name: name.clone(), ..card },
+                None => card,
+            })
+            .collect();
+        let added = new.len();
+        self.cards.extend(new);
+        added
+    }
+}
+
+/// Every card, with the ID of the original if it’s an alternate artwork.
+fn read_cards(db: &Connection) -> rusqlite::Result<Vec<(Card, Option<usize>)>> {
+    let mut statement = db.prepare(
+        "SELECT datas.id, ot, alias, type, atk, def, level, race, attribute, name, desc FROM datas JOIN texts ON datas.id = texts.id ORDER BY datas.id",
+    )?;
+    let rows = statement.query_map([], |row| {
+        let row = CdbRow {
+            id:        row.get(0)?,
+            ot:        row.get(1)?,
+            alias:     row.get(2)?,
+            card_type: row.get(3)?,
+            atk:       row.get(4)?,
+            def:       row.get(5)?,
+            level:     row.get(6)?,
+            race:      row.get(7)?,
+            attribute: row.get(8)?,
+            name:      row.get(9)?,
+            text:      row.get(10)?,
+        };
+        Ok(row)
+    })?;
+    rows.filter(|row| row.as_ref().map_or(true, |r| r.ot & OT_RUSH == 0))
+        .map(|row| {
+            let row = row?;
+            let artwork_of = (row.alias != 0 && (row.alias - row.id).abs() < ARTWORK_ID_OFFSET).then_some(row.alias as usize);
+            Ok((row.into(), artwork_of))
+        })
+        .collect()
+}
+
+struct CdbRow {
+    id:        i64,
+    ot:        i64,
+    alias:     i64,
+    card_type: i64,
+    atk:       i64,
+    def:       i64,
+    level:     i64,
+    race:      i64,
+    attribute: i64,
+    name:      String,
+    text:      String,
+}
+
+impl From<CdbRow> for Card {
+    fn from(row: CdbRow) -> Self {
+        let is_monster = row.card_type & TYPE_MONSTER != 0;
+        let is_link = row.card_type & TYPE_LINK != 0;
+        let stat = |s: i64| (s != UNKNOWN_STAT).then_some(s as i32);
+        // The upper bytes of `level` are the pendulum scales.
+        let level = (row.level & 0xff) as i32;
+        let scale = (row.card_type & TYPE_PENDULUM != 0).then_some(((row.level >> 24) & 0xff) as i32);
+        let formats = [(OT_OCG, "OCG"), (OT_TCG, "TCG")].iter().filter(|(ot, _)| row.ot & ot != 0).map(|(_, f)| f.to_string()).collect();
+        Card {
+            id: row.id as usize,
+            card_type: card_type(row.card_type),
+            name: row.name,
+            text: row.text,
+            atk: stat(row.atk).filter(|_| is_monster),
+            def: stat(row.def).filter(|_| is_monster && !is_link),
+            attribute: flag_name(ATTRIBUTES, row.attribute).filter(|_| is_monster).map(str::to_owned),
+            r#type: match is_monster {
+                true => flag_name(RACES, row.race).unwrap_or_default().to_owned(),
+                false => SPELL_TRAP_TYPES.iter().find(|(flag, _)| row.card_type & flag != 0).map_or("Normal", |(_, t)| t).to_owned(),
+            },
+            level: (is_monster && !is_link).then_some(level),
+            link_rating: is_link.then_some(level),
+            scale,
+            link_arrows: is_link
+                .then(|| LINK_ARROWS.iter().filter(|(flag, _)| row.def & flag != 0).map(|(_, arrow)| arrow.to_string()).collect()),
+            misc_info: vec![MiscInfo { formats, ..Default::default() }],
+            ..Default::default()
+        }
+    }
+}
+
+/// The name for a single-bit flag, where the first name is bit 0.
+fn flag_name(names: &[&'static str], flag: i64) -> Option<&'static str> {
+    names.get(flag.checked_ilog2()? as usize).copied().filter(|_| flag.count_ones() == 1)
+}
+
+/// Builds the type the way the API names it, e.g. “Synchro Tuner Monster” or “Pendulum Effect Monster”.
+fn card_type(flags: i64) -> String {
+    if flags & TYPE_SPELL != 0 {
+        return "Spell Card".to_owned();
+    }
+    if flags & TYPE_TRAP != 0 {
+        return "Trap Card".to_owned();
+    }
+    if flags & TYPE_TOKEN != 0 {
+        return "Token".to_owned();
+    }
+    let extra_deck = EXTRA_DECK_TYPES.iter().find(|(flag, _)| flags & flag != 0).map(|(_, t)| *t);
+    let mut words = Vec::new();
+    words.extend(extra_deck);
+    if flags & TYPE_RITUAL != 0 {
+        words.push("Ritual");
+    }
+    if flags & TYPE_PENDULUM != 0 {
+        words.push("Pendulum");
+    }
+    words.extend(MONSTER_ABILITIES.iter().filter(|(flag, _)| flags & flag != 0).map(|(_, t)| *t));
+    if flags & TYPE_NORMAL != 0 {
+        words.push("Normal");
+    } else if flags & TYPE_EFFECT != 0 && extra_deck.is_none() {
+        words.push("Effect");
+    }
+    words.push("Monster");
+    words.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_cdb_test() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch(
+            "CREATE TABLE datas (id INTEGER PRIMARY KEY, ot INTEGER, alias INTEGER, setcode INTEGER, type INTEGER, atk INTEGER,
+                def INTEGER, level INTEGER, race INTEGER, attribute INTEGER, category INTEGER);
+            CREATE TABLE texts (id INTEGER PRIMARY KEY, name TEXT, desc TEXT);
+            INSERT INTO datas VALUES (46986414, 3, 0, 16, 17, 2500, 2100, 7, 2, 32, 0);
+            INSERT INTO datas VALUES (49202162, 2, 0, 0, 67108897, 3000, 161, 3, 1, 1, 0);
+            INSERT INTO datas VALUES (55144522, 3, 0, 0, 65538, 0, 0, 0, 0, 0, 0);
+            INSERT INTO datas VALUES (160001000, 512, 0, 0, 17, 1500, 1200, 4, 1, 1, 0);
+            INSERT INTO datas VALUES (1, 1, 0, 0, 12321, -2, -2, 327686, 8192, 16, 0);
+            INSERT INTO texts VALUES (46986414, 'Dark Magician', 'The ultimate wizard.');
+            INSERT INTO texts VALUES (49202162, 'Black Luster Soldier - Soldier of Chaos', '3 monsters with different names');
+            INSERT INTO texts VALUES (55144522, 'Pot of Greed', 'Draw 2 cards.');
+            INSERT INTO texts VALUES (160001000, 'Some Rush Card', '');
+            INSERT INTO texts VALUES (1, 'Custom Synchro', '');",
+        )
+        .unwrap();
+        let cards: Vec<_> = read_cards(&db).unwrap().into_iter().map(|(card, _)| card).collect();
+        assert_eq!(cards.len(), 4, "the Rush card is skipped");
+        let dm = &cards[1];
+        assert_eq!(
+            (dm.card_type.as_str(), dm.r#type.as_str(), dm.attribute.as_deref(), dm.level, dm.atk, dm.def),
+            ("Normal Monster", "Spellcaster", Some("DARK"), Some(7), Some(2500), Some(2100))
+        );
+        assert_eq!(dm.misc_info[0].formats, vec!["OCG", "TCG"]);
+        let bls = &cards[2];
+        assert_eq!((bls.card_type.as_str(), bls.link_rating, bls.level, bls.def), ("Link Monster", Some(3), None, None));
+        assert_eq!(bls.link_arrows, Some(vec!["Bottom-Left".to_owned(), "Right".to_owned(), "Top".to_owned()]));
+        let pot = &cards[3];
+        assert_eq!((pot.card_type.as_str(), pot.r#type.as_str(), pot.atk, pot.level), ("Spell Card", "Quick-Play", None, None));
+        let custom = &cards[0];
+        assert_eq!(
+            (custom.card_type.as_str(), custom.r#type.as_str(), custom.level, custom.atk),
+            ("Synchro Tuner Monster", "Dragon", Some(6), None)
+        );
+    }
+
+    #[test]
+    fn alternate_artwork_test() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch(
+            "CREATE TABLE datas (id INTEGER PRIMARY KEY, ot INTEGER, alias INTEGER, setcode INTEGER, type INTEGER, atk INTEGER,
+                def INTEGER, level INTEGER, race INTEGER, attribute INTEGER, category INTEGER);
+            CREATE TABLE texts (id INTEGER PRIMARY KEY, name TEXT, desc TEXT);
+            INSERT INTO datas VALUES (46986415, 3, 46986414, 16, 17, 2500, 2100, 7, 2, 32, 0);
+            INSERT INTO datas VALUES (27927359, 3, 76812113, 0, 33, 1300, 1400, 4, 8, 16, 0);
+            INSERT INTO texts VALUES (46986415, 'Dark Magician (Alt)', 'The ultimate wizard.');
+            INSERT INTO texts VALUES (27927359, 'Harpie Lady 1', '');",
+        )
+        .unwrap();
+        let cards = read_cards(&db).unwrap();
+        assert_eq!(
+            cards.iter().map(|(c, artwork_of)| (c.id, *artwork_of)).collect::<Vec<_>>(),
+            vec![(27927359, None), (46986415, Some(46986414))]
+        );
+        let mut dump = Dump { cards: vec![Card { id: 46986414, name: "Dark Magician".to_owned(), ..Default::default() }], sets: vec![] };
+        assert_eq!(dump.add_cdb_cards(cards), 2);
+        assert_eq!(dump.cards.iter().map(|c| c.name.as_str()).collect::<Vec<_>>(), vec!["Dark Magician", "Harpie Lady 1", "Dark Magician"]);
+    }
+
+    #[test]
+    fn card_type_test() {
+        assert_eq!(card_type(TYPE_MONSTER | TYPE_EFFECT), "Effect Monster");
+        assert_eq!(card_type(TYPE_MONSTER | TYPE_EFFECT | TYPE_PENDULUM), "Pendulum Effect Monster");
+        assert_eq!(card_type(TYPE_MONSTER | TYPE_EFFECT | 0x200000), "Flip Effect Monster");
+        assert_eq!(card_type(TYPE_MONSTER | TYPE_EFFECT | TYPE_RITUAL), "Ritual Effect Monster");
+        assert_eq!(card_type(TYPE_MONSTER | TYPE_EFFECT | 0x800000), "XYZ Monster");
+        assert_eq!(card_type(TYPE_TRAP | 0x100000), "Trap Card");
+    }
+}
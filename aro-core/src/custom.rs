@@ -0,0 +1,58 @@
+//! Homebrew cards for private instances, in a file shaped like `cards.json`.
+use std::collections::HashSet;
+
+use crate::data::{CardInfo, Dump, LoadError};
+
+/// Official passcodes have at most 8 digits, so custom cards that need a new ID get one from here on.
+pub const FIRST_FREE_ID: usize = 100_000_000;
+
+impl Dump {
+    /// Adds all cards from the JSON as custom cards and returns how many there were,
+    /// along with `(old, new)` for every card that had to be given a new ID.
+    /// A custom card never replaces a real one (or an earlier custom card), so IDs that are already taken are changed.
+    pub fn add_custom(&mut self, json: &str) -> Result<(usize, Vec<(usize, usize)>), LoadError> {
+        let custom: CardInfo = serde_json::from_str(json).map_err(|e| LoadError::Parse("custom cards".to_owned(), e))?;
+        let mut taken: HashSet<_> = self.cards.iter().map(|c| c.id).collect();
+        let mut next_free = FIRST_FREE_ID;
+        let mut renamed = Vec::new();
+        let added = custom.data.len();
+        for mut card in custom.data {
+            if !taken.insert(card.id) {
+                while taken.contains(&next_free) {
+                    next_free += 1;
+                }
+                renamed.push((card.id, next_free));
+                card.id = next_free;
+                taken.insert(next_free);
+            }
+            card.custom = true;
+            self.cards.push(card);
+        }
+        Ok((added, renamed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Card;
+
+    #[test]
+    fn add_custom_test() {
+        let mut dump = Dump {
+            cards: vec![Card { id: 46986414, ..Default::default() }, Card { id: FIRST_FREE_ID, ..Default::default() }],
+            sets:  vec![],
+        };
+        let json = r#"{"data": [
+            {"id": 1, "name": "Homebrew Dragon", "type": "Normal Monster", "desc": "", "race": "Dragon"},
+            {"id": 46986414, "name": "Dark Magician, but better", "type": "Normal Monster", "desc": "", "race": "Spellcaster"},
+            {"id": 1, "name": "Homebrew Dragon 2", "type": "Normal Monster", "desc": "", "race": "Dragon"}
+        ]}"#;
+        assert_eq!(dump.add_custom(json).unwrap(), (3, vec![(46986414, FIRST_FREE_ID + 1), (1, FIRST_FREE_ID + 2)]));
+        assert_eq!(
+            dump.cards.iter().map(|c| (c.id, c.custom)).collect::<Vec<_>>(),
+            vec![(46986414, false), (FIRST_FREE_ID, false), (1, true), (FIRST_FREE_ID + 1, true), (FIRST_FREE_ID + 2, true)]
+        );
+        assert!(dump.add_custom("[]").is_err());
+    }
+}
@@ -0,0 +1,1286 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    fmt::{self, Display, Write},
+    io,
+};
+#[cfg(feature = "fs")]
+use std::{fs::File, io::BufReader};
+use time::{Date, Month};
+
+use crate::{rulings::Ruling, yugipedia::YugipediaInfo};
+
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone)]
+pub struct CardInfo {
+    pub data: Vec<Card>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone, Default)]
+pub struct Card {
+    pub id:           usize,
+    #[serde(rename = "type")]
+    pub card_type:    String,
+    pub name:         String,
+    #[serde(rename = "desc")]
+    pub text:         String,
+    // Will also be None for ?
+    pub atk:          Option<i32>,
+    pub def:          Option<i32>,
+    pub attribute:    Option<String>,
+    #[serde(rename = "race")]
+    pub r#type:       String,
+    // also includes rank
+    pub level:        Option<i32>,
+    #[serde(rename = "linkval")]
+    pub link_rating:  Option<i32>,
+    #[serde(rename = "linkmarkers")]
+    pub link_arrows:  Option<Vec<String>>,
+    pub archetype:    Option<String>,
+    /// Pendulum Scale. Left and right have been the same for all cards so far.
+    pub scale:        Option<i32>,
+    /// The two halves of the text of Pendulums, see `pendulum_texts`
+    pub pend_desc:    Option<String>,
+    pub monster_desc: Option<String>,
+    #[serde(default)]
+    pub card_sets:    Vec<CardSet>,
+    pub banlist_info: Option<BanlistInfo>,
+    #[serde(default)]
+    pub card_prices:  Vec<CardPrice>,
+    // Only present if the dump was requested with `misc=yes`, and even then not for every card.
+    #[serde(default)]
+    pub misc_info:    Vec<MiscInfo>,
+    /// Not in the dump, see `Dump::add_yugipedia`
+    #[serde(skip)]
+    pub yugipedia:    Option<YugipediaInfo>,
+    /// Homebrew, see `Dump::add_custom`
+    #[serde(skip)]
+    pub custom:       bool,
+    /// Not in the dump, see `Dump::add_rulings`
+    #[serde(skip)]
+    pub rulings:      Vec<Ruling>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone, Copy, Default)]
+pub struct BanlistInfo {
+    #[serde(default)]
+    pub ban_tcg:  BanlistStatus,
+    #[serde(default)]
+    pub ban_ocg:  BanlistStatus,
+    #[serde(default)]
+    pub ban_goat: BanlistStatus,
+    /// Only in dumps that have it, see `Format::MasterDuel`
+    #[serde(default)]
+    pub ban_md:   BanlistStatus,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone, Copy, Default)]
+pub enum BanlistStatus {
+    Forbidden = 0,
+    Limited = 1,
+    #[serde(rename = "Semi-Limited")]
+    SemiLimited = 2,
+    #[default]
+    Unlimited = 3,
+}
+
+impl BanlistStatus {
+    /// Anything above 2 is unlimited.
+    pub fn from_copies(copies: u8) -> Self {
+        match copies {
+            0 => Self::Forbidden,
+            1 => Self::Limited,
+            2 => Self::SemiLimited,
+            _ => Self::Unlimited,
+        }
+    }
+
+    pub fn copies(self) -> u8 {
+        self as u8
+    }
+
+    pub fn icon(self) -> Option<&'static str> {
+        match self {
+            Self::Forbidden => Some("forbidden.svg"),
+            Self::Limited => Some("limited.svg"),
+            Self::SemiLimited => Some("semi_limited.svg"),
+            Self::Unlimited => None,
+        }
+    }
+}
+
+impl Display for BanlistStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Forbidden => "Forbidden",
+            Self::Limited => "Limited",
+            Self::SemiLimited => "Semi-Limited",
+            Self::Unlimited => "Unlimited",
+        })
+    }
+}
+
+/// Old banlists with the day each one took effect, for `asof:`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct BanlistHistory {
+    /// Sorted by date
+    lists: Vec<(Date, HashMap<usize, BanlistStatus>)>,
+}
+
+impl BanlistHistory {
+    pub fn new(mut lists: Vec<(Date, HashMap<usize, BanlistStatus>)>) -> Self {
+        lists.sort_by_key(|(date, _)| *date);
+        Self { lists }
+    }
+
+    /// Under the newest list from before or on `date`. Before the first one, everything was unlimited.
+    pub fn status_on(&self, id: usize, date: Date) -> BanlistStatus {
+        let in_effect = self.lists.partition_point(|(d, _)| *d <= date);
+        in_effect.checked_sub(1).and_then(|i| self.lists[i].1.get(&id)).copied().unwrap_or_default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.lists.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lists.is_empty()
+    }
+}
+
+/// Formats we have banlists for.
+/// Most dumps don’t have Master Duel banlists, so every card that’s in Master Duel counts as unlimited there.
+/// The server can replace it with a list from an `lflist.conf`, see `legalities` in `cardpage.rs`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Format {
+    Tcg,
+    Ocg,
+    Goat,
+    MasterDuel,
+}
+
+impl Format {
+    pub const ALL: [Self; 4] = [Self::Tcg, Self::Ocg, Self::Goat, Self::MasterDuel];
+
+    /// How the format is called in `misc_info.formats`.
+    fn misc_info_name(self) -> &'static str {
+        match self {
+            Self::Tcg => "TCG",
+            Self::Ocg => "OCG",
+            Self::Goat => "GOAT",
+            Self::MasterDuel => "Master Duel",
+        }
+    }
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.misc_info_name())
+    }
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone, Default)]
+pub struct CardSet {
+    pub set_name:   String,
+    pub set_code:   String,
+    pub set_rarity: String,
+    #[serde(default)]
+    pub set_price:  String,
+}
+
+impl CardSet {
+    /// In cents. The API uses 0 for printings it doesn’t have a price for.
+    pub fn price(&self) -> Option<i32> {
+        parse_cents(&self.set_price).filter(|&p| p > 0)
+    }
+
+    pub fn code(&self) -> SetCode<'_> {
+        SetCode::parse(&self.set_code)
+    }
+
+    /// The set part of the code, e.g. `LOB` for `LOB-EN005`.
+    pub fn set_prefix(&self) -> &str {
+        self.code().prefix
+    }
+
+    /// The language/region part of the set code, e.g. `EN` for `LOB-EN005` or `E` for `LOB-E005`.
+    /// Very old sets don’t have one at all (`LOB-005`).
+    pub fn region_tag(&self) -> &str {
+        self.code().region
+    }
+
+    pub fn region(&self) -> Region {
+        Region::from_tag(self.region_tag())
+    }
+}
+
+/// A set code like `LOB-EN005` taken apart.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct SetCode<'a> {
+    /// `LOB`
+    pub prefix: &'a str,
+    /// `EN`, or empty for the oldest sets
+    pub region: &'a str,
+    /// `005`. Some promos have letters in there too, e.g. `SP` in `DUPO-ENSP1`, which end up in `region` instead.
+    pub number: &'a str,
+}
+
+impl<'a> SetCode<'a> {
+    pub fn parse(code: &'a str) -> Self {
+        let (prefix, rest) = code.split_once('-').unwrap_or((code, ""));
+        let digits = rest.len() - rest.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+        let (region, number) = rest.split_at(rest.len() - digits);
+        Self { prefix, region, number }
+    }
+}
+
+/// Every region tag we know of. Anything else is `Region::Other`.
+const REGION_TAGS: [&str; 20] =
+    ["", "EN", "E", "DE", "G", "FR", "F", "IT", "I", "SP", "S", "PT", "P", "JP", "JA", "KR", "AE", "TC", "SC", "CN"];
+
+/// The tag as a `'static` string, if it’s one we know.
+pub fn known_region_tag(tag: &str) -> Option<&'static str> {
+    REGION_TAGS.into_iter().find(|&t| t == tag)
+}
+
+/// Names `region:` understands, besides the tags themselves
+pub const REGION_NAMES: [&str; 10] = ["na", "eu", "ocg", "en", "de", "fr", "it", "es", "pt", "jp"];
+
+/// The region tags for a `region:` value, which can be a whole region (`eu`), a language (`de` is also the old `G`),
+/// or just a tag (`e` for old European English printings).
+pub fn region_tags(name: &str) -> Option<Vec<&'static str>> {
+    let in_region = |region: Region| REGION_TAGS.into_iter().filter(|&t| Region::from_tag(t) == region).collect();
+    Some(match name.to_lowercase().as_str() {
+        "na" => in_region(Region::NorthAmerica),
+        "eu" => in_region(Region::Europe),
+        "ocg" => in_region(Region::Ocg),
+        "en" => vec!["", "EN", "E"],
+        "de" => vec!["DE", "G"],
+        "fr" => vec!["FR", "F"],
+        "it" => vec!["IT", "I"],
+        "es" | "sp" => vec!["SP", "S"],
+        "pt" => vec!["PT", "P"],
+        "jp" | "ja" => vec!["JP", "JA"],
+        tag => vec![REGION_TAGS.into_iter().find(|t| !t.is_empty() && t.eq_ignore_ascii_case(tag))?],
+    })
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, PartialOrd, Ord, Hash)]
+pub enum Region {
+    NorthAmerica,
+    Europe,
+    Ocg,
+    Other,
+}
+
+impl Region {
+    pub fn from_tag(tag: &str) -> Self {
+        match tag {
+            "" | "EN" => Self::NorthAmerica,
+            "E" | "DE" | "G" | "FR" | "F" | "IT" | "I" | "SP" | "S" | "PT" | "P" => Self::Europe,
+            "JP" | "JA" | "KR" | "AE" | "TC" | "SC" | "CN" => Self::Ocg,
+            _ => Self::Other,
+        }
+    }
+}
+
+impl Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::NorthAmerica => "English",
+            Self::Europe => "European",
+            Self::Ocg => "OCG",
+            Self::Other => "Other",
+        })
+    }
+}
+
+/// Higher is rarer. Unknown rarities are treated like commons.
+pub fn rarity_rank(rarity: &str) -> u8 {
+    match rarity {
+        "Common" | "Short Print" | "Super Short Print" => 0,
+        "Rare" | "Duel Terminal Normal Parallel Rare" | "Normal Parallel Rare" => 1,
+        "Super Rare" | "Duel Terminal Rare Parallel Rare" | "Mosaic Rare" | "Shatterfoil Rare" | "Starfoil Rare" => 2,
+        "Ultra Rare" | "Duel Terminal Super Parallel Rare" | "Gold Rare" | "Platinum Rare" => 3,
+        "Secret Rare" | "Ultra Parallel Rare" | "Duel Terminal Ultra Parallel Rare" | "Premium Gold Rare" | "Gold Secret Rare" => 4,
+        "Prismatic Secret Rare" | "Platinum Secret Rare" | "Ultra Secret Rare" | "Secret Ultra Rare" | "Extra Secret Rare" => 5,
+        "Ultimate Rare" | "Collector's Rare" | "Quarter Century Secret Rare" => 6,
+        "Ghost Rare" | "Ghost/Gold Rare" | "Starlight Rare" => 7,
+        _ => 0,
+    }
+}
+
+/// What users can type for each tier of `rarity_rank`, e.g. in `maxrarity:`.
+const RARITY_TIERS: [&[&str]; 8] = [
+    &["common", "c", "short print", "sp"],
+    &["rare", "r"],
+    &["super", "super rare", "sr"],
+    &["ultra", "ultra rare", "ur"],
+    &["secret", "secret rare", "scr"],
+    &["prismatic", "prismatic secret rare", "platinum secret rare", "ultra secret rare"],
+    &["ultimate", "ultimate rare", "utr", "collector's rare", "cr", "quarter century secret rare", "qcscr"],
+    &["ghost", "ghost rare", "gr", "starlight", "starlight rare", "str"],
+];
+
+/// What users would usually type for a tier, e.g. `ultra`.
+pub fn rarity_name(tier: u8) -> Option<&'static str> {
+    Some(RARITY_TIERS.get(tier as usize)?[0])
+}
+
+/// The tier of a lowercased rarity name or abbreviation.
+pub fn rarity_tier(name: &str) -> Option<u8> {
+    RARITY_TIERS.iter().position(|names| names.contains(&name)).map(|tier| tier as u8)
+}
+
+/// Eras named after the anime series, starting with the first TCG set of the series’ new mechanic (or protagonist for GX and post-Vrains).
+/// Names are written without punctuation, so `5D's` is `5ds` and `Arc-V` is `arcv`.
+const ERAS: [(&str, i32, Month, u8); 7] = [
+    ("dm", 2002, Month::March, 8),
+    // The Lost Millennium, first Elemental Heroes
+    ("gx", 2005, Month::June, 1),
+    // Duelist Genesis, first Synchros
+    ("5ds", 2008, Month::September, 2),
+    // Generation Force, first Xyz
+    ("zexal", 2011, Month::August, 9),
+    // Duelist Alliance, first Pendulums
+    ("arcv", 2014, Month::August, 15),
+    // Code of the Duelist, first Links
+    ("vrains", 2017, Month::August, 4),
+    // Rise of the Duelist
+    ("postvrains", 2020, Month::February, 7),
+];
+
+pub fn era_names() -> impl Iterator<Item = &'static str> {
+    ERAS.iter().map(|(name, ..)| *name)
+}
+
+/// First and last day of an era. The current era ends at `Date::MAX`.
+pub fn era_dates(name: &str) -> Option<(Date, Date)> {
+    let name = name.replace(['\'', '’', '-', ' '], "");
+    let position = ERAS.iter().position(|(era, ..)| *era == name)?;
+    let start = |(_, year, month, day): (&str, i32, Month, u8)| Date::from_calendar_date(year, month, day).ok();
+    let end = match ERAS.get(position + 1) {
+        Some(&next) => start(next)?.previous_day()?,
+        None => Date::MAX,
+    };
+    Some((start(ERAS[position])?, end))
+}
+
+/// For every card ID, how many other cards mention the card’s name or archetype in quotes.
+/// Alternate artworks share a name, so they’re only counted once.
+pub fn support_counts<'a>(cards: impl Iterator<Item = &'a Card> + Clone) -> HashMap<usize, i32> {
+    let mut mentioned_by: HashMap<&str, HashSet<&str>> = HashMap::new();
+    for card in cards.clone() {
+        for name in card.quoted_names().filter(|&n| n != card.name) {
+            mentioned_by.entry(name).or_default().insert(&card.name);
+        }
+    }
+    cards
+        .map(|card| {
+            let supporters: HashSet<&str> = [Some(card.name.as_str()), card.archetype.as_deref()]
+                .into_iter()
+                .flatten()
+                .filter_map(|name| mentioned_by.get(name))
+                .flatten()
+                .copied()
+                .filter(|&n| n != card.name)
+                .collect();
+            (card.id, supporters.len() as i32)
+        })
+        .collect()
+}
+
+/// One card in a set, i.e. everything that shares a set code like `LOB-EN005`.
+#[derive(Debug, PartialEq, Eq)]
+pub struct SetEntry<'a> {
+    pub card:     &'a Card,
+    pub code:     &'a str,
+    /// Rarest first
+    pub rarities: Vec<&'a str>,
+    /// Of the cheapest rarity, falling back to the card’s lowest price
+    pub price:    Option<i32>,
+}
+
+/// Everything printed in the set with the given prefix (e.g. `LOB`), ordered by set code.
+pub fn set_entries<'a>(cards: impl IntoIterator<Item = &'a Card>, prefix: &str) -> Vec<SetEntry<'a>> {
+    let mut entries: Vec<SetEntry> = Vec::new();
+    for card in cards {
+        for printing in card.card_sets.iter().filter(|s| s.set_prefix().eq_ignore_ascii_case(prefix)) {
+            match entries.iter_mut().find(|e| e.code == printing.set_code && e.card.id == card.id) {
+                Some(entry) if entry.rarities.contains(&printing.set_rarity.as_str()) => (),
+                Some(entry) => {
+                    entry.rarities.push(&printing.set_rarity);
+                    entry.price = entry.price.into_iter().chain(printing.price()).min();
+                }
+                None => entries.push(SetEntry {
+                    card,
+                    code: &printing.set_code,
+                    rarities: vec![&printing.set_rarity],
+                    price: printing.price().or_else(|| card.price()),
+                }),
+            }
+        }
+    }
+    entries.iter_mut().for_each(|e| e.rarities.sort_by_key(|r| std::cmp::Reverse(rarity_rank(r))));
+    entries.sort_by(|a, b| a.code.cmp(b.code).then(a.card.id.cmp(&b.card.id)));
+    entries
+}
+
+/// Whether a card isn’t out yet as of `today`. Cards without any dates only count as unreleased if they haven’t been printed either,
+/// e.g. pre-releases from a simulator database.
+pub fn is_unreleased(first_release: Option<Date>, printed: bool, today: Date) -> bool {
+    match first_release {
+        Some(date) => date > today,
+        None => !printed,
+    }
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone)]
+pub struct Set {
+    pub set_name: String,
+    pub tcg_date: Option<Date>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone, Default)]
+pub struct MiscInfo {
+    pub tcg_date:       Option<Date>,
+    pub ocg_date:       Option<Date>,
+    #[serde(default)]
+    pub formats:        Vec<String>,
+    pub genesys_points: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone, Default)]
+pub struct CardPrice {
+    pub cardmarket_price: String,
+    pub tcgplayer_price:  String,
+}
+
+/// All in cents. Cardmarket prices are in euros, TCGplayer prices in dollars.
+/// Vendors that don’t have a price for the card are `None`.
+#[derive(Debug, Serialize, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Prices {
+    pub lowest:     Option<i32>,
+    pub cardmarket: Option<i32>,
+    pub tcgplayer:  Option<i32>,
+}
+
+/// Parses a price like `12.5` or `0.99` into cents. Exact, unlike going through a float, where `1.05` ends up as 104.
+pub fn parse_cents(s: &str) -> Option<i32> {
+    let (whole, fraction) = s.trim().split_once('.').unwrap_or((s.trim(), ""));
+    if fraction.len() > 2 || !fraction.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    // Parsing as unsigned rejects negative prices.
+    let whole: u32 = if whole.is_empty() && !fraction.is_empty() { 0 } else { whole.parse().ok()? };
+    let fraction: u32 = format!("{fraction:0<2}").parse().ok()?;
+    i32::try_from(whole.checked_mul(100)?.checked_add(fraction)?).ok()
+}
+
+/// Everything read from the upstream dumps on startup.
+#[derive(Debug, Default)]
+pub struct Dump {
+    pub cards: Vec<Card>,
+    pub sets:  Vec<Set>,
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Open(String, io::Error),
+    Parse(String, serde_json::Error),
+    #[cfg(feature = "cdb")]
+    Cdb(rusqlite::Error),
+}
+
+impl LoadError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Open(..) => 2,
+            Self::Parse(..) => 3,
+            #[cfg(feature = "cdb")]
+            Self::Cdb(..) => 4,
+        }
+    }
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Open(path, e) => write!(f, "Could not open {path}: {e}"),
+            Self::Parse(path, e) => write!(f, "Could not deserialize {path}: {e}"),
+            #[cfg(feature = "cdb")]
+            Self::Cdb(e) => write!(f, "Could not read card database: {e}"),
+        }
+    }
+}
+
+impl Error for LoadError {}
+
+#[cfg(feature = "fs")]
+pub(crate) fn read_json<T: serde::de::DeserializeOwned>(path: &str) -> Result<T, LoadError> {
+    let file = File::open(path).map_err(|e| LoadError::Open(path.to_owned(), e))?;
+    serde_json::from_reader(BufReader::new(file)).map_err(|e| LoadError::Parse(path.to_owned(), e))
+}
+
+/// Only the official dump, which is all most instances need.
+pub const DEFAULT_SOURCES: &[&str] = &["cards.json"];
+
+/// A source shaped like `cards.json`, but cards may be incomplete if an earlier source has the rest.
+#[cfg(feature = "fs")]
+#[derive(Debug, Deserialize)]
+struct PartialCardInfo {
+    data: Vec<serde_json::Map<String, serde_json::Value>>,
+}
+
+/// Merges cards by ID, field by field, with later sources taking precedence.
+/// That way, e.g. an errata file only needs the `id` and the new `desc` of each card.
+/// Cards keep the position of their first appearance.
+#[cfg(feature = "fs")]
+fn merge_sources(sources: Vec<(&str, PartialCardInfo)>) -> Result<Vec<Card>, LoadError> {
+    let mut merged: Vec<serde_json::Map<String, serde_json::Value>> = Vec::new();
+    let mut index_by_id: HashMap<u64, usize> = HashMap::new();
+    for (name, source) in sources {
+        for card in source.data {
+            let Some(id) = card.get("id").and_then(|id| id.as_u64()) else {
+                return Err(LoadError::Parse(name.to_owned(), serde::de::Error::missing_field("id")));
+            };
+            match index_by_id.get(&id) {
+                Some(&i) => merged[i].extend(card),
+                None => {
+                    index_by_id.insert(id, merged.len());
+                    merged.push(card);
+                }
+            }
+        }
+    }
+    merged
+        .into_iter()
+        .map(|card| {
+            let id = card.get("id").cloned().unwrap_or_default();
+            serde_json::from_value(serde_json::Value::Object(card)).map_err(|e| LoadError::Parse(format!("card {id}"), e))
+        })
+        .collect()
+}
+
+impl Dump {
+    /// Reads all card sources (see `merge_sources`) and `sets.json` from the working directory.
+    #[cfg(feature = "fs")]
+    pub fn load(sources: &[impl AsRef<str>]) -> Result<Self, LoadError> {
+        Ok(Self { cards: Self::load_cards(sources)?, sets: read_json("sets.json")? })
+    }
+
+    #[cfg(feature = "fs")]
+    fn load_cards(sources: &[impl AsRef<str>]) -> Result<Vec<Card>, LoadError> {
+        match sources {
+            // Going through `serde_json::Value` is a lot slower, so only do it if there’s something to merge.
+            [source] => Ok(read_json::<CardInfo>(source.as_ref())?.data),
+            _ => merge_sources(sources.iter().map(|s| Ok((s.as_ref(), read_json(s.as_ref())?))).collect::<Result<_, LoadError>>()?),
+        }
+    }
+
+    /// For when the dumps don’t come from the file system, e.g. in the browser.
+    pub fn from_json(cards: &str, sets: &str) -> Result<Self, LoadError> {
+        Ok(Self {
+            cards: serde_json::from_str::<CardInfo>(cards).map_err(|e| LoadError::Parse("cards.json".to_owned(), e))?.data,
+            sets:  serde_json::from_str(sets).map_err(|e| LoadError::Parse("sets.json".to_owned(), e))?,
+        })
+    }
+
+    /// Only the prices of every card, for refreshing prices without reloading everything else.
+    #[cfg(feature = "fs")]
+    pub fn load_prices(sources: &[impl AsRef<str>]) -> Result<HashMap<usize, Prices>, LoadError> {
+        Ok(Self::load_cards(sources)?.into_iter().map(|c| (c.id, c.prices())).collect())
+    }
+}
+
+impl Card {
+    /// The API wraps this in a list that only ever has one element, but some cards don’t have it at all.
+    pub fn misc_info(&self) -> Option<&MiscInfo> {
+        self.misc_info.first()
+    }
+
+    /// The first TCG release, from the TCG date in misc_info and the dates of the sets it was printed in.
+    /// OCG dates don’t count, because a card that’s only out in Japan isn’t legal at a TCG tournament.
+    pub fn first_release(&self, sets_by_name: &HashMap<String, Set>) -> Option<Date> {
+        let misc_date = self.misc_info().and_then(|mi| mi.tcg_date);
+        let set_dates = self.card_sets.iter().filter_map(|s| sets_by_name.get(&s.set_name.to_lowercase())?.tcg_date);
+        misc_date.into_iter().chain(set_dates).min()
+    }
+
+    /// The material line of Extra Deck monsters, e.g. `2 Level 4 monsters`.
+    /// That’s always the first line of the monster text, which comes after the Pendulum Effect for Pendulums.
+    pub fn materials(&self) -> Option<&str> {
+        if !self.is_extra_deck() {
+            return None;
+        }
+        let monster_text = match self.card_type.contains("Pendulum") {
+            true => self.text.split_once("Monster Effect").map_or(self.text.as_str(), |(_, t)| t.trim_start_matches([' ', ']'])),
+            false => &self.text,
+        };
+        monster_text.trim_start().lines().next().map(str::trim).filter(|l| !l.is_empty())
+    }
+
+    /// The Pendulum Effect and the monster text (effect or flavor text) of Pendulums.
+    /// Falls back to splitting the text for cards that don’t have them separately, e.g. from a cdb.
+    pub fn pendulum_texts(&self) -> Option<(String, String)> {
+        if !self.card_type.contains("Pendulum") {
+            return None;
+        }
+        if let (Some(pendulum), Some(monster)) = (&self.pend_desc, &self.monster_desc) {
+            return Some((pendulum.replace('\r', ""), monster.replace('\r', "")));
+        }
+        let text = self.text.replace('\r', "");
+        let (pendulum, monster) = ["[ Monster Effect ]", "[Monster Effect]", "[ Flavor Text ]", "[Flavor Text]"]
+            .into_iter()
+            .find_map(|separator| text.split_once(separator))?;
+        let pendulum = pendulum.trim_start_matches("[ Pendulum Effect ]").trim_end().trim_end_matches('-');
+        Some((pendulum.trim().to_owned(), monster.trim().to_owned()))
+    }
+
+    /// Card texts refer to other cards (and archetypes) by putting their names in quotes.
+    /// This returns everything in quotes, so callers have to check which of these are actual card names.
+    pub fn quoted_names(&self) -> impl Iterator<Item = &str> {
+        self.text.split('"').skip(1).step_by(2).filter(|s| !s.is_empty())
+    }
+
+    /// `None` if the card isn’t legal in that format at all, e.g. because it’s too new for GOAT.
+    /// Without misc_info, we don’t know which formats a card is available in, so we assume all of them.
+    pub fn legality(&self, format: Format) -> Option<BanlistStatus> {
+        if self.misc_info().is_some_and(|mi| !mi.formats.iter().any(|f| f == format.misc_info_name())) {
+            return None;
+        }
+        let banlist = self.banlist_info.unwrap_or_default();
+        Some(match format {
+            Format::Tcg => banlist.ban_tcg,
+            Format::Ocg => banlist.ban_ocg,
+            Format::Goat => banlist.ban_goat,
+            Format::MasterDuel => banlist.ban_md,
+        })
+    }
+
+    /// How much the card costs in Genesys decks. Cards without points are free.
+    pub fn genesys_points(&self) -> i32 {
+        self.misc_info().and_then(|mi| mi.genesys_points).unwrap_or_default()
+    }
+
+    /// `Tcg` or `Ocg` if the card has only been released in one of them so far.
+    /// This uses the API’s formats and falls back to Yugipedia’s regions. `None` if it’s out in both or we don’t know.
+    pub fn exclusive_to(&self) -> Option<Format> {
+        let released: Vec<&str> = match (self.misc_info(), &self.yugipedia) {
+            (Some(mi), _) if !mi.formats.is_empty() => mi.formats.iter().map(String::as_str).collect(),
+            (_, Some(yp)) if !yp.regions.is_empty() => yp.regions.iter().map(String::as_str).collect(),
+            _ => return None,
+        };
+        match (released.contains(&"TCG"), released.contains(&"OCG")) {
+            (true, false) => Some(Format::Tcg),
+            (false, true) => Some(Format::Ocg),
+            _ => None,
+        }
+    }
+
+    pub fn legalities(&self) -> Vec<(Format, Option<BanlistStatus>)> {
+        Format::ALL.into_iter().map(|f| (f, self.legality(f))).collect()
+    }
+
+    /// The TCG region (`NorthAmerica` or `Europe`) this card has never been printed for, if it was printed for the other one.
+    pub fn unprinted_tcg_region(&self) -> Option<Region> {
+        let printed = |region| self.card_sets.iter().any(|s| s.region() == region);
+        match (printed(Region::NorthAmerica), printed(Region::Europe)) {
+            (true, false) => Some(Region::Europe),
+            (false, true) => Some(Region::NorthAmerica),
+            _ => None,
+        }
+    }
+
+    /// All printings grouped by region. Within a region, they keep the order of `card_sets`.
+    pub fn printings_by_region(&self) -> Vec<(Region, Vec<&CardSet>)> {
+        let mut by_region: Vec<(Region, Vec<&CardSet>)> = Vec::new();
+        for printing in &self.card_sets {
+            match by_region.iter_mut().find(|(r, _)| *r == printing.region()) {
+                Some((_, printings)) => printings.push(printing),
+                None => by_region.push((printing.region(), vec![printing])),
+            }
+        }
+        by_region.sort_by_key(|(r, _)| *r);
+        by_region
+    }
+
+    pub fn short_info(&self) -> Result<String, fmt::Error> {
+        let mut s = String::new();
+        s.push_str(&self.name);
+        s.push('\n');
+        self.basic_info(&mut s, "\n")?;
+        Ok(s)
+    }
+
+    /// Also used for plain text, so the caller is responsible for escaping if this is written into HTML.
+    pub fn basic_info<W: Write>(&self, f: &mut W, newline: &str) -> fmt::Result {
+        self.write_type_line(f)?;
+        if self.is_monster() {
+            f.write_str(newline)?;
+            self.write_stats(f)?;
+        }
+        Ok(())
+    }
+
+    fn is_monster(&self) -> bool {
+        self.card_type.contains("Monster")
+    }
+
+    pub fn is_extra_deck(&self) -> bool {
+        ["Fusion", "Synchro", "XYZ", "Link"].iter().any(|t| self.card_type.contains(t))
+    }
+
+    /// The frame of the card, i.e. the part of the card type that decides where it goes,
+    /// e.g. `Synchro` for a “Synchro Tuner Monster” and a “Synchro Pendulum Effect Monster”.
+    pub fn class(&self) -> &'static str {
+        const FRAMES: &[&str] = &["Fusion", "Synchro", "XYZ", "Link", "Ritual", "Pendulum", "Spell", "Trap", "Token", "Skill", "Normal"];
+        FRAMES.iter().find(|frame| self.card_type.contains(*frame)).copied().unwrap_or("Effect")
+    }
+
+    fn write_type_line<W: Write>(&self, f: &mut W) -> fmt::Result {
+        if let Some(level) = self.level {
+            if self.card_type.contains("XYZ") {
+                f.write_str("Rank ")?;
+            } else {
+                f.write_str("Level ")?;
+            }
+            write!(f, "{level} ")?;
+        } else if let Some(lr) = self.link_rating {
+            write!(f, "Link {lr} ")?;
+        }
+        if let Some(attr) = &self.attribute {
+            write!(f, "{attr}/")?;
+        }
+        write!(f, "{} {}", self.r#type, self.card_type)
+    }
+
+    fn write_stats<W: Write>(&self, f: &mut W) -> fmt::Result {
+        match (self.atk, self.def) {
+            (Some(atk), Some(def)) => write!(f, "{atk} ATK / {def} DEF"),
+            (Some(atk), None) if self.link_rating.is_some() => write!(f, "{atk} ATK"),
+            (None, Some(def)) => write!(f, "? ATK / {def} DEF"),
+            (Some(atk), None) => write!(f, "{atk} ATK / ? DEF"),
+            (None, None) => write!(f, "? ATK / ? DEF"),
+        }
+    }
+
+    /// e.g. “Level 4 LIGHT/Warrior Effect Monster”
+    pub fn type_line(&self) -> String {
+        let mut s = String::new();
+        // Writing to a String can’t fail.
+        let _ = self.write_type_line(&mut s);
+        s
+    }
+
+    /// ATK and DEF, or an empty string for spells and traps.
+    pub fn stats(&self) -> String {
+        let mut s = String::new();
+        if self.is_monster() {
+            let _ = self.write_stats(&mut s);
+        }
+        s
+    }
+
+    /// The lowest price across all vendors, in cents. Vendors use 0 for cards they don’t have a price for.
+    pub fn price(&self) -> Option<i32> {
+        self.card_prices
+            .iter()
+            .flat_map(|p| [parse_cents(&p.cardmarket_price), parse_cents(&p.tcgplayer_price)])
+            .flatten()
+            .filter(|&p| p > 0)
+            .min()
+    }
+
+    pub fn prices(&self) -> Prices {
+        let vendor_price =
+            |price: fn(&CardPrice) -> &str| self.card_prices.iter().filter_map(|p| parse_cents(price(p))).filter(|&p| p > 0).min();
+        Prices {
+            lowest:     self.price(),
+            cardmarket: vendor_price(|p| &p.cardmarket_price),
+            tcgplayer:  vendor_price(|p| &p.tcgplayer_price),
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+
+    pub const RAW_SPELL: &str = r#"
+    {
+      "id": 41142615,
+      "name": "The Cheerful Coffin",
+      "type": "Spell Card",
+      "desc": "Discard up to 3 Monster Cards from your hand to the Graveyard.",
+      "race": "Normal",
+      "card_sets": [
+        {
+          "set_name": "Dark Beginning 1",
+          "set_code": "DB1-EN167",
+          "set_rarity": "Common",
+          "set_rarity_code": "(C)",
+          "set_price": "1.41"
+        },
+        {
+          "set_name": "Metal Raiders",
+          "set_code": "MRD-059",
+          "set_rarity": "Common",
+          "set_rarity_code": "(C)",
+          "set_price": "1.55"
+        }
+      ]
+    }"#;
+
+    pub const RAW_MONSTER: &str = r#"
+    {
+       "id": 2326738,
+       "name": "Des Lacooda",
+       "type": "Effect Monster",
+       "desc": "Once per turn: You can change this card to face-down Defense Position. When this card is Flip Summoned: Draw 1 card.",
+       "atk": 500,
+       "def": 600,
+       "level": 3,
+       "race": "Zombie",
+       "attribute": "EARTH",
+       "misc_info": [
+         {
+           "views": 8341,
+           "formats": ["TCG", "OCG", "Master Duel"],
+           "tcg_date": "2003-10-10",
+           "ocg_date": "2003-05-22"
+         }
+       ],
+       "card_sets": [
+         {
+           "set_name": "Astral Pack Three",
+           "set_code": "AP03-EN018",
+           "set_rarity": "Common",
+           "set_rarity_code": "(C)",
+           "set_price": "1.24"
+         },
+         {
+           "set_name": "Gold Series",
+           "set_code": "GLD1-EN010",
+           "set_rarity": "Common",
+           "set_rarity_code": "(C)",
+           "set_price": "2.07"
+         }
+       ],
+       "card_prices": [
+         {
+           "cardmarket_price": "0.05",
+           "tcgplayer_price": "0.22"
+         }
+       ]
+    }"#;
+
+    pub const RAW_LINK_MONSTER: &str = r#"
+    {
+      "id": 49202162,
+      "name": "Black Luster Soldier - Soldier of Chaos",
+      "type": "Link Monster",
+      "frameType": "link",
+      "desc": "3 monsters with different names\r\nIf this card was Link Summoned using a Level 7 or higher monster(s) as material, your opponent cannot target it with card effects, also it cannot be destroyed by your opponent's card effects. When this card destroys an opponent's monster by battle: You can activate 1 of these effects;\r\n● This card gains 1500 ATK.\r\n● This card can make a second attack during the Battle Phase of your next turn.\r\n● Banish 1 card on the field.",
+      "atk": 3000,
+      "race": "Warrior",
+      "attribute": "EARTH",
+      "archetype": "Black Luster Soldier",
+      "linkval": 3,
+      "linkmarkers": [
+        "Top",
+        "Bottom-Left",
+        "Bottom-Right"
+      ],
+      "card_sets": [
+        {
+          "set_name": "OTS Tournament Pack 17",
+          "set_code": "OP17-EN003",
+          "set_rarity": "Ultimate Rare",
+          "set_rarity_code": "(UtR)",
+          "set_price": "75.77"
+        }
+      ],
+      "card_images": [
+        {
+          "id": 49202162,
+          "image_url": "https://images.ygoprodeck.com/images/cards/49202162.jpg",
+          "image_url_small": "https://images.ygoprodeck.com/images/cards_small/49202162.jpg",
+          "image_url_cropped": "https://images.ygoprodeck.com/images/cards_cropped/49202162.jpg"
+        }
+      ],
+      "card_prices": [
+        {
+          "cardmarket_price": "3.70",
+          "tcgplayer_price": "3.30"
+        }
+      ]
+    }
+    "#;
+
+    #[test]
+    fn test_spell() {
+        let coffin: Card = serde_json::from_str(RAW_SPELL).unwrap();
+        assert_eq!(
+            coffin,
+            Card {
+                id: 41142615,
+                card_type: "Spell Card".to_owned(),
+                name: "The Cheerful Coffin".to_owned(),
+                text: "Discard up to 3 Monster Cards from your hand to the Graveyard.".to_owned(),
+                r#type: "Normal".to_owned(),
+                card_sets: vec![
+                    CardSet {
+                        set_name:   "Dark Beginning 1".to_owned(),
+                        set_code:   "DB1-EN167".to_owned(),
+                        set_rarity: "Common".to_owned(),
+                        set_price:  "1.41".to_owned(),
+                    },
+                    CardSet {
+                        set_name:   "Metal Raiders".to_owned(),
+                        set_code:   "MRD-059".to_owned(),
+                        set_rarity: "Common".to_owned(),
+                        set_price:  "1.55".to_owned(),
+                    }
+                ],
+                ..Default::default()
+            }
+        )
+    }
+
+    #[test]
+    fn test_monster() {
+        let munch: Card = serde_json::from_str(RAW_MONSTER).unwrap();
+        assert_eq!(
+            munch,
+            Card {
+                id: 2326738,
+                card_type: "Effect Monster".to_owned(),
+                name: "Des Lacooda".to_owned(),
+                text:
+                    "Once per turn: You can change this card to face-down Defense Position. When this card is Flip Summoned: Draw 1 card."
+                        .to_owned(),
+                atk: Some(500),
+                def: Some(600),
+                level: Some(3),
+                r#type: "Zombie".to_owned(),
+                attribute: Some("EARTH".to_owned()),
+                card_sets: vec![
+                    CardSet {
+                        set_name:   "Astral Pack Three".to_owned(),
+                        set_code:   "AP03-EN018".to_owned(),
+                        set_rarity: "Common".to_owned(),
+                        set_price:  "1.24".to_owned(),
+                    },
+                    CardSet {
+                        set_name:   "Gold Series".to_owned(),
+                        set_code:   "GLD1-EN010".to_owned(),
+                        set_rarity: "Common".to_owned(),
+                        set_price:  "2.07".to_owned(),
+                    }
+                ],
+                card_prices: vec![CardPrice { tcgplayer_price: "0.22".to_owned(), cardmarket_price: "0.05".to_owned() }],
+                misc_info: vec![MiscInfo {
+                    tcg_date:       Some(Date::from_calendar_date(2003, time::Month::October, 10).unwrap()),
+                    ocg_date:       Some(Date::from_calendar_date(2003, time::Month::May, 22).unwrap()),
+                    formats:        vec!["TCG".to_owned(), "OCG".to_owned(), "Master Duel".to_owned()],
+                    genesys_points: None,
+                }],
+                ..Default::default()
+            },
+        );
+        assert_eq!(munch.prices(), Prices { lowest: Some(5), cardmarket: Some(5), tcgplayer: Some(22) });
+        let free = Card { card_prices: vec![CardPrice { tcgplayer_price: "0.00".to_owned(), ..Default::default() }], ..munch };
+        assert_eq!(free.prices().tcgplayer, None);
+        assert_eq!(free.prices().lowest, None, "not 0, which would set off every price watch");
+    }
+
+    #[test]
+    fn type_line_and_stats_test() {
+        let bls: Card = serde_json::from_str(RAW_LINK_MONSTER).unwrap();
+        assert_eq!(bls.type_line(), "Link 3 EARTH/Warrior Link Monster");
+        assert_eq!(bls.stats(), "3000 ATK");
+        let coffin: Card = serde_json::from_str(RAW_SPELL).unwrap();
+        assert_eq!(coffin.type_line(), "Normal Spell Card");
+        assert_eq!(coffin.stats(), "");
+    }
+
+    #[test]
+    fn printings_by_region_test() {
+        let printing = |code: &str| CardSet { set_code: code.to_owned(), ..Default::default() };
+        let card = Card {
+            card_sets: vec![printing("LOB-E005"), printing("LOB-005"), printing("LOB-EN005"), printing("LOB-JP005")],
+            ..Default::default()
+        };
+        let regions: Vec<_> = card.printings_by_region().into_iter().map(|(r, p)| (r, p.len())).collect();
+        assert_eq!(regions, vec![(Region::NorthAmerica, 2), (Region::Europe, 1), (Region::Ocg, 1)]);
+        assert_eq!(card.unprinted_tcg_region(), None);
+        let na_only = Card { card_sets: vec![printing("LOB-EN005"), printing("LOB-JP005")], ..Default::default() };
+        assert_eq!(na_only.unprinted_tcg_region(), Some(Region::Europe));
+        let eu_only = Card { card_sets: vec![printing("LOB-G005")], ..Default::default() };
+        assert_eq!(eu_only.unprinted_tcg_region(), Some(Region::NorthAmerica));
+        assert_eq!(Card::default().unprinted_tcg_region(), None);
+        assert_eq!(printing("LOB-EN005").region_tag(), "EN");
+        assert_eq!(printing("SDY-006").region_tag(), "");
+        assert_eq!(printing("GLD1-EN010").region_tag(), "EN");
+    }
+
+    #[test]
+    fn class_test() {
+        let class = |card_type: &str| Card { card_type: card_type.to_owned(), ..Default::default() }.class();
+        assert_eq!(class("Synchro Tuner Monster"), "Synchro");
+        assert_eq!(class("Synchro Pendulum Effect Monster"), "Synchro");
+        assert_eq!(class("Pendulum Normal Monster"), "Pendulum");
+        assert_eq!(class("Normal Tuner Monster"), "Normal");
+        assert_eq!(class("Flip Effect Monster"), "Effect");
+        assert_eq!(class("Spell Card"), "Spell");
+    }
+
+    #[test]
+    fn set_code_test() {
+        assert_eq!(SetCode::parse("LOB-EN005"), SetCode { prefix: "LOB", region: "EN", number: "005" });
+        assert_eq!(SetCode::parse("LOB-005"), SetCode { prefix: "LOB", region: "", number: "005" });
+        assert_eq!(SetCode::parse("DUPO-ENSP1"), SetCode { prefix: "DUPO", region: "ENSP", number: "1" });
+        assert_eq!(SetCode::parse("BROKEN"), SetCode { prefix: "BROKEN", region: "", number: "" });
+    }
+
+    #[test]
+    fn region_tags_test() {
+        assert_eq!(region_tags("de"), Some(vec!["DE", "G"]));
+        assert_eq!(region_tags("E"), Some(vec!["E"]));
+        assert_eq!(region_tags("na"), Some(vec!["", "EN"]));
+        assert!(region_tags("eu").unwrap().contains(&"E"));
+        assert_eq!(region_tags("xx"), None);
+    }
+
+    #[test]
+    fn set_entries_test() {
+        let printing = |code: &str, rarity: &str, price: &str| CardSet {
+            set_name:   "Legend of Blue Eyes White Dragon".to_owned(),
+            set_code:   code.to_owned(),
+            set_rarity: rarity.to_owned(),
+            set_price:  price.to_owned(),
+        };
+        let cards = [
+            Card {
+                id: 2,
+                card_sets: vec![
+                    printing("LOB-EN005", "Common", "1.50"),
+                    printing("LOB-EN005", "Secret Rare", "20"),
+                    printing("MRD-EN001", "Rare", "1"),
+                ],
+                ..Default::default()
+            },
+            Card { id: 1, card_sets: vec![printing("LOB-EN001", "Ultra Rare", "0")], ..Default::default() },
+        ];
+        let entries = set_entries(&cards, "lob");
+        assert_eq!(entries.iter().map(|e| (e.card.id, e.code)).collect::<Vec<_>>(), vec![(1, "LOB-EN001"), (2, "LOB-EN005")]);
+        assert_eq!(entries[1].rarities, vec!["Secret Rare", "Common"]);
+        assert_eq!(entries[1].price, Some(150));
+        assert_eq!(entries[0].price, None);
+    }
+
+    #[test]
+    fn rarity_tier_test() {
+        for (tier, names) in RARITY_TIERS.iter().enumerate() {
+            assert_eq!(rarity_tier(names[0]), Some(tier as u8));
+        }
+        assert_eq!(rarity_tier("ghost rare"), Some(rarity_rank("Ghost Rare")));
+        assert_eq!(rarity_tier("qcscr"), Some(rarity_rank("Quarter Century Secret Rare")));
+        assert_eq!(rarity_tier("shiny"), None);
+    }
+
+    #[test]
+    fn era_dates_test() {
+        let day = |year, month, day| Date::from_calendar_date(year, month, day).unwrap();
+        assert_eq!(era_dates("dm"), Some((day(2002, Month::March, 8), day(2005, Month::May, 31))));
+        assert_eq!(era_dates("5d's"), era_dates("5ds"));
+        assert_eq!(era_dates("arc-v").unwrap().1, day(2017, Month::August, 3));
+        assert_eq!(era_dates("post-vrains").unwrap().1, Date::MAX);
+        assert_eq!(era_dates("sevens"), None);
+    }
+
+    #[test]
+    fn quoted_names_test() {
+        let card = Card {
+            text: r#"Target 1 "Dark Magician" in your GY; Special Summon it. You can only activate 1 "Magician's Salvation" per turn."#
+                .to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(card.quoted_names().collect::<Vec<_>>(), vec!["Dark Magician", "Magician's Salvation"]);
+        assert_eq!(Card::default().quoted_names().count(), 0);
+    }
+
+    #[test]
+    fn support_counts_test() {
+        let card = |id, name: &str, archetype: Option<&str>, text: &str| Card {
+            id,
+            name: name.to_owned(),
+            archetype: archetype.map(str::to_owned),
+            text: text.to_owned(),
+            ..Default::default()
+        };
+        let cards = [
+            card(1, "Dark Magician", Some("Dark Magician"), ""),
+            card(2, "Dark Magician", Some("Dark Magician"), ""),
+            card(3, "Dark Magical Circle", Some("Dark Magician"), r#"Add 1 "Dark Magician" or 1 card that mentions it"#),
+            card(4, "Dark Magic Veil", None, r#"Special Summon 1 "Dark Magician" and 1 "Dark Magician" monster"#),
+            card(5, "Pot of Greed", None, "Draw 2 cards."),
+        ];
+        let counts = support_counts(cards.iter());
+        assert_eq!((counts[&1], counts[&2]), (2, 2));
+        assert_eq!(counts[&3], 1, "Dark Magic Veil supports the archetype, but Dark Magical Circle doesn’t count itself");
+        assert_eq!(counts[&5], 0);
+    }
+
+    #[test]
+    fn legality_test() {
+        let bls: Card = serde_json::from_str(RAW_LINK_MONSTER).unwrap();
+        let bls = Card { banlist_info: Some(BanlistInfo { ban_ocg: BanlistStatus::Limited, ..Default::default() }), ..bls };
+        assert_eq!(
+            bls.legalities(),
+            vec![
+                (Format::Tcg, Some(BanlistStatus::Unlimited)),
+                (Format::Ocg, Some(BanlistStatus::Limited)),
+                (Format::Goat, Some(BanlistStatus::Unlimited)),
+                (Format::MasterDuel, Some(BanlistStatus::Unlimited))
+            ]
+        );
+        let lacooda: Card = serde_json::from_str(RAW_MONSTER).unwrap();
+        assert_eq!(lacooda.legality(Format::Tcg), Some(BanlistStatus::Unlimited));
+        assert_eq!(lacooda.legality(Format::Goat), None, "GOAT isn’t in the formats of the test card");
+        assert_eq!(lacooda.legality(Format::MasterDuel), Some(BanlistStatus::Unlimited));
+    }
+
+    #[test]
+    fn materials_test() {
+        let bls: Card = serde_json::from_str(RAW_LINK_MONSTER).unwrap();
+        assert_eq!(bls.materials(), Some("3 monsters with different names"));
+        let lacooda: Card = serde_json::from_str(RAW_MONSTER).unwrap();
+        assert_eq!(lacooda.materials(), None);
+        let odd_eyes = Card {
+            card_type: "Synchro Pendulum Effect Monster".to_owned(),
+            text: "[ Pendulum Effect ]\r\nOnce per turn: You can destroy this card.\r\n----------------------------------------\r\n[ Monster Effect ]\r\n1 Tuner + 1+ non-Tuner Pendulum Monsters\r\nOnce per turn: You can target 1 card.".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(odd_eyes.materials(), Some("1 Tuner + 1+ non-Tuner Pendulum Monsters"));
+        assert_eq!(
+            odd_eyes.pendulum_texts(),
+            Some((
+                "Once per turn: You can destroy this card.".to_owned(),
+                "1 Tuner + 1+ non-Tuner Pendulum Monsters\nOnce per turn: You can target 1 card.".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn pendulum_texts_test() {
+        let card = Card {
+            card_type: "Pendulum Normal Monster".to_owned(),
+            text: "whatever".to_owned(),
+            pend_desc: Some("Scale stuff".to_owned()),
+            monster_desc: Some("Flavor".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(card.pendulum_texts(), Some(("Scale stuff".to_owned(), "Flavor".to_owned())));
+        assert_eq!(Card { card_type: "Pendulum Normal Monster".to_owned(), ..Default::default() }.pendulum_texts(), None);
+        assert_eq!(Card { card_type: "Normal Monster".to_owned(), ..card }.pendulum_texts(), None);
+    }
+
+    #[test]
+    fn exclusive_to_test() {
+        let lacooda: Card = serde_json::from_str(RAW_MONSTER).unwrap();
+        assert_eq!(lacooda.exclusive_to(), None);
+        let ocg = MiscInfo { formats: vec!["OCG".to_owned()], ..Default::default() };
+        assert_eq!(Card { misc_info: vec![ocg], ..lacooda.clone() }.exclusive_to(), Some(Format::Ocg));
+        let yugipedia = YugipediaInfo { regions: vec!["TCG".to_owned(), "Korean".to_owned()], ..Default::default() };
+        let from_yugipedia = Card { misc_info: vec![], yugipedia: Some(yugipedia), ..lacooda };
+        assert_eq!(from_yugipedia.exclusive_to(), Some(Format::Tcg));
+        assert_eq!(Card::default().exclusive_to(), None);
+    }
+
+    #[test]
+    fn banlist_history_test() {
+        let date = |y, m, d| Date::from_calendar_date(y, Month::try_from(m).unwrap(), d).unwrap();
+        let history = BanlistHistory::new(vec![
+            (date(2008, 9, 1), HashMap::from([(1, BanlistStatus::Limited)])),
+            (date(2005, 4, 1), HashMap::from([(1, BanlistStatus::Forbidden), (2, BanlistStatus::SemiLimited)])),
+        ]);
+        assert_eq!(history.status_on(1, date(2005, 3, 31)), BanlistStatus::Unlimited);
+        assert_eq!(history.status_on(1, date(2005, 4, 1)), BanlistStatus::Forbidden);
+        assert_eq!(history.status_on(2, date(2008, 8, 31)), BanlistStatus::SemiLimited);
+        assert_eq!(history.status_on(1, date(2008, 9, 1)), BanlistStatus::Limited);
+        assert_eq!(history.status_on(2, date(2024, 1, 1)), BanlistStatus::Unlimited);
+    }
+
+    #[test]
+    fn first_release_test() {
+        let date = |y, m, d| Date::from_calendar_date(y, Month::try_from(m).unwrap(), d).unwrap();
+        let sets_by_name = HashMap::from([("lob".to_owned(), Set { set_name: "LOB".to_owned(), tcg_date: Some(date(2002, 3, 8)) })]);
+        let printed = Card {
+            misc_info: vec![MiscInfo { ocg_date: Some(date(1999, 1, 1)), ..Default::default() }],
+            card_sets: vec![CardSet { set_name: "LOB".to_owned(), ..Default::default() }],
+            ..Default::default()
+        };
+        assert_eq!(printed.first_release(&sets_by_name), Some(date(2002, 3, 8)), "the OCG release doesn’t count");
+        assert!(!is_unreleased(printed.first_release(&sets_by_name), true, date(2002, 3, 8)));
+        assert!(is_unreleased(printed.first_release(&sets_by_name), true, date(2002, 3, 7)));
+        let ocg_only = Card { misc_info: vec![MiscInfo { ocg_date: Some(date(1999, 1, 1)), ..Default::default() }], ..Default::default() };
+        assert_eq!(ocg_only.first_release(&sets_by_name), None);
+        assert!(is_unreleased(None, false, date(2002, 3, 8)));
+        assert!(!is_unreleased(None, true, date(2002, 3, 8)));
+    }
+
+    #[test]
+    fn dump_from_json_test() {
+        let dump = Dump::from_json(
+            &format!(r#"{{"data": [{RAW_SPELL}, {RAW_MONSTER}]}}"#),
+            r#"[{"set_name": "Gold Series", "tcg_date": "2008-02-02"}]"#,
+        )
+        .unwrap();
+        assert_eq!(dump.cards.len(), 2);
+        assert_eq!(dump.sets[0].set_name, "Gold Series");
+        assert!(matches!(Dump::from_json("{}", "[]"), Err(LoadError::Parse(path, _)) if path == "cards.json"));
+    }
+
+    #[test]
+    fn merge_sources_test() {
+        let source = |json: &str| serde_json::from_str::<PartialCardInfo>(json).unwrap();
+        let official = source(&format!(r#"{{"data": [{RAW_SPELL}, {RAW_MONSTER}]}}"#));
+        let additions = source(&format!(r#"{{"data": [{RAW_LINK_MONSTER}]}}"#));
+        let errata = source(r#"{"data": [{"id": 41142615, "desc": "Discard up to 3 monsters."}]}"#);
+        let cards = merge_sources(vec![("cards.json", official), ("ocg.json", additions), ("errata.json", errata)]).unwrap();
+        assert_eq!(cards.iter().map(|c| c.id).collect::<Vec<_>>(), vec![41142615, 2326738, 49202162]);
+        assert_eq!(cards[0].text, "Discard up to 3 monsters.");
+        assert_eq!(cards[0].name, "The Cheerful Coffin");
+
+        let incomplete = source(r#"{"data": [{"id": 1, "desc": "No name"}]}"#);
+        assert!(matches!(merge_sources(vec![("errata.json", incomplete)]), Err(LoadError::Parse(name, _)) if name == "card 1"));
+        let no_id = source(r#"{"data": [{"desc": "Who am I"}]}"#);
+        assert!(matches!(merge_sources(vec![("errata.json", no_id)]), Err(LoadError::Parse(name, _)) if name == "errata.json"));
+    }
+
+    #[test]
+    fn test_missing_misc_info() {
+        let coffin: Card = serde_json::from_str(RAW_SPELL).unwrap();
+        assert_eq!(coffin.misc_info(), None);
+    }
+
+    #[test]
+    fn parse_cents_test() {
+        assert_eq!(parse_cents("12.34"), Some(1234));
+        assert_eq!(parse_cents("12.5"), Some(1250));
+        assert_eq!(parse_cents(" 3 "), Some(300));
+        assert_eq!(parse_cents(".99"), Some(99));
+        assert_eq!(parse_cents("1.05"), Some(105), "where a float ends up at 104");
+        assert_eq!(parse_cents("0.53"), Some(53));
+        assert_eq!(parse_cents("1.234"), None);
+        assert_eq!(parse_cents("-1"), None);
+        assert_eq!(parse_cents("-0.5"), None);
+        assert_eq!(parse_cents("abc"), None);
+        assert_eq!(parse_cents(""), None);
+        let printing = |price: &str| CardSet { set_price: price.to_owned(), ..Default::default() };
+        assert_eq!(printing("1.05").price(), Some(105));
+        assert_eq!(printing("0.00").price(), None);
+    }
+}
@@ -0,0 +1,997 @@
+use itertools::Itertools;
+use std::{
+    cmp::Ordering,
+    collections::HashMap,
+    sync::{
+        atomic::{self, AtomicI32},
+        OnceLock,
+    },
+};
+use time::{Date, OffsetDateTime};
+
+use crate::{
+    data::{
+        era_dates, is_unreleased, known_region_tag, rarity_rank, rarity_tier, region_tags, BanlistHistory, BanlistStatus, Card, Format,
+        Set, REGION_NAMES,
+    },
+    parser::{Field, Operator, RawCardFilter, Sort, SortKey, Value},
+    stem::{stem, stemmed_words},
+    synonyms::synonyms,
+    tags::{all_tags, effect_tags, is_tag, opt_kinds, OPT_KINDS},
+};
+
+/// A struct derived from `Card` that has all fields lowercased for easier search
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SearchCard {
+    pub id:        usize,
+    card_type:     String,
+    name:          String,
+    text:          String,
+    /// Name and text in their original case, for `=~`
+    cased_name:    String,
+    cased_text:    String,
+    /// See `stemmed_words`
+    stemmed_text:  String,
+    archetype:     Option<String>,
+    /// See `tags.rs`
+    tags:          Vec<&'static str>,
+    /// See `opt_kinds`
+    opt:           Vec<&'static str>,
+    atk:           Option<i32>,
+    def:           Option<i32>,
+    attribute:     Option<String>,
+    r#type:        String,
+    // also includes rank
+    level:         Option<i32>,
+    link_rating:   Option<i32>,
+    link_arrows:   Option<Vec<String>>,
+    /// `main` or `extra`. Tokens and Skills aren’t in any deck.
+    deck:          Option<&'static str>,
+    /// Only for Extra Deck monsters, see `Card::materials`
+    materials:     Option<String>,
+    sets:          Vec<String>,
+    /// Region tags of all printings as in the set code, e.g. `EN` or `G`, see `region_tags`
+    regions:       Vec<&'static str>,
+    /// Distinct sets, so different rarities in the same set don’t count as reprints
+    printings:     usize,
+    /// `anime` and/or `manga`, if we have Yugipedia data for the card
+    appearances:   Vec<String>,
+    release_date:  Option<Date>,
+    /// In the TCG, see `Card::first_release`
+    first_release: Option<Date>,
+    /// Every year the card was printed in, sorted
+    print_years:   Vec<i32>,
+    /// See `rarity_rank`
+    max_rarity:    Option<u8>,
+    legal_copies:  i32,
+    /// See `data::support_counts`. Not part of the card, so it’s 0 unless set with `with_support_count`.
+    support:       i32,
+    /// For `is:tcgonly` and `is:ocgonly`
+    exclusive_to:  Option<Format>,
+    price:         Option<i32>,
+    /// See `Dump::add_custom`
+    custom:        bool,
+    /// All rulings of the card, lowercased
+    rulings:       String,
+}
+
+impl SearchCard {
+    /// `sets_by_name` is only needed for the release date, see [`crate::sets_by_name`].
+    pub fn new(card: &Card, sets_by_name: &HashMap<String, Set>) -> Self {
+        Self {
+            id:            card.id,
+            card_type:     card.card_type.to_lowercase(),
+            name:          card.name.to_lowercase(),
+            text:          card.text.to_lowercase(),
+            cased_name:    card.name.clone(),
+            cased_text:    card.text.clone(),
+            stemmed_text:  stemmed_words(&card.text),
+            archetype:     card.archetype.as_ref().map(|s| s.to_lowercase()),
+            tags:          effect_tags(&card.text),
+            opt:           opt_kinds(&card.text),
+            atk:           card.atk,
+            def:           card.def,
+            attribute:     card.attribute.as_ref().map(|s| s.to_lowercase()),
+            r#type:        card.r#type.to_lowercase(),
+            level:         card.level,
+            link_rating:   card.link_rating,
+            link_arrows:   card.link_arrows.as_ref().map(|arrows| arrows.iter().map(|a| a.to_lowercase()).collect()),
+            // Pendulums can be summoned from the Extra Deck, but they start in the Main Deck.
+            deck:          match card.card_type.as_str() {
+                "Token" | "Skill Card" => None,
+                _ if card.is_extra_deck() => Some("extra"),
+                _ => Some("main"),
+            },
+            materials:     card.materials().map(str::to_lowercase),
+            sets:          card.card_sets.iter().map(|s| s.set_prefix().to_lowercase()).collect(),
+            regions:       card.card_sets.iter().filter_map(|s| known_region_tag(s.region_tag())).unique().collect(),
+            printings:     card.card_sets.iter().map(|s| s.set_name.to_lowercase()).unique().count(),
+            appearances:   card.yugipedia.as_ref().map(|y| y.appearances()).unwrap_or_default(),
+            release_date:  card
+                .card_sets
+                .iter()
+                .filter_map(|s| sets_by_name.get(&s.set_name.to_lowercase()).and_then(|s| s.tcg_date))
+                .min(),
+            first_release: card.first_release(sets_by_name),
+            print_years:   card
+                .card_sets
+                .iter()
+                .filter_map(|s| Some(sets_by_name.get(&s.set_name.to_lowercase())?.tcg_date?.year()))
+                .sorted()
+                .dedup()
+                .collect(),
+            max_rarity:    card.card_sets.iter().map(|s| rarity_rank(&s.set_rarity)).max(),
+            legal_copies:  card.banlist_info.map(|bi| bi.ban_tcg).unwrap_or(BanlistStatus::Unlimited) as i32,
+            support:       0,
+            exclusive_to:  card.exclusive_to(),
+            price:         card.price(),
+            custom:        card.custom,
+            rulings:       card.rulings.iter().map(|r| r.text.to_lowercase()).join("\n"),
+        }
+    }
+
+    /// Upstream dumps don’t guarantee any order, so results are sorted by first release, then name, then ID.
+    /// Unreleased cards come last.
+    pub fn sort_key(&self) -> (Date, &str, usize) {
+        (self.release_date.unwrap_or(Date::MAX), &self.name, self.id)
+    }
+
+    /// Whether the first TCG printing was on the same month and day as `date`, in any year.
+    pub fn released_on_day_of(&self, date: Date) -> bool {
+        self.release_date.is_some_and(|d| (d.month(), d.day()) == (date.month(), date.day()))
+    }
+
+    pub fn release_date(&self) -> Option<Date> {
+        self.release_date
+    }
+
+    /// Whether the card was out in the TCG on `date`, for `asof:`.
+    pub fn released_by(&self, date: Date) -> bool {
+        self.first_release.is_some_and(|d| d <= date)
+    }
+
+    /// Hidden from searches without `include:spoilers`. Custom cards are never released, so they don’t count.
+    pub fn is_unreleased(&self, today: Date) -> bool {
+        !self.custom && is_unreleased(self.first_release, self.printings > 0, today)
+    }
+
+    /// Every ruling, lowercased and separated by newlines, so callers can rule out cards before looking at each ruling.
+    pub fn rulings(&self) -> &str {
+        &self.rulings
+    }
+
+    /// Hidden from searches without `is:custom`.
+    pub fn is_custom(&self) -> bool {
+        self.custom
+    }
+
+    pub fn with_support_count(self, support: i32) -> Self {
+        Self { support, ..self }
+    }
+
+    pub fn tags(&self) -> &[&'static str] {
+        &self.tags
+    }
+
+    /// Everything `any:` searches, from most to least relevant.
+    fn any_fields(&self) -> [&str; 5] {
+        [&self.name, self.archetype.as_deref().unwrap_or_default(), &self.r#type, &self.card_type, &self.text]
+    }
+
+    /// Where an `any:` value matched first, see `any_fields`. Lower is more relevant.
+    pub fn any_rank(&self, value: &Value) -> usize {
+        let fields = self.any_fields();
+        fields.iter().position(|f| filter_value(&Operator::Equal, &Value::String(f.to_string()), value)).unwrap_or(fields.len())
+    }
+
+    /// How well the name matches a name search (already lowercased). Lower is better:
+    /// the exact name, then names starting with it, then names where it starts a word, then anywhere else.
+    /// Shorter names come first within each of those.
+    pub fn name_rank(&self, query: &str) -> (u8, usize) {
+        let starts_word = || self.name.match_indices(query).any(|(i, _)| !self.name[..i].ends_with(char::is_alphanumeric));
+        let quality = match () {
+            _ if self.name == query => 0,
+            _ if self.name.starts_with(query) => 1,
+            _ if starts_word() => 2,
+            _ => 3,
+        };
+        (quality, self.name.chars().count())
+    }
+
+    /// Whether the card only ever had a single printing, and that was released before `cutoff`.
+    fn unreprinted_since(&self, cutoff: Date) -> bool {
+        self.printings == 1 && self.release_date.is_some_and(|d| d < cutoff)
+    }
+}
+
+impl Sort {
+    /// Cards without a value for the sort key (e.g. the ATK of spells) always come last.
+    /// Ties keep the default order.
+    pub fn compare(&self, a: &SearchCard, b: &SearchCard) -> Ordering {
+        let (a_value, b_value) = (sort_value(self.key, a), sort_value(self.key, b));
+        let ordering = match (&a_value, &b_value) {
+            (Some(x), Some(y)) if self.descending => y.cmp(x),
+            (Some(x), Some(y)) => x.cmp(y),
+            _ => b_value.is_some().cmp(&a_value.is_some()),
+        };
+        ordering.then_with(|| a.sort_key().cmp(&b.sort_key()))
+    }
+}
+
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum SortValue<'a> {
+    Number(i32),
+    Text(&'a str),
+    Date(Date),
+}
+
+fn sort_value(key: SortKey, card: &SearchCard) -> Option<SortValue<'_>> {
+    match key {
+        SortKey::Name => Some(SortValue::Text(&card.name)),
+        SortKey::Atk => card.atk.map(SortValue::Number),
+        SortKey::Level => card.level.or(card.link_rating).map(SortValue::Number),
+        SortKey::Date => card.release_date.map(SortValue::Date),
+        SortKey::Price => card.price.map(SortValue::Number),
+        SortKey::Support => Some(SortValue::Number(card.support)),
+        // Like `owned:`, this is up to the caller. Everything compares equal here, so the default order is kept.
+        SortKey::Views => None,
+    }
+}
+
+pub type CardFilter = Box<dyn Fn(&SearchCard) -> bool>;
+
+fn get_field_value(card: &SearchCard, field: Field) -> Option<Value> {
+    Some(match field {
+        Field::Atk => Value::Numerical(card.atk?),
+        Field::Def => Value::Numerical(card.def?),
+        Field::Legal => Value::Numerical(card.legal_copies),
+        Field::Level => Value::Numerical(card.level?),
+        Field::LinkRating => Value::Numerical(card.link_rating?),
+        Field::MaxRarity => Value::Numerical(card.max_rarity?.into()),
+        Field::Year => Value::Numerical(card.release_date?.year()),
+        Field::Era => Value::Numerical(card.release_date?.to_julian_day()),
+        Field::PrintedIn => Value::Multiple(card.print_years.iter().copied().map(Value::Numerical).collect()),
+        Field::Set => Value::Multiple(card.sets.clone().into_iter().map(Value::String).collect()),
+        Field::Appears => Value::Multiple(card.appearances.clone().into_iter().map(Value::String).collect()),
+        Field::Type => Value::String(card.r#type.clone()),
+        Field::Attribute => Value::String(card.attribute.clone().unwrap_or_default()),
+        Field::Class => Value::String(card.card_type.clone()),
+        Field::Name => Value::String(card.name.clone()),
+        Field::Materials => Value::String(card.materials.clone()?),
+        Field::Deck => Value::String(card.deck?.to_owned()),
+        Field::Text => Value::String(card.text.clone()),
+        Field::Ruling => Value::String(card.rulings.clone()),
+        Field::Does => Value::Multiple(card.tags.iter().map(|t| Value::String(t.to_string())).collect()),
+        Field::Opt => Value::Multiple(card.opt.iter().map(|o| Value::String(o.to_string())).collect()),
+        Field::Price => Value::Numerical(card.price?),
+        Field::Support => Value::Numerical(card.support),
+        Field::Any => Value::String(card.any_fields().join("\n")),
+        // Only whole archetypes, so `archetype:hero` doesn’t find Evil HEROs.
+        Field::Archetype => Value::Multiple(card.archetype.clone().map(Value::String).into_iter().collect()),
+        Field::Region => Value::Multiple(card.regions.iter().map(|r| Value::String(r.to_lowercase())).collect()),
+        // Directives are removed by the parser before filters are built, and `is:` has its own filters.
+        Field::Is | Field::Include | Field::Sort | Field::Owned | Field::AsOf | Field::Group => return None,
+    })
+}
+
+fn get_cased_field_value(card: &SearchCard, field: Field) -> Option<Value> {
+    Some(match field {
+        Field::Name => Value::String(card.cased_name.clone()),
+        Field::Text => Value::String(card.cased_text.clone()),
+        _ => return None,
+    })
+}
+
+fn filter_value(op: &Operator, field_value: &Value, query_value: &Value) -> bool {
+    match (field_value, query_value) {
+        (Value::None, _) => false,
+        (Value::Numerical(field), Value::Numerical(query)) => op.filter_number(Some(*field), *query),
+        (Value::Numerical(field), Value::Range(from, to)) => match op {
+            Operator::Equal => (from..=to).contains(&field),
+            Operator::NotEqual => !(from..=to).contains(&field),
+            _ => false,
+        },
+        // Any of them has to match, e.g. any printing year. Negations mean none of them may match.
+        (Value::Multiple(field), query @ (Value::Numerical(_) | Value::Range(..))) => match op {
+            Operator::NotEqual => !field.iter().any(|f| filter_value(&Operator::Equal, f, query)),
+            op => field.iter().any(|f| filter_value(op, f, query)),
+        },
+        (Value::String(field), Value::String(query) | Value::Word(query)) => match op {
+            Operator::Equal => field.contains(query),
+            Operator::NotEqual => !field.contains(query),
+            // greater/less than aren’t supported for string fields.
+            _ => false,
+        },
+        (Value::String(field), Value::Regex(query)) => match op {
+            Operator::Equal => query.is_match(field),
+            Operator::NotEqual => !query.is_match(field),
+            // greater/less than aren’t supported for string fields.
+            _ => false,
+        },
+        // Sets the card was released in and media it appeared in
+        (Value::Multiple(field), query @ Value::String(_)) => match op {
+            Operator::Equal => field.iter().any(|f| f == query),
+            Operator::NotEqual => !field.iter().any(|f| f == query),
+            _ => false,
+        },
+        (Value::Multiple(field), query @ Value::Regex(_)) => match op {
+            Operator::Equal => field.iter().any(|f| filter_value(op, f, query)),
+            Operator::NotEqual => !field.iter().any(|f| filter_value(&Operator::Equal, f, query)),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Rarities are typed as names, but compared by their tier.
+fn rarity_value(value: Value) -> Result<Value, String> {
+    match value {
+        Value::String(name) => Ok(Value::Numerical(rarity_tier(&name).ok_or_else(|| format!("Unknown rarity: {name}"))?.into())),
+        Value::Multiple(values) => Ok(Value::Multiple(values.into_iter().map(rarity_value).collect::<Result<_, _>>()?)),
+        _ => Err(format!("Rarities have to be names like “common” or “ultra”, not {value}")),
+    }
+}
+
+/// How many years ago the only printing has to be for `is:unreprinted`. The server sets this once on startup.
+pub static UNREPRINTED_YEARS: AtomicI32 = AtomicI32::new(5);
+
+/// Banlists for `asof:`. The server sets this once on startup, if it has any.
+pub static BANLIST_HISTORY: OnceLock<BanlistHistory> = OnceLock::new();
+
+/// Everything `flag_filter` understands
+pub const FLAGS: [&str; 4] = ["unreprinted", "tcgonly", "ocgonly", "custom"];
+
+/// `is:` filters are flags that don’t correspond to any one field.
+fn flag_filter(value: &Value) -> Result<CardFilter, String> {
+    Ok(match value {
+        Value::String(flag) if flag == "unreprinted" => {
+            let years = UNREPRINTED_YEARS.load(atomic::Ordering::Relaxed);
+            let today = OffsetDateTime::now_utc().date();
+            // Feb 29 doesn’t exist in most years, but a day earlier won’t make a difference here.
+            let cutoff = today
+                .replace_year(today.year() - years)
+                .or_else(|_| today.replace_day(28).and_then(|d| d.replace_year(today.year() - years)))
+                .unwrap_or(Date::MIN);
+            Box::new(move |card: &SearchCard| card.unreprinted_since(cutoff))
+        }
+        Value::String(flag) if flag == "tcgonly" => Box::new(|card: &SearchCard| card.exclusive_to == Some(Format::Tcg)),
+        Value::String(flag) if flag == "ocgonly" => Box::new(|card: &SearchCard| card.exclusive_to == Some(Format::Ocg)),
+        Value::String(flag) if flag == "custom" => Box::new(|card: &SearchCard| card.custom),
+        Value::Multiple(values) => {
+            let filters: Vec<_> = values.iter().map(flag_filter).collect::<Result<_, _>>()?;
+            Box::new(move |card: &SearchCard| filters.iter().any(|f| f(card)))
+        }
+        _ => Err(format!("Unknown filter: is:{value}"))?,
+    })
+}
+
+/// Eras are compared by their first and last day. `era<5ds` is everything before the first day of 5D’s,
+/// `era>5ds` everything after the last.
+fn era_value(op: Operator, value: Value) -> Result<Value, String> {
+    match value {
+        Value::String(name) => {
+            let (start, end) = era_dates(&name).ok_or_else(|| format!("Unknown era: {name}"))?;
+            Ok(match op {
+                Operator::Equal | Operator::EqualCase | Operator::NotEqual => Value::Range(start.to_julian_day(), end.to_julian_day()),
+                Operator::Less | Operator::GreaterEqual => Value::Numerical(start.to_julian_day()),
+                Operator::Greater | Operator::LessEqual => Value::Numerical(end.to_julian_day()),
+            })
+        }
+        Value::Multiple(values) => Ok(Value::Multiple(values.into_iter().map(|v| era_value(op, v)).collect::<Result<_, _>>()?)),
+        _ => Err(format!("Eras have to be names like “gx” or “arcv”, not {value}")),
+    }
+}
+
+/// All region tags for the names in `value`, see `region_tags`.
+fn region_value(value: &Value) -> Result<Vec<&'static str>, String> {
+    match value {
+        Value::String(name) => region_tags(name).ok_or_else(|| format!("Unknown region: {name}. Try one of {}", REGION_NAMES.join(", "))),
+        Value::Multiple(values) => Ok(values.iter().map(region_value).collect::<Result<Vec<_>, _>>()?.concat()),
+        _ => Err(format!("Regions have to be names like “eu” or “de”, not {value}")),
+    }
+}
+
+/// Whether `value` has any names in it, as opposed to only numbers.
+fn has_name(value: &Value) -> bool {
+    match value {
+        Value::String(_) | Value::Word(_) => true,
+        Value::Multiple(values) => values.iter().any(has_name),
+        _ => false,
+    }
+}
+
+fn has_range(value: &Value) -> bool {
+    match value {
+        Value::Range(..) => true,
+        Value::Multiple(values) => values.iter().any(has_range),
+        _ => false,
+    }
+}
+
+/// Typos in `does:` and `opt:` would otherwise silently find nothing.
+fn check_tags(field: Field, value: &Value) -> Result<(), String> {
+    match value {
+        Value::String(tag) if field == Field::Does && !is_tag(tag) => {
+            Err(format!("Unknown effect: {tag}. Try one of {}", all_tags().join(", ")))
+        }
+        Value::String(kind) if field == Field::Opt && !OPT_KINDS.contains(&kind.as_str()) => {
+            Err(format!("opt has to be one of {}, not {kind}", OPT_KINDS.join(", ")))
+        }
+        Value::Multiple(values) => values.iter().try_for_each(|v| check_tags(field, v)),
+        _ => Ok(()),
+    }
+}
+
+/// What a text search looks for in a card. Unquoted words match anywhere in the text like a normal search,
+/// but also as any other form of the word. Their synonyms only match other forms, because `ss` is part of too many words.
+#[derive(Debug)]
+enum TextNeedle {
+    Substring(String),
+    Stem(String),
+}
+
+impl TextNeedle {
+    fn matches(&self, card: &SearchCard) -> bool {
+        match self {
+            Self::Substring(s) => card.text.contains(s.as_str()),
+            Self::Stem(stem) => card.stemmed_text.contains(stem.as_str()),
+        }
+    }
+
+    /// Start and end of every match in `text`, which has to be lowercase.
+    fn find(&self, text: &str) -> Vec<(usize, usize)> {
+        match self {
+            Self::Substring(s) => text.match_indices(s.as_str()).map(|(i, m)| (i, i + m.len())).collect(),
+            Self::Stem(padded) => word_ranges(text).into_iter().filter(|&(from, to)| stem(&text[from..to]) == padded.trim()).collect(),
+        }
+    }
+}
+
+/// Start and end of every word in `text`, split the same way as in `stemmed_words`.
+fn word_ranges(text: &str) -> Vec<(usize, usize)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        match (start, c.is_alphanumeric()) {
+            (None, true) => start = Some(i),
+            (Some(from), false) => {
+                words.push((from, i));
+                start = None;
+            }
+            _ => (),
+        }
+    }
+    words
+}
+
+/// Where an `o:` value matches in `text` (start and end in bytes, sorted), to show why a card matched.
+/// `text` has to be lowercase. Lowercasing with `to_ascii_lowercase` keeps the positions the same as in the original.
+pub fn text_matches(value: &Value, text: &str) -> Vec<(usize, usize)> {
+    let matches: Vec<_> = match value {
+        Value::Multiple(values) => values.iter().flat_map(|v| text_matches(v, text)).collect(),
+        Value::Regex(regex) => regex.find_iter(text).filter(|m| !m.is_empty()).map(|m| (m.start(), m.end())).collect(),
+        // A word and its stem often match the same place.
+        value => text_needles(value).into_iter().flatten().flat_map(|n| n.find(text)).collect(),
+    };
+    matches.into_iter().sorted_unstable().dedup().collect()
+}
+
+fn text_needles(value: &Value) -> Option<Vec<TextNeedle>> {
+    let stemmed = |word: &str| TextNeedle::Stem(format!(" {} ", stem(word)));
+    let synonym = |term: &str| if term.contains(' ') { TextNeedle::Substring(term.to_owned()) } else { stemmed(term) };
+    Some(match value {
+        Value::Word(w) => [TextNeedle::Substring(w.clone()), stemmed(w)].into_iter().chain(synonyms(w).map(synonym)).collect(),
+        Value::String(s) => std::iter::once(TextNeedle::Substring(s.clone())).chain(synonyms(s).map(synonym)).collect(),
+        Value::Multiple(values) => values.iter().map(text_needles).collect::<Option<Vec<_>>>()?.into_iter().flatten().collect(),
+        _ => return None,
+    })
+}
+
+fn text_filter(op: Operator, needles: Vec<TextNeedle>) -> CardFilter {
+    Box::new(move |card: &SearchCard| needles.iter().any(|n| n.matches(card)) == (op == Operator::Equal))
+}
+
+pub fn build_filter(RawCardFilter(field, op, value): RawCardFilter) -> Result<CardFilter, String> {
+    // A range on anything else could never match.
+    if has_range(&value) && !field.is_numeric() {
+        return Err(format!("Ranges only work for numbers like ATK or years, not {field}"));
+    }
+    if field == Field::Is {
+        let filter = flag_filter(&value)?;
+        return match op {
+            Operator::Equal => Ok(filter),
+            Operator::NotEqual => Ok(Box::new(move |card: &SearchCard| !filter(card))),
+            _ => Err(format!("is: filters can only be negated with !=, not compared with {op}")),
+        };
+    }
+    if let (Field::Text, Operator::Equal | Operator::NotEqual) = (field, op) {
+        if let Some(needles) = text_needles(&value) {
+            return Ok(text_filter(op, needles));
+        }
+    }
+    // `region!=de|fr` means neither, which the generic filters can’t do with several values.
+    // `printedin:` is for years, but also takes regions, as in `printedin:eu`. Not both at once though.
+    if field == Field::Region || (field == Field::PrintedIn && has_name(&value)) {
+        if field == Field::PrintedIn && matches!(&value, Value::Multiple(values) if !values.iter().all(has_name)) {
+            return Err("printedin: takes either years or regions, not both".to_owned());
+        }
+        let tags = region_value(&value)?;
+        let wanted = op == Operator::Equal;
+        return match op {
+            Operator::Equal | Operator::NotEqual => {
+                Ok(Box::new(move |card: &SearchCard| card.regions.iter().any(|r| tags.contains(r)) == wanted))
+            }
+            _ => Err(format!("Regions can only be compared with : or !=, not {op}")),
+        };
+    }
+    // Case-sensitive filters work just like `=`, only on other values.
+    let (op, get_value): (_, fn(&SearchCard, Field) -> Option<Value>) = match op {
+        Operator::EqualCase if matches!(field, Field::Name | Field::Text) => (Operator::Equal, get_cased_field_value),
+        Operator::EqualCase => return Err(format!("=~ only works for names and card text, not {field}")),
+        op => (op, get_field_value),
+    };
+    let value = match field {
+        Field::MaxRarity => rarity_value(value)?,
+        Field::Era => era_value(op, value)?,
+        Field::Does | Field::Opt => {
+            check_tags(field, &value)?;
+            value
+        }
+        _ => value,
+    };
+    Ok(match value {
+        Value::Multiple(values) => Box::new(move |card: &SearchCard| {
+            let field_value = get_value(card, field).unwrap_or_default();
+            values.iter().any(|query_value| filter_value(&op, &field_value, query_value))
+        }),
+        single_value => Box::new(move |card: &SearchCard| {
+            let field_value = get_value(card, field).unwrap_or_default();
+            filter_value(&op, &field_value, &single_value)
+        }),
+    })
+}
+
+/// Like `build_filter`, but `legal:` goes by the banlist that was in effect on `date`.
+pub fn build_filter_as_of(filter: RawCardFilter, date: Date, history: &'static BanlistHistory) -> Result<CardFilter, String> {
+    let RawCardFilter(Field::Legal, op, value) = filter else {
+        return build_filter(filter);
+    };
+    let copies = move |card: &SearchCard| Value::Numerical(history.status_on(card.id, date).copies().into());
+    Ok(match value {
+        Value::Multiple(values) => Box::new(move |card: &SearchCard| values.iter().any(|v| filter_value(&op, &copies(card), v))),
+        single_value => Box::new(move |card: &SearchCard| filter_value(&op, &copies(card), &single_value)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data::{
+            tests::{RAW_LINK_MONSTER, RAW_MONSTER, RAW_SPELL},
+            CardSet, Set,
+        },
+        parser::{parse_filters, parse_filters_with},
+    };
+    use time::Month;
+
+    fn search_card(raw: &str) -> SearchCard {
+        SearchCard::new(&serde_json::from_str(raw).unwrap(), &HashMap::new())
+    }
+
+    #[test]
+    fn released_on_day_of_test() {
+        let lacooda = search_card(RAW_MONSTER);
+        let lacooda = SearchCard { release_date: Some(Date::from_calendar_date(2003, Month::October, 10).unwrap()), ..lacooda };
+        assert!(lacooda.released_on_day_of(Date::from_calendar_date(2026, Month::October, 10).unwrap()));
+        assert!(!lacooda.released_on_day_of(Date::from_calendar_date(2003, Month::October, 11).unwrap()));
+        assert!(!SearchCard { release_date: None, ..lacooda }.released_on_day_of(Date::MIN));
+    }
+
+    #[test]
+    fn level_filter_test() {
+        let lacooda = search_card(RAW_MONSTER);
+        let lacooda_but_level_4 = SearchCard { level: Some(4), ..lacooda.clone() };
+
+        let filter_level_3 = parse_filters("l=3").unwrap().1;
+        assert!(filter_level_3(&lacooda));
+
+        let filter_level_3_4 = parse_filters("l=3|4").unwrap().1;
+        assert!(filter_level_3_4(&lacooda));
+        assert!(filter_level_3_4(&lacooda_but_level_4));
+
+        let filter_level_5 = parse_filters("l=5").unwrap().1;
+        assert!(!filter_level_5(&lacooda));
+    }
+
+    #[test]
+    fn filter_by_level_should_exclude_link_monsters() {
+        let bls = search_card(RAW_LINK_MONSTER);
+        let filter = parse_filters("l<=4").unwrap().1;
+        assert!(!filter(&bls));
+    }
+
+    #[test]
+    fn set_filter_test() {
+        let lacooda = search_card(RAW_MONSTER);
+
+        let astral_pack_filter = parse_filters("set:ap03").unwrap().1;
+        assert!(astral_pack_filter(&lacooda));
+
+        let partial_filter = parse_filters("set:ap0").unwrap().1;
+        assert!(!partial_filter(&lacooda));
+
+        let not_astral_pack_filter = parse_filters("set!=ap03").unwrap().1;
+        assert!(!not_astral_pack_filter(&lacooda));
+
+        let astral_pack_4_filter = parse_filters("set:ap04").unwrap().1;
+        assert!(!astral_pack_4_filter(&lacooda));
+    }
+
+    #[test]
+    fn printed_in_filter_test() {
+        let set = |name: &str, year| {
+            (name.to_lowercase(), Set { set_name: name.to_owned(), tcg_date: Some(Date::from_calendar_date(year, Month::May, 1).unwrap()) })
+        };
+        let sets = HashMap::from([set("Astral Pack Three", 2013), set("Gold Series", 2008)]);
+        let lacooda = SearchCard::new(&serde_json::from_str(RAW_MONSTER).unwrap(), &sets);
+        assert_eq!(lacooda.print_years, vec![2008, 2013]);
+        let matches = |query: &str| parse_filters(query).unwrap().1(&lacooda);
+        assert!(matches("printedin:2008"));
+        assert!(!matches("printedin:2009..2012"));
+        assert!(matches("printedin:2010..2015"));
+        assert!(!matches("printedin:..2007"));
+        assert!(matches("printedin:2013.."));
+        assert!(!matches("printedin!=2008"));
+        assert!(matches("printedin!=2009..2012"));
+        assert!(matches("printedin<2009"));
+        assert!(matches("atk:400..600"));
+        assert!(!matches("atk!=400..600"));
+        assert!(parse_filters("name:1..2").is_err());
+        assert!(parse_filters("set:lob|1..2").is_err());
+    }
+
+    #[test]
+    fn max_rarity_filter_test() {
+        let lacooda = search_card(RAW_MONSTER);
+        let bls = search_card(RAW_LINK_MONSTER);
+        let matches = |query: &str, card| parse_filters(query).unwrap().1(card);
+        assert!(matches("maxrarity:common", &lacooda));
+        assert!(!matches("maxrarity:common", &bls));
+        assert!(matches("maxrarity:ultimate", &bls));
+        assert!(matches("maxrarity>=ultra", &bls));
+        assert!(matches("maxr:c|r", &lacooda));
+        assert!(parse_filters("maxrarity:shiny").is_err());
+        assert!(parse_filters("maxrarity:3").is_err());
+    }
+
+    #[test]
+    fn appears_filter_test() {
+        let lacooda = search_card(RAW_MONSTER);
+        let anime = parse_filters("appears:anime").unwrap().1;
+        assert!(!anime(&lacooda));
+        let lacooda = SearchCard { appearances: vec!["anime".to_owned()], ..lacooda };
+        assert!(anime(&lacooda));
+        assert!(!parse_filters("appears:manga").unwrap().1(&lacooda));
+    }
+
+    #[test]
+    fn era_filter_test() {
+        let lacooda =
+            SearchCard { release_date: Some(Date::from_calendar_date(2008, Month::September, 2).unwrap()), ..search_card(RAW_MONSTER) };
+        let matches = |query: &str| parse_filters(query).unwrap().1(&lacooda);
+        assert!(matches("era:5ds"));
+        assert!(matches("era:\"5d's\""));
+        assert!(!matches("era:gx"));
+        assert!(matches("era:gx|5ds"));
+        assert!(matches("era!=gx"));
+        assert!(matches("era>gx"));
+        assert!(matches("era>=5ds"));
+        assert!(!matches("era<5ds"));
+        assert!(matches("era<zexal"));
+        assert!(parse_filters("era:sevens").is_err());
+    }
+
+    #[test]
+    fn does_filter_test() {
+        let lacooda = search_card(RAW_MONSTER);
+        let bls = search_card(RAW_LINK_MONSTER);
+        assert_eq!(lacooda.tags, vec!["draw"]);
+        assert_eq!(bls.tags, vec!["banish"]);
+        let matches = |query: &str, card| parse_filters(query).unwrap().1(card);
+        assert!(matches("does:draw", &lacooda));
+        assert!(!matches("does:draw", &bls));
+        assert!(matches("does:draw|banish", &bls));
+        assert!(matches("does!=banish", &lacooda));
+        assert!(parse_filters("does:dance").is_err());
+    }
+
+    #[test]
+    fn opt_filter_test() {
+        let lacooda = search_card(RAW_MONSTER);
+        let bls = search_card(RAW_LINK_MONSTER);
+        let matches = |query: &str, card| parse_filters(query).unwrap().1(card);
+        assert!(matches("opt:soft", &lacooda));
+        assert!(!matches("opt:hard", &lacooda));
+        assert!(matches("opt:none", &bls));
+        assert!(matches("opt!=none", &lacooda));
+        assert!(parse_filters("opt:twice").is_err());
+    }
+
+    #[test]
+    fn deck_filter_test() {
+        let lacooda = search_card(RAW_MONSTER);
+        let bls = search_card(RAW_LINK_MONSTER);
+        let pendulum = SearchCard { card_type: "pendulum effect monster".to_owned(), ..lacooda.clone() };
+        let matches = |query: &str, card| parse_filters(query).unwrap().1(card);
+        assert!(matches("deck:main", &lacooda));
+        assert!(matches("deck:extra", &bls));
+        assert!(!matches("deck:main", &bls));
+        assert!(matches("deck:main", &pendulum));
+        assert!(!matches("deck:extra", &SearchCard { deck: None, ..lacooda.clone() }));
+    }
+
+    #[test]
+    fn case_sensitive_filter_test() {
+        let bls = search_card(RAW_LINK_MONSTER);
+        let matches = |query: &str| parse_filters(query).unwrap().1(&bls);
+        assert!(matches("name=~Soldier"));
+        assert!(!matches("name=~soldier"));
+        assert!(matches("name:soldier"));
+        assert!(matches("name=~/^Black Luster/"));
+        assert!(!matches("name=~/^black luster/"));
+        assert!(matches("name=~Chaos|chaos"));
+        assert!(parse_filters("t=~Warrior").is_err());
+    }
+
+    #[test]
+    fn stemmed_text_filter_test() {
+        let lacooda = search_card(RAW_MONSTER);
+        let matches = |query: &str| parse_filters(query).unwrap().1(&lacooda);
+        assert!(matches("o:draws"));
+        assert!(matches("o:summoning"));
+        assert!(matches("o:positions|banished"));
+        assert!(!matches(r#"o:"draws""#), "quotes search for exactly that");
+        assert!(!matches("o!=drawing"));
+        assert!(matches("o:flip"), "substrings still work");
+    }
+
+    #[test]
+    fn synonym_filter_test() {
+        let coffin = search_card(RAW_SPELL);
+        let bls = search_card(RAW_LINK_MONSTER);
+        let matches = |query: &str, card| parse_filters(query).unwrap().1(card);
+        assert!(matches("o:gy", &coffin));
+        assert!(matches("o:bp", &bls));
+        assert!(matches(r#"o:"remove from play""#, &bls));
+        assert!(!matches("o!=gy", &coffin));
+        assert!(!matches("o:ss", &bls), "“special summon”, not just any summon");
+        assert!(!matches("o=~gy", &coffin), "=~ is exact");
+    }
+
+    #[test]
+    fn text_matches_test() {
+        let text = "draw 1 card. if you drew a spell, send it to the graveyard. redraw.";
+        let found = |query: &str| {
+            let RawCardFilter(_, _, value) = parse_filters(query).unwrap().0.positive_filters()[0].clone();
+            text_matches(&value, text).into_iter().map(|(from, to)| &text[from..to]).collect::<Vec<_>>()
+        };
+        assert_eq!(found("o:draw"), vec!["draw", "draw"], "the substring in “redraw”");
+        assert_eq!(found("o:draws"), vec!["draw"]);
+        assert_eq!(found("o:gy"), vec!["graveyard"]);
+        assert_eq!(found(r#"o:"send it""#), vec!["send it"]);
+        assert_eq!(found("o:/dr[ae]w/"), vec!["draw", "drew", "draw"]);
+        assert_eq!(found("o:spell|card"), vec!["card", "spell"]);
+        assert_eq!(word_ranges("a, bc"), vec![(0, 1), (3, 5)]);
+    }
+
+    #[test]
+    fn archetype_filter_test() {
+        let hero =
+            |archetype: &str| SearchCard::new(&Card { archetype: Some(archetype.to_owned()), ..Default::default() }, &HashMap::new());
+        let matches = |query: &str, card: &SearchCard| parse_filters(query).unwrap().1(card);
+        assert!(matches("archetype:hero", &hero("HERO")));
+        assert!(!matches("archetype:hero", &hero("Evil HERO")));
+        assert!(matches("arch:/hero$/", &hero("Evil HERO")));
+        assert!(matches(r#"arch:"evil hero""#, &hero("Evil HERO")));
+        assert!(matches("arch!=hero", &hero("Evil HERO")));
+        assert!(!matches("arch:hero", &search_card(RAW_SPELL)));
+    }
+
+    #[test]
+    fn region_filter_test() {
+        let printing = |code: &str| CardSet { set_code: code.to_owned(), ..Default::default() };
+        let card = Card { card_sets: vec![printing("LOB-E005"), printing("LOB-G005")], ..Default::default() };
+        let card = SearchCard::new(&card, &HashMap::new());
+        let matches = |query: &str| parse_filters(query).unwrap().1(&card);
+        assert!(matches("region:eu"));
+        assert!(matches("region:de"));
+        assert!(matches("region:en"));
+        assert!(matches("region:e"));
+        assert!(!matches("region:na"));
+        assert!(matches("printedin:eu"));
+        assert!(!matches("printedin:na"));
+        assert!(matches("printedin!=na|jp"));
+        assert!(parse_filters("printedin:typo").is_err());
+        assert!(parse_filters("printedin:eu|2019").is_err());
+        assert!(parse_filters("printedin:2019|eu").is_err());
+        assert!(!matches("region:en region!=de"));
+        assert!(matches("region!=fr|jp"));
+        assert!(!matches("region!=fr|de"));
+        assert!(parse_filters("region:xx").is_err());
+        assert!(parse_filters("region>eu").is_err());
+    }
+
+    #[test]
+    fn any_filter_test() {
+        let lacooda = search_card(RAW_MONSTER);
+        let bls = search_card(RAW_LINK_MONSTER);
+        let matches = |query: &str, card| parse_filters(query).unwrap().1(card);
+        assert!(matches("any:warrior", &bls));
+        assert!(!matches("any:warrior", &lacooda));
+        assert!(matches("any:banish", &bls));
+        assert!(matches("any!=banish", &lacooda));
+        assert_eq!(bls.any_rank(&Value::String("soldier".into())), 0);
+        assert_eq!(bls.any_rank(&Value::String("warrior".into())), 2);
+        assert_eq!(bls.any_rank(&Value::String("banish".into())), 4);
+
+        let (_, filter, options, _) = parse_filters("any:/luster|draw/").unwrap();
+        let cards = [lacooda.clone(), bls.clone()];
+        let ids: Vec<_> = crate::search(&cards, &filter, &options).iter().map(|c| c.id).collect();
+        assert_eq!(ids, vec![bls.id, lacooda.id], "name matches come before text matches");
+    }
+
+    #[test]
+    fn name_rank_test() {
+        let printed = vec![CardSet::default()];
+        let card = |id, name: &str| {
+            SearchCard::new(&Card { id, name: name.to_owned(), card_sets: printed.clone(), ..Default::default() }, &HashMap::new())
+        };
+        let cards = [
+            card(1, "Dark Magician Girl the Dragon Knight"),
+            card(2, "The Dark Magicians"),
+            card(3, "Magician's Souls of the Dark Magician"),
+            card(4, "Dark Magician Girl"),
+            card(5, "Dark Magician"),
+            card(6, "Ultra Dark Magician Knight"),
+        ];
+        assert_eq!(cards[4].name_rank("dark magician"), (0, 13));
+        assert_eq!(cards[3].name_rank("dark magician"), (1, 18));
+        assert_eq!(cards[1].name_rank("dark magician"), (2, 18));
+        assert_eq!(card(7, "Darkmagician").name_rank("magician"), (3, 12));
+
+        let ids = |query: &str| {
+            let (_, filter, options, _) = parse_filters(query).unwrap();
+            crate::search(&cards, &filter, &options).iter().map(|c| c.id).collect::<Vec<_>>()
+        };
+        assert_eq!(ids("dark magician"), vec![5, 4, 1, 2, 6, 3]);
+        assert_eq!(ids("Dark Magician sort:-name"), vec![6, 2, 3, 1, 4, 5], "sorting wins");
+        assert_eq!(ids("dark magician include:duplicates"), vec![5, 4, 1, 2, 6, 3]);
+        assert_eq!(ids("dark magician atk>=0"), Vec::<usize>::new());
+        assert_eq!(ids("name:/dark magician/"), vec![1, 2, 3, 4, 5, 6], "regexes keep the default order");
+    }
+
+    #[test]
+    fn support_filter_test() {
+        let lacooda = search_card(RAW_MONSTER).with_support_count(3);
+        let matches = |query: &str| parse_filters(query).unwrap().1(&lacooda);
+        assert!(matches("supportcount>=3"));
+        assert!(!matches("support>3"));
+        let bls = search_card(RAW_LINK_MONSTER);
+        let by_support: Sort = "-support".parse().unwrap();
+        assert_eq!(by_support.compare(&lacooda, &bls), Ordering::Less);
+    }
+
+    #[test]
+    fn materials_filter_test() {
+        let bls = search_card(RAW_LINK_MONSTER);
+        let lacooda = search_card(RAW_MONSTER);
+        let matches = |query: &str, card| parse_filters(query).unwrap().1(card);
+        assert!(matches("materials:\"3 monsters\"", &bls));
+        assert!(matches("mats:/^\\d monsters/", &bls));
+        assert!(!matches("materials:level", &bls), "the effect mentions levels, but the materials don’t");
+        assert!(!matches("materials:monster", &lacooda));
+    }
+
+    #[test]
+    fn unreprinted_filter_test() {
+        let date = |year| Some(Date::from_calendar_date(year, Month::March, 1).unwrap());
+        let lacooda = SearchCard { release_date: date(2003), ..search_card(RAW_MONSTER) };
+        let bls = SearchCard { release_date: date(2003), ..search_card(RAW_LINK_MONSTER) };
+        assert_eq!((lacooda.printings, bls.printings), (2, 1));
+        let cutoff = date(2010).unwrap();
+        assert!(bls.unreprinted_since(cutoff));
+        assert!(!lacooda.unreprinted_since(cutoff));
+        assert!(!SearchCard { release_date: date(2015), ..bls.clone() }.unreprinted_since(cutoff));
+        assert!(!SearchCard { release_date: None, ..bls.clone() }.unreprinted_since(cutoff));
+
+        let matches = |query: &str, card| parse_filters(query).unwrap().1(card);
+        assert!(matches("is:unreprinted", &bls));
+        assert!(!matches("is!=unreprinted", &bls));
+        assert!(matches("is!=unreprinted", &lacooda));
+        assert!(parse_filters("is:shiny").is_err());
+        assert!(parse_filters("is>unreprinted").is_err());
+    }
+
+    #[test]
+    fn exclusive_filter_test() {
+        let lacooda = search_card(RAW_MONSTER);
+        let tcg_only = SearchCard { exclusive_to: Some(Format::Tcg), ..lacooda.clone() };
+        let matches = |query: &str, card| parse_filters(query).unwrap().1(card);
+        assert!(matches("is:tcgonly", &tcg_only));
+        assert!(!matches("is:ocgonly", &tcg_only));
+        assert!(!matches("is:tcgonly", &lacooda));
+        assert!(matches("is:ocgonly|tcgonly", &tcg_only));
+    }
+
+    #[test]
+    fn as_of_test() {
+        let lacooda =
+            SearchCard { first_release: Some(Date::from_calendar_date(2003, Month::October, 10).unwrap()), ..search_card(RAW_MONSTER) };
+        let april_2005 = Date::from_calendar_date(2005, Month::April, 1).unwrap();
+        let history = Box::leak(Box::new(BanlistHistory::new(vec![(april_2005, HashMap::from([(lacooda.id, BanlistStatus::Limited)]))])));
+        let matches = |query: &str, card| parse_filters_with(query, Some(history)).unwrap().1(card);
+        let (_, _, options, _) = parse_filters_with("asof:2005-04-01 legal:1", Some(history)).unwrap();
+        assert_eq!(options.as_of, Some(april_2005));
+        assert!(matches("asof:2005-04-01 legal:1", &lacooda));
+        assert!(!matches("asof:2005-03-31 legal:1", &lacooda));
+        assert!(matches("asof:2005-03-31 legal:3", &lacooda));
+        assert!(!matches("legal:1", &lacooda), "not limited today");
+        assert!(!matches("asof:2003-10-09 legal:3", &lacooda), "not released yet");
+        assert!(parse_filters_with("asof:yesterday", Some(history)).is_err());
+        assert!(parse_filters("asof:2005-04-01").is_err(), "no old banlists");
+    }
+
+    #[test]
+    fn ruling_filter_test() {
+        let lacooda = search_card(RAW_MONSTER);
+        let ruled = SearchCard { rulings: "you can activate this in the damage step.".to_owned(), ..lacooda.clone() };
+        let matches = |query: &str, card| parse_filters(query).unwrap().1(card);
+        assert!(matches("ruling:\"damage step\"", &ruled));
+        assert!(!matches("ruling:\"damage step\"", &lacooda));
+        assert!(matches("rulings!=chain", &ruled));
+    }
+
+    #[test]
+    fn regex_filter_test() {
+        let lacooda = search_card(RAW_MONSTER);
+        let bls = search_card(RAW_LINK_MONSTER);
+        let draw_filter = parse_filters("o:/draw \\d cards?/").unwrap().1;
+        assert!(draw_filter(&lacooda));
+        assert!(!draw_filter(&bls));
+    }
+
+    #[test]
+    fn sort_key_test() {
+        let lacooda = search_card(RAW_MONSTER);
+        let old_lacooda = SearchCard { release_date: Some(Date::MIN), ..lacooda.clone() };
+        let mut cards = [lacooda.clone(), SearchCard { id: 1, ..lacooda.clone() }, old_lacooda.clone()];
+        cards.sort_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+        assert_eq!(cards.iter().map(|c| c.id).collect::<Vec<_>>(), vec![old_lacooda.id, 1, lacooda.id]);
+    }
+
+    #[test]
+    fn sort_test() {
+        let lacooda = search_card(RAW_MONSTER);
+        let bls = search_card(RAW_LINK_MONSTER);
+        let spell = SearchCard { atk: None, ..lacooda.clone() };
+        let by_atk = Sort { key: SortKey::Atk, descending: false };
+        let by_atk_desc = Sort { descending: true, ..by_atk };
+        assert_eq!(by_atk.compare(&lacooda, &bls), Ordering::Less);
+        assert_eq!(by_atk_desc.compare(&lacooda, &bls), Ordering::Greater);
+        // no ATK always comes last
+        assert_eq!(by_atk.compare(&spell, &lacooda), Ordering::Greater);
+        assert_eq!(by_atk_desc.compare(&spell, &lacooda), Ordering::Greater);
+    }
+
+    #[test]
+    fn price_filter_test() {
+        let lacooda = search_card(RAW_MONSTER);
+        let bls = search_card(RAW_LINK_MONSTER);
+        let price_filter = parse_filters("p>300").unwrap().1;
+        assert!(!price_filter(&lacooda));
+        assert!(price_filter(&bls));
+        let price_filter_2 = parse_filters("p<350").unwrap().1;
+        assert!(price_filter_2(&bls), "Should filter by the cheaper version");
+    }
+}
@@ -0,0 +1,70 @@
+//! The card data and search engine behind aro, without any of the web parts.
+//!
+//! A search takes three steps:
+//! ```no_run
+//! use aro_core::{
+//!     data::{Dump, DEFAULT_SOURCES},
+//!     filter::SearchCard,
+//!     parser::parse_filters,
+//!     search, sets_by_name,
+//! };
+//!
+//! // Reads `cards.json` and `sets.json` from the working directory.
+//! let dump = Dump::load(DEFAULT_SOURCES)?;
+//! let sets = sets_by_name(dump.sets);
+//! let cards: Vec<_> = dump.cards.iter().map(|c| SearchCard::new(c, &sets)).collect();
+//! let (_, filter, options, _) = parse_filters("c:spell o:draw sort:name")?;
+//! for card in search(&cards, &filter, &options) {
+//!     println!("{}", card.id);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+use std::collections::HashMap;
+use time::OffsetDateTime;
+
+#[cfg(feature = "cdb")]
+mod cdb;
+pub mod custom;
+pub mod data;
+pub mod filter;
+pub mod parser;
+pub mod query;
+pub mod rulings;
+pub mod scryfall;
+pub mod stem;
+pub mod synonyms;
+pub mod tags;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod yugipedia;
+
+use data::Set;
+use filter::{CardFilter, SearchCard};
+use parser::SearchOptions;
+
+/// All cards matching the filter, sorted as requested in `options` or in the order of `cards` otherwise
+/// (after ranking by where `any:` matched, if there is one, or by how well the name matched for name-only searches). Unreleased cards are left out unless `include:spoilers` is set,
+/// custom ones unless the query asks for `is:custom`.
+/// Other options (e.g. `owned:`) need data that only the caller has and are up to them.
+pub fn search<'a>(cards: &'a [SearchCard], filter: &CardFilter, options: &SearchOptions) -> Vec<&'a SearchCard> {
+    let today = OffsetDateTime::now_utc().date();
+    let mut matches: Vec<_> = cards
+        .iter()
+        .filter(|card| options.include_spoilers || !card.is_unreleased(today))
+        .filter(|card| options.include_custom || !card.is_custom())
+        .filter(|card| filter(card))
+        .collect();
+    if let Some(sort) = options.sort {
+        matches.sort_by(|a, b| sort.compare(a, b));
+    } else if !options.rank_by.is_empty() {
+        matches.sort_by_cached_key(|card| options.rank_by.iter().map(|v| card.any_rank(v)).sum::<usize>());
+    } else if let Some(name) = &options.rank_by_name {
+        matches.sort_by_cached_key(|card| card.name_rank(name));
+    }
+    matches
+}
+
+/// Sets keyed by their lowercased name, which is how cards refer to them.
+pub fn sets_by_name(sets: impl IntoIterator<Item = Set>) -> HashMap<String, Set> {
+    sets.into_iter().map(|s| (s.set_name.to_lowercase(), s)).collect()
+}
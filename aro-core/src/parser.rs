@@ -0,0 +1,980 @@
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+use crate::{
+    data::{BanlistHistory, Card},
+    filter::{build_filter_as_of, CardFilter, SearchCard, BANLIST_HISTORY},
+    query::{warnings, Query},
+};
+use itertools::Itertools;
+use nom::{
+    branch::alt,
+    bytes::complete::{take_until1, take_while, take_while_m_n},
+    character::complete::{char, multispace0},
+    combinator::{complete, map_res, recognize, rest, verify},
+    multi::{many_m_n, separated_list1},
+    sequence::{delimited, preceded, tuple},
+    IResult,
+};
+use regex::{Regex, RegexBuilder};
+use time::{format_description::well_known::Iso8601, Date};
+use unicode_normalization::UnicodeNormalization;
+
+/// Queries pasted from phones, chat clients, or typed with a Japanese IME often contain fullwidth characters
+/// (`ｌ：４`) or typographic quotes (`“…”`), neither of which the parser understands.
+pub fn normalize_query(input: &str) -> String {
+    input
+        .nfkc()
+        .map(|c| match c {
+            '“' | '”' | '„' | '‟' | '″' | '«' | '»' | '「' | '」' => '"',
+            '‘' | '’' | '‚' | '‛' | '′' => '\'',
+            c => c,
+        })
+        .collect()
+}
+
+/// The query (already optimized), the filter built from it, the directives, and warnings about likely mistakes.
+pub fn parse_filters(input: &str) -> Result<(Query, CardFilter, SearchOptions, Vec<String>), String> {
+    parse_filters_with(input, BANLIST_HISTORY.get())
+}
+
+/// Like `parse_filters`, but with the banlists for `asof:` passed in instead of taken from `BANLIST_HISTORY`.
+pub fn parse_filters_with(
+    input: &str,
+    banlist_history: Option<&'static BanlistHistory>,
+) -> Result<(Query, CardFilter, SearchOptions, Vec<String>), String> {
+    let input = &normalize_query(input);
+    let parse_error = |e: nom::Err<nom::error::Error<&str>>| match &e {
+        nom::Err::Error(error) | nom::Err::Failure(error) if misspelled_field(first_word(error.input.trim_start())).is_some() => {
+            leftover_error(error.input)
+        }
+        _ => format!("Error while parsing filters “{input}”: {e:?}"),
+    };
+    parse_raw_filters(input).map_err(parse_error).and_then(|(rest, v)| {
+        if rest.is_empty() {
+            let (directives, v): (Vec<_>, Vec<_>) = v.into_iter().partition(|RawCardFilter(f, _, _)| f.is_directive());
+            let options = directives.into_iter().try_fold(SearchOptions { banlist_history, ..Default::default() }, SearchOptions::with)?;
+            let warnings = warnings(&v);
+            let query = Query::And(v.into_iter().map(Query::Filter).collect()).optimize();
+            query.check_cost()?;
+            let rank_by = query
+                .filters()
+                .into_iter()
+                .filter(|RawCardFilter(f, op, _)| *f == Field::Any && *op == Operator::Equal)
+                .map(|RawCardFilter(_, _, value)| value.clone())
+                .collect();
+            let custom = Value::String("custom".to_owned());
+            let include_custom = query.filters().into_iter().any(|RawCardFilter(f, op, value)| {
+                *f == Field::Is
+                    && *op == Operator::Equal
+                    && (*value == custom || matches!(value, Value::Multiple(values) if values.contains(&custom)))
+            });
+            let rank_by_name = match &query {
+                Query::Filter(RawCardFilter(Field::Name, Operator::Equal, Value::String(name))) => Some(name.clone()),
+                _ => None,
+            };
+            let options = SearchOptions { rank_by, rank_by_name, include_custom, ..options };
+            let filter = match options.as_of.zip(options.banlist_history) {
+                Some((date, history)) => {
+                    let filter = query.clone().build_with(&|f| build_filter_as_of(f, date, history))?;
+                    // Cards from after that day weren’t on any banlist yet, so they can’t have been legal.
+                    Box::new(move |card: &SearchCard| card.released_by(date) && filter(card))
+                }
+                None => query.clone().build()?,
+            };
+            Ok((query, filter, options, warnings))
+        } else {
+            Err(leftover_error(rest))
+        }
+    })
+}
+
+/// Usually a typo in a field name, because anything else would have been a name search.
+fn leftover_error(rest: &str) -> String {
+    let word = first_word(rest.trim_start());
+    match misspelled_field(word) {
+        Some((name, field)) => {
+            format!("Unknown field “{name}”. Did you mean “{}”? To search for “{word}” in card names, put it in quotes.", field.names()[0])
+        }
+        None => format!("Input was not fully parsed. Left over: “{rest}”"),
+    }
+}
+
+fn first_word(input: &str) -> &str {
+    input.split(' ').next().unwrap_or_default()
+}
+
+/// For words like `lvl:4` or `attack>2000` that look like a filter, but with an unknown field,
+/// the name of that field and the closest one we know.
+fn misspelled_field(word: &str) -> Option<(&str, Field)> {
+    let (name, rest) = word.split_at(word.find(|c: char| !c.is_alphabetic())?);
+    let value = rest.trim_start_matches(OPERATOR_CHARS);
+    // Short words are too likely to be part of a name, e.g. `Hi!Five`.
+    if name.chars().count() < 3 || value.len() == rest.len() || value.is_empty() || name.parse::<Field>().is_ok() {
+        return None;
+    }
+    let lowercase = &name.to_lowercase();
+    let (distance, field) = Field::ALL
+        .into_iter()
+        .flat_map(|field| field.names().iter().map(move |n| (edit_distance(lowercase, n), field)))
+        .min_by_key(|(distance, _)| *distance)?;
+    (distance <= name.chars().count() / 3 + 1).then_some((name, field))
+}
+
+/// Levenshtein distance
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+fn parse_raw_filters(input: &str) -> IResult<&str, Vec<RawCardFilter>> {
+    many_m_n(1, 32, parse_raw_filter)(input)
+}
+
+fn word_non_empty(input: &str) -> IResult<&str, &str> {
+    verify(alt((take_until1(" "), rest)), |s: &str| !s.is_empty())(input)
+}
+
+fn sanitize(query: &str, case_sensitive: bool) -> Result<String, String> {
+    match query {
+        "" => Err("Query must not be empty".to_owned()),
+        _ if case_sensitive => Ok(query.to_owned()),
+        _ => Ok(query.to_lowercase()),
+    }
+}
+
+fn fallback_filter(query: &str) -> Result<RawCardFilter, String> {
+    Ok(RawCardFilter(Field::Name, Operator::Equal, Value::String(sanitize(query, false)?)))
+}
+
+/// Fails for misspelled fields, so they can be reported instead of finding nothing.
+fn unquoted_fallback_filter(word: &str) -> Result<RawCardFilter, String> {
+    match misspelled_field(word) {
+        Some(_) => Err(format!("Unknown field in {word}")),
+        None => fallback_filter(word),
+    }
+}
+
+fn parse_raw_filter(input: &str) -> IResult<&str, RawCardFilter> {
+    preceded(
+        multispace0,
+        alt((
+            complete(filter_with_operator),
+            // Quoted names may contain spaces and operator characters, e.g. "S:P Little Knight".
+            map_res(delimited(char('"'), take_until1("\""), char('"')), fallback_filter),
+            map_res(word_non_empty, unquoted_fallback_filter),
+        )),
+    )(input)
+}
+
+fn filter_with_operator(input: &str) -> IResult<&str, RawCardFilter> {
+    let (input, (field, op)) = tuple((field, operator))(input)?;
+    let quoted = input.starts_with('"');
+    let (input, value) = values(input, op == Operator::EqualCase)?;
+    // Quotes are how to search for exactly what was typed.
+    let value = match (field, op) {
+        (Field::Text, Operator::Equal | Operator::NotEqual) if !quoted => value.into_words(),
+        _ => value,
+    };
+    Ok((input, RawCardFilter(field, op, value)))
+}
+
+fn field(input: &str) -> IResult<&str, Field> {
+    map_res(take_while(char::is_alphabetic), str::parse)(input)
+}
+
+/// In bytes. The default of 10 MB is meant for trusted patterns, and things like `\w{500}` easily get there.
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+
+pub const OPERATOR_CHARS: &[char] = &['=', '<', '>', ':', '!', '~'];
+
+fn operator(input: &str) -> IResult<&str, Operator> {
+    map_res(take_while_m_n(1, 2, |c| OPERATOR_CHARS.contains(&c)), str::parse)(input)
+}
+
+fn values(input: &str, case_sensitive: bool) -> IResult<&str, Value> {
+    map_res(
+        alt((
+            delimited(char('"'), take_until1("\""), char('"')),
+            recognize(delimited(char('/'), take_until1("/"), char('/'))),
+            recognize(separated_list1(char('|'), take_until1(" |"))),
+            take_until1(" "),
+            rest,
+        )),
+        |input| parse_values(input, case_sensitive),
+    )(input)
+}
+
+fn parse_values(input: &str, case_sensitive: bool) -> Result<Value, String> {
+    Ok(if let Some(regex) = input.strip_prefix('/').and_then(|i| i.strip_suffix('/')) {
+        let flags = if case_sensitive { "" } else { "(?i)" };
+        Value::Regex(RegexBuilder::new(&format!("{flags}{regex}")).size_limit(REGEX_SIZE_LIMIT).build().map_err(|e| match e {
+            regex::Error::CompiledTooBig(_) => format!("Regex is too complex: {regex}"),
+            _ => format!("Invalid regex: {regex}"),
+        })?)
+    } else {
+        let values = input.split('|').map(|v| parse_single_value(v, case_sensitive)).collect::<Result<Vec<Value>, String>>()?;
+        match values.as_slice() {
+            [v] => v.clone(),
+            _ => Value::Multiple(values),
+        }
+    })
+}
+
+fn parse_single_value(input: &str, case_sensitive: bool) -> Result<Value, String> {
+    if let Some(range) = parse_range(input) {
+        return Ok(range);
+    }
+    Ok(match input.parse() {
+        Ok(n) => Value::Numerical(n),
+        Err(_) => Value::String(sanitize(input, case_sensitive)?),
+    })
+}
+
+/// `2019..2021`, both ends inclusive. Either end can be left out, e.g. `..2005`.
+fn parse_range(input: &str) -> Option<Value> {
+    let (from, to) = input.split_once("..")?;
+    let bound = |s: &str, open: i32| if s.is_empty() { Some(open) } else { s.parse().ok() };
+    let (from, to) = (bound(from, i32::MIN)?, bound(to, i32::MAX)?);
+    (from <= to && (from, to) != (i32::MIN, i32::MAX)).then_some(Value::Range(from, to))
+}
+
+/// Settings that don’t filter cards but change how the results are presented.
+/// They use the same syntax as filters, e.g. `include:duplicates`.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct SearchOptions {
+    pub include_duplicates: bool,
+    /// Also show cards that aren’t released yet, see `SearchCard::is_unreleased`
+    pub include_spoilers:   bool,
+    /// Set if the query has `is:custom` anywhere. Custom cards are hidden otherwise.
+    pub include_custom:     bool,
+    /// Use the banlist from this day for `legal:` and the banlist icons
+    pub as_of:              Option<Date>,
+    /// Needed for `asof:`, see `parse_filters_with`
+    pub banlist_history:    Option<&'static BanlistHistory>,
+    pub sort:               Option<Sort>,
+    /// Only (not) show cards in the user’s collection
+    pub owned:              Option<bool>,
+    /// Values of `any:` filters. Without a sort order, cards are ranked by where these matched.
+    pub rank_by:            Vec<Value>,
+    /// Set if the query only searches the name (after merging the words into one filter).
+    /// Without a sort order, cards are then ranked by how well their name matches, see `SearchCard::name_rank`.
+    pub rank_by_name:       Option<String>,
+    /// Show the results in sections, see `GroupKey::sort`
+    pub group:              Option<GroupKey>,
+}
+
+impl SearchOptions {
+    fn with(mut self, RawCardFilter(field, op, value): RawCardFilter) -> Result<Self, String> {
+        match (field, op, value) {
+            (Field::Include, Operator::Equal, Value::String(s)) if s == "duplicates" => self.include_duplicates = true,
+            (Field::Include, Operator::Equal, Value::String(s)) if s == "spoilers" => self.include_spoilers = true,
+            (Field::Sort, Operator::Equal, Value::String(s)) => self.sort = Some(s.parse()?),
+            (Field::Group, Operator::Equal, Value::String(s)) => self.group = Some(s.parse()?),
+            (Field::Owned, Operator::Equal, Value::String(s)) if s == "yes" => self.owned = Some(true),
+            (Field::Owned, Operator::Equal, Value::String(s)) if s == "no" => self.owned = Some(false),
+            (Field::AsOf, Operator::Equal, Value::String(s)) => {
+                if self.banlist_history.is_none() {
+                    Err("There are no old banlists for asof:")?;
+                }
+                self.as_of = Some(Date::parse(&s, &Iso8601::DATE).map_err(|_| format!("asof: needs a date like 2008-09-01, not {s}"))?);
+            }
+            (field, op, value) => Err(format!("Invalid directive: {field} {op} {value}"))?,
+        }
+        Ok(self)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SortKey {
+    Name,
+    Atk,
+    Level,
+    Date,
+    Price,
+    Support,
+    /// How often the card was viewed recently. Only the server knows this, see `filter::sort_value`.
+    Views,
+}
+
+impl SortKey {
+    pub const ALL: [Self; 7] = [Self::Name, Self::Atk, Self::Level, Self::Date, Self::Price, Self::Support, Self::Views];
+
+    /// The name used in queries, e.g. `sort:atk`.
+    pub fn keyword(self) -> &'static str {
+        match self {
+            Self::Name => "name",
+            Self::Atk => "atk",
+            Self::Level => "level",
+            Self::Date => "date",
+            Self::Price => "price",
+            Self::Support => "support",
+            Self::Views => "views",
+        }
+    }
+}
+
+impl Display for SortKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Name => "Name",
+            Self::Atk => "ATK",
+            Self::Level => "Level",
+            Self::Date => "Release date",
+            Self::Price => "Price",
+            Self::Support => "Support",
+            Self::Views => "Views",
+        })
+    }
+}
+
+/// `sort:atk` sorts ascending, `sort:-atk` descending.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Sort {
+    pub key:        SortKey,
+    pub descending: bool,
+}
+
+impl FromStr for Sort {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (descending, key) = match s.strip_prefix('-') {
+            Some(key) => (true, key),
+            None => (false, s),
+        };
+        let key = match key {
+            "name" => SortKey::Name,
+            "atk" => SortKey::Atk,
+            "level" | "l" => SortKey::Level,
+            "date" | "year" | "release" => SortKey::Date,
+            "price" | "p" => SortKey::Price,
+            "support" | "supportcount" => SortKey::Support,
+            "views" => SortKey::Views,
+            _ => Err(format!("Unknown sort order: {s}"))?,
+        };
+        Ok(Self { key, descending })
+    }
+}
+
+impl Display for Sort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.key)?;
+        if self.descending {
+            f.write_str(" (descending)")?;
+        }
+        Ok(())
+    }
+}
+
+/// What `group:` splits the results by. Takes the same names as the fields, e.g. `group:c` for the card type.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GroupKey {
+    Class,
+    Type,
+    Attribute,
+    Archetype,
+    /// Of the first printing
+    Set,
+}
+
+impl GroupKey {
+    /// The group of the card, or `None` if it doesn’t have the value, e.g. spells have no attribute.
+    pub fn value(self, card: &Card) -> Option<&str> {
+        match self {
+            Self::Class => Some(card.class()),
+            Self::Type => Some(&card.r#type),
+            Self::Attribute => card.attribute.as_deref(),
+            Self::Archetype => card.archetype.as_deref(),
+            Self::Set => card.card_sets.first().map(|s| s.set_name.as_str()),
+        }
+    }
+
+    /// Moves cards of the same group next to each other. Groups are in the order of their first card,
+    /// so the sort order still decides which ones come first. Cards without a group go last.
+    pub fn sort(self, cards: &mut [&Card]) {
+        let mut first_seen = HashMap::new();
+        for (i, card) in cards.iter().enumerate() {
+            first_seen.entry(self.value(card)).or_insert(i);
+        }
+        cards.sort_by_key(|c| (self.value(c).is_none(), first_seen[&self.value(c)]));
+    }
+}
+
+impl FromStr for GroupKey {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.parse() {
+            Ok(Field::Class) => Self::Class,
+            Ok(Field::Type) => Self::Type,
+            Ok(Field::Attribute) => Self::Attribute,
+            Ok(Field::Archetype) => Self::Archetype,
+            Ok(Field::Set) => Self::Set,
+            _ => Err(format!("Results can only be grouped by class, type, attribute, archetype, or set, not {s}"))?,
+        })
+    }
+}
+
+impl Display for GroupKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let field = match self {
+            Self::Class => Field::Class,
+            Self::Type => Field::Type,
+            Self::Attribute => Field::Attribute,
+            Self::Archetype => Field::Archetype,
+            Self::Set => Field::Set,
+        };
+        field.fmt(f)
+    }
+}
+
+/// Ordinals are given highest = fastest to filter.
+/// This is used to sort filters before applying them.
+/// Directives are removed before sorting, so their ordinals don’t matter.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum Field {
+    Atk = 1,
+    Def = 2,
+    Legal = 3,
+    Level = 4,
+    MaxRarity = 5,
+    LinkRating = 6,
+    PrintedIn = 7,
+    Year = 8,
+    Price = 9,
+    Set = 10,
+    Appears = 11,
+    Type = 12,
+    Is = 13,
+    Attribute = 14,
+    Era = 15,
+    Class = 16,
+    Deck = 17,
+    Materials = 18,
+    Name = 19,
+    Does = 20,
+    Text = 21,
+    Opt = 22,
+    Support = 23,
+    Any = 24,
+    Region = 25,
+    Archetype = 26,
+    Ruling = 27,
+    Include = 100,
+    Sort = 101,
+    Owned = 102,
+    AsOf = 103,
+    Group = 104,
+}
+
+impl Field {
+    pub const ALL: [Self; 32] = [
+        Self::Atk,
+        Self::Def,
+        Self::Legal,
+        Self::Level,
+        Self::MaxRarity,
+        Self::LinkRating,
+        Self::PrintedIn,
+        Self::Year,
+        Self::Price,
+        Self::Set,
+        Self::Appears,
+        Self::Type,
+        Self::Is,
+        Self::Attribute,
+        Self::Era,
+        Self::Class,
+        Self::Deck,
+        Self::Materials,
+        Self::Name,
+        Self::Does,
+        Self::Text,
+        Self::Opt,
+        Self::Support,
+        Self::Any,
+        Self::Region,
+        Self::Archetype,
+        Self::Ruling,
+        Self::Include,
+        Self::Sort,
+        Self::Owned,
+        Self::AsOf,
+        Self::Group,
+    ];
+
+    pub fn is_directive(self) -> bool {
+        matches!(self, Self::Include | Self::Sort | Self::Owned | Self::AsOf | Self::Group)
+    }
+
+    /// Fields whose values are plain numbers, the only ones where ranges like `1500..1900` make sense.
+    pub fn is_numeric(self) -> bool {
+        matches!(
+            self,
+            Self::Atk
+                | Self::Def
+                | Self::Legal
+                | Self::Level
+                | Self::LinkRating
+                | Self::PrintedIn
+                | Self::Year
+                | Self::Price
+                | Self::Support
+        )
+    }
+
+    /// Everything the parser accepts for this field, the full name first.
+    pub fn names(self) -> &'static [&'static str] {
+        match self {
+            Self::Atk => &["atk"],
+            Self::Def => &["def"],
+            Self::Level => &["level", "l"],
+            Self::Type => &["type", "t"],
+            Self::Attribute => &["attribute", "attr", "a"],
+            Self::Class => &["class", "c"],
+            Self::Deck => &["deck"],
+            Self::Text => &["text", "o", "eff", "effect", "e"],
+            Self::LinkRating => &["linkrating", "lr"],
+            Self::MaxRarity => &["maxrarity", "maxr"],
+            Self::Name => &["name"],
+            Self::Materials => &["materials", "mats"],
+            Self::Set => &["set", "s"],
+            Self::Appears => &["appears"],
+            Self::Does => &["does"],
+            Self::Opt => &["opt"],
+            Self::Is => &["is"],
+            Self::Year => &["year", "y"],
+            Self::Era => &["era"],
+            Self::PrintedIn => &["printedin", "printed"],
+            Self::Legal => &["legal", "copies"],
+            Self::Price => &["price", "p"],
+            Self::Support => &["supportcount", "support"],
+            Self::Any => &["any"],
+            Self::Region => &["region"],
+            Self::Archetype => &["archetype", "arch"],
+            Self::Ruling => &["ruling", "rulings"],
+            Self::Include => &["include"],
+            Self::Sort => &["sort"],
+            Self::Owned => &["owned"],
+            Self::AsOf => &["asof"],
+            Self::Group => &["group"],
+        }
+    }
+
+    /// The operators that make sense for this field. Everything else is an error or never matches.
+    pub fn operators(self) -> &'static [Operator] {
+        use Operator::*;
+        match self {
+            Self::Atk
+            | Self::Def
+            | Self::Legal
+            | Self::Level
+            | Self::MaxRarity
+            | Self::LinkRating
+            | Self::PrintedIn
+            | Self::Year
+            | Self::Price
+            | Self::Era
+            | Self::Support => &[Equal, NotEqual, Less, LessEqual, Greater, GreaterEqual],
+            Self::Name | Self::Text => &[Equal, EqualCase, NotEqual],
+            Self::Include | Self::Sort | Self::Owned | Self::AsOf | Self::Group => &[Equal],
+            _ => &[Equal, NotEqual],
+        }
+    }
+}
+
+impl Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Text => "text",
+            Self::Name => "name",
+            Self::Materials => "materials",
+            Self::Class => "card type",
+            Self::Deck => "deck",
+            Self::Attribute => "attribute",
+            Self::Type => "type",
+            Self::Level => "level/rank",
+            Self::Atk => "ATK",
+            Self::Def => "DEF",
+            Self::LinkRating => "link rating",
+            Self::MaxRarity => "highest rarity",
+            Self::Set => "set",
+            Self::Appears => "appears in",
+            Self::Does => "effect",
+            Self::Opt => "once per turn",
+            Self::Is => "card",
+            Self::Year => "year",
+            Self::Era => "era",
+            Self::PrintedIn => "printed in",
+            Self::Legal => "allowed copies",
+            Self::Price => "price",
+            Self::Support => "supporting cards",
+            Self::Any => "name, archetype, type, or text",
+            Self::Region => "printed for region",
+            Self::Archetype => "archetype",
+            Self::Ruling => "rulings",
+            Self::Include => "include",
+            Self::Sort => "sort",
+            Self::Owned => "owned",
+            Self::AsOf => "as of",
+            Self::Group => "group",
+        })
+    }
+}
+
+impl FromStr for Field {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_lowercase();
+        Self::ALL.into_iter().find(|f| f.names().contains(&s.as_str())).ok_or(s)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Operator {
+    Equal,
+    /// `=~`, like `Equal` but without lowercasing anything. Only for names and card text.
+    EqualCase,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+}
+
+impl Operator {
+    pub fn filter_number(&self, a: Option<i32>, b: i32) -> bool {
+        if let Some(a) = a {
+            match self {
+                Self::Equal | Self::EqualCase => a == b,
+                Self::Less => a < b,
+                Self::LessEqual => a <= b,
+                Self::Greater => a > b,
+                Self::GreaterEqual => a >= b,
+                Self::NotEqual => a != b,
+            }
+        } else {
+            self == &Self::NotEqual
+        }
+    }
+
+    /// How to type it in a query
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Self::Equal => ":",
+            Self::EqualCase => "=~",
+            Self::NotEqual => "!=",
+            Self::Less => "<",
+            Self::LessEqual => "<=",
+            Self::Greater => ">",
+            Self::GreaterEqual => ">=",
+        }
+    }
+}
+
+impl FromStr for Operator {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "=" | "==" | ":" => Self::Equal,
+            "=~" => Self::EqualCase,
+            ">=" | "=>" => Self::GreaterEqual,
+            "<=" | "=<" => Self::LessEqual,
+            ">" => Self::Greater,
+            "<" => Self::Less,
+            "!=" => Self::NotEqual,
+            _ => Err(s.to_owned())?,
+        })
+    }
+}
+
+impl Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Equal => "is",
+            Self::EqualCase => "is (matching case)",
+            Self::NotEqual => "is not",
+            Self::Less => "<",
+            Self::LessEqual => "<=",
+            Self::Greater => ">",
+            Self::GreaterEqual => ">=",
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RawCardFilter(pub Field, pub Operator, pub Value);
+
+impl Display for RawCardFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.0, self.1, self.2)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub enum Value {
+    String(String),
+    /// A single unquoted word in card text, which also matches other forms of it, see `stem.rs`
+    Word(String),
+    Regex(Regex),
+    Numerical(i32),
+    /// Inclusive
+    Range(i32, i32),
+    Multiple(Vec<Value>),
+    #[default]
+    None,
+}
+
+// Manually implementing this because `Regex` isn’t `PartialEq`
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::String(s1), Value::String(s2)) | (Value::Word(s1), Value::Word(s2)) => s1 == s2,
+            (Value::Numerical(a), Value::Numerical(b)) => a == b,
+            (Value::Range(a1, b1), Value::Range(a2, b2)) => (a1, b1) == (a2, b2),
+            (Value::Multiple(v1), Value::Multiple(v2)) => v1 == v2,
+            (Value::Regex(r1), Value::Regex(r2)) => r1.as_str() == r2.as_str(),
+            (Value::None, Value::None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Value {}
+
+impl Value {
+    fn into_words(self) -> Self {
+        match self {
+            Self::String(s) if !s.contains(' ') => Self::Word(s),
+            Self::Multiple(values) => Self::Multiple(values.into_iter().map(Self::into_words).collect()),
+            value => value,
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self {
+            Self::String(s) => {
+                if s.contains(' ') {
+                    write!(f, "\"{s}\"")
+                } else {
+                    f.write_str(s)
+                }
+            }
+            Self::Word(w) => f.write_str(w),
+            Self::Regex(r) => write!(f, "Regex \"{}\"", r.as_str()),
+            Self::Numerical(n) => write!(f, "{n}"),
+            Self::Range(from, i32::MAX) => write!(f, "{from} or later"),
+            Self::Range(i32::MIN, to) => write!(f, "{to} or earlier"),
+            Self::Range(from, to) => write!(f, "{from} to {to}"),
+            Self::Multiple(m) => {
+                write!(f, "one of [{}]", m.iter().map(Value::to_string).join(", "))
+            }
+            Self::None => f.write_str("none"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("t=pyro" => Ok(("", RawCardFilter(Field::Type, Operator::Equal, Value::String("pyro".into())))))]
+    #[test_case("t:PYro" => Ok(("", RawCardFilter(Field::Type, Operator::Equal, Value::String("pyro".into())))); "input is lowercased")]
+    #[test_case("t==warrior" => Ok(("", RawCardFilter(Field::Type, Operator::Equal, Value::String("warrior".into())))))]
+    #[test_case("atk>=100" => Ok(("", RawCardFilter(Field::Atk, Operator::GreaterEqual, Value::Numerical(100)))))]
+    #[test_case("Necrovalley" => Ok(("", RawCardFilter(Field::Name, Operator::Equal, Value::String("necrovalley".into())))))]
+    #[test_case("l=10" => Ok(("", RawCardFilter(Field::Level, Operator::Equal, Value::Numerical(10)))))]
+    #[test_case("Ib" => Ok(("", RawCardFilter(Field::Name, Operator::Equal, Value::String("ib".to_owned())))))]
+    #[test_case("c!=synchro" => Ok(("", RawCardFilter(Field::Class, Operator::NotEqual, Value::String("synchro".to_owned())))))]
+    #[test_case(r#"name=~"HERO""# => Ok(("", RawCardFilter(Field::Name, Operator::EqualCase, Value::String("HERO".into())))))]
+    #[test_case("o=~Draw|DRAW" => Ok(("", RawCardFilter(Field::Text, Operator::EqualCase, Value::Multiple(vec![Value::String("Draw".into()), Value::String("DRAW".into())])))))]
+    #[test_case("o:destroyed" => Ok(("", RawCardFilter(Field::Text, Operator::Equal, Value::Word("destroyed".into())))))]
+    #[test_case("p<150" => Ok(("", RawCardFilter(Field::Price, Operator::Less, Value::Numerical(150)))))]
+    #[test_case("D/D/D" => Ok(("", RawCardFilter(Field::Name, Operator::Equal, Value::String("d/d/d".to_owned())))))]
+    #[test_case("c39:" => Ok(("", RawCardFilter(Field::Name, Operator::Equal, Value::String("c39:".to_owned())))))]
+    #[test_case("printedin:2019..2021" => Ok(("", RawCardFilter(Field::PrintedIn, Operator::Equal, Value::Range(2019, 2021)))))]
+    #[test_case("printed:..2005" => Ok(("", RawCardFilter(Field::PrintedIn, Operator::Equal, Value::Range(i32::MIN, 2005)))))]
+    #[test_case("printedin:2021..2019" => Ok(("", RawCardFilter(Field::PrintedIn, Operator::Equal, Value::String("2021..2019".to_owned())))); "backwards ranges are just strings")]
+    #[test_case(r#""S:P Little Knight""# => Ok(("", RawCardFilter(Field::Name, Operator::Equal, Value::String("s:p little knight".to_owned())))); "quoted name with operator")]
+    fn successful_parsing_test(input: &str) -> IResult<&str, RawCardFilter> {
+        parse_raw_filter(input)
+    }
+
+    #[test]
+    fn sequential_parsing_test() {
+        let (rest, filter) = parse_raw_filter("atk>=100 l:4").unwrap();
+        assert_eq!(filter, RawCardFilter(Field::Atk, Operator::GreaterEqual, Value::Numerical(100)));
+        assert_eq!(parse_raw_filter(rest), Ok(("", RawCardFilter(Field::Level, Operator::Equal, Value::Numerical(4)))));
+
+        assert_eq!(
+            parse_raw_filters("atk>=100 l=4"),
+            Ok((
+                "",
+                vec![
+                    RawCardFilter(Field::Atk, Operator::GreaterEqual, Value::Numerical(100)),
+                    RawCardFilter(Field::Level, Operator::Equal, Value::Numerical(4))
+                ]
+            ))
+        );
+
+        assert_eq!(
+            parse_raw_filters(r#"t:counter c:trap o:"negate the summon""#),
+            Ok((
+                "",
+                vec![
+                    RawCardFilter(Field::Type, Operator::Equal, Value::String("counter".into())),
+                    RawCardFilter(Field::Class, Operator::Equal, Value::String("trap".into())),
+                    RawCardFilter(Field::Text, Operator::Equal, Value::String("negate the summon".into())),
+                ]
+            ))
+        );
+    }
+
+    #[test]
+    fn quoted_name_with_operators_test() {
+        assert_eq!(
+            parse_raw_filters(r#"l:4 "I:P Masquerena""#),
+            Ok((
+                "",
+                vec![
+                    RawCardFilter(Field::Level, Operator::Equal, Value::Numerical(4)),
+                    RawCardFilter(Field::Name, Operator::Equal, Value::String("i:p masquerena".into())),
+                ]
+            ))
+        );
+    }
+
+    #[test_case("lvl:4" => Err("Unknown field “lvl”. Did you mean “level”? To search for “lvl:4” in card names, put it in quotes.".to_owned()))]
+    #[test_case("c:spell attack>2000" => Err("Unknown field “attack”. Did you mean “atk”? To search for “attack>2000” in card names, put it in quotes.".to_owned()))]
+    #[test_case("ATTRIBUT:dark" => Err("Unknown field “ATTRIBUT”. Did you mean “attribute”? To search for “ATTRIBUT:dark” in card names, put it in quotes.".to_owned()))]
+    #[test_case(r#""lvl:4""# => Ok(()); "quoted")]
+    #[test_case("danger!?" => Ok(()); "not close to any field")]
+    #[test_case("hi!five" => Ok(()); "too short")]
+    #[test_case("c39:" => Ok(()); "no value")]
+    fn misspelled_field_test(input: &str) -> Result<(), String> {
+        parse_filters(input).map(|_| ())
+    }
+
+    #[test_case("kitten", "sitting" => 3)]
+    #[test_case("lvl", "level" => 2)]
+    #[test_case("", "atk" => 3)]
+    fn edit_distance_test(a: &str, b: &str) -> usize {
+        edit_distance(a, b)
+    }
+
+    #[test]
+    fn field_names_test() {
+        for field in Field::ALL {
+            for name in field.names() {
+                assert_eq!(name.parse(), Ok(field), "{name} is used twice");
+            }
+        }
+        assert_eq!("ATTR".parse(), Ok(Field::Attribute));
+    }
+
+    #[test]
+    fn directives_are_not_filters_test() {
+        let (query, _, options, _) = parse_filters("include:duplicates dark magician").unwrap();
+        assert_eq!(query, Query::Filter(RawCardFilter(Field::Name, Operator::Equal, Value::String("dark magician".into()))));
+        assert_eq!(
+            options,
+            SearchOptions { include_duplicates: true, rank_by_name: Some("dark magician".to_owned()), ..Default::default() }
+        );
+        assert_eq!(parse_filters("include:spoilers").unwrap().2, SearchOptions { include_spoilers: true, ..Default::default() });
+        assert!(parse_filters("is:custom|tcgonly").unwrap().2.include_custom);
+        assert!(!parse_filters("is!=custom").unwrap().2.include_custom);
+        assert_eq!(parse_filters("owned:no c:spell").unwrap().2, SearchOptions { owned: Some(false), ..Default::default() });
+        assert!(parse_filters("owned:maybe").is_err());
+        assert_eq!(
+            parse_filters("dark magician").unwrap().2,
+            SearchOptions { rank_by_name: Some("dark magician".to_owned()), ..Default::default() }
+        );
+        assert_eq!(parse_filters("dark magician c:normal").unwrap().2.rank_by_name, None);
+        assert_eq!(parse_filters("name:/dark/").unwrap().2.rank_by_name, None);
+        assert!(parse_filters("include:everything").is_err());
+    }
+
+    #[test_case("sort:atk" => Ok(Some(Sort { key: SortKey::Atk, descending: false })))]
+    #[test_case("sort:-price c:spell" => Ok(Some(Sort { key: SortKey::Price, descending: true })))]
+    #[test_case("sort:l sort:date" => Ok(Some(Sort { key: SortKey::Date, descending: false })); "last one wins")]
+    #[test_case("sort:-views" => Ok(Some(Sort { key: SortKey::Views, descending: true })))]
+    #[test_case("sort:-" => Err("Unknown sort order: -".to_owned()))]
+    fn sort_directive_test(input: &str) -> Result<Option<Sort>, String> {
+        parse_filters(input).map(|(_, _, options, _)| options.sort)
+    }
+
+    #[test]
+    fn group_directive_test() {
+        assert_eq!(parse_filters("c:synchro group:attr").unwrap().2.group, Some(GroupKey::Attribute));
+        assert_eq!(parse_filters("group:c").unwrap().2.group, Some(GroupKey::Class));
+        assert!(parse_filters("group:atk").is_err());
+        assert_eq!(GroupKey::Class.to_string(), "card type");
+
+        let card = |id, attribute: Option<&str>| Card { id, attribute: attribute.map(str::to_owned), ..Default::default() };
+        let (spell, light, dark, other_light) = (card(1, None), card(2, Some("LIGHT")), card(3, Some("DARK")), card(4, Some("LIGHT")));
+        let mut cards = vec![&spell, &light, &dark, &other_light];
+        GroupKey::Attribute.sort(&mut cards);
+        assert_eq!(cards.iter().map(|c| c.id).collect_vec(), vec![2, 4, 3, 1]);
+        let tuner = Card { card_type: "Synchro Tuner Monster".to_owned(), ..Default::default() };
+        assert_eq!(GroupKey::Class.value(&tuner), Some("Synchro"));
+    }
+
+    #[test_case("ｌ：４" => "l:4")]
+    #[test_case("o:“destroy that target”" => r#"o:"destroy that target""#)]
+    #[test_case("ａｔｋ＞＝２０００\u{3000}ｃ：ｓｙｎｃｈｒｏ" => "atk>=2000 c:synchro"; "fullwidth with ideographic space")]
+    #[test_case("Ash Blossom & Joyous Spring" => "Ash Blossom & Joyous Spring")]
+    fn normalization_test(input: &str) -> String {
+        normalize_query(input)
+    }
+
+    #[test]
+    fn parse_multiple_values() {
+        let input = "level=4|5|6";
+        let expected_output = vec![RawCardFilter(
+            Field::Level,
+            Operator::Equal,
+            Value::Multiple(vec![Value::Numerical(4), Value::Numerical(5), Value::Numerical(6)]),
+        )];
+        assert_eq!(parse_raw_filters(input), Ok(("", expected_output)));
+    }
+
+    #[test]
+    fn quoted_value_test() {
+        let (rest, filter) = parse_raw_filter(r#"o:"destroy that target""#).unwrap();
+        assert_eq!(rest, "");
+        assert_eq!(filter, RawCardFilter(Field::Text, Operator::Equal, Value::String("destroy that target".into())));
+    }
+
+    #[test]
+    fn regex_should_have_precedence_over_split() {
+        let RawCardFilter(field, op, value) = parse_raw_filters("o:/(if|when) this card is synchro summoned:/").unwrap().1[0].clone();
+        assert_eq!(field, Field::Text);
+        assert_eq!(op, Operator::Equal);
+        match value {
+            Value::Regex(r) => assert_eq!(r.as_str(), "(?i)(if|when) this card is synchro summoned:"),
+            _ => panic!("Should have been a regex"),
+        }
+        let RawCardFilter(field, op, value) = parse_raw_filters("name:/(xyz|pendulum|synchro|fusion) dragon/").unwrap().1[0].clone();
+        assert_eq!(field, Field::Name);
+        assert_eq!(op, Operator::Equal);
+        match value {
+            Value::Regex(r) => assert_eq!(r.as_str(), "(?i)(xyz|pendulum|synchro|fusion) dragon"),
+            _ => panic!("Should have been a regex"),
+        }
+    }
+}
@@ -0,0 +1,484 @@
+//! Queries as an expression tree, and the optimizer that simplifies them before any filters are built.
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+};
+
+use itertools::Itertools;
+
+use crate::{
+    filter::{build_filter, CardFilter, SearchCard},
+    parser::{Field, Operator, RawCardFilter, Value},
+};
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Query {
+    Filter(RawCardFilter),
+    /// Every part has to match. Empty matches every card.
+    And(Vec<Query>),
+    /// At least one part has to match. Empty matches no card.
+    Or(Vec<Query>),
+    Not(Box<Query>),
+}
+
+/// Fields that only ever hold plain numbers, so bounds on them can be checked against each other.
+const NUMERIC_FIELDS: &[Field] =
+    &[Field::Atk, Field::Def, Field::Level, Field::LinkRating, Field::Legal, Field::Year, Field::Price, Field::Support];
+
+/// Words that no value of the field has more than one of, e.g. no card type is both a spell and a trap.
+/// Text filters match anything that contains them, so two filters with different words from one list can’t both match.
+/// Not every word is here, only ones that can’t be part of another value (“beast” is, in “beast-warrior”).
+const EXCLUSIVE_WORDS: &[(Field, &[&str])] = &[
+    (Field::Class, &["spell", "trap", "token", "skill", "fusion", "synchro", "xyz", "link", "ritual"]),
+    (Field::Class, &["spell", "trap", "token", "skill", "monster"]),
+    (Field::Class, &["normal", "effect"]),
+    (Field::Attribute, &["dark", "light", "earth", "water", "fire", "wind", "divine"]),
+    (Field::Deck, &["main", "extra"]),
+    (
+        Field::Type,
+        &[
+            "spellcaster",
+            "fairy",
+            "fiend",
+            "zombie",
+            "machine",
+            "aqua",
+            "pyro",
+            "rock",
+            "plant",
+            "insect",
+            "thunder",
+            "dragon",
+            "dinosaur",
+            "fish",
+            "sea serpent",
+            "reptile",
+            "psychic",
+            "wyrm",
+            "cyberse",
+            "illusion",
+            "quick-play",
+            "continuous",
+            "equip",
+            "field",
+            "counter",
+        ],
+    ),
+];
+
+impl Query {
+    pub const EVERYTHING: Self = Self::And(Vec::new());
+    pub const NOTHING: Self = Self::Or(Vec::new());
+
+    pub fn matches_everything(&self) -> bool {
+        *self == Self::EVERYTHING
+    }
+
+    pub fn matches_nothing(&self) -> bool {
+        *self == Self::NOTHING
+    }
+
+    /// All filters anywhere in the query, in order.
+    pub fn filters(&self) -> Vec<&RawCardFilter> {
+        match self {
+            Self::Filter(f) => vec![f],
+            Self::And(parts) | Self::Or(parts) => parts.iter().flat_map(Self::filters).collect(),
+            Self::Not(inner) => inner.filters(),
+        }
+    }
+
+    /// Like `filters`, but without the negated parts, so only the filters a result can have matched.
+    pub fn positive_filters(&self) -> Vec<&RawCardFilter> {
+        match self {
+            Self::Filter(f) => vec![f],
+            Self::And(parts) | Self::Or(parts) => parts.iter().flat_map(Self::positive_filters).collect(),
+            Self::Not(_) => Vec::new(),
+        }
+    }
+
+    pub fn build(self) -> Result<CardFilter, String> {
+        self.build_with(&build_filter)
+    }
+
+    /// With a different function for the individual filters, e.g. `build_filter_as_of`.
+    pub fn build_with(self, build: &dyn Fn(RawCardFilter) -> Result<CardFilter, String>) -> Result<CardFilter, String> {
+        Ok(match self {
+            Self::Filter(f) => build(f)?,
+            Self::And(parts) => {
+                let filters = parts.into_iter().map(|p| p.build_with(build)).collect::<Result<Vec<_>, _>>()?;
+                Box::new(move |card: &SearchCard| filters.iter().all(|f| f(card)))
+            }
+            Self::Or(parts) => {
+                let filters = parts.into_iter().map(|p| p.build_with(build)).collect::<Result<Vec<_>, _>>()?;
+                Box::new(move |card: &SearchCard| filters.iter().any(|f| f(card)))
+            }
+            Self::Not(inner) => {
+                let filter = inner.build_with(build)?;
+                Box::new(move |card: &SearchCard| !filter(card))
+            }
+        })
+    }
+
+    /// Flattens nested groups, removes duplicates and double negations,
+    /// replaces groups that can’t match anything (e.g. `atk>3000 atk<1000`) with `NOTHING`,
+    /// and puts the cheapest filters first so `And` and `Or` can stop early.
+    pub fn optimize(self) -> Self {
+        match self {
+            Self::Filter(_) => self,
+            Self::Not(inner) => match inner.optimize() {
+                Self::Not(inner) => *inner,
+                inner if inner.matches_everything() => Self::NOTHING,
+                inner if inner.matches_nothing() => Self::EVERYTHING,
+                inner => Self::Not(Box::new(inner)),
+            },
+            Self::And(parts) => {
+                let mut parts = flatten(parts, |q| match q {
+                    Self::And(parts) => Ok(parts),
+                    q => Err(q),
+                });
+                if parts.iter().any(Self::matches_nothing) || is_contradiction(&parts) {
+                    return Self::NOTHING;
+                }
+                sort_by_evaluation_order(&mut parts);
+                single_or(merge_names(parts), Self::And)
+            }
+            Self::Or(parts) => {
+                let mut parts = flatten(parts, |q| match q {
+                    Self::Or(parts) => Ok(parts),
+                    q => Err(q),
+                });
+                if parts.iter().any(Self::matches_everything) {
+                    return Self::EVERYTHING;
+                }
+                sort_by_evaluation_order(&mut parts);
+                single_or(parts, Self::Or)
+            }
+        }
+    }
+
+    /// Lower is cheaper. Groups go last because they contain at least one filter.
+    fn evaluation_order(&self) -> u8 {
+        match self {
+            Self::Filter(RawCardFilter(field, _, _)) => *field as u8,
+            Self::Not(inner) => inner.evaluation_order(),
+            Self::And(_) | Self::Or(_) => u8::MAX,
+        }
+    }
+
+    /// Roughly how much work the query is per card, assuming nothing can stop early.
+    /// Regexes dominate this because the regex crate is built without its DFA,
+    /// so every unbounded repetition makes matching a lot slower.
+    pub fn estimated_cost(&self) -> u32 {
+        match self {
+            Self::Filter(RawCardFilter(field, _, value)) => value_cost(value).saturating_mul(field_weight(*field)),
+            Self::And(parts) | Self::Or(parts) => parts.iter().map(Self::estimated_cost).fold(0, u32::saturating_add),
+            Self::Not(inner) => inner.estimated_cost(),
+        }
+    }
+
+    /// Rejects queries that would keep the server busy for everyone else, like `o:/.*.*.*/`.
+    pub fn check_cost(&self) -> Result<(), String> {
+        match self.estimated_cost() {
+            cost if cost > MAX_COST => Err(format!(
+                "This query is too expensive to run (cost {cost}, the limit is {MAX_COST}). \
+                 Try fewer or more specific regexes, e.g. o:/draw \\d cards?/ instead of o:/.*draw.*/"
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// See `Query::estimated_cost`. A plain text search costs 6 and a card text regex with three `.*` 240.
+pub const MAX_COST: u32 = 200;
+
+/// Card text (and rulings) are much longer than anything else we search, and `any:` includes the text.
+fn field_weight(field: Field) -> u32 {
+    match field {
+        Field::Text | Field::Any | Field::Ruling => 3,
+        _ => 1,
+    }
+}
+
+fn value_cost(value: &Value) -> u32 {
+    match value {
+        Value::Regex(r) => 10 << unbounded_repetitions(r.as_str()).min(16),
+        Value::Multiple(values) => values.iter().map(value_cost).sum(),
+        Value::String(_) | Value::Word(_) => 2,
+        _ => 1,
+    }
+}
+
+/// Counts `*`, `+`, and `{n,}`, skipping escaped characters and character classes.
+fn unbounded_repetitions(pattern: &str) -> u32 {
+    let mut count = 0;
+    let mut in_class = false;
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '[' => in_class = true,
+            ']' => in_class = false,
+            '*' | '+' if !in_class => count += 1,
+            '{' if !in_class => {
+                let bounds: String = chars.by_ref().take_while(|&c| c != '}').collect();
+                if bounds.ends_with(',') {
+                    count += 1;
+                }
+            }
+            _ => (),
+        }
+    }
+    count
+}
+
+/// Optimizes all parts, pulls up the parts of nested groups of the same kind, and removes duplicates.
+/// Empty nested groups of the same kind disappear this way, which is exactly what they mean.
+fn flatten(parts: Vec<Query>, same_kind: impl Fn(Query) -> Result<Vec<Query>, Query>) -> Vec<Query> {
+    let mut flat: Vec<Query> = Vec::with_capacity(parts.len());
+    for part in parts.into_iter().map(Query::optimize) {
+        for part in same_kind(part).unwrap_or_else(|part| vec![part]) {
+            if !flat.contains(&part) {
+                flat.push(part);
+            }
+        }
+    }
+    flat
+}
+
+/// Stable, so filters on the same field keep the order they were typed in.
+fn sort_by_evaluation_order(parts: &mut [Query]) {
+    parts.sort_by_key(Query::evaluation_order);
+}
+
+fn single_or(mut parts: Vec<Query>, group: fn(Vec<Query>) -> Query) -> Query {
+    match parts.len() {
+        1 => parts.pop().unwrap(),
+        _ => group(parts),
+    }
+}
+
+/// Combines multiple name searches into one. This makes the readable query nicer
+/// (“Showing 21 results where name is ally and name is of and name is justice” becomes
+/// “Showing 21 results where name is ‘ally of justice’”)
+/// and improves search performance by only performing one String::contains.
+/// Expects the parts to be sorted so all names are next to each other.
+fn merge_names(parts: Vec<Query>) -> Vec<Query> {
+    parts
+        .into_iter()
+        .coalesce(|a, b| match (&a, &b) {
+            (
+                Query::Filter(RawCardFilter(Field::Name, Operator::Equal, Value::String(s1))),
+                Query::Filter(RawCardFilter(Field::Name, Operator::Equal, Value::String(s2))),
+            ) => Ok(Query::Filter(RawCardFilter(Field::Name, Operator::Equal, Value::String(format!("{s1} {s2}"))))),
+            _ => Err((a, b)),
+        })
+        .collect()
+}
+
+/// Whether these parts of an `And` can never all match at the same time.
+fn is_contradiction(parts: &[Query]) -> bool {
+    let negated = parts.iter().any(|p| matches!(p, Query::Not(inner) if parts.contains(inner)));
+    negated || !bounds(parts).values().all(Bounds::is_satisfiable) || has_exclusive_words(parts)
+}
+
+/// See `EXCLUSIVE_WORDS`
+fn has_exclusive_words(parts: &[Query]) -> bool {
+    EXCLUSIVE_WORDS.iter().any(|(field, words)| {
+        let values = parts.iter().filter_map(|part| match part {
+            Query::Filter(RawCardFilter(f, Operator::Equal, Value::String(s) | Value::Word(s))) if f == field => Some(s),
+            _ => None,
+        });
+        values.flat_map(|s| words.iter().filter(|&word| s.contains(word))).unique().count() > 1
+    })
+}
+
+/// The range a numeric field can still be in, and the values excluded with `!=`.
+#[derive(Debug)]
+struct Bounds {
+    min:      i64,
+    max:      i64,
+    excluded: Vec<i64>,
+}
+
+impl Bounds {
+    fn is_satisfiable(&self) -> bool {
+        self.min <= self.max && !(self.min == self.max && self.excluded.contains(&self.min))
+    }
+}
+
+fn bounds(parts: &[Query]) -> HashMap<Field, Bounds> {
+    let mut bounds = HashMap::new();
+    for part in parts {
+        let Query::Filter(RawCardFilter(field, op, Value::Numerical(n))) = part else {
+            continue;
+        };
+        if !NUMERIC_FIELDS.contains(field) {
+            continue;
+        }
+        let n = *n as i64;
+        let b = bounds.entry(*field).or_insert(Bounds { min: i64::MIN, max: i64::MAX, excluded: Vec::new() });
+        match op {
+            Operator::Equal => (b.min, b.max) = (b.min.max(n), b.max.min(n)),
+            Operator::Less => b.max = b.max.min(n - 1),
+            Operator::LessEqual => b.max = b.max.min(n),
+            Operator::Greater => b.min = b.min.max(n + 1),
+            Operator::GreaterEqual => b.min = b.min.max(n),
+            Operator::NotEqual => b.excluded.push(n),
+            // Only valid for text, building the filter will complain.
+            Operator::EqualCase => (),
+        }
+    }
+    bounds
+}
+
+/// The values each field can have. Anything outside of that never matches.
+const VALID_RANGES: [(Field, i64, i64); 3] = [(Field::Level, 0, 13), (Field::LinkRating, 1, 6), (Field::Legal, 0, 3)];
+const MONSTER_FIELDS: &[Field] = &[Field::Atk, Field::Def, Field::Level, Field::LinkRating, Field::Attribute, Field::Materials];
+/// Card types and what they don’t have. `c:` searches for substrings, so `link` also matches “Link Monster”.
+const MISSING_FIELDS: [(&str, &str, &[Field]); 3] =
+    [("spell", "Spells", MONSTER_FIELDS), ("trap", "Traps", MONSTER_FIELDS), ("link", "Link monsters", &[Field::Def, Field::Level])];
+
+/// Things in a query that are probably mistakes, like filters that can never match or clauses that are there twice.
+/// They don’t stop the search, but explain why there are no (or unexpected) results.
+/// Expects the filters as typed, because the optimizer removes duplicates.
+pub fn warnings(filters: &[RawCardFilter]) -> Vec<String> {
+    let mut warnings = Vec::new();
+    for (i, filter) in filters.iter().enumerate() {
+        // Only once, on the second time it appears
+        if filters[..i].iter().filter(|f| *f == filter).count() == 1 {
+            warnings.push(format!("“{filter}” is in the query more than once"));
+        }
+    }
+    let parts: Vec<_> = filters.iter().cloned().map(Query::Filter).collect();
+    let bounds = bounds(&parts);
+    for (field, min, max) in VALID_RANGES {
+        if bounds.get(&field).is_some_and(|b| b.is_satisfiable() && b.min.max(min) > b.max.min(max)) {
+            warnings.push(format!("There are only cards with a {field} from {min} to {max}"));
+        }
+    }
+    // Negations are fine, `c:spell atk!=0` just matches every spell.
+    let has = |field: Field| filters.iter().any(|RawCardFilter(f, op, _)| *f == field && *op != Operator::NotEqual);
+    for (class, cards, missing) in MISSING_FIELDS {
+        let required = Value::String(class.to_owned());
+        if filters.iter().any(|f| *f == RawCardFilter(Field::Class, Operator::Equal, required.clone())) {
+            warnings.extend(missing.iter().filter(|&&f| has(f)).map(|field| format!("{cards} have no {field}")));
+        }
+    }
+    if has(Field::LinkRating) {
+        let missing = [Field::Level, Field::Def].into_iter().filter(|&f| has(f));
+        warnings.extend(missing.map(|field| format!("Only Link monsters have a link rating, and they have no {field}")));
+    }
+    warnings
+}
+
+/// Readable, e.g. for “Showing 3 results where …”.
+impl Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Filter(filter) => write!(f, "{filter}"),
+            q if q.matches_nothing() => f.write_str("nothing"),
+            Self::And(parts) => write!(f, "{}", parts.iter().map(|p| group(p, "and")).join(" and ")),
+            Self::Or(parts) => write!(f, "{}", parts.iter().map(|p| group(p, "or")).join(" or ")),
+            Self::Not(inner) => write!(f, "not {}", group(inner, "not")),
+        }
+    }
+}
+
+/// Parenthesizes nested groups so `a and (b or c)` doesn’t read as `(a and b) or c`.
+fn group(part: &Query, parent: &str) -> String {
+    match part {
+        Query::And(_) if parent != "and" => format!("({part})"),
+        Query::Or(_) if parent != "or" => format!("({part})"),
+        _ => part.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_filters;
+    use test_case::test_case;
+
+    fn filter(field: Field, op: Operator, value: Value) -> Query {
+        Query::Filter(RawCardFilter(field, op, value))
+    }
+
+    fn atk(op: Operator, n: i32) -> Query {
+        filter(Field::Atk, op, Value::Numerical(n))
+    }
+
+    fn name(s: &str) -> Query {
+        filter(Field::Name, Operator::Equal, Value::String(s.to_owned()))
+    }
+
+    #[test_case("atk>3000 atk<1000" => true)]
+    #[test_case("atk>=2500 atk<=2500" => false)]
+    #[test_case("atk=2500 atk!=2500" => true)]
+    #[test_case("l=4 l=5" => true)]
+    #[test_case("l=4 atk=5" => false; "different fields")]
+    #[test_case("set:ap03 set!=ap03" => false; "negated sets aren’t checked")]
+    #[test_case("c:spell c:trap" => true)]
+    #[test_case("c:synchro c:xyz" => true)]
+    #[test_case("c:synchro c:tuner" => false; "not exclusive")]
+    #[test_case("a:dark a:light" => true)]
+    #[test_case("deck:main deck:extra" => true)]
+    #[test_case("t:beast t:warrior" => false; "beast-warrior")]
+    #[test_case("t:dragon t:fish" => true)]
+    fn contradiction_test(query: &str) -> bool {
+        parse_filters(query).unwrap().0.matches_nothing()
+    }
+
+    #[test_case("c:spell l=4" => true)]
+    #[test_case("o:/draw \\d+ cards?/" => true)]
+    #[test_case("o:/(if|when) .* summoned.*draw/" => true)]
+    #[test_case("name:/.*.*.*/" => true; "names are short")]
+    #[test_case("o:/.*.*.*/" => false)]
+    #[test_case("o:/a.{2,}b.+c.*/" => false)]
+    #[test_case("o:/[*+]\\*.{1,5}/" => true; "not repetitions")]
+    fn cost_test(query: &str) -> bool {
+        parse_filters(query).is_ok()
+    }
+
+    #[test]
+    fn optimize_test() {
+        let c_spell = filter(Field::Class, Operator::Equal, Value::String("spell".to_owned()));
+        // Nested groups are flattened, duplicates removed, and the cheaper ATK filter goes first.
+        let query = Query::And(vec![c_spell.clone(), Query::And(vec![atk(Operator::Greater, 5), c_spell.clone()])]);
+        assert_eq!(query.optimize(), Query::And(vec![atk(Operator::Greater, 5), c_spell.clone()]));
+
+        assert_eq!(Query::Not(Box::new(Query::Not(Box::new(c_spell.clone())))).optimize(), c_spell);
+        assert_eq!(Query::And(vec![c_spell.clone(), Query::Not(Box::new(c_spell.clone()))]).optimize(), Query::NOTHING);
+        assert_eq!(Query::Or(vec![c_spell.clone(), Query::EVERYTHING]).optimize(), Query::EVERYTHING);
+        assert_eq!(Query::Or(vec![c_spell.clone(), Query::NOTHING]).optimize(), c_spell);
+        assert_eq!(
+            Query::And(vec![c_spell.clone(), Query::Or(vec![atk(Operator::Greater, 3000), atk(Operator::Less, 1000)])]).optimize(),
+            Query::And(vec![c_spell.clone(), Query::Or(vec![atk(Operator::Greater, 3000), atk(Operator::Less, 1000)])]),
+            "contradictions only matter within an And",
+        );
+        assert_eq!(
+            Query::And(vec![name("ally"), atk(Operator::Equal, 5), name("of"), name("justice")]).optimize(),
+            Query::And(vec![atk(Operator::Equal, 5), name("ally of justice")]),
+        );
+    }
+
+    #[test_case("l=4 c:xyz" => Vec::<String>::new())]
+    #[test_case("l=4 c:xyz l=4 l=4" => vec!["“level/rank is 4” is in the query more than once"])]
+    #[test_case("l>13" => vec!["There are only cards with a level/rank from 0 to 13"])]
+    #[test_case("lr=7" => vec!["There are only cards with a link rating from 1 to 6"])]
+    #[test_case("c:spell lr=2" => vec!["Spells have no link rating"])]
+    #[test_case("c:spell atk!=0" => Vec::<String>::new(); "negations")]
+    #[test_case("c:link def>=2000" => vec!["Link monsters have no DEF"])]
+    #[test_case("lr=2 l=4" => vec!["Only Link monsters have a link rating, and they have no level/rank"])]
+    fn warnings_test(query: &str) -> Vec<String> {
+        parse_filters(query).unwrap().3
+    }
+
+    #[test]
+    fn display_test() {
+        let query = Query::And(vec![atk(Operator::Equal, 5), Query::Or(vec![name("a"), Query::Not(Box::new(name("b")))])]);
+        assert_eq!(query.to_string(), "ATK is 5 and (name is a or not name is b)");
+        assert_eq!(Query::NOTHING.to_string(), "nothing");
+    }
+}
@@ -0,0 +1,76 @@
+//! Rulings for `ruling:` and the rulings page, from a JSON list like
+//! `[{"id": 46986414, "text": "Dark Magician can be ...", "url": "https://db.ygorganization.com/qa#1234"}]`.
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::data::{Dump, LoadError};
+
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone)]
+pub struct Ruling {
+    pub text: String,
+    /// Where the ruling comes from, if it’s online
+    #[serde(default)]
+    pub url:  Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Entry {
+    id:     usize,
+    #[serde(flatten)]
+    ruling: Ruling,
+}
+
+impl Dump {
+    /// Attaches the rulings to their cards and returns how many cards got any.
+    /// Rulings don’t depend on the artwork, so alternate artworks get them too.
+    pub fn add_rulings(&mut self, json: &str) -> Result<usize, LoadError> {
+        let entries: Vec<Entry> = serde_json::from_str(json).map_err(|e| LoadError::Parse("rulings".to_owned(), e))?;
+        let names_by_id: HashMap<usize, &str> = self.cards.iter().map(|c| (c.id, c.name.as_str())).collect();
+        let mut by_name: HashMap<String, Vec<Ruling>> = HashMap::new();
+        for Entry { id, mut ruling } in entries {
+            let Some(name) = names_by_id.get(&id) else { continue };
+            // These end up in links, so nothing but actual web links.
+            ruling.url = ruling.url.filter(|url| url.starts_with("https://"));
+            by_name.entry(name.to_string()).or_default().push(ruling);
+        }
+        let mut added = 0;
+        for card in &mut self.cards {
+            if let Some(rulings) = by_name.get(&card.name) {
+                card.rulings = rulings.clone();
+                added += 1;
+            }
+        }
+        Ok(added)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Card;
+
+    #[test]
+    fn add_rulings_test() {
+        let dark_magician = Card { id: 46986414, name: "Dark Magician".to_owned(), ..Default::default() };
+        let mut dump = Dump {
+            cards: vec![dark_magician.clone(), Card { id: 36996508, ..dark_magician }, Card { id: 1, ..Default::default() }],
+            sets:  vec![],
+        };
+        let json = r#"[
+            {"id": 46986414, "text": "It can be Normal Summoned.", "url": "https://db.ygorganization.com/qa#1"},
+            {"id": 46986414, "text": "It’s a Normal Monster.", "url": "javascript:alert(1)"},
+            {"id": 2, "text": "Unknown card"}
+        ]"#;
+        assert_eq!(dump.add_rulings(json).unwrap(), 2);
+        assert_eq!(
+            dump.cards[0].rulings,
+            vec![
+                Ruling { text: "It can be Normal Summoned.".to_owned(), url: Some("https://db.ygorganization.com/qa#1".to_owned()) },
+                Ruling { text: "It’s a Normal Monster.".to_owned(), url: None },
+            ]
+        );
+        assert_eq!(dump.cards[1].rulings, dump.cards[0].rulings);
+        assert!(dump.cards[2].rulings.is_empty());
+        assert!(dump.add_rulings("{}").is_err());
+    }
+}
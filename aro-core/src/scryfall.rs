@@ -0,0 +1,229 @@
+//! Best-effort translation of Scryfall queries, so Magic players can type what they already know.
+//! Only syntax that means nothing (or nothing useful) in our own dialect is rewritten,
+//! so queries that already work are passed through unchanged.
+use itertools::Itertools;
+
+use crate::parser::OPERATOR_CHARS;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Translation {
+    pub query: String,
+    /// What was translated, for showing to the user. Empty if nothing was.
+    pub notes: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Token {
+    Open,
+    Close,
+    Or,
+    Term(String),
+}
+
+/// Scryfall fields that map to one of ours, and what we call them.
+const FIELDS: &[(&[&str], &str)] =
+    &[(&["cmc", "mv", "manavalue"], "l"), (&["pow", "power"], "atk"), (&["tou", "toughness"], "def"), (&["order"], "sort")];
+
+/// Magic card types and the closest Yugioh equivalent.
+const TYPES: &[(&str, &str)] = &[
+    ("creature", "c:monster"),
+    ("instant", "t:quick-play"),
+    ("sorcery", "c:spell"),
+    ("enchantment", "t:continuous"),
+    ("artifact", "t:equip"),
+    ("land", "t:field"),
+];
+
+const ORDERS: &[(&str, &str)] = &[("cmc", "level"), ("power", "atk"), ("released", "date"), ("usd", "price"), ("eur", "price")];
+
+const COLORS: &[&str] = &["white", "blue", "black", "red", "green", "colorless", "multicolor"];
+
+pub fn translate(query: &str) -> Result<Translation, String> {
+    let mut notes = Vec::new();
+    let mut tokens = tokenize(query);
+    let has_or = tokens.contains(&Token::Or);
+    for token in tokens.iter_mut() {
+        if let Token::Term(term) = token {
+            if let Some(translated) = translate_term(term) {
+                notes.push(match translated.as_str() {
+                    "" => format!("“{term}” was ignored because Yugioh has nothing like it"),
+                    _ => format!("“{term}” became “{translated}”"),
+                });
+                *term = translated;
+            }
+        }
+    }
+    let tokens: Vec<_> = if has_or {
+        // Without OR, parentheses don’t change anything because all filters have to match anyway.
+        if tokens.contains(&Token::Open) {
+            notes.push("Parentheses were removed".to_owned());
+        }
+        tokens.into_iter().filter(|t| !matches!(t, Token::Open | Token::Close)).collect()
+    } else {
+        tokens
+    };
+    let mut terms: Vec<String> = Vec::new();
+    let mut tokens = tokens.into_iter().peekable();
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Or => match (terms.last(), tokens.peek()) {
+                // `dark or light` is a perfectly fine name search.
+                (Some(prev), Some(Token::Term(next))) if split_term(prev).is_none() && split_term(next).is_none() => {
+                    terms.push("or".to_owned())
+                }
+                (Some(prev), Some(Token::Term(next))) => {
+                    let merged = merge(prev, next)
+                        .ok_or_else(|| format!("“{prev} or {next}” can’t be translated. Only alternatives for the same filter can be combined, e.g. t:dragon or t:warrior"))?;
+                    notes.push(format!("“{prev} or {next}” became “{merged}”"));
+                    *terms.last_mut().unwrap() = merged;
+                    tokens.next();
+                }
+                _ => Err("OR needs a filter on both sides".to_owned())?,
+            },
+            Token::Open => match tokens.peek() {
+                Some(Token::Term(next)) => {
+                    let next = format!("({next}");
+                    tokens.next();
+                    terms.push(next);
+                }
+                _ => terms.push("(".to_owned()),
+            },
+            Token::Close => match terms.last_mut() {
+                Some(prev) => prev.push(')'),
+                None => terms.push(")".to_owned()),
+            },
+            Token::Term(term) => terms.push(term),
+        }
+    }
+    Ok(Translation { query: terms.into_iter().filter(|t| !t.is_empty()).join(" "), notes: notes.into_iter().unique().collect() })
+}
+
+/// Whitespace-separated, except within quotes. Parentheses are split off the start and end of words.
+fn tokenize(query: &str) -> Vec<Token> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    for c in query.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                word.push(c);
+            }
+            c if c.is_whitespace() && !quoted => words.push(std::mem::take(&mut word)),
+            c => word.push(c),
+        }
+    }
+    words.push(word);
+    let mut tokens = Vec::new();
+    for word in words.iter().filter(|w| !w.is_empty()) {
+        let inner = word.trim_start_matches('(');
+        tokens.extend(std::iter::repeat(Token::Open).take(word.len() - inner.len()));
+        let trimmed = inner.trim_end_matches(')');
+        match trimmed {
+            "" => (),
+            "or" | "OR" => tokens.push(Token::Or),
+            _ => tokens.push(Token::Term(trimmed.to_owned())),
+        }
+        tokens.extend(std::iter::repeat(Token::Close).take(inner.len() - trimmed.len()));
+    }
+    tokens
+}
+
+/// `(negated, field, operator, value)`
+fn split_term(term: &str) -> Option<(bool, &str, &str, &str)> {
+    let (negated, term) = match term.strip_prefix('-') {
+        Some(term) => (true, term),
+        None => (false, term),
+    };
+    let field_len = term.find(|c: char| !c.is_alphabetic())?;
+    let op_len = term[field_len..].find(|c| !OPERATOR_CHARS.contains(&c)).unwrap_or(term.len() - field_len);
+    let (field, rest) = term.split_at(field_len);
+    let (op, value) = rest.split_at(op_len);
+    (!field.is_empty() && (1..=2).contains(&op.len()) && !value.is_empty()).then_some((negated, field, op, value))
+}
+
+/// `None` if the term doesn’t need translating, or an empty string if it should be dropped.
+fn translate_term(term: &str) -> Option<String> {
+    if let Some(name) = term.strip_prefix('!').filter(|n| n.starts_with('"')) {
+        return Some(name.to_owned());
+    }
+    let Some((negated, field, op, value)) = split_term(term) else {
+        // `-word` excludes names
+        return term.strip_prefix('-').filter(|w| !w.is_empty()).map(|w| format!("name!={w}"));
+    };
+    let lowercase_field = field.to_lowercase();
+    let lowercase_value = value.to_lowercase();
+    let (field, value) = match lowercase_field.as_str() {
+        "t" | "type" => match TYPES.iter().find(|(t, _)| *t == lowercase_value) {
+            Some((_, translated)) => translated.split_once(':').unwrap_or_default(),
+            None => (field, value),
+        },
+        "color" | "ci" | "id" | "identity" if is_color(&lowercase_value, true) => return Some(String::new()),
+        "c" if is_color(&lowercase_value, false) => return Some(String::new()),
+        "r" | "rarity" => return Some(String::new()),
+        "order" => ("sort", ORDERS.iter().find(|(o, _)| *o == lowercase_value).map_or(value, |(_, translated)| translated)),
+        f => (FIELDS.iter().find(|(names, _)| names.contains(&f)).map_or(field, |(_, translated)| translated), value),
+    };
+    let op = if negated { negate(op)? } else { op };
+    let translated = format!("{field}{op}{value}");
+    (translated != term).then_some(translated)
+}
+
+fn is_color(value: &str, allow_letters: bool) -> bool {
+    COLORS.contains(&value) || (allow_letters && value.chars().all(|c| "wubrgc".contains(c)))
+}
+
+fn negate(op: &str) -> Option<&'static str> {
+    Some(match op {
+        ":" | "=" | "==" => "!=",
+        "!=" => ":",
+        "<" => ">=",
+        "<=" | "=<" => ">",
+        ">" => "<=",
+        ">=" | "=>" => "<",
+        _ => return None,
+    })
+}
+
+/// `t:dragon` and `t:warrior` become `t:dragon|warrior`, which is the only kind of OR we support.
+fn merge(a: &str, b: &str) -> Option<String> {
+    let (false, field_a, op_a, value_a) = split_term(a)? else { return None };
+    let (false, field_b, op_b, value_b) = split_term(b)? else { return None };
+    let quoted = |v: &str| v.contains('"') || v.starts_with('/');
+    (field_a.eq_ignore_ascii_case(field_b) && op_a == op_b && !quoted(value_a) && !quoted(value_b))
+        .then(|| format!("{field_a}{op_a}{value_a}|{value_b}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("l:4 c:warrior" => "l:4 c:warrior"; "our own syntax is untouched")]
+    #[test_case("dark or light" => "dark or light"; "or in names")]
+    #[test_case("(Token)" => "(Token)")]
+    #[test_case("cmc>=3 pow>2000" => "l>=3 atk>2000")]
+    #[test_case("-o:draw -t:dragon -cmc<4" => "o!=draw t!=dragon l>=4")]
+    #[test_case("t:creature -t:instant" => "c:monster t!=quick-play")]
+    #[test_case("t:dragon or t:warrior" => "t:dragon|warrior")]
+    #[test_case("(t:dragon or t:warrior or t:zombie) cmc=4" => "t:dragon|warrior|zombie l=4")]
+    #[test_case("c:spell color:ub r:mythic" => "c:spell")]
+    #[test_case("!\"Dark Magician\" -girl" => "\"Dark Magician\" name!=girl")]
+    #[test_case("order:cmc" => "sort:level")]
+    fn translate_test(query: &str) -> String {
+        translate(query).unwrap().query
+    }
+
+    #[test]
+    fn translation_notes_test() {
+        assert_eq!(translate("l:4").unwrap().notes, Vec::<String>::new());
+        assert_eq!(translate("cmc:4 mv:4").unwrap().notes, vec!["“cmc:4” became “l:4”", "“mv:4” became “l:4”"]);
+        assert_eq!(
+            translate("(a:dark or a:light)").unwrap().notes,
+            vec!["Parentheses were removed", "“a:dark or a:light” became “a:dark|light”"]
+        );
+        assert!(translate("t:dragon or o:draw").is_err());
+        assert!(translate("o:\"draw a card\" or o:draw").is_err());
+        assert!(translate("or t:dragon").is_err());
+    }
+}
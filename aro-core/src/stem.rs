@@ -0,0 +1,88 @@
+//! A very small stemmer for card text, so `o:destroyed` also finds “destroys” and “destroy”.
+//! Card text only uses a few regular word forms, so stripping suffixes is enough and much simpler than Porter.
+
+/// Lowercase words of the text, stemmed, each surrounded by spaces so whole words can be found with `contains(" word ")`.
+pub fn stemmed_words(text: &str) -> String {
+    let mut stemmed = String::from(" ");
+    for word in text.split(|c: char| !c.is_alphanumeric()).filter(|w| !w.is_empty()) {
+        stemmed.push_str(&stem(&word.to_lowercase()));
+        stemmed.push(' ');
+    }
+    stemmed
+}
+
+/// Expects a lowercase word. Different forms of the same word end up the same, but not necessarily as a real word,
+/// e.g. `negate`, `negates`, and `negated` are all `negat`.
+pub fn stem(word: &str) -> String {
+    let mut word = strip_suffix(word);
+    if word.len() > 3 && word.ends_with('e') {
+        word.pop();
+    }
+    word
+}
+
+fn strip_suffix(word: &str) -> String {
+    // Anything shorter than this after stripping was probably not a suffix, e.g. `need` or `this`.
+    let long_enough = |stem: &str| stem.len() >= 3;
+    if let Some(stem) = word.strip_suffix("ies").filter(|s| long_enough(s)) {
+        return format!("{stem}y");
+    }
+    if let Some(stem) = word.strip_suffix("ied").filter(|s| long_enough(s)) {
+        return format!("{stem}y");
+    }
+    for suffix in ["sses", "shes", "ches", "xes", "zes"] {
+        if let Some(stem) = word.strip_suffix(suffix).filter(|s| long_enough(s)) {
+            return format!("{stem}{}", &suffix[..suffix.len() - 2]);
+        }
+    }
+    for suffix in ["ing", "ed"] {
+        if let Some(stem) = word.strip_suffix(suffix).filter(|s| long_enough(s)) {
+            return undouble(stem).to_owned();
+        }
+    }
+    match word.strip_suffix('s') {
+        Some(stem) if long_enough(stem) && !stem.ends_with(['s', 'u', 'i']) => stem.to_owned(),
+        _ => word.to_owned(),
+    }
+}
+
+/// `stopped` → `stopp` → `stop`, but `called` stays `call`.
+fn undouble(stem: &str) -> &str {
+    let bytes = stem.as_bytes();
+    match bytes {
+        [.., a, b] if a == b && !b"lsz".contains(b) && b.is_ascii_alphabetic() => &stem[..stem.len() - 1],
+        _ => stem,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case(&["destroy", "destroys", "destroyed", "destroying"])]
+    #[test_case(&["negate", "negates", "negated", "negating"])]
+    #[test_case(&["banish", "banishes", "banished", "banishing"])]
+    #[test_case(&["card", "cards"])]
+    #[test_case(&["copy", "copies", "copied"])]
+    #[test_case(&["stop", "stops", "stopped"])]
+    #[test_case(&["summon", "summoned", "summoning", "summons"])]
+    fn same_stem_test(words: &[&str]) {
+        let stems: Vec<_> = words.iter().map(|w| stem(w)).collect();
+        assert!(stems.iter().all(|s| *s == stems[0]), "{words:?} became {stems:?}");
+    }
+
+    #[test_case("this" => "this")]
+    #[test_case("need" => "need")]
+    #[test_case("bonus" => "bonus")]
+    #[test_case("called" => "call")]
+    #[test_case("gy" => "gy")]
+    fn stem_test(word: &str) -> String {
+        stem(word)
+    }
+
+    #[test]
+    fn stemmed_words_test() {
+        assert_eq!(stemmed_words("Destroy 2 cards; draw 1 card."), " destroy 2 card draw 1 card ");
+    }
+}
@@ -0,0 +1,43 @@
+//! Game terms that mean the same thing, mostly because the wording changed over the years.
+//! Text searches for one of them also find the others, so `o:banish` finds old cards that “remove from play”.
+use crate::stem::stem;
+
+const SYNONYMS: &[&[&str]] = &[
+    &["gy", "graveyard"],
+    &["banish", "remove from play", "removed from play"],
+    &["forbidden", "banned"],
+    &["piercing", "inflicts piercing battle damage", "inflict piercing battle damage"],
+    &["ss", "special summon"],
+    &["bp", "battle phase"],
+    &["ep", "end phase"],
+];
+
+/// The other terms that mean the same as `term`, which has to be lowercase. Single words compare by their stem,
+/// so `banished` finds “removed from play” as well.
+pub fn synonyms(term: &str) -> impl Iterator<Item = &'static str> {
+    let key = normalize(term);
+    let group = SYNONYMS.iter().find(|group| group.iter().any(|t| normalize(t) == key)).copied().unwrap_or_default();
+    group.iter().copied().filter(move |t| normalize(t) != key)
+}
+
+fn normalize(term: &str) -> String {
+    match term.contains(' ') {
+        true => term.to_owned(),
+        false => stem(term),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("gy" => vec!["graveyard"])]
+    #[test_case("graveyard" => vec!["gy"])]
+    #[test_case("banished" => vec!["remove from play", "removed from play"])]
+    #[test_case("remove from play" => vec!["banish", "removed from play"])]
+    #[test_case("draw" => Vec::<&str>::new())]
+    fn synonyms_test(term: &str) -> Vec<&'static str> {
+        synonyms(term).collect()
+    }
+}
@@ -0,0 +1,85 @@
+//! Rough classification of what a card does, for `does:` and the tags on card pages, and of its once-per-turn clauses for `opt:`.
+//! This only looks for the usual PSCT phrasing, so it’s neither complete nor perfect, but good enough to narrow down a search.
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// Tag names and what they look for in the lowercased card text.
+/// The order is the order in which tags are shown.
+const RULES: &[(&str, &str)] = &[
+    ("negate", r"\bnegate\b"),
+    ("destroy", r"\bdestroy\b"),
+    ("banish", r"\bbanish\b"),
+    ("bounce", r"\breturn\b[^.;]*\bto the (?:owner's )?hand\b"),
+    ("control", r"\btake control of\b"),
+    ("draw", r"\bdraw \d+ cards?\b"),
+    ("search", r"\badd\b[^.;]*\bfrom your deck to your hand\b"),
+    ("mill", r"\bsend\b[^.;]*\bfrom (?:your|the top of your) deck to the (?:gy|graveyard)\b"),
+    ("revive", r"\bspecial summon\b[^.;]*\bfrom (?:your|either player's|your opponent's)(?: hand or)? (?:gy|graveyard)\b"),
+    ("discard", r"\bdiscard\b"),
+    ("burn", r"\binflict\b[^.;]*\bdamage to your opponent\b"),
+];
+
+static COMPILED: LazyLock<Vec<(&str, Regex)>> =
+    LazyLock::new(|| RULES.iter().map(|(tag, pattern)| (*tag, Regex::new(pattern).unwrap())).collect());
+
+/// All tags that apply to the card text, in the order of `RULES`.
+pub fn effect_tags(text: &str) -> Vec<&'static str> {
+    let text = text.to_lowercase();
+    COMPILED.iter().filter(|(_, pattern)| pattern.is_match(&text)).map(|(tag, _)| *tag).collect()
+}
+
+pub fn is_tag(name: &str) -> bool {
+    RULES.iter().any(|(tag, _)| *tag == name)
+}
+
+pub fn all_tags() -> impl Iterator<Item = &'static str> {
+    RULES.iter().map(|(tag, _)| *tag)
+}
+
+/// What users can type for `opt:`.
+pub const OPT_KINDS: [&str; 3] = ["hard", "soft", "none"];
+
+/// `hard` if any effect has a “You can only use this effect of … once per turn” or “You can only activate 1 … per turn” clause,
+/// and `soft` if any effect is just “Once per turn”. Cards can have both. `none` if neither.
+pub fn opt_kinds(text: &str) -> Vec<&'static str> {
+    let text = text.to_lowercase();
+    let (mut hard, mut soft) = (false, false);
+    for sentence in text.split(['.', '\n']) {
+        if sentence.contains("you can only") && sentence.contains("per turn") {
+            hard = true;
+        } else if sentence.contains("once per turn") {
+            soft = true;
+        }
+    }
+    match (hard, soft) {
+        (false, false) => vec!["none"],
+        (hard, soft) => [(hard, "hard"), (soft, "soft")].into_iter().filter(|(applies, _)| *applies).map(|(_, kind)| kind).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("Draw 2 cards." => vec!["draw"])]
+    #[test_case("When your opponent activates a card or effect: Negate the activation, and if you do, destroy that card." => vec!["negate", "destroy"])]
+    #[test_case("Add 1 \"Branded\" Spell/Trap from your Deck to your hand." => vec!["search"])]
+    #[test_case("Target 1 monster in your GY; Special Summon it. You can only Special Summon 1 monster from your GY this way." => vec!["revive"])]
+    #[test_case("Send 1 Dragon monster from your Deck to the GY." => vec!["mill"])]
+    #[test_case("This card cannot be destroyed by battle. It cannot be banished." => Vec::<&str>::new(); "protection is not removal")]
+    #[test_case("Target 1 card your opponent controls; return it to the hand." => vec!["bounce"])]
+    #[test_case("Inflict 500 damage to your opponent." => vec!["burn"])]
+    fn effect_tags_test(text: &str) -> Vec<&'static str> {
+        effect_tags(text)
+    }
+
+    #[test_case("Draw 2 cards." => vec!["none"])]
+    #[test_case("Once per turn: You can change this card to face-down Defense Position." => vec!["soft"])]
+    #[test_case("You can only use this effect of \"Ash Blossom & Joyous Spring\" once per turn." => vec!["hard"])]
+    #[test_case("You can only activate 1 \"Pot of Prosperity\" per turn." => vec!["hard"])]
+    #[test_case("Once per turn: You can draw 1 card.\r\nYou can only use each effect of \"Something\" once per turn." => vec!["hard", "soft"])]
+    fn opt_kinds_test(text: &str) -> Vec<&'static str> {
+        opt_kinds(text)
+    }
+}
@@ -0,0 +1,43 @@
+//! Bindings for running the search in the browser, e.g. for an offline version of the site.
+//! Build with `wasm-pack build aro-core --target web --no-default-features --features wasm`.
+//! ```js
+//! import init, { Search } from "./pkg/aro_core.js";
+//! await init();
+//! const [cards, sets] = await Promise.all([fetch("cards.json"), fetch("sets.json")].map(r => r.then(r => r.text())));
+//! const search = new Search(cards, sets);
+//! const ids = search.search("c:spell o:draw");
+//! ```
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    data::{support_counts, Dump},
+    filter::SearchCard,
+    parser::parse_filters,
+    search, sets_by_name,
+};
+
+#[wasm_bindgen]
+pub struct Search {
+    cards: Vec<SearchCard>,
+}
+
+#[wasm_bindgen]
+impl Search {
+    /// Takes the contents of the same `cards.json` and `sets.json` the server reads.
+    #[wasm_bindgen(constructor)]
+    pub fn new(cards: &str, sets: &str) -> Result<Search, JsError> {
+        let dump = Dump::from_json(cards, sets)?;
+        let sets = sets_by_name(dump.sets);
+        let support = support_counts(dump.cards.iter());
+        let mut cards: Vec<_> = dump.cards.iter().map(|c| SearchCard::new(c, &sets).with_support_count(support[&c.id])).collect();
+        cards.sort_unstable_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+        Ok(Self { cards })
+    }
+
+    /// IDs of all matching cards, in the same order as on the site.
+    /// Alternate artworks are not merged, so the caller may want to dedupe by name.
+    pub fn search(&self, query: &str) -> Result<Vec<u32>, JsError> {
+        let (_, filter, options, _) = parse_filters(query.trim()).map_err(|e| JsError::new(&e))?;
+        Ok(search(&self.cards, &filter, &options).into_iter().map(|c| c.id as u32).collect())
+    }
+}
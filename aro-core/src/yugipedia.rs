@@ -0,0 +1,98 @@
+//! Things the API doesn’t know, extracted from a Yugipedia dump. The extract is a JSON list like
+//! `[{"passcode": "46986414", "regions": ["OCG", "TCG"], "anime": ["Yu-Gi-Oh! Duel Monsters"], "manga": [],
+//! "trivia": [{"title": "Dark Magician/Trivia", "url": "https://yugipedia.com/wiki/Dark_Magician/Trivia"}]}]`.
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::data::{Dump, LoadError};
+
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone, Default)]
+pub struct YugipediaInfo {
+    /// Where the card was released, e.g. `OCG` or `Korean`
+    #[serde(default)]
+    pub regions: Vec<String>,
+    /// Series the card appeared in
+    #[serde(default)]
+    pub anime:   Vec<String>,
+    #[serde(default)]
+    pub manga:   Vec<String>,
+    #[serde(default)]
+    pub trivia:  Vec<TriviaLink>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone)]
+pub struct TriviaLink {
+    pub title: String,
+    pub url:   String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Entry {
+    /// A string because Yugipedia keeps the leading zeroes
+    passcode: String,
+    #[serde(flatten)]
+    info:     YugipediaInfo,
+}
+
+impl YugipediaInfo {
+    /// Lowercased media the card appeared in, for `appears:`
+    pub fn appearances(&self) -> Vec<String> {
+        [("anime", &self.anime), ("manga", &self.manga)]
+            .into_iter()
+            .filter(|(_, series)| !series.is_empty())
+            .map(|(m, _)| m.to_owned())
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty() && self.anime.is_empty() && self.manga.is_empty() && self.trivia.is_empty()
+    }
+}
+
+impl Dump {
+    /// Attaches the extract to all cards with a matching passcode and returns how many there were.
+    pub fn add_yugipedia(&mut self, json: &str) -> Result<usize, LoadError> {
+        let entries: Vec<Entry> = serde_json::from_str(json).map_err(|e| LoadError::Parse("Yugipedia extract".to_owned(), e))?;
+        let mut by_id: HashMap<usize, YugipediaInfo> = entries
+            .into_iter()
+            .filter_map(|Entry { passcode, mut info }| {
+                // These end up in links, so nothing but actual web links.
+                info.trivia.retain(|t| t.url.starts_with("https://"));
+                Some((passcode.parse().ok()?, info))
+            })
+            .collect();
+        let mut added = 0;
+        for card in &mut self.cards {
+            if let Some(info) = by_id.remove(&card.id) {
+                card.yugipedia = Some(info);
+                added += 1;
+            }
+        }
+        Ok(added)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Card;
+
+    #[test]
+    fn add_yugipedia_test() {
+        let mut dump =
+            Dump { cards: vec![Card { id: 46986414, ..Default::default() }, Card { id: 1, ..Default::default() }], sets: vec![] };
+        let json = r#"[
+            {"passcode": "46986414", "regions": ["OCG", "TCG"], "anime": ["Yu-Gi-Oh! Duel Monsters"],
+             "trivia": [{"title": "Trivia", "url": "https://yugipedia.com/wiki/Dark_Magician/Trivia"}, {"title": "Evil", "url": "javascript:alert(1)"}]},
+            {"passcode": "00000002", "manga": ["Yu-Gi-Oh! R"]},
+            {"passcode": "unknown"}
+        ]"#;
+        assert_eq!(dump.add_yugipedia(json).unwrap(), 1);
+        let info = dump.cards[0].yugipedia.as_ref().unwrap();
+        assert_eq!(info.regions, vec!["OCG", "TCG"]);
+        assert_eq!(info.trivia.len(), 1);
+        assert_eq!(info.appearances(), vec!["anime"]);
+        assert_eq!(dump.cards[1].yugipedia, None);
+        assert!(dump.add_yugipedia("{}").is_err());
+    }
+}
@@ -0,0 +1,31 @@
+//! Content negotiation with the `Accept` header.
+
+/// The media types in `header`, most preferred first. Earlier entries win ties, and anything with `q=0` is refused.
+pub fn by_preference(header: &str) -> Vec<&str> {
+    let mut types: Vec<(&str, f32)> = header
+        .split(',')
+        .filter_map(|entry| {
+            let mut parts = entry.split(';');
+            let mime = parts.next()?.trim();
+            let quality = parts.find_map(|p| p.trim().strip_prefix("q=")).map_or(Some(1.0), |q| q.trim().parse::<f32>().ok())?;
+            Some((mime, quality)).filter(|&(mime, quality)| !mime.is_empty() && quality > 0.0)
+        })
+        .collect();
+    // Stable, so ties keep their order.
+    types.sort_by(|a, b| b.1.total_cmp(&a.1));
+    types.into_iter().map(|(mime, _)| mime).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn by_preference_test() {
+        assert_eq!(by_preference("text/html, text/plain"), ["text/html", "text/plain"]);
+        assert_eq!(by_preference("text/html;q=0.5, text/plain"), ["text/plain", "text/html"]);
+        assert_eq!(by_preference("text/plain; q=0, */*"), ["*/*"]);
+        assert_eq!(by_preference("text/plain;q=nope, text/html"), ["text/html"]);
+        assert!(by_preference("").is_empty());
+    }
+}
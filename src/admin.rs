@@ -0,0 +1,254 @@
+//! The admin page and its actions, and who may write at all. See `auth.rs` for the tokens.
+use actix_web::{cookie::Cookie, http::header, route, web, HttpRequest, HttpResponse};
+use askama::Template;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::Ordering;
+use time::{format_description::well_known::Rfc2822, OffsetDateTime};
+
+use crate::{
+    api::api_response,
+    auth::{audit, Scope, TOKEN_COOKIE},
+    cookie_path,
+    jobs::refresh_prices,
+    loading::{load_data, reload_data},
+    locale,
+    memory::{map_size, MemoryStats, StructureSize},
+    pages::{AdminPage, CacheRow, Meta, NAME},
+    proxy::url,
+    state::{AppState, SharedState, State},
+    stats::format_uptime,
+    theme, AnyResult, CARD_SOURCES, CARD_VIEWS, COLLECTIONS, DATA_READY, PRICES, PRICE_HISTORY, PROTECT_WRITES, QUERY_STATS, READ_ONLY,
+    SCHEDULER, SHORTLINKS, SITEMAP, STARTED, TOKENS, TOP_QUERIES,
+};
+
+/// Whether the request may store something on the server. Writes with a token are audited.
+pub fn may_write(req: &HttpRequest, action: &str) -> bool {
+    match TOKENS.authenticate(req, Scope::Write) {
+        Some(name) => {
+            audit(req, name, action);
+            true
+        }
+        None => !*PROTECT_WRITES,
+    }
+}
+
+pub fn unauthorized() -> HttpResponse {
+    HttpResponse::Unauthorized().insert_header((header::WWW_AUTHENTICATE, "Bearer")).body("This needs a valid token")
+}
+
+fn admin_page_with(req: &HttpRequest, state: &AppState, message: Option<String>) -> AnyResult<HttpResponse> {
+    if !TOKENS.has_admin() {
+        return Ok(HttpResponse::NotFound().finish());
+    }
+    let logged_in = TOKENS.authenticate(req, Scope::Admin).is_some();
+    // Other tokens can log in too, so the cookie works for uploads with `PROTECT_WRITES`.
+    let message = match message {
+        None if !logged_in && TOKENS.authenticate(req, Scope::Write).is_some() => Some("This token can’t use the admin page".to_owned()),
+        message => message,
+    };
+    let data_ready = DATA_READY.load(Ordering::Acquire);
+    let page = AdminPage {
+        meta: Meta::new(format!("Admin - {NAME}"), "Server status".to_owned()).with_theme(theme(req)).with_locale(locale(req)),
+        logged_in,
+        message,
+        uptime: format_uptime(STARTED.elapsed()),
+        read_only: READ_ONLY.load(Ordering::Relaxed),
+        data_ready,
+        data_files: CARD_SOURCES
+            .iter()
+            .map(|path| {
+                let modified = std::fs::metadata(path).and_then(|m| m.modified()).ok().map(OffsetDateTime::from);
+                (path.clone(), modified.and_then(|m| m.format(&Rfc2822).ok()))
+            })
+            .collect(),
+        card_count: state.cards.len(),
+        set_count: state.sets_by_name.len(),
+        views: Meta::view_count(),
+        top_queries: QUERY_STATS.top(TOP_QUERIES),
+        caches: cache_rows(state),
+        jobs: SCHEDULER.statuses(),
+        memory: memory_stats(state),
+    };
+    let mut status = if logged_in { HttpResponse::Ok() } else { HttpResponse::Unauthorized() };
+    Ok(status.insert_header(header::ContentType::html()).body(page.render()?))
+}
+
+fn cache_rows(state: &AppState) -> Vec<CacheRow> {
+    vec![
+        CacheRow {
+            name:     "Query results",
+            entries:  state.results.len(),
+            lookups:  state.results.stats.lookups(),
+            hit_rate: state.results.stats.hit_rate(),
+        },
+        CacheRow {
+            name:     "Collections",
+            entries:  COLLECTIONS.cached(),
+            lookups:  COLLECTIONS.stats.lookups(),
+            hit_rate: COLLECTIONS.stats.hit_rate(),
+        },
+        CacheRow {
+            name:     "Shortlinks",
+            entries:  SHORTLINKS.len(),
+            lookups:  SHORTLINKS.stats.lookups(),
+            hit_rate: SHORTLINKS.stats.hit_rate(),
+        },
+    ]
+}
+
+/// What the card data and caches take up, see `memory.rs`.
+fn memory_stats(state: &AppState) -> MemoryStats {
+    let caches = [
+        StructureSize { name: "Prices", bytes: map_size(&PRICES.read().unwrap(), |_, _| 0) },
+        StructureSize { name: "Price history", bytes: PRICE_HISTORY.estimated_size() },
+        StructureSize { name: "Query results", bytes: state.results.estimated_size() },
+        StructureSize { name: "Collection cache", bytes: COLLECTIONS.estimated_size() },
+        StructureSize { name: "Shortlinks", bytes: SHORTLINKS.estimated_size() },
+        StructureSize { name: "Card views", bytes: CARD_VIEWS.estimated_size() },
+    ];
+    MemoryStats::now(state.sizes.iter().copied().chain(caches).collect())
+}
+
+/// The memory section of the admin page for scripts, e.g. to graph it over time.
+#[route("/admin/memory", method = "GET")]
+pub async fn admin_memory(req: HttpRequest, state: State) -> AnyResult<HttpResponse> {
+    if TOKENS.authenticate(&req, Scope::Analytics).is_none() {
+        return Ok(unauthorized());
+    }
+    api_response(&req, &mut HttpResponse::Ok(), &memory_stats(&state))
+}
+
+#[derive(Debug, Serialize)]
+struct Analytics {
+    uptime_secs: u64,
+    views:       usize,
+    top_queries: Vec<(String, usize)>,
+    caches:      Vec<CacheRow>,
+}
+
+/// The traffic numbers of the admin page for scripts.
+#[route("/admin/stats", method = "GET")]
+pub async fn admin_stats(req: HttpRequest, state: State) -> AnyResult<HttpResponse> {
+    if TOKENS.authenticate(&req, Scope::Analytics).is_none() {
+        return Ok(unauthorized());
+    }
+    let analytics = Analytics {
+        uptime_secs: STARTED.elapsed().as_secs(),
+        views:       Meta::view_count(),
+        top_queries: QUERY_STATS.top(TOP_QUERIES),
+        caches:      cache_rows(&state),
+    };
+    api_response(&req, &mut HttpResponse::Ok(), &analytics)
+}
+
+#[route("/admin", method = "GET")]
+pub async fn admin_page(req: HttpRequest, state: State) -> AnyResult<HttpResponse> {
+    admin_page_with(&req, &state, None)
+}
+
+#[derive(Debug, Deserialize)]
+struct AdminLogin {
+    token: String,
+}
+
+#[route("/admin/login", method = "POST")]
+pub async fn admin_login(req: HttpRequest, state: State, form: web::Form<AdminLogin>) -> AnyResult<HttpResponse> {
+    let token = form.into_inner().token;
+    match TOKENS.find(token.trim()) {
+        Some((name, _)) => audit(&req, name, "logged in"),
+        None => {
+            audit(&req, "unknown", "failed to log in");
+            return admin_page_with(&req, &state, Some("That token is not valid".to_owned()));
+        }
+    }
+    let mut res = HttpResponse::SeeOther().insert_header((header::LOCATION, url("/admin"))).finish();
+    // Sent everywhere so the token also works for uploads with `PROTECT_WRITES`.
+    // Strict so other sites can’t make an admin click the buttons.
+    let cookie = Cookie::build(TOKEN_COOKIE, token.trim().to_owned())
+        .path(cookie_path())
+        .http_only(true)
+        .same_site(actix_web::cookie::SameSite::Strict)
+        .finish();
+    res.add_cookie(&cookie)?;
+    Ok(res)
+}
+
+#[route("/admin/logout", method = "POST")]
+pub async fn admin_logout() -> AnyResult<HttpResponse> {
+    let mut res = HttpResponse::SeeOther().insert_header((header::LOCATION, url("/admin"))).finish();
+    res.add_removal_cookie(&Cookie::build(TOKEN_COOKIE, "").path(cookie_path()).finish())?;
+    Ok(res)
+}
+
+/// Only the prices, which is much faster than `admin_reload_data`.
+#[route("/admin/reload", method = "POST")]
+pub async fn admin_reload(req: HttpRequest, state: State) -> AnyResult<HttpResponse> {
+    let Some(name) = TOKENS.authenticate(&req, Scope::Admin) else {
+        return admin_page_with(&req, &state, None);
+    };
+    audit(&req, name, "reloaded prices");
+    let message = match DATA_READY.load(Ordering::Acquire) {
+        true => {
+            let state = state.to_arc();
+            match web::block(move || refresh_prices(&state)).await? {
+                Ok(()) => "Reloaded prices".to_owned(),
+                Err(e) => e,
+            }
+        }
+        false => "The card data isn’t loaded yet".to_owned(),
+    };
+    admin_page_with(&req, &state, Some(message))
+}
+
+/// Reads all card data again and swaps it in, e.g. after the dumps were updated.
+#[route("/admin/reload-data", method = "POST")]
+pub async fn admin_reload_data(req: HttpRequest, shared: web::Data<SharedState>) -> AnyResult<HttpResponse> {
+    let Some(name) = TOKENS.authenticate(&req, Scope::Admin) else {
+        return admin_page_with(&req, &shared.load(), None);
+    };
+    audit(&req, name, "reloaded the card data");
+    let loaded = {
+        let shared = shared.clone();
+        web::block(move || match DATA_READY.load(Ordering::Acquire) {
+            true => reload_data(&shared),
+            false => load_data(&shared).map(|()| shared.load().cards.len()).map_err(|e| e.to_string()),
+        })
+        .await?
+    };
+    let message = match loaded {
+        Ok(cards) => format!("Reloaded {cards} cards"),
+        Err(e) => format!("Could not reload the card data: {e}"),
+    };
+    admin_page_with(&req, &shared.load(), Some(message))
+}
+
+#[route("/admin/clear-cache", method = "POST")]
+pub async fn admin_clear_cache(req: HttpRequest, state: State) -> AnyResult<HttpResponse> {
+    let Some(name) = TOKENS.authenticate(&req, Scope::Admin) else {
+        return admin_page_with(&req, &state, None);
+    };
+    let (results, collections) = (state.results.clear(), COLLECTIONS.clear_cache());
+    SITEMAP.clear();
+    let cleared = format!("{results} cached query results, {collections} cached collections, and the sitemap");
+    audit(&req, name, &format!("cleared {cleared}"));
+    admin_page_with(&req, &state, Some(format!("Cleared {cleared}")))
+}
+
+#[derive(Debug, Deserialize)]
+struct ReadOnlyForm {
+    enabled: bool,
+}
+
+#[route("/admin/read-only", method = "POST")]
+pub async fn admin_read_only(req: HttpRequest, state: State, form: web::Form<ReadOnlyForm>) -> AnyResult<HttpResponse> {
+    let Some(name) = TOKENS.authenticate(&req, Scope::Admin) else {
+        return admin_page_with(&req, &state, None);
+    };
+    READ_ONLY.store(form.enabled, Ordering::Relaxed);
+    let (action, message) = match form.enabled {
+        true => ("enabled read-only mode", "Read-only mode is on"),
+        false => ("disabled read-only mode", "Read-only mode is off"),
+    };
+    audit(&req, name, action);
+    admin_page_with(&req, &state, Some(message.to_owned()))
+}
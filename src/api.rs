@@ -0,0 +1,367 @@
+//! The endpoints for scripts and the search-as-you-type box, see the API section in the readme.
+use actix_web::{
+    http::{header, StatusCode},
+    route, web, HttpRequest, HttpResponse, HttpResponseBuilder,
+};
+use aro_core::{query::MAX_COST, scryfall};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::Ordering;
+use time::{Date, OffsetDateTime};
+
+use crate::{
+    apiformat::ApiFormat,
+    browse::released_on_day_of,
+    changes::Change,
+    collection,
+    collection::Collection,
+    cursor::Cursor,
+    data::Prices,
+    errors::api_error,
+    i18n::Locale,
+    parser,
+    searchpage::matching_cards,
+    state::{AppState, State},
+    AnyResult, CHANGES, DATA_READY, IMG_HOST, PAGE_SIZE, PRICES, PRICE_WATCHES,
+};
+
+// This is called on every keystroke, so it’s kept intentionally small.
+const QUICKSEARCH_RESULTS: usize = 10;
+const QUICKSEARCH_MAX_LEN: usize = 64;
+// Enough for a large binder or buylist in one request
+const MAX_PRICE_IDS: usize = 5000;
+// Enough to check a whole deck in one request
+const MAX_BATCH_QUERIES: usize = 100;
+/// For the estimated cost of all queries in a batch together (see `Query::estimated_cost`), i.e. a handful of expensive ones.
+const MAX_BATCH_COST: u32 = 5 * MAX_COST;
+const COMBO_DEPTH: usize = 2;
+// Staples like Dark Magician are mentioned by so many cards that there would be thousands of chains otherwise.
+const MAX_COMBOS: usize = 200;
+
+/// JSON, or MessagePack or CBOR if the client asks for them, see `apiformat.rs`.
+pub fn api_response(req: &HttpRequest, response: &mut HttpResponseBuilder, value: &impl Serialize) -> AnyResult<HttpResponse> {
+    let format = ApiFormat::from_accept(req.headers().get(header::ACCEPT).and_then(|a| a.to_str().ok()));
+    let body = format.encode(value)?;
+    Ok(response.insert_header((header::CONTENT_TYPE, format.content_type())).insert_header((header::VARY, "Accept")).body(body))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QuickQuery {
+    pub q: String,
+}
+
+#[derive(Debug, Serialize)]
+struct QuickResult<'a> {
+    id:    usize,
+    name:  &'a str,
+    thumb: String,
+}
+
+/// Name-only search for search-as-you-type. Unlike `/`, this doesn’t parse the query at all.
+#[route("/api/quicksearch", method = "GET")]
+pub async fn quicksearch(req: HttpRequest, state: State, q: web::Query<QuickQuery>) -> AnyResult<HttpResponse> {
+    if !DATA_READY.load(Ordering::Acquire) {
+        return Ok(api_error(&req, StatusCode::SERVICE_UNAVAILABLE, "The card data is currently unavailable"));
+    }
+    let q: String = parser::normalize_query(q.q.trim()).to_lowercase().chars().take(QUICKSEARCH_MAX_LEN).collect();
+    let results: Vec<_> = name_matches(&state.name_index, &q, QUICKSEARCH_RESULTS)
+        .into_iter()
+        .filter_map(|id| state.cards_by_id.get(&id))
+        .map(|c| QuickResult { id: c.id, name: c.name.as_str(), thumb: format!("{}/static/thumb/{}.jpg", IMG_HOST.as_str(), c.id) })
+        .collect();
+    // The data rarely changes, so clients and proxies can keep these for a while.
+    api_response(&req, HttpResponse::Ok().insert_header((header::CACHE_CONTROL, "public, max-age=3600")), &results)
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiSearchQuery {
+    q:      String,
+    /// `next_cursor` of the previous page
+    cursor: Option<String>,
+    /// results per page
+    n:      Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct ApiCard<'a> {
+    id:        usize,
+    name:      &'a str,
+    #[serde(rename = "type")]
+    card_type: &'a str,
+    text:      &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct ApiSearchResults<'a> {
+    total:       usize,
+    cards:       Vec<ApiCard<'a>>,
+    /// `None` on the last page
+    next_cursor: Option<String>,
+}
+
+/// Search results as JSON. Instead of page numbers, every page has a cursor for the next one, see `cursor.rs`.
+#[route("/api/search", method = "GET")]
+pub async fn search_api(req: HttpRequest, state: State, q: web::Query<ApiSearchQuery>) -> AnyResult<HttpResponse> {
+    if !DATA_READY.load(Ordering::Acquire) {
+        return Ok(api_error(&req, StatusCode::SERVICE_UNAVAILABLE, "The card data is currently unavailable"));
+    }
+    let cursor = match q.cursor.as_deref().map(str::parse::<Cursor>).transpose() {
+        Ok(c) => c,
+        Err(e) => return Ok(api_error(&req, StatusCode::BAD_REQUEST, &e)),
+    };
+    match api_results(&state, &q.q, cursor, api_page_size(q.n), collection(&req).as_deref()) {
+        Ok(results) => api_response(&req, &mut HttpResponse::Ok(), &results),
+        Err(e) => Ok(api_error(&req, StatusCode::BAD_REQUEST, &e)),
+    }
+}
+
+fn api_page_size(n: Option<usize>) -> usize {
+    n.map_or(PAGE_SIZE, |n| n.clamp(1, PAGE_SIZE))
+}
+
+/// The page of results after `cursor`, or the first one. The error is the message for the client.
+fn api_results<'a>(
+    state: &'a AppState,
+    query: &str,
+    cursor: Option<Cursor>,
+    page_size: usize,
+    collection: Option<&Collection>,
+) -> Result<ApiSearchResults<'a>, String> {
+    let matches = matching_cards(state, query, collection, Locale::En)?.cards;
+    let start = cursor.map_or(0, |c| c.resume(matches.iter().map(|c| c.id))).min(matches.len());
+    let page = &matches[start..(start + page_size).min(matches.len())];
+    let next_cursor = match page.last() {
+        Some(last) if start + page.len() < matches.len() => Some(Cursor::new(start + page.len(), last.id).to_string()),
+        _ => None,
+    };
+    let cards = page
+        .iter()
+        .map(|c| state.plain_card(c.id).unwrap_or(c))
+        .map(|c| ApiCard { id: c.id, name: c.name.as_str(), card_type: c.card_type.as_str(), text: c.text.as_str() })
+        .collect();
+    Ok(ApiSearchResults { total: matches.len(), cards, next_cursor })
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchQuery {
+    /// results per query
+    n: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum BatchResult<'a> {
+    Results(ApiSearchResults<'a>),
+    Error { error: String },
+}
+
+/// Takes a JSON list of queries and returns the first page of results for each, in the same order.
+/// A query that fails doesn’t fail the others, it just gets `{"error": "…"}` instead.
+#[route("/api/searches", method = "POST")]
+pub async fn batch_search_api(req: HttpRequest, state: State, q: web::Query<BatchQuery>, body: web::Bytes) -> AnyResult<HttpResponse> {
+    if !DATA_READY.load(Ordering::Acquire) {
+        return Ok(api_error(&req, StatusCode::SERVICE_UNAVAILABLE, "The card data is currently unavailable"));
+    }
+    let queries: Vec<String> = match serde_json::from_slice(&body) {
+        Ok(queries) => queries,
+        Err(e) => return Ok(api_error(&req, StatusCode::BAD_REQUEST, &format!("Invalid request: {e}"))),
+    };
+    if queries.len() > MAX_BATCH_QUERIES {
+        return Ok(api_error(&req, StatusCode::BAD_REQUEST, &format!("At most {MAX_BATCH_QUERIES} queries per request")));
+    }
+    // Queries that don’t parse cost nothing, they just get their error.
+    let cost = queries
+        .iter()
+        .filter_map(|query| parser::parse_filters(&scryfall::translate(query.trim()).ok()?.query).ok())
+        .map(|(query, ..)| query.estimated_cost())
+        .fold(0, u32::saturating_add);
+    if cost > MAX_BATCH_COST {
+        let message = format!("These queries are too expensive to run together (cost {cost}, the limit is {MAX_BATCH_COST})");
+        return Ok(api_error(&req, StatusCode::BAD_REQUEST, &message));
+    }
+    let format = ApiFormat::from_accept(req.headers().get(header::ACCEPT).and_then(|a| a.to_str().ok()));
+    let (state, collection, page_size) = (state.to_arc(), collection(&req), api_page_size(q.n));
+    // Up to a hundred searches would hold up everything else on this worker.
+    let body = web::block(move || {
+        let results: Vec<_> = queries
+            .iter()
+            .map(|query| match api_results(&state, query, None, page_size, collection.as_deref()) {
+                Ok(results) => BatchResult::Results(results),
+                Err(error) => BatchResult::Error { error },
+            })
+            .collect();
+        format.encode(&results).map_err(|e| e.to_string())
+    })
+    .await??;
+    Ok(HttpResponse::Ok().insert_header((header::CONTENT_TYPE, format.content_type())).insert_header((header::VARY, "Accept")).body(body))
+}
+
+/// Names starting with the query come first, then names containing it anywhere.
+fn name_matches(index: &[(String, usize)], q: &str, limit: usize) -> Vec<usize> {
+    if q.is_empty() {
+        return Vec::new();
+    }
+    let start = index.partition_point(|(name, _)| name.as_str() < q);
+    let prefix_matches = index[start..].iter().take_while(|(name, _)| name.starts_with(q));
+    let other_matches = index.iter().filter(|(name, _)| !name.starts_with(q) && name.contains(q));
+    prefix_matches.chain(other_matches).map(|&(_, id)| id).take(limit).collect()
+}
+
+#[derive(Debug, Serialize)]
+struct ReleaseResult<'a> {
+    id:       usize,
+    name:     &'a str,
+    released: Date,
+}
+
+#[route("/api/onthisday", method = "GET")]
+pub async fn on_this_day_api(req: HttpRequest, state: State) -> AnyResult<HttpResponse> {
+    if !DATA_READY.load(Ordering::Acquire) {
+        return Ok(api_error(&req, StatusCode::SERVICE_UNAVAILABLE, "The card data is currently unavailable"));
+    }
+    let results: Vec<_> = released_on_day_of(&state, OffsetDateTime::now_utc().date())
+        .into_iter()
+        .map(|(released, c)| ReleaseResult { id: c.id, name: c.name.as_str(), released })
+        .collect();
+    api_response(&req, &mut HttpResponse::Ok(), &results)
+}
+
+/// A hash of the card data, when it was loaded, and how many cards there are, see `dataversion.rs`.
+#[route("/api/dataversion", method = "GET")]
+pub async fn data_version_api(req: HttpRequest, state: State) -> AnyResult<HttpResponse> {
+    match &state.version {
+        Some(version) => api_response(&req, &mut HttpResponse::Ok(), version),
+        None => Ok(api_error(&req, StatusCode::SERVICE_UNAVAILABLE, "The card data is currently unavailable")),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct ChangesQuery {
+    /// A `hash` from `/api/dataversion`
+    since: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ChangesResponse<'a> {
+    /// The current data version, for the next request
+    version: &'a str,
+    changes: Vec<Change>,
+}
+
+/// Everything that changed since an earlier data version, see `changes.rs`.
+/// Versions from before we started recording are unknown, so clients have to download the whole dump then.
+#[route("/api/changes", method = "GET")]
+pub async fn changes_api(req: HttpRequest, state: State, q: web::Query<ChangesQuery>) -> AnyResult<HttpResponse> {
+    let Some(version) = &state.version else {
+        return Ok(api_error(&req, StatusCode::SERVICE_UNAVAILABLE, "The card data is currently unavailable"));
+    };
+    match CHANGES.since(&q.since) {
+        Some(changes) => api_response(&req, &mut HttpResponse::Ok(), &ChangesResponse { version: &version.hash, changes }),
+        None => Ok(api_error(&req, StatusCode::NOT_FOUND, &format!("Unknown data version {}. Download the whole dump instead.", q.since))),
+    }
+}
+
+/// Every search field with its operators and, where there are only a few, its values.
+#[route("/api/fields", method = "GET")]
+pub async fn fields_api(req: HttpRequest, state: State) -> AnyResult<HttpResponse> {
+    if !DATA_READY.load(Ordering::Acquire) {
+        return Ok(api_error(&req, StatusCode::SERVICE_UNAVAILABLE, "The card data is currently unavailable"));
+    }
+    api_response(&req, HttpResponse::Ok().insert_header((header::CACHE_CONTROL, "public, max-age=3600")), &state.field_info)
+}
+
+#[derive(Debug, Deserialize)]
+struct PriceAlertsQuery {
+    /// Unix timestamp, so bots can only fetch what they haven’t seen yet
+    since: Option<i64>,
+}
+
+#[route("/api/pricewatch/alerts", method = "GET")]
+pub async fn price_alerts_api(req: HttpRequest, q: web::Query<PriceAlertsQuery>) -> AnyResult<HttpResponse> {
+    api_response(&req, &mut HttpResponse::Ok(), &PRICE_WATCHES.alerts(q.since))
+}
+
+#[derive(Debug, Deserialize)]
+struct PricesRequest {
+    ids: Vec<usize>,
+}
+
+#[derive(Debug, Serialize)]
+struct CardPrices {
+    id:     usize,
+    #[serde(flatten)]
+    prices: Prices,
+}
+
+/// Takes `{"ids": [...]}` and returns the current prices of all known cards in the same order.
+/// Unknown IDs are left out.
+#[route("/api/prices", method = "POST")]
+pub async fn bulk_prices(req: HttpRequest, state: State, body: web::Bytes) -> AnyResult<HttpResponse> {
+    if !DATA_READY.load(Ordering::Acquire) {
+        return Ok(api_error(&req, StatusCode::SERVICE_UNAVAILABLE, "The card data is currently unavailable"));
+    }
+    let request: PricesRequest = match serde_json::from_slice(&body) {
+        Ok(r) => r,
+        Err(e) => return Ok(api_error(&req, StatusCode::BAD_REQUEST, &format!("Invalid request: {e}"))),
+    };
+    if request.ids.len() > MAX_PRICE_IDS {
+        return Ok(api_error(&req, StatusCode::BAD_REQUEST, &format!("At most {MAX_PRICE_IDS} IDs per request")));
+    }
+    let prices = PRICES.read().unwrap();
+    let cards: Vec<_> = request
+        .ids
+        .into_iter()
+        .filter(|id| state.cards_by_id.contains_key(id))
+        .map(|id| CardPrices { id, prices: prices.get(&id).copied().unwrap_or_default() })
+        .collect();
+    api_response(&req, &mut HttpResponse::Ok(), &cards)
+}
+
+#[derive(Debug, Deserialize)]
+struct CombosQuery {
+    id: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct ChainLink<'a> {
+    id:   usize,
+    name: &'a str,
+}
+
+/// Chains of cards that mention each other, e.g. searcher → target → payoff, that include the given card.
+#[route("/api/combos", method = "GET")]
+pub async fn combos(req: HttpRequest, state: State, q: web::Query<CombosQuery>) -> AnyResult<HttpResponse> {
+    if !DATA_READY.load(Ordering::Acquire) {
+        return Ok(api_error(&req, StatusCode::SERVICE_UNAVAILABLE, "The card data is currently unavailable"));
+    }
+    let Some(card) = state.cards_by_id.get(&q.id) else {
+        return Ok(api_error(&req, StatusCode::NOT_FOUND, "Card not found"));
+    };
+    let chains: Vec<Vec<_>> = state
+        .mentions
+        .chains(state.canonical_version(card).id, COMBO_DEPTH)
+        .into_iter()
+        .take(MAX_COMBOS)
+        .map(|chain| {
+            chain.into_iter().filter_map(|id| state.cards_by_id.get(&id)).map(|c| ChainLink { id: c.id, name: c.name.as_str() }).collect()
+        })
+        .collect();
+    api_response(&req, &mut HttpResponse::Ok(), &chains)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name_matches_test() {
+        let index: Vec<_> = ["blue-eyes white dragon", "dark magician", "dark magician girl", "the dark magicians"]
+            .into_iter()
+            .enumerate()
+            .map(|(id, name)| (name.to_owned(), id))
+            .collect();
+        assert_eq!(name_matches(&index, "dark magician", 10), vec![1, 2, 3]);
+        assert_eq!(name_matches(&index, "dark", 1), vec![1]);
+        assert_eq!(name_matches(&index, "dragon", 10), vec![0]);
+        assert_eq!(name_matches(&index, "", 10), Vec::<usize>::new());
+    }
+}
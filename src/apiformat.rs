@@ -0,0 +1,80 @@
+//! MessagePack and CBOR as more compact alternatives to JSON for the API, picked with the `Accept` header.
+use serde::Serialize;
+use std::error::Error;
+
+use crate::accept;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum ApiFormat {
+    #[default]
+    Json,
+    MessagePack,
+    Cbor,
+}
+
+impl ApiFormat {
+    /// The client’s most preferred of our formats. Anything else, including no header, gets JSON.
+    pub fn from_accept(accept: Option<&str>) -> Self {
+        accept
+            .map(accept::by_preference)
+            .unwrap_or_default()
+            .into_iter()
+            .find_map(|mime| match mime {
+                "application/json" => Some(Self::Json),
+                "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => Some(Self::MessagePack),
+                "application/cbor" => Some(Self::Cbor),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::MessagePack => "application/msgpack",
+            Self::Cbor => "application/cbor",
+        }
+    }
+
+    /// The same structure in every format, i.e. structs are maps with the field names as keys.
+    pub fn encode(self, value: &impl Serialize) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(match self {
+            Self::Json => serde_json::to_vec(value)?,
+            Self::MessagePack => rmp_serde::to_vec_named(value)?,
+            Self::Cbor => {
+                let mut bytes = Vec::new();
+                ciborium::into_writer(value, &mut bytes)?;
+                bytes
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn from_accept_test() {
+        assert_eq!(ApiFormat::from_accept(None), ApiFormat::Json);
+        assert_eq!(ApiFormat::from_accept(Some("*/*")), ApiFormat::Json);
+        assert_eq!(ApiFormat::from_accept(Some("application/msgpack")), ApiFormat::MessagePack);
+        assert_eq!(ApiFormat::from_accept(Some("text/html, application/cbor;q=0.9, application/json")), ApiFormat::Json);
+        assert_eq!(ApiFormat::from_accept(Some("text/html, application/cbor, application/json")), ApiFormat::Cbor);
+        assert_eq!(ApiFormat::from_accept(Some("application/msgpack;q=0")), ApiFormat::Json);
+        assert_eq!(ApiFormat::from_accept(Some("application/json;q=0.1, application/msgpack;q=0.5")), ApiFormat::MessagePack);
+        assert_eq!(ApiFormat::from_accept(Some("application/json, application/x-msgpack")), ApiFormat::Json);
+    }
+
+    #[test]
+    fn encode_test() {
+        let value = BTreeMap::from([("id", 46986414)]);
+        assert_eq!(ApiFormat::Json.encode(&value).unwrap(), br#"{"id":46986414}"#);
+        let msgpack = ApiFormat::MessagePack.encode(&value).unwrap();
+        assert_eq!(rmp_serde::from_slice::<BTreeMap<String, u32>>(&msgpack).unwrap(), BTreeMap::from([("id".to_owned(), 46986414)]));
+        let cbor = ApiFormat::Cbor.encode(&value).unwrap();
+        assert_eq!(ciborium::from_reader::<BTreeMap<String, u32>, _>(&cbor[..]).unwrap(), BTreeMap::from([("id".to_owned(), 46986414)]));
+        assert!(msgpack.len() < 15 && cbor.len() < 15);
+    }
+}
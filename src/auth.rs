@@ -0,0 +1,117 @@
+//! Bearer tokens for the admin page, the stats, and, if `PROTECT_WRITES` is set, for everything that stores data on the server.
+use std::str::FromStr;
+
+use actix_web::{http::header, HttpRequest};
+
+use crate::proxy::client_ip;
+
+pub const TOKEN_COOKIE: &str = "token";
+
+/// What a token may do. Each scope includes the ones before it.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Scope {
+    /// Storing data with `PROTECT_WRITES`, e.g. uploading collections
+    #[default]
+    Write,
+    /// Reading the stats, e.g. `/admin/memory`
+    Analytics,
+    /// The admin page and its actions
+    Admin,
+}
+
+impl FromStr for Scope {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "write" => Ok(Self::Write),
+            "analytics" => Ok(Self::Analytics),
+            "admin" => Ok(Self::Admin),
+            _ => Err(format!("Unknown token scope {s}")),
+        }
+    }
+}
+
+/// Named tokens, so the audit log can tell who did something without logging the tokens themselves.
+#[derive(Debug, Default)]
+pub struct Tokens(Vec<(String, String, Scope)>);
+
+impl Tokens {
+    /// `API_TOKENS` is a comma-separated list of `name:token` or `name:token:scope`. `ADMIN_TOKEN` is another token called `admin`
+    /// with the admin scope.
+    pub fn from_env() -> Self {
+        let mut tokens = Self::parse(&std::env::var("API_TOKENS").unwrap_or_default());
+        if let Some(token) = std::env::var("ADMIN_TOKEN").ok().filter(|t| !t.is_empty()) {
+            tokens.0.push(("admin".to_owned(), token, Scope::Admin));
+        }
+        tokens
+    }
+
+    /// Entries without a name or token, or with an unknown scope, are skipped.
+    fn parse(s: &str) -> Self {
+        Self(
+            s.split(',')
+                .filter_map(|entry| {
+                    let mut parts = entry.trim().splitn(3, ':');
+                    let (name, token) = (parts.next()?, parts.next()?);
+                    let scope = match parts.next() {
+                        Some(scope) => scope.parse().map_err(|e| eprintln!("{e}, ignoring the token called {name}")).ok()?,
+                        None => Scope::default(),
+                    };
+                    Some((name, token, scope))
+                })
+                .filter(|(name, token, _)| !name.is_empty() && !token.is_empty())
+                .map(|(name, token, scope)| (name.to_owned(), token.to_owned(), scope))
+                .collect(),
+        )
+    }
+
+    /// Whether the admin page exists at all.
+    pub fn has_admin(&self) -> bool {
+        self.0.iter().any(|(_, _, scope)| *scope == Scope::Admin)
+    }
+
+    /// The name and scope of the matching token. Checks all of them so the response time doesn’t tell which one was close.
+    pub fn find(&self, given: &str) -> Option<(&str, Scope)> {
+        self.0.iter().fold(None, |found, (name, token, scope)| match constant_time_eq(given.as_bytes(), token.as_bytes()) {
+            true => Some((name, *scope)),
+            false => found,
+        })
+    }
+
+    /// The name of the token if it has at least `scope`.
+    /// From `Authorization: Bearer …` for scripts or the cookie set by logging in on the admin page for browsers.
+    pub fn authenticate(&self, req: &HttpRequest, scope: Scope) -> Option<&str> {
+        let bearer = req.headers().get(header::AUTHORIZATION).and_then(|h| h.to_str().ok()?.strip_prefix("Bearer ").map(str::to_owned));
+        let given = bearer.or_else(|| Some(req.cookie(TOKEN_COOKIE)?.value().to_owned()))?;
+        self.find(given.trim()).filter(|&(_, s)| s >= scope).map(|(name, _)| name)
+    }
+}
+
+/// Compares every byte so the response time doesn’t tell how much of the token was right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Everything done with a token ends up here, so it can be traced back to the token and address.
+pub fn audit(req: &HttpRequest, name: &str, action: &str) {
+    println!("Audit: {name} ({}) {action}", client_ip(req));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_test() {
+        let tokens = Tokens::parse("ci:abc123, bot:xyz:analytics,:nameless,empty:,broken,ops:root:admin,typo:t0ken:admn");
+        assert_eq!(tokens.find("abc123"), Some(("ci", Scope::Write)));
+        assert_eq!(tokens.find("xyz"), Some(("bot", Scope::Analytics)));
+        assert_eq!(tokens.find("root"), Some(("ops", Scope::Admin)));
+        assert_eq!(tokens.find("abc12"), None);
+        assert_eq!(tokens.find("nameless"), None);
+        assert_eq!(tokens.find("t0ken"), None);
+        assert_eq!(tokens.find(""), None);
+        assert!(tokens.has_admin());
+        assert!(!Tokens::parse("ci:abc123").has_admin());
+    }
+}
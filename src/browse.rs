@@ -0,0 +1,168 @@
+//! Pages for looking around without a query: help, rulings, sets, trending cards, and what came out on this day.
+use actix_web::{route, web, HttpRequest, HttpResponse};
+use itertools::Itertools;
+use serde::Deserialize;
+use std::sync::atomic::Ordering;
+use time::{Date, OffsetDateTime};
+
+use crate::{
+    collection,
+    data::{set_entries, Card},
+    filter, html, is_building_deck, locale, maintenance_page,
+    pages::{HelpPage, MessagePage, Meta, OnThisDayPage, RulingsPage, SetOverview, SetPage, TrendingPage, NAME},
+    rulings,
+    searchpage::{owned_counts, unreleased},
+    state::{AppState, State},
+    theme,
+    views::TRENDING_DAYS,
+    AnyResult, CARD_VIEWS, DATA_READY, SITEMAP,
+};
+
+const TRENDING_CARDS: usize = 50;
+
+#[route("/help", method = "GET", method = "HEAD")]
+pub async fn help(req: HttpRequest) -> AnyResult<HttpResponse> {
+    html(&HelpPage {
+        meta:              Meta::new(format!("{} - {NAME}", locale(&req).tr("Query Syntax")), String::new())
+            .with_theme(theme(&req))
+            .with_locale(locale(&req)),
+        unreprinted_years: filter::UNREPRINTED_YEARS.load(Ordering::Relaxed),
+    })
+}
+
+/// More than enough for a judge looking something up, and the page stays small.
+const MAX_RULING_CARDS: usize = 50;
+
+#[derive(Debug, Deserialize)]
+struct RulingsQuery {
+    #[serde(default)]
+    q: String,
+}
+
+#[route("/rulings", method = "GET", method = "HEAD")]
+pub async fn rulings_page(req: HttpRequest, state: State, q: web::Query<RulingsQuery>) -> AnyResult<HttpResponse> {
+    if !DATA_READY.load(Ordering::Acquire) {
+        return maintenance_page(&req);
+    }
+    let today = OffsetDateTime::now_utc().date();
+    let canonical = state
+        .search_cards
+        .iter()
+        .filter(|c| !c.is_custom() && !c.is_unreleased(today))
+        .filter_map(|c| Some((c, state.cards_by_id.get(&c.id)?)))
+        .filter(|(_, c)| state.canonical_version(c).id == c.id);
+    let mut results = rulings::search(canonical, &q.q);
+    let total = results.len();
+    results.truncate(MAX_RULING_CARDS);
+    html(&RulingsPage {
+        meta: Meta::new(format!("{} - {NAME}", locale(&req).tr("Rulings")), locale(&req).tr("Search the text of rulings").to_owned())
+            .with_theme(theme(&req))
+            .with_locale(locale(&req)),
+        query: q.into_inner().q,
+        total,
+        results,
+    })
+}
+
+#[route("/sitemap.xml", method = "GET", method = "HEAD")]
+pub async fn sitemap_xml(req: HttpRequest, state: State) -> AnyResult<HttpResponse> {
+    if !DATA_READY.load(Ordering::Acquire) {
+        return maintenance_page(&req);
+    }
+    let state = state.to_arc();
+    let xml = web::block(move || SITEMAP.get(&state, OffsetDateTime::now_utc().date())).await?;
+    Ok(HttpResponse::Ok().content_type("application/xml").body(xml.to_string()))
+}
+
+#[route("/onthisday", method = "GET", method = "HEAD")]
+pub async fn on_this_day(req: HttpRequest, state: State) -> AnyResult<HttpResponse> {
+    let theme = theme(&req);
+    if !DATA_READY.load(Ordering::Acquire) {
+        return maintenance_page(&req);
+    }
+    let today = OffsetDateTime::now_utc().date();
+    let day = format!("{} {}", today.month(), today.day());
+    let cards = released_on_day_of(&state, today);
+    html(&OnThisDayPage {
+        owned: owned_counts(&state, collection(&req).as_deref(), &cards.iter().map(|&(_, c)| c).collect_vec()),
+        unreleased: unreleased(&state, &cards.iter().map(|&(_, c)| c).collect_vec()),
+        deck_mode: is_building_deck(&req),
+        meta: Meta::new(
+            format!("{} - {NAME}", locale(&req).trf("Released on {day}", &[("day", &day)])),
+            locale(&req).trf("{n} cards were released on {day}", &[("n", &cards.len()), ("day", &day)]),
+        )
+        .with_theme(theme)
+        .with_locale(locale(&req)),
+        day,
+        years: cards
+            .chunk_by(|(a, _), (b, _)| a.year() == b.year())
+            .map(|chunk| (chunk[0].0.year(), chunk.iter().map(|&(_, c)| c).collect()))
+            .collect(),
+    })
+}
+
+#[route("/trending", method = "GET", method = "HEAD")]
+pub async fn trending(req: HttpRequest, state: State) -> AnyResult<HttpResponse> {
+    let theme = theme(&req);
+    if !DATA_READY.load(Ordering::Acquire) {
+        return maintenance_page(&req);
+    }
+    let cards: Vec<_> = CARD_VIEWS
+        .trending(OffsetDateTime::now_utc(), TRENDING_CARDS)
+        .into_iter()
+        .filter_map(|(id, views)| Some((state.cards_by_id.get(&id)?, views)))
+        .filter(|(card, _)| !card.custom)
+        .collect();
+    html(&TrendingPage {
+        meta: Meta::new(
+            format!("{} - {NAME}", locale(&req).tr("Trending")),
+            locale(&req).trf("The most viewed cards of the last {n} days", &[("n", &TRENDING_DAYS)]),
+        )
+        .with_theme(theme)
+        .with_locale(locale(&req)),
+        days: TRENDING_DAYS,
+        cards,
+    })
+}
+
+/// Cards whose first TCG printing shares the month and day of `date`, newest first.
+pub fn released_on_day_of(state: &AppState, date: Date) -> Vec<(Date, &Card)> {
+    state
+        .search_cards
+        .iter()
+        .rev()
+        .filter(|c| !c.is_custom() && c.released_on_day_of(date))
+        .filter_map(|c| Some((c.release_date()?, state.canonical_version(state.cards_by_id.get(&c.id)?))))
+        .unique_by(|(_, c)| c.id)
+        .collect()
+}
+
+#[route("/set/{code}", method = "GET", method = "HEAD")]
+pub async fn set_overview(req: HttpRequest, state: State, code: web::Path<String>) -> AnyResult<HttpResponse> {
+    let theme = theme(&req);
+    if !DATA_READY.load(Ordering::Acquire) {
+        return maintenance_page(&req);
+    }
+    let code = code.to_uppercase();
+    let entries = set_entries(state.cards.iter(), &code);
+    let Some(first) = entries.first() else {
+        return html(&MessagePage {
+            meta:    Meta::new(format!("{} - {NAME}", locale(&req).tr("Set not found")), String::new())
+                .with_theme(theme)
+                .with_locale(locale(&req)),
+            message: locale(&req).trf("There is no set with the code {code}", &[("code", &code)]),
+        });
+    };
+    let name = first.card.card_sets.iter().find(|s| s.set_code == first.code).map(|s| s.set_name.as_str()).unwrap_or_default();
+    html(&SetPage {
+        meta: Meta::new(
+            format!("{name} - {NAME}"),
+            locale(&req).trf("Prices and rarities of {name} ({code})", &[("name", &name), ("code", &code)]),
+        )
+        .with_theme(theme)
+        .with_locale(locale(&req)),
+        overview: SetOverview::new(&entries),
+        name,
+        code,
+    })
+}
@@ -0,0 +1,289 @@
+//! A card’s own page, and the QR code and embed image that link to it.
+use actix_web::{http::header, route, web, HttpRequest, HttpResponse};
+use askama::Template;
+use image::ImageError;
+use itertools::Itertools;
+use serde::Deserialize;
+use std::{
+    io,
+    sync::{atomic::Ordering, Arc},
+};
+use time::OffsetDateTime;
+
+use crate::{
+    card_lists, collection,
+    collection::Collection,
+    data::{Card, Format},
+    html,
+    i18n::Locale,
+    lists::CardLists,
+    locale, maintenance_page, page_size,
+    pages::{CardPage, Legality, MessagePage, Meta, PriceRange, Printing, PrintingOrder, ResultNavigation, Theme, NAME},
+    plain,
+    proxy::{client_ip, public_url, url},
+    qr, render,
+    searchpage::{find_matching_cards, plain_text, vary_accept, wants_plain_text},
+    state::{AppState, State},
+    theme, AnyResult, BANLISTS, CARD_VIEWS, DATA_READY, IMG_HOST, MIN_PAGE_SIZE, OG_IMAGES, PAGE_SIZE, PRICE_HISTORY, PUBLIC_URL,
+};
+
+const SIMILAR_CARDS: usize = 8;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct CardQuery {
+    #[serde(default)]
+    printings: PrintingOrder,
+    #[serde(default)]
+    prices:    PriceRange,
+    /// The query whose results the card was opened from
+    from:      Option<String>,
+    /// Position of the card in those results, counting from 0 across all pages
+    i:         Option<usize>,
+    /// Page size of those results, for the link back to them
+    n:         Option<usize>,
+    plain:     Option<String>,
+}
+
+impl CardQuery {
+    /// Everything that isn’t the default, for links back to the same page
+    fn pairs(&self) -> Vec<(&'static str, String)> {
+        let printings = (self.printings != PrintingOrder::default()).then(|| ("printings", self.printings.to_string()));
+        let prices = (self.prices != PriceRange::default()).then(|| ("prices", self.prices.to_string()));
+        let from = self.from.clone().map(|from| ("from", from));
+        let navigation = [("i", self.i), ("n", self.n)].into_iter().filter_map(|(key, value)| Some((key, value?.to_string())));
+        printings.into_iter().chain(prices).chain(from).chain(navigation).collect()
+    }
+}
+
+#[route("/card/{id}", method = "GET", method = "HEAD")]
+pub async fn card_info(
+    req: HttpRequest,
+    state: State,
+    card_id: web::Path<usize>,
+    params: web::Query<CardQuery>,
+) -> AnyResult<HttpResponse> {
+    let theme = theme(&req);
+    if !DATA_READY.load(Ordering::Acquire) {
+        return maintenance_page(&req);
+    }
+    match state.cards_by_id.get(&card_id) {
+        Some(card) => {
+            // Alternate artworks count for the card itself. Custom cards don’t trend.
+            if !card.custom {
+                CARD_VIEWS.record(state.canonical_version(card).id, &client_ip(&req), OffsetDateTime::now_utc());
+            }
+            if wants_plain_text(&req, params.plain.as_deref()) {
+                let unrendered = state.plain_card(card.id).unwrap_or(card);
+                return Ok(plain_text(plain::card_page(unrendered, &legalities(card))?));
+            }
+            let page = card_page(&Visitor::of(&req), &state, card, &params)?.render()?;
+            Ok(HttpResponse::Ok().insert_header(header::ContentType::html()).insert_header(vary_accept()).body(page))
+        }
+        None => {
+            let locale = locale(&req);
+            let title = format!("{} - {NAME}", locale.tr("Card not found"));
+            html(&MessagePage {
+                meta:    Meta::new(title.clone(), title).with_theme(theme).with_locale(locale),
+                message: locale.tr("Card not found").to_owned(),
+            })
+        }
+    }
+}
+
+/// The parts of a request that pages depend on, so they can also be rendered without one, e.g. by the self-test.
+pub struct Visitor {
+    theme:      Theme,
+    locale:     Locale,
+    collection: Option<Arc<Collection>>,
+    lists:      CardLists,
+    /// See `page_size`
+    page_size:  usize,
+}
+
+impl Visitor {
+    fn of(req: &HttpRequest) -> Self {
+        Self {
+            theme:      theme(req),
+            locale:     locale(req),
+            collection: collection(req),
+            lists:      card_lists(req),
+            page_size:  page_size(req),
+        }
+    }
+
+    /// Someone without any cookies or headers
+    pub fn anonymous() -> Self {
+        Self {
+            theme:      Theme::default(),
+            locale:     Locale::En,
+            collection: None,
+            lists:      CardLists::default(),
+            page_size:  PAGE_SIZE,
+        }
+    }
+}
+
+/// Without counting the view, so the self-test can render it too.
+pub fn card_page<'a>(visitor: &Visitor, state: &'a AppState, card: &'a Card, params: &CardQuery) -> AnyResult<CardPage<'a>> {
+    let canonical_id = state.canonical_version(card).id;
+    let description = card.short_info()?;
+    Ok(CardPage {
+        meta: Meta {
+            og_image: Some(og_image_url(card.id)),
+            json_ld: Some(render::product_json_ld(
+                card,
+                &description,
+                &public_url(&format!("/card/{}", card.id)),
+                &card_image_url(card.id),
+            )),
+            ..Meta::new(format!("{} - {NAME}", card.name), description).with_theme(visitor.theme).with_locale(visitor.locale)
+        },
+        card,
+        text: state.linked_texts.get(&card.id).unwrap_or(&card.text),
+        extended_info: render::extended_info(card).unwrap_or_else(|_| String::new()),
+        other_versions: state.other_versions(card),
+        referenced_by: state
+            .mentions
+            .referenced_by(canonical_id)
+            .iter()
+            .filter_map(|id| state.cards_by_id.get(id))
+            .sorted_by_key(|c| c.name.as_str())
+            .collect(),
+        similar: state.similar.similar(canonical_id, SIMILAR_CARDS).iter().filter_map(|id| state.cards_by_id.get(id)).collect(),
+        archetype_members: card
+            .archetype
+            .as_deref()
+            .and_then(|a| state.archetypes.get(a))
+            .map(|ids| ids.iter().filter(|&&id| id != canonical_id).filter_map(|id| state.cards_by_id.get(id)).collect())
+            .unwrap_or_default(),
+        printings: Printing::grouped(card, params.printings, &state.sets_by_name),
+        released: !state.is_unreleased(card.id, OffsetDateTime::now_utc().date()),
+        price_chart: {
+            let now = OffsetDateTime::now_utc().unix_timestamp();
+            render::price_chart(&PRICE_HISTORY.get(card.id), params.prices.start(now), now)
+        },
+        price_range: params.prices,
+        legalities: legalities(card),
+        navigation: params
+            .from
+            .as_deref()
+            .and_then(|from| result_navigation(visitor, state, card.id, from, params.i.unwrap_or_default(), params.n)),
+        tags: state.effect_tags.get(&card.id).cloned().unwrap_or_default(),
+        support_count: state.support_counts.get(&card.id).copied().unwrap_or_default(),
+        list_names: visitor.lists.names().into_iter().map(str::to_owned).collect(),
+        params: params.pairs(),
+    })
+}
+
+/// The full-size image of a card as an absolute URL, for embeds and structured data.
+fn card_image_url(id: usize) -> String {
+    let image = format!("{}/static/full/{id}.jpg", IMG_HOST.as_str());
+    // `IMG_HOST` is only a path unless the images are on another server.
+    if image.starts_with('/') {
+        format!("{}{image}", PUBLIC_URL.as_str())
+    } else {
+        image
+    }
+}
+
+/// The banner from `ogimage.rs` if those are enabled, otherwise just the card image.
+fn og_image_url(id: usize) -> String {
+    match OG_IMAGES.as_ref() {
+        Some(_) => public_url(&format!("/card/{id}/og.jpg")),
+        None => card_image_url(id),
+    }
+}
+
+/// Only uses results that are still cached, so viewing a card never runs a search.
+/// Links from results that have expired (or changed) just don’t get the navigation.
+fn result_navigation(
+    visitor: &Visitor,
+    state: &AppState,
+    id: usize,
+    query: &str,
+    index: usize,
+    page_size: Option<usize>,
+) -> Option<ResultNavigation> {
+    let cards = find_matching_cards(state, query, visitor.collection.as_deref(), visitor.locale, false).ok()?.cards;
+    let page_size = page_size.map_or(visitor.page_size, |n| n.clamp(MIN_PAGE_SIZE, PAGE_SIZE));
+    // Alternate artworks are only in the results with `include:duplicates`.
+    let index = match cards.get(index) {
+        Some(card) if card.id == id => index,
+        _ => cards.iter().position(|c| c.id == id)?,
+    };
+    let link = |index: usize| cards.get(index).map(|c| ResultNavigation::card_link(c.id, query, index, page_size));
+    Some(ResultNavigation {
+        previous: index.checked_sub(1).and_then(link),
+        next:     link(index + 1),
+        back:     url(&format!(
+            "/?{}",
+            serde_urlencoded::to_string([("q", query), ("p", &(index / page_size + 1).to_string()), ("n", &page_size.to_string()),])
+                .ok()?
+        )),
+        position: index + 1,
+        total:    cards.len(),
+    })
+}
+
+/// A list from `LFLIST_FILE` with the same name as one of the dump’s formats (e.g. “Master Duel”) replaces its statuses.
+fn legalities(card: &Card) -> Vec<Legality> {
+    let extra = |name: &str| BANLISTS.iter().find(|list| list.name.eq_ignore_ascii_case(name));
+    let from_dump = card.legalities().into_iter().map(|(format, status)| {
+        let format = format.to_string();
+        // Cards that aren’t in the format at all stay that way.
+        let status = extra(&format).map_or(status, |list| status.and(list.status(card.id)));
+        Legality { format, status }
+    });
+    let is_format = |name: &str| Format::ALL.iter().any(|f| f.to_string().eq_ignore_ascii_case(name));
+    let extra = BANLISTS
+        .iter()
+        .filter(|list| !is_format(&list.name))
+        .map(|list| Legality { format: list.name.clone(), status: list.status(card.id) });
+    from_dump.chain(extra).collect()
+}
+
+#[derive(Debug, Deserialize)]
+struct QrQuery {
+    /// Save the file instead of showing it
+    #[serde(default)]
+    download: bool,
+}
+
+#[route("/card/{id}/qr.svg", method = "GET", method = "HEAD")]
+pub async fn card_qr(state: State, card_id: web::Path<usize>, params: web::Query<QrQuery>) -> AnyResult<HttpResponse> {
+    let Some(card) = state.cards_by_id.get(&card_id) else {
+        return Ok(HttpResponse::NotFound().body("Card not found"));
+    };
+    let mut res = HttpResponse::Ok();
+    res.insert_header((header::CONTENT_TYPE, "image/svg+xml")).insert_header((header::CACHE_CONTROL, "public, max-age=86400"));
+    if params.download {
+        res.insert_header((header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}-qr.svg\"", card.id)));
+    }
+    Ok(res.body(qr::svg(&public_url(&format!("/card/{}", card.id)))?))
+}
+
+/// Falls back to the plain card image if there’s no art to build the banner from.
+#[route("/card/{id}/og.jpg", method = "GET", method = "HEAD")]
+pub async fn card_og_image(state: State, card_id: web::Path<usize>) -> AnyResult<HttpResponse> {
+    let Some(card) = state.cards_by_id.get(&card_id) else {
+        return Ok(HttpResponse::NotFound().body("Card not found"));
+    };
+    let plain =
+        || HttpResponse::Found().insert_header((header::LOCATION, format!("{}/static/full/{}.jpg", IMG_HOST.as_str(), card.id))).finish();
+    let Some(og_images) = OG_IMAGES.as_ref() else {
+        return Ok(plain());
+    };
+    let (state, id) = (state.to_arc(), card.id);
+    match web::block(move || og_images.get(&state.cards_by_id[&id])).await? {
+        Ok(jpeg) => Ok(HttpResponse::Ok()
+            .insert_header((header::CONTENT_TYPE, "image/jpeg"))
+            .insert_header((header::CACHE_CONTROL, "public, max-age=86400"))
+            .body(jpeg)),
+        // Not every card has an image yet, e.g. pre-releases from a simulator database.
+        Err(ImageError::IoError(e)) if e.kind() == io::ErrorKind::NotFound => Ok(plain()),
+        Err(e) => {
+            eprintln!("Could not render the embed image for {}: {e}", card.id);
+            Ok(plain())
+        }
+    }
+}
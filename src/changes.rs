@@ -0,0 +1,259 @@
+//! What changed in the card data between loads, for `/api/changes`, so other tools can sync without downloading the whole dump.
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::PathBuf,
+    str::FromStr,
+    sync::RwLock,
+};
+
+use crate::{
+    data::{Card, Format},
+    dataversion::{fnv1a, FNV_OFFSET},
+};
+
+#[derive(Debug, Serialize, PartialEq, Eq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Text,
+    Price,
+    TcgBanlist,
+    OcgBanlist,
+}
+
+impl ChangeKind {
+    const ALL: [Self; 6] = [Self::Added, Self::Removed, Self::Text, Self::Price, Self::TcgBanlist, Self::OcgBanlist];
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Added => "added",
+            Self::Removed => "removed",
+            Self::Text => "text",
+            Self::Price => "price",
+            Self::TcgBanlist => "tcg_banlist",
+            Self::OcgBanlist => "ocg_banlist",
+        }
+    }
+}
+
+impl FromStr for ChangeKind {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.into_iter().find(|k| k.as_str() == s).ok_or_else(|| format!("Unknown change: {s}"))
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq, Clone)]
+pub struct Change {
+    /// The data version (see `dataversion.rs`) that has this change
+    pub version: String,
+    pub id:      usize,
+    pub kind:    ChangeKind,
+    /// The name for added and removed cards, the price in cents, or the banlist status.
+    /// Empty if there was none, and for text changes.
+    pub old:     String,
+    pub new:     String,
+}
+
+/// What we remember about a card until the next load to tell what changed.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CardState {
+    name:        String,
+    /// Only a hash, the texts themselves are in the dump
+    text:        u64,
+    price:       String,
+    tcg_banlist: String,
+    ocg_banlist: String,
+}
+
+impl CardState {
+    pub fn of(card: &Card) -> Self {
+        let banlist = |format| card.legality(format).map(|s| s.to_string()).unwrap_or_default();
+        Self {
+            name:        card.name.replace(['\t', '\n'], " "),
+            text:        fnv1a(fnv1a(FNV_OFFSET, card.name.as_bytes()), card.text.as_bytes()),
+            price:       card.prices().lowest.map(|p| p.to_string()).unwrap_or_default(),
+            tcg_banlist: banlist(Format::Tcg),
+            ocg_banlist: banlist(Format::Ocg),
+        }
+    }
+
+    fn parse(line: &str) -> Option<(usize, Self)> {
+        let mut parts = line.split('\t');
+        let id = parts.next()?.parse().ok()?;
+        let name = parts.next()?.to_owned();
+        let text = u64::from_str_radix(parts.next()?, 16).ok()?;
+        let mut next = || parts.next().map(str::to_owned);
+        Some((id, Self { name, text, price: next()?, tcg_banlist: next()?, ocg_banlist: next()? }))
+    }
+}
+
+/// Every data version in the order they were loaded, with what changed since the one before.
+/// The changes are appended to a file as a `version\t<version>` line followed by one `id\tkind\told\tnew` line per change.
+/// The state of the last load is kept in `<file>.snapshot`, which is all we need to compare the next one against.
+#[derive(Debug)]
+pub struct ChangeLog {
+    path:     PathBuf,
+    /// Oldest first
+    versions: RwLock<Vec<(String, Vec<Change>)>>,
+}
+
+impl ChangeLog {
+    pub fn empty(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), versions: RwLock::default() }
+    }
+
+    /// A missing file just means no versions were recorded yet.
+    pub fn load(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let mut versions: Vec<(String, Vec<Change>)> = Vec::new();
+        match File::open(&path) {
+            Ok(f) => {
+                for line in BufReader::new(f).lines() {
+                    let line = line?;
+                    if let Some(version) = line.strip_prefix("version\t") {
+                        versions.push((version.to_owned(), Vec::new()));
+                        continue;
+                    }
+                    let Some((version, changes)) = versions.last_mut() else { continue };
+                    let mut parts = line.split('\t');
+                    if let (Some(Ok(id)), Some(Ok(kind)), Some(old), Some(new)) =
+                        (parts.next().map(str::parse), parts.next().map(str::parse), parts.next(), parts.next())
+                    {
+                        changes.push(Change { version: version.clone(), id, kind, old: old.to_owned(), new: new.to_owned() });
+                    }
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+            Err(e) => return Err(e),
+        }
+        Ok(Self { path, versions: RwLock::new(versions) })
+    }
+
+    /// Compares the cards to the last recorded version and returns the changes.
+    /// Nothing is recorded if the version didn’t change, e.g. on a restart. The first version has no changes.
+    pub fn record(&self, version: &str, cards: impl IntoIterator<Item = (usize, CardState)>) -> io::Result<Vec<Change>> {
+        let mut versions = self.versions.write().unwrap();
+        if versions.last().is_some_and(|(v, _)| v == version) {
+            return Ok(Vec::new());
+        }
+        let cards: HashMap<usize, CardState> = cards.into_iter().collect();
+        let snapshot_path = self.snapshot_path();
+        let changes = match fs::read_to_string(&snapshot_path) {
+            Ok(snapshot) => diff(version, &snapshot.lines().filter_map(CardState::parse).collect(), &cards),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        let mut file = BufWriter::new(OpenOptions::new().create(true).append(true).open(&self.path)?);
+        writeln!(file, "version\t{version}")?;
+        for Change { id, kind, old, new, .. } in &changes {
+            writeln!(file, "{id}\t{}\t{old}\t{new}", kind.as_str())?;
+        }
+        file.flush()?;
+        let mut snapshot = BufWriter::new(File::create(snapshot_path)?);
+        for (id, c) in &cards {
+            writeln!(snapshot, "{id}\t{}\t{:x}\t{}\t{}\t{}", c.name, c.text, c.price, c.tcg_banlist, c.ocg_banlist)?;
+        }
+        snapshot.flush()?;
+        versions.push((version.to_owned(), changes.clone()));
+        Ok(changes)
+    }
+
+    /// All changes after `version`, oldest first, or `None` if we don’t know it.
+    pub fn since(&self, version: &str) -> Option<Vec<Change>> {
+        let versions = self.versions.read().unwrap();
+        let i = versions.iter().rposition(|(v, _)| v == version)?;
+        Some(versions[i + 1..].iter().flat_map(|(_, changes)| changes.iter().cloned()).collect())
+    }
+
+    fn snapshot_path(&self) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(".snapshot");
+        path.into()
+    }
+}
+
+/// Sorted by ID, so the same data always gives the same changes.
+fn diff(version: &str, old: &HashMap<usize, CardState>, new: &HashMap<usize, CardState>) -> Vec<Change> {
+    let change =
+        |id, kind, old: &str, new: &str| Change { version: version.to_owned(), id, kind, old: old.to_owned(), new: new.to_owned() };
+    let mut changes = Vec::new();
+    for (&id, card) in new {
+        let Some(before) = old.get(&id) else {
+            changes.push(change(id, ChangeKind::Added, "", &card.name));
+            continue;
+        };
+        if before.text != card.text {
+            changes.push(change(id, ChangeKind::Text, "", ""));
+        }
+        for (kind, old, new) in [
+            (ChangeKind::Price, &before.price, &card.price),
+            (ChangeKind::TcgBanlist, &before.tcg_banlist, &card.tcg_banlist),
+            (ChangeKind::OcgBanlist, &before.ocg_banlist, &card.ocg_banlist),
+        ] {
+            if old != new {
+                changes.push(change(id, kind, old, new));
+            }
+        }
+    }
+    changes.extend(old.iter().filter(|(id, _)| !new.contains_key(id)).map(|(&id, card)| change(id, ChangeKind::Removed, &card.name, "")));
+    changes.sort_by_key(|c| c.id);
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        data::{BanlistInfo, BanlistStatus, CardPrice},
+        tempdir::TempDir,
+    };
+
+    #[test]
+    fn change_log_test() {
+        let dir = TempDir::new("changes");
+        let path = dir.join("changes.tsv");
+        let dark_magician = Card { id: 46986414, name: "Dark Magician".to_owned(), ..Default::default() };
+        let pot = Card { id: 55144522, name: "Pot of Greed".to_owned(), ..Default::default() };
+        let log = ChangeLog::empty(&path);
+        assert_eq!(log.record("a", [&dark_magician, &pot].map(|c| (c.id, CardState::of(c)))).unwrap().len(), 0);
+        assert_eq!(log.record("a", []).unwrap().len(), 0, "same version");
+
+        let new_dark_magician = Card {
+            text: "The ultimate wizard.".to_owned(),
+            card_prices: vec![CardPrice { cardmarket_price: "0.20".to_owned(), ..Default::default() }],
+            ..dark_magician.clone()
+        };
+        let banned_pot = Card { banlist_info: Some(BanlistInfo { ban_tcg: BanlistStatus::Forbidden, ..Default::default() }), ..pot };
+        let raigeki = Card { id: 12580477, name: "Raigeki".to_owned(), ..Default::default() };
+        let cards = [&new_dark_magician, &banned_pot, &raigeki].map(|c| (c.id, CardState::of(c)));
+        assert_eq!(log.record("b", cards.clone()).unwrap().len(), 4);
+        assert_eq!(log.record("c", [cards[0].clone()]).unwrap().len(), 2);
+
+        let log = ChangeLog::load(&path).unwrap();
+        let change = |version: &str, id, kind, old: &str, new: &str| Change {
+            version: version.to_owned(),
+            id,
+            kind,
+            old: old.to_owned(),
+            new: new.to_owned(),
+        };
+        let b = vec![
+            change("b", 12580477, ChangeKind::Added, "", "Raigeki"),
+            change("b", 46986414, ChangeKind::Text, "", ""),
+            change("b", 46986414, ChangeKind::Price, "", "20"),
+            change("b", 55144522, ChangeKind::TcgBanlist, "Unlimited", "Forbidden"),
+        ];
+        assert_eq!(
+            log.since("b").unwrap(),
+            vec![change("c", 12580477, ChangeKind::Removed, "Raigeki", ""), change("c", 55144522, ChangeKind::Removed, "Pot of Greed", ""),]
+        );
+        assert_eq!(log.since("a").unwrap()[..4], b);
+        assert_eq!(log.since("c"), Some(vec![]));
+        assert_eq!(log.since("d"), None);
+    }
+}
@@ -0,0 +1,364 @@
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::Display,
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    mem::size_of,
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, Mutex, OnceLock},
+};
+
+use crate::{i18n::Locale, memory::map_size, random_token, stats::CacheStats};
+
+/// A user’s cards, parsed from a CSV or JSON export.
+/// In CSV, every line is either a card ID or a set code (e.g. `LOB-EN005`), optionally followed by a quantity.
+/// A header line is skipped, so most exports work as long as the ID or code is in the first column.
+/// JSON is a list of objects with an `id` or `set_code` and an optional `quantity`.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Collection {
+    by_id:       HashMap<usize, u32>,
+    by_set_code: HashMap<String, u32>,
+    /// Both of the above resolved to card IDs, built on first use
+    owned:       OnceLock<HashMap<usize, u32>>,
+}
+
+impl Collection {
+    /// Owning a card by ID counts for every printing of it.
+    pub fn owns_printing(&self, id: usize, set_code: &str) -> bool {
+        self.by_id.contains_key(&id) || self.by_set_code.contains_key(&set_code.to_uppercase())
+    }
+
+    /// Copies owned of each card ID, with set codes resolved to the card they’re printed on.
+    /// This is only built once per collection, so `id_by_set_code` has to be the same on every call.
+    /// New data can move set codes, which is why `swap_in` drops all cached collections.
+    pub fn owned_ids(&self, id_by_set_code: impl Fn(&str) -> Option<usize>) -> &HashMap<usize, u32> {
+        self.owned.get_or_init(|| {
+            let mut owned = self.by_id.clone();
+            for (code, &n) in &self.by_set_code {
+                if let Some(id) = id_by_set_code(code) {
+                    *owned.entry(id).or_default() += n;
+                }
+            }
+            owned
+        })
+    }
+
+    /// Copies owned of any of the given IDs, which should be all versions of the same card.
+    pub fn count(&self, ids: &[usize], id_by_set_code: impl Fn(&str) -> Option<usize>) -> u32 {
+        let owned = self.owned_ids(id_by_set_code);
+        ids.iter().filter_map(|id| owned.get(id)).sum()
+    }
+
+    /// Total number of cards, including duplicates
+    pub fn len(&self) -> u32 {
+        self.by_id.values().chain(self.by_set_code.values()).sum()
+    }
+
+    /// For the admin page, see `memory.rs`.
+    pub fn estimated_size(&self) -> usize {
+        let owned = self.owned.get().map_or(0, |owned| map_size(owned, |_, _| 0));
+        map_size(&self.by_id, |_, _| 0) + map_size(&self.by_set_code, |code, _| code.capacity()) + owned
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_id.is_empty() && self.by_set_code.is_empty()
+    }
+
+    /// Market value in cents. Printings are valued at the price of their card,
+    /// so it doesn’t matter whether the user listed their cards by ID or by set code.
+    pub fn value(&self, price: impl Fn(usize) -> Option<i32>, id_by_set_code: impl Fn(&str) -> Option<usize>) -> i32 {
+        let by_id = self.by_id.iter().map(|(&id, &n)| (Some(id), n));
+        let by_code = self.by_set_code.iter().map(|(code, &n)| (id_by_set_code(code), n));
+        by_id
+            .chain(by_code)
+            .filter_map(|(id, n)| Some(price(id?)?.saturating_mul(i32::try_from(n).unwrap_or(i32::MAX))))
+            .fold(0, i32::saturating_add)
+    }
+
+    /// Like `from_str`, but with the error in `locale`.
+    pub fn parse_in(s: &str, locale: Locale) -> Result<Self, String> {
+        if s.trim_start().starts_with('[') {
+            return Self::from_json(s, locale);
+        }
+        let mut collection = Self::default();
+        for (i, line) in s.lines().enumerate().filter(|(_, l)| !l.trim().is_empty()) {
+            let mut columns = line.split([',', ';', '\t']).map(|c| c.trim().trim_matches('"'));
+            let first = columns.next().unwrap_or_default();
+            let id = first.parse::<usize>().ok();
+            if id.is_none() && !is_set_code(first) {
+                match i {
+                    0 => continue,
+                    _ => {
+                        let args: [(&str, &dyn Display); 2] = [("line", &(i + 1)), ("value", &first)];
+                        return Err(locale.trf("Line {line} is neither a card ID nor a set code: {value}", &args));
+                    }
+                }
+            }
+            let quantity = match columns.next().filter(|c| !c.is_empty()) {
+                Some(q) => {
+                    q.parse().map_err(|_| locale.trf("Invalid quantity in line {line}: {value}", &[("line", &(i + 1)), ("value", &q)]))?
+                }
+                None => 1,
+            };
+            match id {
+                Some(id) => *collection.by_id.entry(id).or_default() += quantity,
+                None => *collection.by_set_code.entry(first.to_uppercase()).or_default() += quantity,
+            }
+        }
+        Ok(collection)
+    }
+
+    fn from_json(s: &str, locale: Locale) -> Result<Self, String> {
+        let entries: Vec<JsonEntry> = serde_json::from_str(s).map_err(|e| locale.trf("Invalid JSON: {error}", &[("error", &e)]))?;
+        let mut collection = Self::default();
+        for entry in entries {
+            match entry {
+                JsonEntry { id: Some(id), quantity, .. } => *collection.by_id.entry(id).or_default() += quantity,
+                JsonEntry { set_code: Some(code), quantity, .. } => {
+                    *collection.by_set_code.entry(code.to_uppercase()).or_default() += quantity
+                }
+                _ => return Err(locale.tr("Every entry needs an id or a set_code").to_owned()),
+            }
+        }
+        Ok(collection)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonEntry {
+    id:       Option<usize>,
+    set_code: Option<String>,
+    #[serde(default = "one")]
+    quantity: u32,
+}
+
+fn one() -> u32 {
+    1
+}
+
+impl FromStr for Collection {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_in(s, Locale::En)
+    }
+}
+
+/// Collections only stay in memory while they’re in use, the rest are read from disk again when needed.
+const MAX_CACHED_COLLECTIONS: usize = 1000;
+
+#[derive(Debug)]
+struct Cached {
+    collections: HashMap<String, Arc<Collection>>,
+    /// Oldest first, for evicting
+    order:       VecDeque<String>,
+    max:         usize,
+}
+
+impl Cached {
+    fn insert(&mut self, token: String, collection: Arc<Collection>) {
+        if self.collections.insert(token.clone(), collection).is_none() {
+            self.order.push_back(token);
+        }
+        while self.collections.len() > self.max {
+            let Some(oldest) = self.order.pop_front() else { break };
+            self.collections.remove(&oldest);
+        }
+    }
+}
+
+/// Uploaded collections, stored as one file per token so users only have to keep the token (in a cookie).
+/// The value history of each collection is kept next to it in `<token>.values`.
+#[derive(Debug)]
+pub struct CollectionStore {
+    dir:       PathBuf,
+    cache:     Mutex<Cached>,
+    pub stats: CacheStats,
+}
+
+impl CollectionStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self::with_max_cached(dir, MAX_CACHED_COLLECTIONS)
+    }
+
+    fn with_max_cached(dir: impl Into<PathBuf>, max: usize) -> Self {
+        let cache = Cached { collections: HashMap::new(), order: VecDeque::new(), max };
+        Self { dir: dir.into(), cache: Mutex::new(cache), stats: CacheStats::default() }
+    }
+
+    /// `raw` is what the user uploaded and has to be what `collection` was parsed from.
+    /// Returns the new token.
+    pub fn save(&self, raw: &str, collection: Collection) -> io::Result<String> {
+        let token = random_token();
+        fs::create_dir_all(&self.dir)?;
+        fs::write(self.dir.join(&token), raw)?;
+        self.cache.lock().unwrap().insert(token.clone(), Arc::new(collection));
+        Ok(token)
+    }
+
+    pub fn get(&self, token: &str) -> Option<Arc<Collection>> {
+        if !is_token(token) {
+            return None;
+        }
+        if let Some(collection) = self.cache.lock().unwrap().collections.get(token) {
+            self.stats.hit();
+            return Some(Arc::clone(collection));
+        }
+        self.stats.miss();
+        let collection = Arc::new(self.read(token)?);
+        self.cache.lock().unwrap().insert(token.to_owned(), Arc::clone(&collection));
+        Some(collection)
+    }
+
+    /// Straight from disk, without going through the cache, e.g. to go through all collections.
+    pub fn read(&self, token: &str) -> Option<Collection> {
+        if !is_token(token) {
+            return None;
+        }
+        fs::read_to_string(self.dir.join(token)).ok()?.parse().ok()
+    }
+
+    pub fn cached(&self) -> usize {
+        self.cache.lock().unwrap().collections.len()
+    }
+
+    pub fn estimated_size(&self) -> usize {
+        let cache = self.cache.lock().unwrap();
+        map_size(&cache.collections, |token, collection| token.capacity() + size_of::<Collection>() + collection.estimated_size())
+    }
+
+    /// Everything is still on disk, so this only costs a few reads.
+    pub fn clear_cache(&self) -> usize {
+        let mut cache = self.cache.lock().unwrap();
+        let cleared = cache.collections.len();
+        cache.collections.clear();
+        cache.order.clear();
+        cleared
+    }
+
+    /// Every collection on disk, e.g. to update all of their values.
+    pub fn tokens(&self) -> io::Result<Vec<String>> {
+        match fs::read_dir(&self.dir) {
+            Ok(entries) => Ok(entries.filter_map(|e| e.ok()?.file_name().into_string().ok()).filter(|name| is_token(name)).collect()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    pub fn record_value(&self, token: &str, time: i64, cents: i32) -> io::Result<()> {
+        if !is_token(token) {
+            return Err(io::Error::other("Invalid collection token"));
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(self.dir.join(format!("{token}.values")))?;
+        writeln!(file, "{time}\t{cents}")
+    }
+
+    /// Unix timestamps and values in cents, oldest first.
+    pub fn value_history(&self, token: &str) -> Vec<(i64, i32)> {
+        if !is_token(token) {
+            return Vec::new();
+        }
+        fs::read_to_string(self.dir.join(format!("{token}.values")))
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| {
+                let (time, cents) = line.split_once('\t')?;
+                Some((time.parse().ok()?, cents.parse().ok()?))
+            })
+            .collect()
+    }
+}
+
+/// Tokens end up in file names, so don’t even try anything that we couldn’t have generated.
+fn is_token(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn is_set_code(s: &str) -> bool {
+    s.split_once('-').is_some_and(|(set, number)| {
+        !set.is_empty() && !number.is_empty() && set.chars().chain(number.chars()).all(|c| c.is_ascii_alphanumeric())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+
+    #[test]
+    fn parse_collection_test() {
+        let collection: Collection = "code,quantity\nLOB-EN005,2\n\n46986414\n\"lob-en001\";1\n46986414\t3".parse().unwrap();
+        assert_eq!(collection.by_id, HashMap::from([(46986414, 4)]));
+        assert_eq!(collection.by_set_code, HashMap::from([("LOB-EN005".to_owned(), 2), ("LOB-EN001".to_owned(), 1)]));
+        assert!(collection.owns_printing(46986414, "SDY-EN006"));
+        assert!(collection.owns_printing(1, "lob-en005"));
+        assert!(!collection.owns_printing(1, "LOB-EN002"));
+
+        assert_eq!(collection.len(), 7);
+        assert!("LOB-EN005\nDark Magician".parse::<Collection>().is_err());
+        assert!("LOB-EN005,two".parse::<Collection>().is_err());
+    }
+
+    #[test]
+    fn parse_json_collection_test() {
+        let collection: Collection = r#"[{"id": 46986414, "quantity": 2}, {"set_code": "lob-en005"}]"#.parse().unwrap();
+        assert_eq!(collection.by_id, HashMap::from([(46986414, 2)]));
+        assert_eq!(collection.by_set_code, HashMap::from([("LOB-EN005".to_owned(), 1)]));
+        assert!(r#"[{"quantity": 2}]"#.parse::<Collection>().is_err());
+    }
+
+    #[test]
+    fn count_test() {
+        let collection: Collection = "LOB-EN005,2\n46986414\nSDY-006,3\nLOB-EN001".parse().unwrap();
+        let id_by_set_code = |code: &str| match code {
+            "LOB-EN005" => Some(46986414),
+            "SDY-006" => Some(36996508),
+            _ => None,
+        };
+        assert_eq!(collection.count(&[46986414], id_by_set_code), 3);
+        assert_eq!(collection.count(&[46986414, 36996508], id_by_set_code), 6);
+        assert_eq!(collection.count(&[1], id_by_set_code), 0);
+        assert_eq!(collection.owned_ids(|_| None), &HashMap::from([(46986414, 3), (36996508, 3)]), "built only once");
+    }
+
+    #[test]
+    fn value_test() {
+        let collection: Collection = "LOB-EN005,2\n46986414\nLOB-EN001\n1".parse().unwrap();
+        let price = |id| match id {
+            46986414 => Some(150),
+            89631139 => Some(1000),
+            _ => None,
+        };
+        let id_by_set_code = |code: &str| match code {
+            "LOB-EN005" => Some(46986414),
+            "LOB-EN001" => Some(89631139),
+            _ => None,
+        };
+        assert_eq!(collection.value(price, id_by_set_code), 3 * 150 + 1000);
+        assert_eq!(Collection::default().value(price, id_by_set_code), 0);
+    }
+
+    #[test]
+    fn store_test() {
+        let dir = TempDir::new("collections");
+        let store = CollectionStore::new(&*dir);
+        let token = store.save("46986414,2", "46986414,2".parse().unwrap()).unwrap();
+        assert_eq!(store.get(&token).unwrap().len(), 2);
+        assert_eq!(CollectionStore::new(&*dir).get(&token).unwrap().len(), 2, "reading from disk");
+        assert_eq!(CollectionStore::new(&*dir).get("../etc/passwd"), None);
+
+        store.record_value(&token, 1, 500).unwrap();
+        store.record_value(&token, 2, 450).unwrap();
+        assert_eq!(store.value_history(&token), vec![(1, 500), (2, 450)]);
+        assert_eq!(store.tokens().unwrap(), vec![token.clone()]);
+        assert!(store.record_value("../etc/passwd", 1, 1).is_err());
+        assert_eq!(store.read(&token).unwrap().len(), 2);
+
+        let store = CollectionStore::with_max_cached(&*dir, 1);
+        let other = store.save("1", "1".parse().unwrap()).unwrap();
+        store.get(&token).unwrap();
+        assert_eq!(store.cached(), 1, "the oldest collection makes room");
+        assert_eq!(store.get(&other).unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,296 @@
+//! Pages for what users keep track of: their collection, card lists, and price watches.
+use actix_web::{http::header, route, web, HttpMessage, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use std::sync::atomic::Ordering;
+use time::{format_description::well_known::Rfc2822, Date, OffsetDateTime};
+
+use crate::{
+    admin::{may_write, unauthorized},
+    card_lists, collection,
+    collection::Collection,
+    data::{parse_cents, set_entries},
+    deck::Deck,
+    deckpages::{deck_export, export_format},
+    escape_html, html,
+    jobs::collection_value,
+    lists::{csv_field, CardLists, DEFAULT_LIST},
+    locale, maintenance_page,
+    pages::{format_cents, CollectionPage, ListRow, ListsPage, MessagePage, Meta, SetCompletion, SetCompletionPage, NAME},
+    persistent_cookie,
+    proxy::{public_url, url},
+    read_only_page,
+    settings::redirect_back,
+    state::{AppState, State},
+    theme, AnyResult, COLLECTIONS, COLLECTION_COOKIE, DATA_READY, LISTS_COOKIE, MAX_COOKIE_LEN, PRICE_WATCHES, READ_ONLY,
+};
+
+#[derive(Debug, Deserialize)]
+struct CollectionForm {
+    collection: String,
+}
+
+#[route("/set/{code}/complete", method = "GET", method = "POST")]
+pub async fn set_completion(
+    req: HttpRequest,
+    state: State,
+    code: web::Path<String>,
+    form: Option<web::Form<CollectionForm>>,
+) -> AnyResult<HttpResponse> {
+    let theme = theme(&req);
+    if !DATA_READY.load(Ordering::Acquire) {
+        return maintenance_page(&req);
+    }
+    let code = code.to_uppercase();
+    let entries = set_entries(state.cards.iter(), &code);
+    let locale = locale(&req);
+    let meta = Meta::new(
+        format!("{} - {NAME}", locale.trf("Set completion: {code}", &[("code", &code)])),
+        locale.trf("What you’re missing to complete {code}", &[("code", &code)]),
+    )
+    .with_theme(theme)
+    .with_locale(locale);
+    if entries.is_empty() {
+        return html(&MessagePage { meta, message: locale.trf("There is no set with the code {code}", &[("code", &code)]) });
+    }
+    let collection = form.map(|f| f.into_inner().collection).unwrap_or_default();
+    let (completion, error) = match Collection::parse_in(&collection, locale) {
+        // Fall back to the uploaded collection if nothing was pasted
+        Ok(c) if c.is_empty() => (self::collection(&req).map(|c| SetCompletion::new(entries, &c)), None),
+        Ok(c) => (Some(SetCompletion::new(entries, &c)), None),
+        Err(e) => (None, Some(e)),
+    };
+    html(&SetCompletionPage { meta, code, collection, error, completion })
+}
+
+#[route("/collection", method = "GET", method = "HEAD")]
+pub async fn collection_page(req: HttpRequest) -> AnyResult<HttpResponse> {
+    collection_page_with(&req, None)
+}
+
+fn collection_page_with(req: &HttpRequest, error: Option<String>) -> AnyResult<HttpResponse> {
+    html(&CollectionPage {
+        meta: Meta::new(
+            format!("{} - {NAME}", locale(req).tr("Your collection")),
+            locale(req).tr("Upload your collection to search within it").to_owned(),
+        )
+        .with_theme(theme(req))
+        .with_locale(locale(req)),
+        values: req.cookie(COLLECTION_COOKIE).map(|c| collection_values(c.value())).unwrap_or_default(),
+        collection: collection(req),
+        error,
+    })
+}
+
+/// Takes either the form on `/collection` or a raw CSV/JSON body, e.g. from scripts.
+/// The form sets a cookie and sends the user back to the page; raw uploads just get the token.
+#[route("/collection", method = "POST")]
+pub async fn upload_collection(req: HttpRequest, state: State, body: web::Bytes) -> AnyResult<HttpResponse> {
+    if READ_ONLY.load(Ordering::Relaxed) {
+        return read_only_page(&req);
+    }
+    if !may_write(&req, "uploaded a collection") {
+        return Ok(unauthorized());
+    }
+    let is_form = req.content_type() == "application/x-www-form-urlencoded";
+    let raw = match is_form {
+        true => serde_urlencoded::from_bytes::<CollectionForm>(&body)?.collection,
+        false => String::from_utf8(body.to_vec())?,
+    };
+    let collection = match Collection::parse_in(&raw, locale(&req)) {
+        Ok(c) if !c.is_empty() => c,
+        Ok(_) if is_form => return collection_page_with(&req, Some(locale(&req).tr("That collection is empty").to_owned())),
+        Err(e) if is_form => return collection_page_with(&req, Some(e)),
+        Ok(_) => return Ok(HttpResponse::BadRequest().body("That collection is empty")),
+        Err(e) => return Ok(HttpResponse::BadRequest().body(e)),
+    };
+    let value = collection_value(&state, &collection);
+    let token = COLLECTIONS.save(&raw, collection)?;
+    COLLECTIONS.record_value(&token, OffsetDateTime::now_utc().unix_timestamp(), value)?;
+    if !is_form {
+        return Ok(HttpResponse::Created().body(token));
+    }
+    let mut res = HttpResponse::SeeOther().insert_header((header::LOCATION, url("/collection"))).finish();
+    res.add_cookie(&persistent_cookie(COLLECTION_COOKIE, token))?;
+    Ok(res)
+}
+
+/// The last recorded value of every day, newest first.
+fn collection_values(token: &str) -> Vec<(Date, i32)> {
+    let mut values: Vec<(Date, i32)> = COLLECTIONS
+        .value_history(token)
+        .into_iter()
+        .filter_map(|(time, cents)| Some((OffsetDateTime::from_unix_timestamp(time).ok()?.date(), cents)))
+        .collect();
+    values.reverse();
+    values.dedup_by_key(|(date, _)| *date);
+    values
+}
+
+/// Only forgets the token. The collection itself stays on disk in case the user wrote the token down.
+#[route("/collection/forget", method = "POST")]
+pub async fn forget_collection() -> AnyResult<HttpResponse> {
+    let mut res = HttpResponse::SeeOther().insert_header((header::LOCATION, url("/collection"))).finish();
+    res.add_removal_cookie(&persistent_cookie(COLLECTION_COOKIE, String::new()))?;
+    Ok(res)
+}
+
+#[derive(Debug, Deserialize)]
+struct ListForm {
+    #[serde(default)]
+    list:     String,
+    id:       usize,
+    /// Empty if the user doesn’t care about the printing
+    #[serde(default)]
+    printing: String,
+}
+
+impl ListForm {
+    fn list(&self) -> &str {
+        Some(self.list.trim()).filter(|l| !l.is_empty()).unwrap_or(DEFAULT_LIST)
+    }
+
+    fn printing(&self) -> Option<&str> {
+        Some(self.printing.as_str()).filter(|p| !p.is_empty())
+    }
+}
+
+#[route("/lists/add", method = "POST")]
+pub async fn add_to_list(req: HttpRequest, state: State, form: web::Form<ListForm>) -> AnyResult<HttpResponse> {
+    if !state.cards_by_id.contains_key(&form.id) {
+        return Ok(HttpResponse::BadRequest().body("Unknown card"));
+    }
+    let mut lists = card_lists(&req);
+    lists.add(form.list(), form.id, form.printing());
+    let lists = lists.to_string();
+    if lists.len() > MAX_COOKIE_LEN {
+        return html(&MessagePage {
+            meta:    Meta::new(format!("{} - {NAME}", locale(&req).tr("Lists full")), String::new())
+                .with_theme(theme(&req))
+                .with_locale(locale(&req)),
+            message: locale(&req)
+                .tr("Your lists are too long to be stored in your browser. Export and remove some cards first.")
+                .to_owned(),
+        });
+    }
+    let mut res = redirect_back(&req);
+    res.add_cookie(&persistent_cookie(LISTS_COOKIE, lists))?;
+    Ok(res)
+}
+
+#[route("/lists/remove", method = "POST")]
+pub async fn remove_from_list(req: HttpRequest, form: web::Form<ListForm>) -> AnyResult<HttpResponse> {
+    let mut lists = card_lists(&req);
+    lists.remove(form.list(), form.id, form.printing());
+    let mut res = HttpResponse::SeeOther().insert_header((header::LOCATION, url("/lists"))).finish();
+    res.add_cookie(&persistent_cookie(LISTS_COOKIE, lists.to_string()))?;
+    Ok(res)
+}
+
+#[route("/lists", method = "GET", method = "HEAD")]
+pub async fn lists_page(req: HttpRequest, state: State) -> AnyResult<HttpResponse> {
+    let theme = theme(&req);
+    if !DATA_READY.load(Ordering::Acquire) {
+        return maintenance_page(&req);
+    }
+    let lists = card_lists(&req);
+    html(&ListsPage {
+        meta:  Meta::new(format!("{} - {NAME}", locale(&req).tr("Your lists")), String::new()).with_theme(theme).with_locale(locale(&req)),
+        lists: lists.names().into_iter().map(|name| (name.to_owned(), list_rows(&state, &lists, name))).collect(),
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportQuery {
+    list:   String,
+    /// Without this (or an Accept header asking for a deck format), lists are exported as CSV.
+    format: Option<String>,
+}
+
+#[route("/lists/export", method = "GET")]
+pub async fn export_list(req: HttpRequest, state: State, q: web::Query<ExportQuery>) -> AnyResult<HttpResponse> {
+    if !DATA_READY.load(Ordering::Acquire) {
+        return Ok(HttpResponse::ServiceUnavailable().finish());
+    }
+    let filename: String = q.list.chars().filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_').collect();
+    let rows = list_rows(&state, &card_lists(&req), &q.list);
+    if let Some(format) = export_format(&req, q.format.as_deref())? {
+        let deck = Deck::from_cards(rows.iter().map(|row| (row.card, row.entry.quantity)));
+        return Ok(deck_export(&state, &deck, format, &filename));
+    }
+    let mut csv = String::from("name,id,quantity,set_code,rarity,price\r\n");
+    for row in rows {
+        let ListRow { entry, card, rarity, price } = row;
+        let fields = [
+            csv_field(&card.name),
+            card.id.to_string(),
+            entry.quantity.to_string(),
+            csv_field(entry.printing.as_deref().unwrap_or_default()),
+            csv_field(rarity.unwrap_or_default()),
+            format_cents(price),
+        ];
+        csv.push_str(&fields.join(","));
+        csv.push_str("\r\n");
+    }
+    Ok(HttpResponse::Ok()
+        .insert_header((header::CONTENT_TYPE, "text/csv; charset=utf-8"))
+        .insert_header((header::CONTENT_DISPOSITION, format!("attachment; filename=\"{filename}.csv\"")))
+        .body(csv))
+}
+
+fn list_rows<'a>(state: &'a AppState, lists: &CardLists, name: &str) -> Vec<ListRow<'a>> {
+    lists.entries(name).filter_map(|e| Some(ListRow::new(e.clone(), state.cards_by_id.get(&e.id)?))).collect()
+}
+
+#[derive(Debug, Deserialize)]
+struct PriceWatchForm {
+    id:        usize,
+    /// e.g. `4.99`
+    threshold: String,
+}
+
+#[route("/pricewatch", method = "POST")]
+pub async fn watch_price(req: HttpRequest, state: State, form: web::Form<PriceWatchForm>) -> AnyResult<HttpResponse> {
+    if READ_ONLY.load(Ordering::Relaxed) {
+        return read_only_page(&req);
+    }
+    if !may_write(&req, "watched a price") {
+        return Ok(unauthorized());
+    }
+    if !state.cards_by_id.contains_key(&form.id) {
+        return Ok(HttpResponse::BadRequest().body("Unknown card"));
+    }
+    let Some(threshold) = parse_cents(&form.threshold) else {
+        return html(&MessagePage {
+            meta:    Meta::new(format!("{} - {NAME}", locale(&req).tr("Invalid price")), String::new())
+                .with_theme(theme(&req))
+                .with_locale(locale(&req)),
+            message: locale(&req).trf("{price} is not a valid price. Use something like 4.99.", &[("price", &form.threshold)]),
+        });
+    };
+    PRICE_WATCHES.add(form.id, threshold)?;
+    Ok(redirect_back(&req))
+}
+
+#[route("/pricewatch/alerts.rss", method = "GET")]
+pub async fn price_alerts_feed(state: State) -> AnyResult<HttpResponse> {
+    let base = escape_html(&public_url(""));
+    let mut rss = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?><rss version="2.0"><channel><title>{NAME} price alerts</title><link>{base}/</link><description>Cards that dropped below a watched price</description>"#
+    );
+    for alert in PRICE_WATCHES.alerts(None) {
+        let name = state.cards_by_id.get(&alert.id).map(|c| c.name.as_str()).unwrap_or("Unknown card");
+        let date = OffsetDateTime::from_unix_timestamp(alert.time)?.format(&Rfc2822)?;
+        rss.push_str(&format!(
+            r#"<item><title>{} is now {}</title><link>{base}/card/{}</link><description>Dropped to or below {}</description><pubDate>{date}</pubDate><guid isPermaLink="false">{}-{}-{}</guid></item>"#,
+            escape_html(name),
+            format_cents(Some(alert.price)),
+            alert.id,
+            format_cents(Some(alert.threshold)),
+            alert.id,
+            alert.threshold,
+            alert.time,
+        ));
+    }
+    rss.push_str("</channel></rss>");
+    Ok(HttpResponse::Ok().insert_header((header::CONTENT_TYPE, "application/rss+xml; charset=utf-8")).body(rss))
+}
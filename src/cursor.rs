@@ -0,0 +1,55 @@
+//! Cursors for paging through `/api/search`. Clients should treat them as opaque.
+use std::{fmt, str::FromStr};
+
+/// Where the previous page ended: the number of results before the next page, and the last card on the previous one.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Cursor {
+    offset:  usize,
+    last_id: usize,
+}
+
+impl Cursor {
+    pub fn new(offset: usize, last_id: usize) -> Self {
+        Self { offset, last_id }
+    }
+
+    /// Where the next page starts in `ids`, which are the result IDs of the same query, possibly in a different order by now.
+    /// That’s right after the last card if it still matches, so nothing is skipped or shown twice when the results
+    /// change in front of it, and the old offset otherwise.
+    pub fn resume(&self, ids: impl IntoIterator<Item = usize>) -> usize {
+        ids.into_iter().position(|id| id == self.last_id).map_or(self.offset, |i| i + 1)
+    }
+}
+
+impl fmt::Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:x}.{:x}", self.offset, self.last_id)
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse =
+            |(offset, id): (&str, &str)| Some(Self::new(usize::from_str_radix(offset, 16).ok()?, usize::from_str_radix(id, 16).ok()?));
+        s.split_once('.').and_then(parse).ok_or_else(|| format!("Invalid cursor: {s}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_test() {
+        let cursor = Cursor::new(2, 46986414);
+        assert_eq!(cursor.to_string().parse(), Ok(cursor));
+        assert!("2".parse::<Cursor>().is_err());
+        assert!("x.1".parse::<Cursor>().is_err());
+
+        assert_eq!(cursor.resume([1, 46986414, 3]), 2);
+        assert_eq!(cursor.resume([0, 1, 46986414, 3]), 3, "a new card in front");
+        assert_eq!(cursor.resume([46986414, 3]), 1, "a card in front is gone");
+        assert_eq!(cursor.resume([1, 2, 3]), 2, "the last card is gone");
+    }
+}
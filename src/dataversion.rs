@@ -0,0 +1,65 @@
+//! A fingerprint of the loaded card data for `/api/dataversion`, so clients and mirrors know when to refresh their caches.
+use serde::Serialize;
+use std::{fs, io, path::Path};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct DataVersion {
+    /// Of all files the data was loaded from, so it only changes when they do, not on every restart
+    pub hash:      String,
+    pub loaded_at: String,
+    pub cards:     usize,
+    /// When the card dump was last downloaded, going by the newest modification time of the card sources
+    pub dump_date: Option<String>,
+}
+
+impl DataVersion {
+    /// `files` are all inputs, `dumps` only the card dumps from upstream.
+    pub fn new(files: &[impl AsRef<Path>], dumps: &[impl AsRef<Path>], cards: usize, loaded_at: OffsetDateTime) -> io::Result<Self> {
+        let mut hash = FNV_OFFSET;
+        for file in files {
+            hash = fnv1a(hash, &fs::read(file)?);
+        }
+        let dump_date = dumps.iter().filter_map(|d| fs::metadata(d).and_then(|m| m.modified()).ok()).max().map(OffsetDateTime::from);
+        Ok(Self { hash: format!("{hash:016x}"), loaded_at: rfc3339(loaded_at), cards, dump_date: dump_date.map(rfc3339) })
+    }
+}
+
+fn rfc3339(time: OffsetDateTime) -> String {
+    time.format(&Rfc3339).unwrap_or_default()
+}
+
+pub const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+
+/// FNV-1a, because the std hashers aren’t guaranteed to give the same result across Rust versions.
+pub fn fnv1a(mut hash: u64, bytes: &[u8]) -> u64 {
+    for &b in bytes {
+        hash ^= u64::from(b);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+
+    #[test]
+    fn data_version_test() {
+        assert_eq!(fnv1a(FNV_OFFSET, b"a"), 0xaf63dc4c8601ec8c);
+        let dir = TempDir::new("dataversion");
+        let (cards, sets) = (dir.join("cards.json"), dir.join("sets.json"));
+        fs::write(&cards, "{\"data\": []}").unwrap();
+        fs::write(&sets, "[]").unwrap();
+        let loaded_at = OffsetDateTime::UNIX_EPOCH;
+        let version = DataVersion::new(&[&cards, &sets], &[&cards], 0, loaded_at).unwrap();
+        assert_eq!(version.loaded_at, "1970-01-01T00:00:00Z");
+        assert!(version.dump_date.is_some());
+        assert_eq!(DataVersion::new(&[&cards, &sets], &[&cards], 0, loaded_at).unwrap(), version);
+
+        fs::write(&sets, "[{}]").unwrap();
+        assert_ne!(DataVersion::new(&[&cards, &sets], &[&cards], 0, loaded_at).unwrap().hash, version.hash);
+        assert!(DataVersion::new(&[dir.join("missing.json")], &[&cards], 0, loaded_at).is_err());
+    }
+}
@@ -0,0 +1,451 @@
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    fmt::{self, Display, Write},
+    str::FromStr,
+    sync::Mutex,
+    time::Instant,
+};
+
+use crate::{
+    data::{BanlistStatus, Card},
+    i18n::Locale,
+    random_token,
+};
+
+const MAIN_MIN: u32 = 40;
+const MAIN_MAX: u32 = 60;
+const EXTRA_MAX: u32 = 15;
+const SIDE_MAX: u32 = 15;
+// Sessions are only kept in memory, so we need some limit.
+const MAX_SESSIONS: usize = 10_000;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Section {
+    Main,
+    Extra,
+    Side,
+}
+
+impl Section {
+    pub const ALL: [Self; 3] = [Self::Main, Self::Extra, Self::Side];
+
+    /// The heading on the deck page, translated there.
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Main => "Main deck",
+            Self::Extra => "Extra deck",
+            Self::Side => "Side deck",
+        }
+    }
+}
+
+impl FromStr for Section {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "main" => Ok(Self::Main),
+            "extra" => Ok(Self::Extra),
+            "side" => Ok(Self::Side),
+            _ => Err(format!("Unknown deck section: {s}")),
+        }
+    }
+}
+
+impl Display for Section {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Main => "main",
+            Self::Extra => "extra",
+            Self::Side => "side",
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ExportFormat {
+    /// What YGOPro, EDOPro and most other simulators read
+    Ydk,
+    /// Quantities and names, which is what Master Duel deck importers expect on the clipboard
+    MasterDuel,
+    /// Quantities, names and the set code of the oldest printing, for people who want to buy the deck
+    Text,
+}
+
+impl ExportFormat {
+    /// For content negotiation when there’s no explicit `format=`. Only looks at the types, not at quality values.
+    pub fn from_accept(accept: &str) -> Option<Self> {
+        accept.split(',').map(|t| t.split(';').next().unwrap_or_default().trim()).find_map(|mime| match mime {
+            "application/x-ydk" => Some(Self::Ydk),
+            "text/plain" => Some(Self::Text),
+            _ => None,
+        })
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Ydk => "application/x-ydk; charset=utf-8",
+            Self::MasterDuel | Self::Text => "text/plain; charset=utf-8",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Ydk => "ydk",
+            Self::MasterDuel | Self::Text => "txt",
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ydk" => Ok(Self::Ydk),
+            "masterduel" | "md" => Ok(Self::MasterDuel),
+            "text" | "txt" => Ok(Self::Text),
+            _ => Err(format!("Unknown export format: {s}")),
+        }
+    }
+}
+
+/// Genesys has no banlist. Instead, every card costs points, and a deck can only spend so many.
+#[derive(Debug)]
+pub struct GenesysPoints<'a> {
+    pub total: i32,
+    /// Cards that cost anything, the most expensive (all copies together) first, with their copies and points per copy
+    pub cards: Vec<(&'a Card, u32, i32)>,
+}
+
+/// Card IDs and how many copies of each are in the deck, in the order they were added.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Deck {
+    main:  Vec<(usize, u32)>,
+    extra: Vec<(usize, u32)>,
+    side:  Vec<(usize, u32)>,
+}
+
+impl Deck {
+    pub fn section(&self, section: Section) -> &[(usize, u32)] {
+        match section {
+            Section::Main => &self.main,
+            Section::Extra => &self.extra,
+            Section::Side => &self.side,
+        }
+    }
+
+    fn section_mut(&mut self, section: Section) -> &mut Vec<(usize, u32)> {
+        match section {
+            Section::Main => &mut self.main,
+            Section::Extra => &mut self.extra,
+            Section::Side => &mut self.side,
+        }
+    }
+
+    pub fn size(&self, section: Section) -> u32 {
+        self.section(section).iter().map(|(_, n)| n).sum()
+    }
+
+    /// Goes into the main or extra deck depending on the card unless `to_side` is set.
+    pub fn add(&mut self, card: &Card, to_side: bool) {
+        let section = match (to_side, card.is_extra_deck()) {
+            (true, _) => Section::Side,
+            (false, true) => Section::Extra,
+            (false, false) => Section::Main,
+        };
+        let cards = self.section_mut(section);
+        match cards.iter_mut().find(|(id, _)| *id == card.id) {
+            Some((_, n)) => *n += 1,
+            None => cards.push((card.id, 1)),
+        }
+    }
+
+    /// e.g. for exporting a list like a deck. Quantities are capped so a typo can’t make us allocate a huge string.
+    pub fn from_cards<'a>(cards: impl IntoIterator<Item = (&'a Card, u32)>) -> Self {
+        let mut deck = Self::default();
+        for (card, n) in cards {
+            (0..n.min(MAIN_MAX)).for_each(|_| deck.add(card, false));
+        }
+        deck
+    }
+
+    /// Removes one copy.
+    pub fn remove(&mut self, id: usize, section: Section) {
+        let cards = self.section_mut(section);
+        if let Some((_, n)) = cards.iter_mut().find(|(other, _)| *other == id) {
+            *n -= 1;
+        }
+        cards.retain(|(_, n)| *n > 0);
+    }
+
+    /// Everything that would make the deck illegal in the TCG, in a human-readable form.
+    pub fn problems<'a>(&self, lookup: impl Fn(usize) -> Option<&'a Card>, locale: Locale) -> Vec<String> {
+        let mut problems = Vec::new();
+        let main = self.size(Section::Main);
+        if main < MAIN_MIN {
+            problems.push(locale.trf("The main deck needs at least {min} cards, but it has {n}.", &[("min", &MAIN_MIN), ("n", &main)]));
+        }
+        let too_big = [
+            (Section::Main, MAIN_MAX, "The main deck can’t have more than {max} cards, but it has {n}."),
+            (Section::Extra, EXTRA_MAX, "The extra deck can’t have more than {max} cards, but it has {n}."),
+            (Section::Side, SIDE_MAX, "The side deck can’t have more than {max} cards, but it has {n}."),
+        ];
+        for (section, max, message) in too_big {
+            let size = self.size(section);
+            if size > max {
+                problems.push(locale.trf(message, &[("max", &max), ("n", &size)]));
+            }
+        }
+        // Alternate artworks have different IDs but count as the same card.
+        let mut copies: Vec<(&Card, u32)> = Vec::new();
+        for (id, n) in Section::ALL.iter().flat_map(|&s| self.section(s)) {
+            let Some(card) = lookup(*id) else { continue };
+            match copies.iter_mut().find(|(c, _)| c.name == card.name) {
+                Some((_, total)) => *total += n,
+                None => copies.push((card, *n)),
+            }
+        }
+        for (card, n) in copies {
+            let allowed = card.banlist_info.map(|b| b.ban_tcg).unwrap_or(BanlistStatus::Unlimited) as u32;
+            if n > allowed {
+                let message = match allowed {
+                    1 => "{card} is limited to 1 copy, but the deck has {n}.",
+                    _ => "{card} is limited to {allowed} copies, but the deck has {n}.",
+                };
+                problems.push(locale.trf(message, &[("card", &card.name), ("allowed", &allowed), ("n", &n)]));
+            }
+        }
+        problems
+    }
+
+    /// Points of all sections together. Alternate artworks are grouped with the original, like in `problems`.
+    pub fn genesys_points<'a>(&self, lookup: impl Fn(usize) -> Option<&'a Card>) -> GenesysPoints<'a> {
+        let mut cards: Vec<(&Card, u32, i32)> = Vec::new();
+        for (id, n) in Section::ALL.iter().flat_map(|&s| self.section(s)) {
+            let Some(card) = lookup(*id).filter(|c| c.genesys_points() > 0) else { continue };
+            match cards.iter_mut().find(|(c, _, _)| c.name == card.name) {
+                Some((_, total, _)) => *total += n,
+                None => cards.push((card, *n, card.genesys_points())),
+            }
+        }
+        cards.sort_by_key(|&(card, n, points)| (Reverse(n as i32 * points), card.name.clone()));
+        GenesysPoints { total: cards.iter().map(|&(_, n, points)| n as i32 * points).sum(), cards }
+    }
+
+    /// Cards that `lookup` doesn’t know are left out, except in `.ydk` which only needs the IDs.
+    pub fn export<'a>(&self, format: ExportFormat, lookup: impl Fn(usize) -> Option<&'a Card>) -> String {
+        let mut s = String::new();
+        // Writing to a String can’t fail.
+        let _ = match format {
+            ExportFormat::Ydk => self.write_ydk(&mut s),
+            ExportFormat::MasterDuel | ExportFormat::Text => self.write_text(&mut s, format == ExportFormat::Text, lookup),
+        };
+        s
+    }
+
+    fn write_ydk<W: Write>(&self, f: &mut W) -> fmt::Result {
+        writeln!(f, "#created by aro")?;
+        for (section, header) in [(Section::Main, "#main"), (Section::Extra, "#extra"), (Section::Side, "!side")] {
+            writeln!(f, "{header}")?;
+            for &(id, n) in self.section(section) {
+                (0..n).try_for_each(|_| writeln!(f, "{id}"))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_text<'a, W: Write>(&self, f: &mut W, with_set_codes: bool, lookup: impl Fn(usize) -> Option<&'a Card>) -> fmt::Result {
+        for section in Section::ALL.into_iter().filter(|&s| !self.section(s).is_empty()) {
+            let title = match section {
+                Section::Main => "Main Deck",
+                Section::Extra => "Extra Deck",
+                Section::Side => "Side Deck",
+            };
+            match with_set_codes {
+                true => writeln!(f, "{title} ({})", self.size(section))?,
+                false => writeln!(f, "{title}")?,
+            }
+            for (card, n) in self.section(section).iter().filter_map(|&(id, n)| Some((lookup(id)?, n))) {
+                match card.card_sets.first().filter(|_| with_set_codes) {
+                    Some(printing) => writeln!(f, "{n} {} [{}]", card.name, printing.set_code)?,
+                    None => writeln!(f, "{n} {}", card.name)?,
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads a `.ydk`. Lines that aren’t IDs (e.g. comments) are ignored, but there has to be at least one card.
+impl FromStr for Deck {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut deck = Self::default();
+        let mut section = Section::Main;
+        for line in s.lines().map(str::trim) {
+            match line {
+                "#main" => section = Section::Main,
+                "#extra" => section = Section::Extra,
+                "!side" => section = Section::Side,
+                _ => {
+                    let Ok(id) = line.parse() else { continue };
+                    let cards = deck.section_mut(section);
+                    match cards.iter_mut().find(|(other, _)| *other == id) {
+                        Some((_, n)) => *n += 1,
+                        None => cards.push((id, 1)),
+                    }
+                }
+            }
+        }
+        match Section::ALL.iter().all(|&s| deck.section(s).is_empty()) {
+            true => Err("That doesn’t look like a .ydk file, there are no card IDs in it".to_owned()),
+            false => Ok(deck),
+        }
+    }
+}
+
+/// Decks being built, keyed by a token in the user’s cookies.
+#[derive(Debug, Default)]
+pub struct DeckSessions {
+    decks: Mutex<HashMap<String, (Instant, Deck)>>,
+}
+
+impl DeckSessions {
+    /// Starts an empty deck and returns its token. If there are too many sessions, the least recently used one is dropped.
+    pub fn create(&self) -> String {
+        let mut decks = self.decks.lock().unwrap();
+        if decks.len() >= MAX_SESSIONS {
+            if let Some(oldest) = decks.iter().min_by_key(|(_, (used, _))| *used).map(|(token, _)| token.clone()) {
+                decks.remove(&oldest);
+            }
+        }
+        let token = random_token();
+        decks.insert(token.clone(), (Instant::now(), Deck::default()));
+        token
+    }
+
+    pub fn get(&self, token: &str) -> Option<Deck> {
+        self.decks.lock().unwrap().get(token).map(|(_, deck)| deck.clone())
+    }
+
+    pub fn update<R>(&self, token: &str, f: impl FnOnce(&mut Deck) -> R) -> Option<R> {
+        let mut decks = self.decks.lock().unwrap();
+        let (used, deck) = decks.get_mut(token)?;
+        *used = Instant::now();
+        Some(f(deck))
+    }
+
+    pub fn remove(&self, token: &str) {
+        self.decks.lock().unwrap().remove(token);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{BanlistInfo, CardSet, MiscInfo};
+
+    #[test]
+    fn deck_test() {
+        let normal = Card { id: 1, name: "Dark Magician".to_owned(), card_type: "Normal Monster".to_owned(), ..Default::default() };
+        let alt_art = Card { id: 2, ..normal.clone() };
+        let synchro = Card { id: 3, name: "Stardust Dragon".to_owned(), card_type: "Synchro Monster".to_owned(), ..Default::default() };
+        let limited = Card {
+            id: 4,
+            name: "Ash Blossom & Joyous Spring".to_owned(),
+            card_type: "Tuner Monster".to_owned(),
+            banlist_info: Some(BanlistInfo { ban_tcg: BanlistStatus::Limited, ..Default::default() }),
+            ..Default::default()
+        };
+        let cards = [&normal, &alt_art, &synchro, &limited];
+        let lookup = |id| cards.iter().find(|c| c.id == id).copied();
+
+        let mut deck = Deck::default();
+        (0..3).for_each(|_| deck.add(&normal, false));
+        deck.add(&alt_art, true);
+        deck.add(&synchro, false);
+        deck.add(&limited, false);
+        deck.add(&limited, true);
+        assert_eq!(deck.section(Section::Main), &[(1, 3), (4, 1)]);
+        assert_eq!(deck.section(Section::Extra), &[(3, 1)]);
+        assert_eq!(deck.section(Section::Side), &[(2, 1), (4, 1)]);
+        assert_eq!(
+            deck.problems(lookup, Locale::En),
+            vec![
+                "The main deck needs at least 40 cards, but it has 4.",
+                "Dark Magician is limited to 3 copies, but the deck has 4.",
+                "Ash Blossom & Joyous Spring is limited to 1 copy, but the deck has 2.",
+            ]
+        );
+
+        deck.remove(1, Section::Main);
+        deck.remove(4, Section::Side);
+        deck.remove(3, Section::Side);
+        assert_eq!(deck.section(Section::Main), &[(1, 2), (4, 1)]);
+        assert_eq!(deck.section(Section::Side), &[(2, 1)]);
+        assert_eq!(deck.problems(lookup, Locale::En).len(), 1);
+    }
+
+    #[test]
+    fn export_test() {
+        let printing = CardSet { set_code: "LOB-EN005".to_owned(), ..Default::default() };
+        let normal = Card { id: 1, name: "Dark Magician".to_owned(), card_sets: vec![printing], ..Default::default() };
+        let link = Card { id: 2, name: "Linkuriboh".to_owned(), card_type: "Link Monster".to_owned(), ..Default::default() };
+        let mut deck = Deck::from_cards([(&normal, 2), (&link, 1)]);
+        deck.add(&link, true);
+        let lookup = |id| [&normal, &link].into_iter().find(|c| c.id == id);
+
+        assert_eq!(deck.export(ExportFormat::Ydk, lookup), "#created by aro\n#main\n1\n1\n#extra\n2\n!side\n2\n");
+        assert_eq!(
+            deck.export(ExportFormat::MasterDuel, lookup),
+            "Main Deck\n2 Dark Magician\n\nExtra Deck\n1 Linkuriboh\n\nSide Deck\n1 Linkuriboh\n\n"
+        );
+        assert_eq!(Deck::from_cards([(&normal, 3)]).export(ExportFormat::Text, lookup), "Main Deck (3)\n3 Dark Magician [LOB-EN005]\n\n");
+    }
+
+    #[test]
+    fn ydk_test() {
+        let deck: Deck = "#created by someone\n#main\n1\n1\n3\n#extra\n2\n!side\n1\n".parse().unwrap();
+        assert_eq!(deck.section(Section::Main), &[(1, 2), (3, 1)]);
+        assert_eq!(deck.section(Section::Extra), &[(2, 1)]);
+        assert_eq!(deck.section(Section::Side), &[(1, 1)]);
+        assert_eq!(deck.export(ExportFormat::Ydk, |_| None), "#created by aro\n#main\n1\n1\n3\n#extra\n2\n!side\n1\n");
+        assert!("#main\n#extra\n".parse::<Deck>().is_err());
+    }
+
+    #[test]
+    fn genesys_points_test() {
+        let points = |genesys_points| vec![MiscInfo { genesys_points: Some(genesys_points), ..Default::default() }];
+        let ash = Card { id: 1, name: "Ash Blossom & Joyous Spring".to_owned(), misc_info: points(10), ..Default::default() };
+        let alt_art = Card { id: 2, ..ash.clone() };
+        let droll = Card { id: 3, name: "Droll & Lock Bird".to_owned(), misc_info: points(33), ..Default::default() };
+        let free = Card { id: 4, name: "Pot of Greed".to_owned(), ..Default::default() };
+        let cards = [&ash, &alt_art, &droll, &free];
+        let deck: Deck = "#main\n1\n1\n3\n4\n4\n4\n!side\n2\n".parse().unwrap();
+        let genesys = deck.genesys_points(|id| cards.iter().find(|c| c.id == id).copied());
+        assert_eq!(genesys.total, 63);
+        assert_eq!(genesys.cards.iter().map(|&(c, n, p)| (c.id, n, p)).collect::<Vec<_>>(), vec![(3, 1, 33), (1, 3, 10)]);
+    }
+
+    #[test]
+    fn export_format_test() {
+        assert_eq!(ExportFormat::from_accept("application/x-ydk"), Some(ExportFormat::Ydk));
+        assert_eq!(ExportFormat::from_accept("text/html, text/plain;q=0.9"), Some(ExportFormat::Text));
+        assert_eq!(ExportFormat::from_accept("text/html,*/*;q=0.8"), None);
+        assert_eq!("md".parse(), Ok(ExportFormat::MasterDuel));
+        assert!("pdf".parse::<ExportFormat>().is_err());
+    }
+
+    #[test]
+    fn sessions_test() {
+        let sessions = DeckSessions::default();
+        let token = sessions.create();
+        let card = Card { id: 1, card_type: "Spell Card".to_owned(), ..Default::default() };
+        assert_eq!(sessions.update(&token, |deck| deck.add(&card, false)), Some(()));
+        assert_eq!(sessions.get(&token).unwrap().size(Section::Main), 1);
+        assert_eq!(sessions.update("nope", |deck| deck.add(&card, false)), None);
+        sessions.remove(&token);
+        assert_eq!(sessions.get(&token), None);
+    }
+}
@@ -0,0 +1,218 @@
+//! Building a deck, checking it, and exporting it. See `deck.rs` for the deck itself.
+use actix_web::{http::header, route, web, HttpRequest, HttpResponse};
+use aro_core::scryfall;
+use serde::Deserialize;
+use std::sync::atomic::Ordering;
+
+use crate::{
+    admin::{may_write, unauthorized},
+    deck::{Deck, ExportFormat, Section},
+    deck_token, html, lflist, locale, maintenance_page,
+    pages::{DeckPage, DeckSection, GenesysPage, Meta, NAME},
+    parser, persistent_cookie,
+    proxy::url,
+    read_only_page,
+    settings::redirect_back,
+    state::{AppState, State},
+    theme, AnyResult, DATA_READY, DECKS, DECK_COOKIE, GENESYS_CAP, READ_ONLY,
+};
+
+#[route("/deck/new", method = "POST")]
+pub async fn new_deck(req: HttpRequest) -> AnyResult<HttpResponse> {
+    if READ_ONLY.load(Ordering::Relaxed) {
+        return read_only_page(&req);
+    }
+    if !may_write(&req, "started a deck") {
+        return Ok(unauthorized());
+    }
+    let mut res = HttpResponse::SeeOther().insert_header((header::LOCATION, url("/deck/current"))).finish();
+    res.add_cookie(&persistent_cookie(DECK_COOKIE, DECKS.create()))?;
+    Ok(res)
+}
+
+#[derive(Debug, Deserialize)]
+struct AddToDeckForm {
+    id:   usize,
+    /// Otherwise, the card goes into the main or extra deck.
+    #[serde(default)]
+    side: bool,
+}
+
+#[route("/deck/add", method = "POST")]
+pub async fn add_to_deck(req: HttpRequest, state: State, form: web::Form<AddToDeckForm>) -> AnyResult<HttpResponse> {
+    if READ_ONLY.load(Ordering::Relaxed) {
+        return read_only_page(&req);
+    }
+    if !may_write(&req, "added a card to a deck") {
+        return Ok(unauthorized());
+    }
+    let Some(card) = state.cards_by_id.get(&form.id) else {
+        return Ok(HttpResponse::BadRequest().body("Unknown card"));
+    };
+    match deck_token(&req).and_then(|token| DECKS.update(&token, |deck| deck.add(card, form.side))) {
+        Some(()) => Ok(redirect_back(&req)),
+        // The session is gone, e.g. because the server restarted. The deck page offers to start a new one.
+        None => Ok(HttpResponse::SeeOther().insert_header((header::LOCATION, url("/deck/current"))).finish()),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RemoveFromDeckForm {
+    id:      usize,
+    section: String,
+}
+
+#[route("/deck/remove", method = "POST")]
+pub async fn remove_from_deck(req: HttpRequest, form: web::Form<RemoveFromDeckForm>) -> AnyResult<HttpResponse> {
+    if READ_ONLY.load(Ordering::Relaxed) {
+        return read_only_page(&req);
+    }
+    if !may_write(&req, "removed a card from a deck") {
+        return Ok(unauthorized());
+    }
+    let section: Section = match form.section.parse() {
+        Ok(section) => section,
+        Err(e) => return Ok(HttpResponse::BadRequest().body(e)),
+    };
+    if let Some(token) = deck_token(&req) {
+        DECKS.update(&token, |deck| deck.remove(form.id, section));
+    }
+    Ok(HttpResponse::SeeOther().insert_header((header::LOCATION, url("/deck/current"))).finish())
+}
+
+#[route("/deck/close", method = "POST")]
+pub async fn close_deck(req: HttpRequest) -> AnyResult<HttpResponse> {
+    if READ_ONLY.load(Ordering::Relaxed) {
+        return read_only_page(&req);
+    }
+    if !may_write(&req, "closed a deck") {
+        return Ok(unauthorized());
+    }
+    if let Some(token) = deck_token(&req) {
+        DECKS.remove(&token);
+    }
+    let mut res = HttpResponse::SeeOther().insert_header((header::LOCATION, url("/deck/current"))).finish();
+    res.add_removal_cookie(&persistent_cookie(DECK_COOKIE, String::new()))?;
+    Ok(res)
+}
+
+#[route("/deck/current", method = "GET", method = "HEAD")]
+pub async fn deck_page(req: HttpRequest, state: State) -> AnyResult<HttpResponse> {
+    let theme = theme(&req);
+    if !DATA_READY.load(Ordering::Acquire) {
+        return maintenance_page(&req);
+    }
+    let deck = deck_token(&req).and_then(|token| DECKS.get(&token));
+    html(&DeckPage {
+        meta:     Meta::new(format!("{} - {NAME}", locale(&req).tr("Your deck")), String::new())
+            .with_theme(theme)
+            .with_locale(locale(&req)),
+        problems: deck.as_ref().map(|d| d.problems(|id| state.cards_by_id.get(&id), locale(&req))).unwrap_or_default(),
+        sections: deck.map(|deck| {
+            Section::ALL
+                .into_iter()
+                .map(|section| DeckSection {
+                    section,
+                    size: deck.size(section),
+                    cards: deck.section(section).iter().filter_map(|&(id, n)| Some((state.cards_by_id.get(&id)?, n))).collect(),
+                })
+                .collect()
+        }),
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct YdkForm {
+    #[serde(default)]
+    ydk: String,
+}
+
+/// Checks a pasted `.ydk` against the Genesys points, or the deck that’s being built if nothing was pasted.
+#[route("/deck/genesys", method = "GET", method = "POST")]
+pub async fn genesys_check(req: HttpRequest, state: State, form: Option<web::Form<YdkForm>>) -> AnyResult<HttpResponse> {
+    if !DATA_READY.load(Ordering::Acquire) {
+        return maintenance_page(&req);
+    }
+    let ydk = form.map(|f| f.into_inner().ydk).unwrap_or_default();
+    let (deck, error) = match ydk.trim() {
+        "" => (deck_token(&req).and_then(|token| DECKS.get(&token)), None),
+        ydk => match ydk.parse::<Deck>() {
+            Ok(deck) => (Some(deck), None),
+            Err(e) => (None, Some(locale(&req).tr(&e).to_owned())),
+        },
+    };
+    html(&GenesysPage {
+        meta: Meta::new(
+            format!("{} - {NAME}", locale(&req).tr("Genesys points")),
+            locale(&req).tr("Check how many Genesys points a deck uses").to_owned(),
+        )
+        .with_theme(theme(&req))
+        .with_locale(locale(&req)),
+        cap: *GENESYS_CAP,
+        points: deck.map(|d| d.genesys_points(|id| state.cards_by_id.get(&id))),
+        ydk,
+        error,
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct DeckExportQuery {
+    format: Option<String>,
+}
+
+/// Defaults to `.ydk` because that’s what most people want to load into a simulator.
+#[route("/deck/export", method = "GET")]
+pub async fn export_deck(req: HttpRequest, state: State, q: web::Query<DeckExportQuery>) -> AnyResult<HttpResponse> {
+    if !DATA_READY.load(Ordering::Acquire) {
+        return Ok(HttpResponse::ServiceUnavailable().finish());
+    }
+    let Some(deck) = deck_token(&req).and_then(|token| DECKS.get(&token)) else {
+        return Ok(HttpResponse::NotFound().body("You’re not building a deck"));
+    };
+    let format = export_format(&req, q.format.as_deref())?.unwrap_or(ExportFormat::Ydk);
+    Ok(deck_export(&state, &deck, format, "deck"))
+}
+
+/// An explicit `format=` wins over the Accept header.
+pub fn export_format(req: &HttpRequest, format: Option<&str>) -> AnyResult<Option<ExportFormat>> {
+    match format {
+        Some(format) => Ok(Some(format.parse()?)),
+        None => Ok(req.headers().get(header::ACCEPT).and_then(|a| a.to_str().ok()).and_then(ExportFormat::from_accept)),
+    }
+}
+
+pub fn deck_export(state: &AppState, deck: &Deck, format: ExportFormat, filename: &str) -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header((header::CONTENT_TYPE, format.content_type()))
+        .insert_header((header::CONTENT_DISPOSITION, format!("attachment; filename=\"{filename}.{}\"", format.extension())))
+        .body(deck.export(format, |id| state.cards_by_id.get(&id)))
+}
+
+#[derive(Debug, Deserialize)]
+struct LflistQuery {
+    q:      String,
+    name:   Option<String>,
+    /// How many copies of each matching card are allowed. Defaults to 3.
+    copies: Option<u8>,
+}
+
+/// Every card matching the query at `copies`, and everything else forbidden, for custom formats in EDOPro.
+#[route("/lflist", method = "GET")]
+pub async fn export_lflist(state: State, q: web::Query<LflistQuery>) -> AnyResult<HttpResponse> {
+    if !DATA_READY.load(Ordering::Acquire) {
+        return Ok(HttpResponse::ServiceUnavailable().finish());
+    }
+    let parsed = scryfall::translate(q.q.trim()).and_then(|t| parser::parse_filters(&t.query));
+    let (_, filter, options, _) = match parsed {
+        Ok(parsed) => parsed,
+        Err(e) => return Ok(HttpResponse::BadRequest().body(format!("Could not parse query: {e}"))),
+    };
+    // Not deduplicated, because the simulator needs every ID.
+    let cards = aro_core::search(&state.search_cards, &filter, &options).into_iter().filter_map(|c| state.cards_by_id.get(&c.id));
+    let name = q.name.as_deref().filter(|n| !n.trim().is_empty()).unwrap_or("aro");
+    let list = lflist::whitelist(name, &q.q, cards, q.copies.unwrap_or(3).min(3));
+    Ok(HttpResponse::Ok()
+        .insert_header((header::CONTENT_TYPE, "text/plain; charset=utf-8"))
+        .insert_header((header::CONTENT_DISPOSITION, "attachment; filename=\"lflist.conf\""))
+        .body(list))
+}
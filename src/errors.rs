@@ -0,0 +1,165 @@
+//! Request IDs, and error responses that include them, so users can report failures that can actually be found in the logs.
+use actix_web::{
+    body::{self, BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    error::InternalError,
+    http::{
+        header::{self, HeaderName, HeaderValue},
+        StatusCode,
+    },
+    middleware::Next,
+    HttpMessage, HttpRequest, HttpResponse,
+};
+use askama::Template;
+use serde::Serialize;
+
+use crate::{
+    locale,
+    pages::{ErrorPage, Meta, NAME},
+    proxy::client_ip,
+    random_token, theme,
+};
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Stored in the request extensions for handlers that want to log something.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+/// What every `/api/` endpoint returns on errors.
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct ErrorBody<'a> {
+    pub error:      &'a str,
+    /// The HTTP status
+    pub code:       u16,
+    pub request_id: &'a str,
+}
+
+/// Reuses the ID from a proxy in front of us so both logs match, but only if it looks harmless.
+fn request_id(incoming: Option<&str>) -> String {
+    incoming
+        .filter(|id| (1..=64).contains(&id.len()) && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-'))
+        .map_or_else(random_token, str::to_owned)
+}
+
+/// Middleware that tags every request with an ID and makes sure errors mention it.
+pub async fn with_request_id(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let id = request_id(req.headers().get(&REQUEST_ID_HEADER).and_then(|v| v.to_str().ok()));
+    req.extensions_mut().insert(RequestId(id.clone()));
+    let (method, path) = (req.method().clone(), req.path().to_owned());
+    let mut res = match next.call(req).await {
+        Ok(res) => res.map_into_boxed_body(),
+        // Errors that come back as such instead of as a response are turned into one later, which needs the ID just as much.
+        Err(e) => {
+            let mut response = e.error_response();
+            eprintln!("[{id}] {method} {path} {}: {e}", response.status().as_u16());
+            response.headers_mut().insert(REQUEST_ID_HEADER, HeaderValue::from_str(&id).expect("request IDs are ASCII"));
+            return Err(InternalError::from_response(e, response).into());
+        }
+    };
+    if res.status().is_client_error() || res.status().is_server_error() {
+        res = error_response(res).await;
+    }
+    // The ID is either ours (hex) or was checked above, so it’s always a valid header value.
+    res.headers_mut().insert(REQUEST_ID_HEADER, HeaderValue::from_str(&id).expect("request IDs are ASCII"));
+    Ok(res)
+}
+
+async fn error_response(res: ServiceResponse<BoxBody>) -> ServiceResponse<BoxBody> {
+    let status = res.status();
+    let (req, response) = res.into_parts();
+    let id = req.extensions().get::<RequestId>().map(|RequestId(id)| id.clone()).unwrap_or_default();
+    let id = id.as_str();
+    let content_type = response.headers().get(header::CONTENT_TYPE).and_then(|ct| ct.to_str().ok()).unwrap_or_default().to_owned();
+    let error = response.error().map(ToString::to_string);
+    // Pages that are already rendered (e.g. maintenance) stay as they are, they only get the header.
+    if content_type.starts_with("text/html") || content_type.starts_with("application/json") {
+        log(&req, status, id, error.as_deref().or(status.canonical_reason()).unwrap_or_default());
+        return ServiceResponse::new(req, response);
+    }
+    // Headers like `WWW-Authenticate` still apply to the new body.
+    let headers = response.headers().clone();
+    let body = body::to_bytes(response.into_body()).await.unwrap_or_default();
+    let message = match error {
+        Some(error) => error,
+        None if !body.is_empty() => String::from_utf8_lossy(&body).into_owned(),
+        None => status.canonical_reason().unwrap_or("Error").to_owned(),
+    };
+    log(&req, status, id, &message);
+    let mut response = match req.path().starts_with("/api/") {
+        true => HttpResponse::build(status).json(ErrorBody { error: &message, code: status.as_u16(), request_id: id }),
+        false => {
+            let page = ErrorPage {
+                meta: Meta::new(format!("{} - {NAME}", status.canonical_reason().unwrap_or("Error")), message.clone())
+                    .with_theme(theme(&req))
+                    .with_locale(locale(&req)),
+                message,
+                request_id: id.to_owned(),
+            };
+            match page.render() {
+                Ok(html) => HttpResponse::build(status).insert_header(header::ContentType::html()).body(html),
+                Err(_) => HttpResponse::build(status).body(page.message),
+            }
+        }
+    };
+    for (name, value) in headers.iter().filter(|(name, _)| *name != header::CONTENT_TYPE && *name != header::CONTENT_LENGTH) {
+        response.headers_mut().append(name.clone(), value.clone());
+    }
+    ServiceResponse::new(req, response)
+}
+
+/// An error from an `/api/` endpoint, in the same form as the ones this middleware makes.
+pub fn api_error(req: &HttpRequest, status: StatusCode, message: &str) -> HttpResponse {
+    let id = req.extensions().get::<RequestId>().map(|RequestId(id)| id.clone()).unwrap_or_default();
+    HttpResponse::build(status).json(ErrorBody { error: message, code: status.as_u16(), request_id: &id })
+}
+
+fn log(req: &HttpRequest, status: StatusCode, id: &str, message: &str) {
+    eprintln!("[{id}] {} {} {} {}: {message}", client_ip(req), req.method(), req.path(), status.as_u16());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{
+        error::ErrorBadRequest,
+        middleware::from_fn,
+        test::{call_service, init_service, try_call_service, TestRequest},
+        web, App,
+    };
+
+    #[test]
+    fn request_id_test() {
+        assert_eq!(request_id(Some("abc-123")), "abc-123");
+        assert_eq!(request_id(None).len(), 32);
+        assert_eq!(request_id(Some("a\nb")).len(), 32);
+        assert_ne!(request_id(Some(&"a".repeat(65))), "a".repeat(65));
+        assert_ne!(request_id(None), request_id(None));
+    }
+
+    #[actix_web::test]
+    async fn every_error_has_an_id_test() {
+        let app = init_service(
+            App::new()
+                .wrap(from_fn(|_: ServiceRequest, _: Next<BoxBody>| async { Err::<ServiceResponse, _>(ErrorBadRequest("Nope")) }))
+                .wrap(from_fn(with_request_id))
+                .route("/", web::get().to(HttpResponse::Ok)),
+        )
+        .await;
+        let request = TestRequest::default().insert_header((REQUEST_ID_HEADER, "abc")).to_request();
+        let res = try_call_service(&app, request).await.unwrap_err().error_response();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(res.headers().get(REQUEST_ID_HEADER).unwrap(), "abc");
+
+        let app = init_service(
+            App::new().wrap(from_fn(with_request_id)).route("/api/json", web::get().to(|| async { HttpResponse::NotFound().json("gone") })),
+        )
+        .await;
+        let res = call_service(&app, TestRequest::get().uri("/api/json").to_request()).await;
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+        assert!(res.headers().contains_key(REQUEST_ID_HEADER));
+    }
+}
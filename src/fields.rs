@@ -0,0 +1,88 @@
+//! Everything a UI or bot needs to build a query without guessing, for `/api/fields`.
+use std::collections::BTreeSet;
+
+use aro_core::{
+    data::{era_names, rarity_name, rarity_rank, Card, REGION_NAMES},
+    filter::FLAGS,
+    parser::Field,
+    tags::{all_tags, OPT_KINDS},
+};
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct FieldInfo {
+    pub name:        &'static str,
+    /// Shorter names that work just the same
+    pub aliases:     &'static [&'static str],
+    /// As used in the readable query, e.g. “level/rank”
+    pub description: String,
+    pub operators:   Vec<&'static str>,
+    /// Every value that can match something, if the field only has a few of them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub values:      Option<Vec<String>>,
+}
+
+/// All filter fields. Directives like `sort:` change the results instead of filtering them, so they aren’t listed.
+pub fn field_info(cards: &[Card]) -> Vec<FieldInfo> {
+    Field::ALL
+        .into_iter()
+        .filter(|f| !f.is_directive())
+        .map(|field| FieldInfo {
+            name:        field.names()[0],
+            aliases:     &field.names()[1..],
+            description: field.to_string(),
+            operators:   field.operators().iter().map(|op| op.symbol()).collect(),
+            values:      values(field, cards),
+        })
+        .collect()
+}
+
+fn values(field: Field, cards: &[Card]) -> Option<Vec<String>> {
+    let known = |values: &[&str]| Some(values.iter().map(|v| v.to_string()).collect());
+    match field {
+        Field::Attribute => from_cards(cards, |c| c.attribute.iter().map(|a| a.to_lowercase()).collect()),
+        Field::Class => from_cards(cards, |c| vec![c.card_type.to_lowercase()]),
+        Field::Type => from_cards(cards, |c| vec![c.r#type.to_lowercase()]),
+        Field::MaxRarity => {
+            let tiers: BTreeSet<u8> = cards.iter().flat_map(|c| &c.card_sets).map(|s| rarity_rank(&s.set_rarity)).collect();
+            Some(tiers.into_iter().filter_map(rarity_name).map(str::to_owned).collect())
+        }
+        Field::Deck => known(&["main", "extra"]),
+        Field::Appears => known(&["anime", "manga"]),
+        Field::Era => Some(era_names().map(str::to_owned).collect()),
+        Field::Does => Some(all_tags().map(str::to_owned).collect()),
+        Field::Opt => known(&OPT_KINDS),
+        Field::Is => known(&FLAGS),
+        Field::Region => known(&REGION_NAMES),
+        _ => None,
+    }
+}
+
+/// Sorted and deduplicated
+fn from_cards(cards: &[Card], values: impl Fn(&Card) -> Vec<String>) -> Option<Vec<String>> {
+    Some(cards.iter().flat_map(values).collect::<BTreeSet<_>>().into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn field_info_test() {
+        let card = |attribute: &str, card_type: &str| Card {
+            attribute: Some(attribute.to_owned()),
+            card_type: card_type.to_owned(),
+            r#type: "Warrior".to_owned(),
+            ..Default::default()
+        };
+        let fields = field_info(&[card("DARK", "Effect Monster"), card("LIGHT", "Link Monster"), card("DARK", "Normal Monster")]);
+        let field = |name| fields.iter().find(|f| f.name == name).unwrap();
+        assert_eq!(field("attribute").values, Some(vec!["dark".to_owned(), "light".to_owned()]));
+        assert_eq!(field("type").values, Some(vec!["warrior".to_owned()]));
+        assert_eq!(field("class").aliases, ["c"]);
+        assert_eq!(field("atk").operators, [":", "!=", "<", "<=", ">", ">="]);
+        assert_eq!(field("atk").values, None);
+        assert!(field("name").operators.contains(&"=~"));
+        assert!(fields.iter().all(|f| f.name != "sort"));
+    }
+}
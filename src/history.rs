@@ -0,0 +1,68 @@
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+const MAX_ENTRIES: usize = 10;
+// Cookies are limited to 4 KB, so very long queries just aren’t remembered.
+const MAX_QUERY_LEN: usize = 200;
+
+/// The most recent queries of a user and how many results they had, newest first.
+/// Stored url-encoded in a cookie, so nothing about it is kept on the server.
+#[derive(Debug, PartialEq, Eq, Default)]
+pub struct History(Vec<(String, usize)>);
+
+impl History {
+    pub fn push(&mut self, query: &str, results: usize) {
+        if query.len() > MAX_QUERY_LEN {
+            return;
+        }
+        self.0.retain(|(q, _)| q != query);
+        self.0.insert(0, (query.to_owned(), results));
+        self.0.truncate(MAX_ENTRIES);
+    }
+
+    pub fn entries(&self) -> &[(String, usize)] {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl FromStr for History {
+    type Err = serde_urlencoded::de::Error;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_urlencoded::from_str(s).map(Self)
+    }
+}
+
+impl Display for History {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&serde_urlencoded::to_string(&self.0).map_err(|_| fmt::Error)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn history_test() {
+        let mut history = History::default();
+        history.push("c:spell", 1500);
+        history.push("o:\"draw 2\" & l:4", 3);
+        history.push("c:spell", 1501);
+        history.push(&"a".repeat(MAX_QUERY_LEN + 1), 0);
+        assert_eq!(history.entries(), &[("c:spell".to_owned(), 1501), ("o:\"draw 2\" & l:4".to_owned(), 3)]);
+
+        let serialized = history.to_string();
+        assert!(!serialized.contains([';', ',', ' ', '"']), "{serialized} is not a valid cookie value");
+        assert_eq!(serialized.parse::<History>().unwrap(), history);
+
+        (0..20).for_each(|i| history.push(&i.to_string(), i));
+        assert_eq!(history.entries().len(), MAX_ENTRIES);
+        assert_eq!(history.entries()[0], ("19".to_owned(), 19));
+    }
+}
@@ -0,0 +1,536 @@
+//! Interface languages. The card data itself is whatever the dump has, this only covers our own strings.
+//! Strings are looked up by their English text, so the templates stay readable and anything without a translation is just English.
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{self, HeaderValue},
+    middleware::Next,
+};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    str::FromStr,
+    sync::LazyLock,
+};
+
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+    Ja,
+}
+
+impl Locale {
+    pub const ALL: [Self; 3] = [Self::En, Self::De, Self::Ja];
+
+    /// In the language itself, for the language links.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::En => "English",
+            Self::De => "Deutsch",
+            Self::Ja => "日本語",
+        }
+    }
+
+    /// The best supported language from an `Accept-Language` header, if any.
+    pub fn from_accept_language(header: &str) -> Option<Self> {
+        header
+            .split(',')
+            .enumerate()
+            .filter_map(|(i, entry)| {
+                let mut parts = entry.split(';');
+                let tag = parts.next()?.trim();
+                let quality = parts.find_map(|p| p.trim().strip_prefix("q=")).map_or(Some(1.0), |q| q.parse::<f32>().ok())?;
+                let locale = tag.split('-').next()?.to_lowercase().parse().ok()?;
+                Some((locale, quality, i))
+            })
+            .filter(|&(_, quality, _)| quality > 0.0)
+            // Earlier entries win ties.
+            .max_by(|a, b| a.1.total_cmp(&b.1).then(b.2.cmp(&a.2)))
+            .map(|(locale, ..)| locale)
+    }
+
+    /// The translation of `en`, or `en` itself if there is none.
+    pub fn tr(self, en: &str) -> &str {
+        let index = match self {
+            Self::En => return en,
+            Self::De => 0,
+            Self::Ja => 1,
+        };
+        TABLE.get(en).map_or(en, |translations| translations[index])
+    }
+
+    /// Like `tr`, but replaces `{name}` placeholders with the values.
+    pub fn trf(self, en: &str, args: &[(&str, &dyn Display)]) -> String {
+        args.iter().fold(self.tr(en).to_owned(), |s, (name, value)| s.replace(&format!("{{{name}}}"), &value.to_string()))
+    }
+}
+
+impl FromStr for Locale {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "en" => Ok(Self::En),
+            "de" => Ok(Self::De),
+            "ja" => Ok(Self::Ja),
+            _ => Err(format!("Unknown language: {s}")),
+        }
+    }
+}
+
+/// Also the value of `<html lang>`.
+impl Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::En => "en",
+            Self::De => "de",
+            Self::Ja => "ja",
+        })
+    }
+}
+
+/// Pages are in the language from `Accept-Language` unless there’s a cookie, so caches must not mix them up.
+/// Error pages are rendered by `errors::with_request_id`, so this has to run outside of it.
+pub async fn vary_by_language(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let mut res = next.call(req).await?;
+    let is_html = res.headers().get(header::CONTENT_TYPE).and_then(|ct| ct.to_str().ok()).is_some_and(|ct| ct.starts_with("text/html"));
+    if is_html {
+        res.headers_mut().append(header::VARY, HeaderValue::from_static("accept-language"));
+    }
+    Ok(res)
+}
+
+static TABLE: LazyLock<HashMap<&str, [&str; 2]>> = LazyLock::new(|| TRANSLATIONS.iter().map(|&(en, de, ja)| (en, [de, ja])).collect());
+
+/// English, German, Japanese. Placeholders like `{n}` have to stay the same in every language.
+/// `{base}` is only for strings with links and is replaced with `BASE_PATH`, see `Meta::tr_html`.
+const TRANSLATIONS: &[(&str, &str, &str)] = &[
+    // Every page
+    (
+        "Enter query (e.g. l:5 c:synchro atk>2000)",
+        "Suche eingeben (z. B. l:5 c:synchro atk>2000)",
+        "検索クエリを入力（例: l:5 c:synchro atk>2000）",
+    ),
+    (
+        "Maintenance: collections, decks, shortlinks, and price watches are read-only for now. Searching still works.",
+        "Wartung: Sammlungen, Decks, Kurzlinks und Preisalarme können gerade nicht geändert werden. Die Suche funktioniert weiterhin.",
+        "メンテナンス中: コレクション、デッキ、短縮リンク、価格アラートは現在読み取り専用です。検索は引き続き利用できます。",
+    ),
+    ("Home", "Startseite", "ホーム"),
+    ("Query Syntax", "Suchsyntax", "検索構文"),
+    ("Collection", "Sammlung", "コレクション"),
+    ("Deck", "Deck", "デッキ"),
+    ("Switch to dark theme", "Zum dunklen Design wechseln", "ダークテーマに切り替え"),
+    ("Switch to light theme", "Zum hellen Design wechseln", "ライトテーマに切り替え"),
+    // Start page
+    (
+        "Welcome to my cheap Scryfall clone for Yugioh.",
+        "Willkommen bei meinem billigen Scryfall-Klon für Yugioh.",
+        "遊戯王版の安上がりなScryfallクローンへようこそ。",
+    ),
+    ("Enter a query above to search", "Gib oben eine Suche ein", "上の欄に検索クエリを入力してください"),
+    (
+        "Enter a query above to search or read the <a href='{base}/help'>query syntax</a> for more information.",
+        "Gib oben eine Suche ein oder lies die <a href='{base}/help'>Suchsyntax</a> für mehr Informationen.",
+        "上の欄にクエリを入力して検索するか、詳しくは<a href='{base}/help'>検索構文</a>をご覧ください。",
+    ),
+    (
+        "Or see which cards were <a href='{base}/onthisday'>released on this day</a>, or which ones are <a href='{base}/trending'>trending</a>.",
+        "Oder sieh dir an, welche Karten <a href='{base}/onthisday'>an diesem Tag erschienen sind</a> oder welche gerade <a href='{base}/trending'>beliebt</a> sind.",
+        "<a href='{base}/onthisday'>今日発売されたカード</a>や<a href='{base}/trending'>人気のカード</a>もご覧いただけます。",
+    ),
+    ("Recent searches", "Letzte Suchen", "最近の検索"),
+    (
+        "Your recent searches are stored in a cookie.",
+        "Deine letzten Suchen werden in einem Cookie gespeichert.",
+        "最近の検索はCookieに保存されます。",
+    ),
+    ("Stop remembering them", "Nicht mehr speichern", "保存しない"),
+    ("Remember my recent searches", "Meine letzten Suchen speichern", "最近の検索を保存する"),
+    (
+        "The source code is available <a href='https://github.com/kageru/aro'>on Github</a>.",
+        "Der Quellcode ist <a href='https://github.com/kageru/aro'>auf Github</a> verfügbar.",
+        "ソースコードは<a href='https://github.com/kageru/aro'>GitHub</a>で公開しています。",
+    ),
+    (
+        "If you have any feedback, feel free to add @kageru on Discord or send an email to &lt;that name&gt;@encode.moe.",
+        "Für Feedback kannst du @kageru auf Discord hinzufügen oder eine E-Mail an &lt;diesen Namen&gt;@encode.moe schicken.",
+        "ご意見はDiscordで@kageruを追加するか、&lt;その名前&gt;@encode.moe までメールでお寄せください。",
+    ),
+    // Results
+    ("{n} results", "{n} Ergebnisse", "{n}件"),
+    ("No results", "Keine Ergebnisse", "該当なし"),
+    (
+        "Showing {start}-{end} of {total} results {details}",
+        "Ergebnisse {start}–{end} von {total} {details}",
+        "{total}件中{start}～{end}件を表示 {details}",
+    ),
+    ("Showing {total} results {details}", "{total} Ergebnisse {details}", "{total}件を表示 {details}"),
+    ("where {query}", "mit {query}", "条件: {query}"),
+    ("because the filters contradict each other", "weil sich die Filter widersprechen", "（フィルターが互いに矛盾しています）"),
+    ("only cards you own", "nur Karten, die du besitzt", "所持カードのみ"),
+    ("only cards you don’t own", "nur Karten, die du nicht besitzt", "未所持カードのみ"),
+    ("with the banlist from {date}", "mit der Banliste vom {date}", "{date}時点の制限リストで"),
+    ("sorted by {sort}", "sortiert nach {sort}", "{sort}順"),
+    ("grouped by {group}", "gruppiert nach {group}", "{group}でグループ化"),
+    ("Other", "Sonstige", "その他"),
+    ("took", "Dauer", "所要時間"),
+    ("Sort by:", "Sortieren nach:", "並べ替え:"),
+    ("Per page:", "Pro Seite:", "表示件数:"),
+    ("View:", "Ansicht:", "表示:"),
+    ("Grid", "Raster", "グリッド"),
+    ("List", "Liste", "リスト"),
+    ("EDOPro banlist", "EDOPro-Banlist", "EDOPro用リミットレギュレーション"),
+    (
+        "Allows 3 of every result and nothing else",
+        "Erlaubt 3 von jedem Ergebnis und sonst nichts",
+        "検索結果のカードを3枚まで許可し、それ以外は禁止します",
+    ),
+    ("Name", "Name", "名前"),
+    ("Type", "Typ", "種類"),
+    ("Stats", "Werte", "ステータス"),
+    ("Set", "Set", "セット"),
+    ("Price", "Preis", "価格"),
+    ("First page", "Erste Seite", "最初のページ"),
+    ("Previous page", "Vorherige Seite", "前のページ"),
+    ("Next page", "Nächste Seite", "次のページ"),
+    ("Last page", "Letzte Seite", "最後のページ"),
+    // Titles and errors
+    ("Card not found", "Karte nicht gefunden", "カードが見つかりません"),
+    ("Link not found", "Link nicht gefunden", "リンクが見つかりません"),
+    ("Set not found", "Set nicht gefunden", "セットが見つかりません"),
+    ("Maintenance", "Wartung", "メンテナンス"),
+    ("The card data is currently unavailable", "Die Kartendaten sind gerade nicht verfügbar", "カードデータは現在利用できません"),
+    (
+        "The card data is currently being updated. Please try again in a few minutes.",
+        "Die Kartendaten werden gerade aktualisiert. Bitte versuche es in ein paar Minuten noch einmal.",
+        "カードデータを更新中です。数分後にもう一度お試しください。",
+    ),
+    ("Read-only", "Schreibgeschützt", "読み取り専用"),
+    ("This feature is temporarily unavailable", "Diese Funktion ist vorübergehend nicht verfügbar", "この機能は一時的に利用できません"),
+    (
+        "This is temporarily disabled for maintenance. Searching still works, please try again later.",
+        "Das ist wegen Wartungsarbeiten vorübergehend deaktiviert. Die Suche funktioniert weiterhin, bitte versuche es später noch einmal.",
+        "メンテナンスのため一時的に無効になっています。検索は引き続き利用できます。しばらくしてからもう一度お試しください。",
+    ),
+    (
+        "If you report this, please include the request ID:",
+        "Wenn du das meldest, gib bitte diese Request-ID an:",
+        "報告する際は、次のリクエストIDを記載してください:",
+    ),
+    ("Could not parse query: {error}", "Suche konnte nicht verarbeitet werden: {error}", "クエリを解析できませんでした: {error}"),
+    (
+        "You need to upload your collection to use owned:",
+        "Lade zuerst deine Sammlung hoch, um owned: zu verwenden",
+        "owned: を使うには、まずコレクションをアップロードしてください",
+    ),
+    (
+        "Only unreleased cards match. Add include:spoilers to see them.",
+        "Nur noch nicht erschienene Karten passen. Füge include:spoilers hinzu, um sie zu sehen.",
+        "未発売のカードのみが該当します。表示するには include:spoilers を追加してください。",
+    ),
+    ("This shortlink doesn’t exist.", "Diesen Kurzlink gibt es nicht.", "この短縮リンクは存在しません。"),
+    ("There is no set with the code {code}", "Es gibt kein Set mit dem Code {code}", "コード{code}のセットはありません"),
+    (
+        "Your lists are too long to be stored in your browser. Export and remove some cards first.",
+        "Deine Listen sind zu lang, um im Browser gespeichert zu werden. Exportiere sie und entferne zuerst ein paar Karten.",
+        "リストが長すぎてブラウザに保存できません。先にエクスポートして、いくつかのカードを削除してください。",
+    ),
+    (
+        "{price} is not a valid price. Use something like 4.99.",
+        "{price} ist kein gültiger Preis. Gib etwas wie 4.99 ein.",
+        "{price}は有効な価格ではありません。4.99のように入力してください。",
+    ),
+    ("That collection is empty", "Diese Sammlung ist leer", "このコレクションは空です"),
+    ("Invalid JSON: {error}", "Ungültiges JSON: {error}", "無効なJSONです: {error}"),
+    ("Every entry needs an id or a set_code", "Jeder Eintrag braucht eine id oder einen set_code", "各エントリにはidまたはset_codeが必要です"),
+    (
+        "Line {line} is neither a card ID nor a set code: {value}",
+        "Zeile {line} ist weder eine Karten-ID noch ein Set-Code: {value}",
+        "{line}行目はカードIDでもセットコードでもありません: {value}",
+    ),
+    ("Invalid quantity in line {line}: {value}", "Ungültige Anzahl in Zeile {line}: {value}", "{line}行目の枚数が無効です: {value}"),
+    (
+        "That doesn’t look like a .ydk file, there are no card IDs in it",
+        "Das sieht nicht nach einer .ydk-Datei aus, es sind keine Karten-IDs darin",
+        ".ydkファイルではないようです。カードIDが含まれていません",
+    ),
+    // Descriptions
+    ("Search the text of rulings", "Durchsuche den Text von Rulings", "裁定のテキストを検索"),
+    ("{n} cards were released on {day}", "{n} Karten sind am {day} erschienen", "{day}に発売されたカードは{n}枚です"),
+    ("The most viewed cards of the last {n} days", "Die meistaufgerufenen Karten der letzten {n} Tage", "過去{n}日間で最も閲覧されたカード"),
+    ("Prices and rarities of {name} ({code})", "Preise und Seltenheiten von {name} ({code})", "{name}（{code}）の価格とレアリティ"),
+    ("What you’re missing to complete {code}", "Was dir zum Vervollständigen von {code} fehlt", "{code}をコンプリートするのに足りないカード"),
+    ("Upload your collection to search within it", "Lade deine Sammlung hoch, um darin zu suchen", "コレクションをアップロードして、その中から検索できます"),
+    ("Check how many Genesys points a deck uses", "Prüfe, wie viele Genesys-Punkte ein Deck verbraucht", "デッキが使うGenesysポイントを確認"),
+    ("Trending", "Beliebt", "人気"),
+    ("Released on {day}", "Erschienen am {day}", "{day}発売"),
+    ("Your collection", "Deine Sammlung", "あなたのコレクション"),
+    ("Your lists", "Deine Listen", "あなたのリスト"),
+    ("Lists full", "Listen voll", "リストがいっぱいです"),
+    ("Your deck", "Dein Deck", "あなたのデッキ"),
+    ("Invalid price", "Ungültiger Preis", "無効な価格"),
+    ("Rulings", "Rulings", "裁定"),
+    ("Genesys points", "Genesys-Punkte", "Genesysポイント"),
+    ("Set completion: {code}", "Set-Vervollständigung: {code}", "コンプリート状況: {code}"),
+    // Card page
+    ("← Previous", "← Zurück", "← 前へ"),
+    ("Next →", "Weiter →", "次へ →"),
+    ("Result {position} of {total}", "Ergebnis {position} von {total}", "{total}件中{position}件目"),
+    ("Card Image", "Kartenbild", "カード画像"),
+    ("Lowest price:", "Niedrigster Preis:", "最安値:"),
+    ("Show:", "Zeitraum:", "表示:"),
+    ("Other versions:", "Andere Versionen:", "他のバージョン:"),
+    ("Extra info", "Weitere Infos", "追加情報"),
+    ("Released in:", "Erschienen in:", "発売地域:"),
+    ("Anime appearances:", "Auftritte im Anime:", "アニメでの登場:"),
+    ("Manga appearances:", "Auftritte im Manga:", "漫画での登場:"),
+    ("Source:", "Quelle:", "出典:"),
+    ("Supported by 1 card that mentions it", "Unterstützt von 1 Karte, die sie erwähnt", "このカードに言及しているカードが1枚あります"),
+    ("Supported by {n} cards that mention it", "Unterstützt von {n} Karten, die sie erwähnen", "このカードに言及しているカードが{n}枚あります"),
+    (
+        "Supported by 1 card that mentions it or the {archetype} archetype",
+        "Unterstützt von 1 Karte, die sie oder den Archetyp {archetype} erwähnt",
+        "このカードまたは「{archetype}」アーキタイプに言及しているカードが1枚あります",
+    ),
+    (
+        "Supported by {n} cards that mention it or the {archetype} archetype",
+        "Unterstützt von {n} Karten, die sie oder den Archetyp {archetype} erwähnen",
+        "このカードまたは「{archetype}」アーキタイプに言及しているカードが{n}枚あります",
+    ),
+    ("Other {archetype} cards:", "Andere {archetype}-Karten:", "他の「{archetype}」カード:"),
+    ("See all {n} {archetype} cards", "Alle {n} {archetype}-Karten ansehen", "「{archetype}」のカード{n}枚をすべて見る"),
+    ("Referenced by:", "Erwähnt von:", "言及しているカード:"),
+    ("Similar cards:", "Ähnliche Karten:", "類似カード:"),
+    ("Wishlist", "Wunschliste", "欲しいものリスト"),
+    ("Any printing", "Beliebiger Druck", "任意の版"),
+    ("Add to list", "Zur Liste hinzufügen", "リストに追加"),
+    ("Alert me when the price drops to", "Benachrichtige mich, wenn der Preis fällt auf", "価格がこの値以下になったら通知:"),
+    ("Watch price", "Preis beobachten", "価格を監視"),
+    ("Price alerts feed", "Feed der Preisalarme", "価格アラートのフィード"),
+    ("Legality:", "Legalität:", "使用可否:"),
+    ("Format", "Format", "フォーマット"),
+    ("Copies", "Exemplare", "枚数"),
+    ("Status", "Status", "状態"),
+    ("Not legal", "Nicht erlaubt", "使用不可"),
+    ("Forbidden", "Verboten", "禁止"),
+    ("Limited", "Limitiert", "制限"),
+    ("Semi-Limited", "Semi-Limitiert", "準制限"),
+    ("Unlimited", "Unbeschränkt", "無制限"),
+    ("Printings:", "Drucke:", "収録:"),
+    ("Date", "Datum", "日付"),
+    ("Rarity", "Seltenheit", "レアリティ"),
+    (
+        "Only printed for North America so far, never for Europe.",
+        "Bisher nur für Nordamerika gedruckt, nie für Europa.",
+        "これまで北米版のみで、欧州版はありません。",
+    ),
+    (
+        "Only printed for Europe so far, never for North America.",
+        "Bisher nur für Europa gedruckt, nie für Nordamerika.",
+        "これまで欧州版のみで、北米版はありません。",
+    ),
+    ("English", "Englisch", "英語版"),
+    ("European", "Europäisch", "欧州版"),
+    ("Code", "Code", "コード"),
+    ("Release", "Erscheinung", "発売日"),
+    ("Buy", "Kaufen", "購入"),
+    ("Download a QR code that opens this page", "QR-Code herunterladen, der diese Seite öffnet", "このページを開くQRコードをダウンロード"),
+    ("QR code for this page", "QR-Code für diese Seite", "このページのQRコード"),
+    ("QR code", "QR-Code", "QRコード"),
+    // Collection and set completion
+    (
+        "Your collection has {n} cards. Search for <code>owned:yes</code> or <code>owned:no</code> to filter by it.",
+        "Deine Sammlung hat {n} Karten. Suche nach <code>owned:yes</code> oder <code>owned:no</code>, um danach zu filtern.",
+        "コレクションには{n}枚のカードがあります。<code>owned:yes</code>または<code>owned:no</code>で絞り込めます。",
+    ),
+    ("Value", "Wert", "価値"),
+    (
+        "We don’t know the value of your collection yet. It’s updated whenever prices are.",
+        "Wir kennen den Wert deiner Sammlung noch nicht. Er wird aktualisiert, sobald sich die Preise ändern.",
+        "コレクションの価値はまだわかりません。価格が更新されるたびに更新されます。",
+    ),
+    ("Forget my collection", "Meine Sammlung vergessen", "コレクションを削除"),
+    ("Replace it", "Ersetzen", "置き換える"),
+    (
+        "Upload your collection to search within it using <code>owned:yes</code> or <code>owned:no</code> and see how many copies you have of each card.",
+        "Lade deine Sammlung hoch, um mit <code>owned:yes</code> oder <code>owned:no</code> darin zu suchen und zu sehen, wie viele Exemplare du von jeder Karte hast.",
+        "コレクションをアップロードすると、<code>owned:yes</code>や<code>owned:no</code>で検索したり、各カードの所持枚数を確認したりできます。",
+    ),
+    (
+        "Paste your collection as CSV or JSON. In CSV, every line needs a card ID or a set code (e.g. LOB-EN005) in the first column and can have a quantity in the second. JSON has to be a list of objects with an <code>id</code> or <code>set_code</code> and an optional <code>quantity</code>.",
+        "Füge deine Sammlung als CSV oder JSON ein. In CSV braucht jede Zeile eine Karten-ID oder einen Set-Code (z. B. LOB-EN005) in der ersten Spalte und kann eine Anzahl in der zweiten haben. JSON muss eine Liste von Objekten mit <code>id</code> oder <code>set_code</code> und optional <code>quantity</code> sein.",
+        "コレクションをCSVまたはJSONで貼り付けてください。CSVでは、各行の1列目にカードIDまたはセットコード（例: LOB-EN005）が必要で、2列目に枚数を書くこともできます。JSONは、<code>id</code>または<code>set_code</code>と、任意の<code>quantity</code>を持つオブジェクトのリストにしてください。",
+    ),
+    ("Upload", "Hochladen", "アップロード"),
+    (
+        "Paste your collection as CSV. Every line needs a card ID or a set code (e.g. LOB-EN005) in the first column and can have a quantity in the second.",
+        "Füge deine Sammlung als CSV ein. Jede Zeile braucht eine Karten-ID oder einen Set-Code (z. B. LOB-EN005) in der ersten Spalte und kann eine Anzahl in der zweiten haben.",
+        "コレクションをCSVで貼り付けてください。各行の1列目にカードIDまたはセットコード（例: LOB-EN005）が必要で、2列目に枚数を書くこともできます。",
+    ),
+    ("Check", "Prüfen", "確認"),
+    (
+        "You own {owned} of {total} cards. Completing the set costs about {cost}.",
+        "Du besitzt {owned} von {total} Karten. Das Set zu vervollständigen kostet etwa {cost}.",
+        "{total}枚中{owned}枚を所持しています。コンプリートには約{cost}かかります。",
+    ),
+    (
+        "You own {owned} of {total} cards. Completing the set costs about {cost} (plus {unpriced} cards without a price).",
+        "Du besitzt {owned} von {total} Karten. Das Set zu vervollständigen kostet etwa {cost} (dazu {unpriced} Karten ohne Preis).",
+        "{total}枚中{owned}枚を所持しています。コンプリートには約{cost}かかります（価格のないカード{unpriced}枚を除く）。",
+    ),
+    ("Rarities", "Seltenheiten", "レアリティ"),
+    // Set page
+    (
+        "{cards} cards. One of each costs about {one}, a playset of each about {playset}, always using the cheapest rarity.",
+        "{cards} Karten. Eine von jeder kostet etwa {one}, ein Playset von jeder etwa {playset}, jeweils in der günstigsten Seltenheit.",
+        "{cards}種類。各1枚で約{one}、各3枚で約{playset}です（いずれも最安のレアリティで計算）。",
+    ),
+    (
+        "{cards} cards. One of each costs about {one}, a playset of each about {playset} (plus {unpriced} cards without a price), always using the cheapest rarity.",
+        "{cards} Karten. Eine von jeder kostet etwa {one}, ein Playset von jeder etwa {playset} (dazu {unpriced} Karten ohne Preis), jeweils in der günstigsten Seltenheit.",
+        "{cards}種類。各1枚で約{one}、各3枚で約{playset}です（価格のないカード{unpriced}枚を除く、いずれも最安のレアリティで計算）。",
+    ),
+    ("Search this set", "In diesem Set suchen", "このセットを検索"),
+    ("Check what you’re missing", "Prüfen, was dir fehlt", "足りないカードを確認"),
+    ("Most expensive", "Am teuersten", "高額カード"),
+    ("By rarity", "Nach Seltenheit", "レアリティ別"),
+    ("Printings", "Drucke", "収録数"),
+    ("Share of the set’s value", "Anteil am Wert des Sets", "セット全体の価値に占める割合"),
+    // Deck and lists
+    ("This deck is legal in the TCG.", "Dieses Deck ist im TCG legal.", "このデッキはTCGで使用できます。"),
+    ("Main deck", "Main Deck", "メインデッキ"),
+    ("Extra deck", "Extra Deck", "エクストラデッキ"),
+    ("Side deck", "Side Deck", "サイドデッキ"),
+    (
+        "No cards yet. Search for cards and use the buttons next to them to add them.",
+        "Noch keine Karten. Suche nach Karten und füge sie mit den Knöpfen daneben hinzu.",
+        "まだカードがありません。カードを検索し、横のボタンで追加してください。",
+    ),
+    ("Remove one", "Eine entfernen", "1枚削除"),
+    ("Export:", "Exportieren:", "エクスポート:"),
+    ("Text with set codes", "Text mit Set-Codes", "セットコード付きテキスト"),
+    ("Stop building this deck", "Deckbau beenden", "デッキ作成を終了"),
+    (
+        "You’re not building a deck right now. While you are, search results get buttons to add cards to it.",
+        "Du baust gerade kein Deck. Währenddessen haben Suchergebnisse Knöpfe, um Karten hinzuzufügen.",
+        "現在デッキを作成していません。作成中は、検索結果にカードを追加するボタンが表示されます。",
+    ),
+    ("Start a new deck", "Neues Deck beginnen", "新しいデッキを作成"),
+    ("Add to deck", "Zum Deck hinzufügen", "デッキに追加"),
+    ("Add to side", "Zum Side Deck hinzufügen", "サイドに追加"),
+    (
+        "The main deck needs at least {min} cards, but it has {n}.",
+        "Das Main Deck braucht mindestens {min} Karten, hat aber {n}.",
+        "メインデッキには{min}枚以上必要ですが、{n}枚しかありません。",
+    ),
+    (
+        "The main deck can’t have more than {max} cards, but it has {n}.",
+        "Das Main Deck darf höchstens {max} Karten haben, hat aber {n}.",
+        "メインデッキは{max}枚までですが、{n}枚あります。",
+    ),
+    (
+        "The extra deck can’t have more than {max} cards, but it has {n}.",
+        "Das Extra Deck darf höchstens {max} Karten haben, hat aber {n}.",
+        "エクストラデッキは{max}枚までですが、{n}枚あります。",
+    ),
+    (
+        "The side deck can’t have more than {max} cards, but it has {n}.",
+        "Das Side Deck darf höchstens {max} Karten haben, hat aber {n}.",
+        "サイドデッキは{max}枚までですが、{n}枚あります。",
+    ),
+    (
+        "{card} is limited to 1 copy, but the deck has {n}.",
+        "{card} ist auf 1 Exemplar beschränkt, aber das Deck hat {n}.",
+        "{card}は1枚までですが、デッキには{n}枚あります。",
+    ),
+    (
+        "{card} is limited to {allowed} copies, but the deck has {n}.",
+        "{card} ist auf {allowed} Exemplare beschränkt, aber das Deck hat {n}.",
+        "{card}は{allowed}枚までですが、デッキには{n}枚あります。",
+    ),
+    ("Export as CSV", "Als CSV exportieren", "CSVでエクスポート"),
+    ("Text", "Text", "テキスト"),
+    ("Quantity", "Anzahl", "枚数"),
+    ("Printing", "Druck", "版"),
+    ("Any", "Beliebig", "指定なし"),
+    ("Remove", "Entfernen", "削除"),
+    (
+        "You don’t have any lists yet. Add cards to a list from their card pages.",
+        "Du hast noch keine Listen. Füge Karten auf ihren Kartenseiten zu einer Liste hinzu.",
+        "まだリストがありません。カードのページからリストに追加できます。",
+    ),
+    // Genesys
+    (
+        "Paste a .ydk file to see how many points it uses. Without one, this checks the deck you’re building.",
+        "Füge eine .ydk-Datei ein, um zu sehen, wie viele Punkte sie verbraucht. Ohne Datei wird das Deck geprüft, das du gerade baust.",
+        ".ydkファイルを貼り付けると、使用ポイントを確認できます。貼り付けない場合は、作成中のデッキを確認します。",
+    ),
+    (
+        "This deck uses {total} points, {over} more than the cap of {cap}.",
+        "Dieses Deck verbraucht {total} Punkte, {over} mehr als die Obergrenze von {cap}.",
+        "このデッキは{total}ポイントを使用しており、上限の{cap}を{over}ポイント超えています。",
+    ),
+    ("This deck uses {total} of {cap} points.", "Dieses Deck verbraucht {total} von {cap} Punkten.", "このデッキは{cap}ポイント中{total}ポイントを使用しています。"),
+    ("Points", "Punkte", "ポイント"),
+    ("Total", "Gesamt", "合計"),
+    ("You’re not building a deck right now.", "Du baust gerade kein Deck.", "現在デッキを作成していません。"),
+    // Trending, rulings, on this day
+    (
+        "The most viewed cards of the last {n} days. Use <code>sort:-views</code> to sort any search by this.",
+        "Die meistaufgerufenen Karten der letzten {n} Tage. Mit <code>sort:-views</code> kannst du jede Suche danach sortieren.",
+        "過去{n}日間で最も閲覧されたカードです。<code>sort:-views</code>で任意の検索をこの順に並べ替えられます。",
+    ),
+    ("No cards have been viewed yet.", "Bisher wurden keine Karten aufgerufen.", "まだ閲覧されたカードはありません。"),
+    ("Views", "Aufrufe", "閲覧数"),
+    ("e.g. negate activation", "z. B. negate activation", "例: negate activation"),
+    ("Search rulings", "Rulings durchsuchen", "裁定を検索"),
+    (
+        "Showing {shown} of {total} cards with matching rulings. Add more words to narrow it down.",
+        "{shown} von {total} Karten mit passenden Rulings. Füge weitere Wörter hinzu, um die Suche einzugrenzen.",
+        "該当する裁定があるカード{total}枚中{shown}枚を表示しています。語句を追加して絞り込んでください。",
+    ),
+    ("{n} cards with matching rulings", "{n} Karten mit passenden Rulings", "該当する裁定があるカード: {n}枚"),
+    ("Source", "Quelle", "出典"),
+    ("No cards were released on this day.", "An diesem Tag ist keine Karte erschienen.", "この日に発売されたカードはありません。"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use itertools::Itertools;
+    use test_case::test_case;
+
+    #[test_case("de-DE,de;q=0.9,en;q=0.8" => Some(Locale::De))]
+    #[test_case("en-US,en;q=0.9,ja;q=0.8" => Some(Locale::En))]
+    #[test_case("fr-FR, ja;q=0.5, de;q=0.7" => Some(Locale::De))]
+    #[test_case("ja, de" => Some(Locale::Ja); "ties go to the first one")]
+    #[test_case("fr, de;q=0" => None)]
+    #[test_case("*" => None)]
+    fn accept_language_test(header: &str) -> Option<Locale> {
+        Locale::from_accept_language(header)
+    }
+
+    #[test]
+    fn tr_test() {
+        assert_eq!(Locale::En.tr("Home"), "Home");
+        assert_eq!(Locale::De.tr("Home"), "Startseite");
+        assert_eq!(Locale::Ja.tr("Not translated yet"), "Not translated yet");
+        assert_eq!(Locale::De.trf("{n} results", &[("n", &3)]), "3 Ergebnisse");
+    }
+
+    #[test]
+    fn placeholders_match() {
+        let placeholders =
+            |s: &str| s.split('{').skip(1).filter_map(|p| p.split_once('}')).map(|(name, _)| name.to_owned()).sorted().collect_vec();
+        for (en, de, ja) in TRANSLATIONS {
+            assert_eq!(placeholders(en), placeholders(de), "{en}");
+            assert_eq!(placeholders(en), placeholders(ja), "{en}");
+        }
+        assert_eq!(TABLE.len(), TRANSLATIONS.len(), "duplicate keys");
+    }
+}
@@ -0,0 +1,89 @@
+//! What the scheduler runs in the background, see `scheduler.rs`.
+use std::{sync::atomic::Ordering, time::Duration};
+use time::OffsetDateTime;
+
+use crate::{
+    collection::Collection, data::Dump, i18n::Locale, scheduler::Schedule, searchpage::matching_cards, state::AppState, CARD_SOURCES,
+    CARD_VIEWS, COLLECTIONS, PRICES, PRICE_HISTORY, PRICE_WATCHES, QUERY_STATS, READ_ONLY, TOP_QUERIES,
+};
+
+/// Re-reads the prices from the dump (which is updated externally) and checks them against all price watches.
+pub fn refresh_prices(state: &AppState) -> Result<(), String> {
+    let prices = Dump::load_prices(&CARD_SOURCES).map_err(|e| format!("Could not refresh prices: {e}"))?;
+    *PRICES.write().unwrap() = prices;
+    let prices = PRICES.read().unwrap();
+    // Watches are only checked when their state can be saved, so alerts come once read-only mode is over.
+    if READ_ONLY.load(Ordering::Relaxed) {
+        println!("Refreshed prices of {} cards", prices.len());
+        return Ok(());
+    }
+    let alerts = PRICE_WATCHES
+        .check(|id| prices.get(&id)?.lowest, OffsetDateTime::now_utc())
+        .map_err(|e| format!("Could not save price watches: {e}"))?;
+    println!("Refreshed prices of {} cards, {alerts} new price alerts", prices.len());
+    let snapshot: Vec<_> = prices.iter().filter_map(|(&id, p)| Some((id, p.lowest?))).collect();
+    drop(prices);
+    let history = PRICE_HISTORY.record(snapshot, OffsetDateTime::now_utc().unix_timestamp());
+    let history = history.map(drop).map_err(|e| format!("Could not update price history: {e}"));
+    let values = record_collection_values(state).map_err(|e| format!("Could not update collection values: {e}"));
+    history.and(values)
+}
+
+fn record_collection_values(state: &AppState) -> std::io::Result<()> {
+    let now = OffsetDateTime::now_utc().unix_timestamp();
+    // Read from disk so this doesn’t pull every collection into the cache.
+    for token in COLLECTIONS.tokens()? {
+        if let Some(collection) = COLLECTIONS.read(&token) {
+            COLLECTIONS.record_value(&token, now, collection_value(state, &collection))?;
+        }
+    }
+    Ok(())
+}
+
+pub fn collection_value(state: &AppState, collection: &Collection) -> i32 {
+    let prices = PRICES.read().unwrap();
+    collection.value(|id| prices.get(&id)?.lowest, |code| state.ids_by_set_code.get(code).copied())
+}
+
+/// A number of seconds from the environment.
+pub fn env_secs(name: &str) -> Option<Duration> {
+    std::env::var(name).ok().and_then(|s| s.parse().ok()).map(Duration::from_secs)
+}
+
+/// Prices are only refreshed if this is set.
+pub fn price_refresh_schedule() -> Option<Schedule> {
+    Schedule::from_env("PRICE_REFRESH", None)
+}
+
+/// Views are only counted in memory in between, so this is how many could be lost in a crash.
+pub fn views_flush_schedule() -> Option<Schedule> {
+    Schedule::from_env("CARD_VIEWS_FLUSH", Some(Duration::from_secs(300)))
+}
+
+/// Views stay in memory while read-only, so they’re saved once it’s over (unless the server stops in the meantime).
+pub fn flush_card_views() -> Result<(), String> {
+    if READ_ONLY.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+    CARD_VIEWS.flush(OffsetDateTime::now_utc()).map_err(|e| format!("Could not save card views: {e}"))
+}
+
+/// If set, a failed data load doesn’t stop the server. Instead, a maintenance page is shown while loading is retried.
+pub fn data_retry_schedule() -> Option<Schedule> {
+    Schedule::from_env("DATA_RETRY", None)
+}
+
+/// If set, the card data is read again regularly, e.g. when a cronjob updates the dumps.
+pub fn data_refresh_schedule() -> Option<Schedule> {
+    Schedule::from_env("DATA_REFRESH", None)
+}
+
+/// Searches the most common queries again, so they’re still in the result cache when someone wants them.
+pub fn warm_result_cache(state: &AppState) -> Result<(), String> {
+    let queries = QUERY_STATS.top(TOP_QUERIES);
+    for (query, _) in &queries {
+        // Errors are shown to whoever searched this, there’s nothing to do about them here.
+        let _ = matching_cards(state, query, None, Locale::En);
+    }
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use std::{collections::HashMap, fmt::Write};
+
+use time::{Date, Month};
+
+use crate::data::{BanlistHistory, BanlistStatus, Card};
+
+/// A banlist read from an EDOPro `lflist.conf`, for formats the dump doesn’t have, e.g. Master Duel or Edison.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Banlist {
+    pub name:  String,
+    /// Cards that aren’t on a whitelist can’t be played at all. On a normal list, they are unlimited.
+    whitelist: bool,
+    copies:    HashMap<usize, u8>,
+}
+
+impl Banlist {
+    /// `None` if the card isn’t on a whitelist.
+    pub fn status(&self, id: usize) -> Option<BanlistStatus> {
+        match self.copies.get(&id) {
+            Some(&copies) => Some(BanlistStatus::from_copies(copies)),
+            None if self.whitelist => None,
+            None => Some(BanlistStatus::Unlimited),
+        }
+    }
+}
+
+/// Every list in the file, in order. Lines that aren’t `id copies` are ignored like EDOPro does.
+pub fn parse(conf: &str) -> Vec<Banlist> {
+    let mut lists: Vec<Banlist> = Vec::new();
+    for line in conf.lines().map(str::trim) {
+        if let Some(name) = line.strip_prefix('!') {
+            lists.push(Banlist { name: name.trim().to_owned(), whitelist: false, copies: HashMap::new() });
+            continue;
+        }
+        let Some(list) = lists.last_mut() else { continue };
+        if line == "$whitelist" {
+            list.whitelist = true;
+            continue;
+        }
+        let mut parts = line.split_whitespace();
+        if let (Some(Ok(id)), Some(Ok(copies))) = (parts.next().map(str::parse), parts.next().map(str::parse)) {
+            list.copies.insert(id, copies);
+        }
+    }
+    lists
+}
+
+/// Lists whose names start with the day they took effect, e.g. `!2008.09.01 TCG` or `!2008-09-01`.
+/// Other lists and whitelists are left out, since they don’t say what happened to cards that aren’t on them.
+pub fn history(lists: Vec<Banlist>) -> BanlistHistory {
+    BanlistHistory::new(
+        lists
+            .into_iter()
+            .filter(|list| !list.whitelist)
+            .filter_map(|list| {
+                let date = list_date(&list.name)?;
+                Some((date, list.copies.into_iter().map(|(id, copies)| (id, BanlistStatus::from_copies(copies))).collect()))
+            })
+            .collect(),
+    )
+}
+
+fn list_date(name: &str) -> Option<Date> {
+    let mut parts = name.split_whitespace().next()?.split(['.', '-']);
+    let year = parts.next()?.parse().ok()?;
+    let month = Month::try_from(parts.next()?.parse::<u8>().ok()?).ok()?;
+    // EDOPro names most lists by month only.
+    let day = match parts.next() {
+        Some(day) => day.parse().ok()?,
+        None => 1,
+    };
+    Date::from_calendar_date(year, month, day).ok()
+}
+
+/// An EDOPro banlist (`lflist.conf`) that allows `copies` of each of the given cards and forbids everything else.
+/// Simulators go by ID, so alternate artworks have to be in `cards` as well.
+pub fn whitelist<'a>(name: &str, query: &str, cards: impl IntoIterator<Item = &'a Card>, copies: u8) -> String {
+    let one_line = |s: &str| s.replace(['\r', '\n'], " ");
+    let name = one_line(name);
+    let mut list = format!("#[{name}]\n!{name}\n#generated by aro from: {}\n$whitelist\n", one_line(query));
+    for card in cards {
+        // Writing to a String can’t fail.
+        let _ = writeln!(list, "{} {copies} --{}", card.id, one_line(&card.name));
+    }
+    list
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        let lists = parse(&format!(
+            "#[Master Duel]\n!Master Duel\n55144522 0 --Pot of Greed\n14558127 1\n#comment\n{}",
+            whitelist("Goat", "y<2006", &[Card { id: 46986414, name: "Dark Magician".to_owned(), ..Default::default() }], 2)
+        ));
+        assert_eq!(lists.iter().map(|l| l.name.as_str()).collect::<Vec<_>>(), ["Master Duel", "Goat"]);
+        assert_eq!(lists[0].status(55144522), Some(BanlistStatus::Forbidden));
+        assert_eq!(lists[0].status(14558127), Some(BanlistStatus::Limited));
+        assert_eq!(lists[0].status(1), Some(BanlistStatus::Unlimited));
+        assert_eq!(lists[1].status(46986414), Some(BanlistStatus::SemiLimited));
+        assert_eq!(lists[1].status(1), None, "not on the whitelist");
+    }
+
+    #[test]
+    fn history_test() {
+        let conf = "!2005.04 TCG\n1 0\n!2008-09-01\n1 1\n!Master Duel\n1 3\n!2010.01.01\n$whitelist\n2 3\n";
+        let history = history(parse(conf));
+        assert_eq!(history.len(), 2);
+        let date = |y, m, d| Date::from_calendar_date(y, Month::try_from(m).unwrap(), d).unwrap();
+        assert_eq!(history.status_on(1, date(2005, 4, 1)), BanlistStatus::Forbidden);
+        assert_eq!(history.status_on(1, date(2009, 1, 1)), BanlistStatus::Limited);
+        assert_eq!(history.status_on(1, date(2004, 1, 1)), BanlistStatus::Unlimited);
+    }
+
+    #[test]
+    fn whitelist_test() {
+        let cards = [
+            Card { id: 46986414, name: "Dark Magician".to_owned(), ..Default::default() },
+            Card { id: 36996508, name: "Dark Magician".to_owned(), ..Default::default() },
+        ];
+        assert_eq!(
+            whitelist("Goat\nformat", "y<2006", &cards, 3),
+            "#[Goat format]\n!Goat format\n#generated by aro from: y<2006\n$whitelist\n46986414 3 --Dark Magician\n36996508 3 --Dark Magician\n"
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use itertools::Itertools;
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+pub const DEFAULT_LIST: &str = "Wishlist";
+
+/// Named lists of cards, e.g. a wishlist or the contents of a binder.
+/// Like the search history, they only live in a cookie.
+#[derive(Debug, PartialEq, Eq, Default)]
+pub struct CardLists(Vec<ListEntry>);
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ListEntry {
+    pub list:     String,
+    pub id:       usize,
+    /// Set code of the printing the user wants, if they care
+    pub printing: Option<String>,
+    pub quantity: u32,
+}
+
+impl CardLists {
+    /// Adding a card that’s already on the list (in the same printing) increases its quantity.
+    pub fn add(&mut self, list: &str, id: usize, printing: Option<&str>) {
+        match self.0.iter_mut().find(|e| e.list == list && e.id == id && e.printing.as_deref() == printing) {
+            Some(entry) => entry.quantity += 1,
+            None => self.0.push(ListEntry { list: list.to_owned(), id, printing: printing.map(str::to_owned), quantity: 1 }),
+        }
+    }
+
+    pub fn remove(&mut self, list: &str, id: usize, printing: Option<&str>) {
+        self.0.retain(|e| !(e.list == list && e.id == id && e.printing.as_deref() == printing));
+    }
+
+    /// In the order they were created
+    pub fn names(&self) -> Vec<&str> {
+        self.0.iter().map(|e| e.list.as_str()).unique().collect()
+    }
+
+    pub fn entries<'a>(&'a self, list: &'a str) -> impl Iterator<Item = &'a ListEntry> {
+        self.0.iter().filter(move |e| e.list == list)
+    }
+}
+
+impl FromStr for CardLists {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let pairs: Vec<(String, String)> = serde_urlencoded::from_str(s).map_err(|e| e.to_string())?;
+        pairs
+            .into_iter()
+            .map(|(list, entry)| {
+                let mut parts = entry.splitn(3, ',');
+                let id = parts.next().and_then(|id| id.parse().ok()).ok_or_else(|| format!("Invalid list entry: {entry}"))?;
+                let quantity = parts.next().and_then(|q| q.parse().ok()).ok_or_else(|| format!("Invalid list entry: {entry}"))?;
+                let printing = parts.next().filter(|p| !p.is_empty()).map(str::to_owned);
+                Ok(ListEntry { list, id, printing, quantity })
+            })
+            .collect::<Result<_, _>>()
+            .map(Self)
+    }
+}
+
+impl Display for CardLists {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pairs = self.0.iter().map(|e| (&e.list, format!("{},{},{}", e.id, e.quantity, e.printing.as_deref().unwrap_or_default())));
+        f.write_str(&serde_urlencoded::to_string(pairs.collect_vec()).map_err(|_| fmt::Error)?)
+    }
+}
+
+/// Quotes a CSV field if it needs to be.
+pub fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn card_lists_test() {
+        let mut lists = CardLists::default();
+        lists.add(DEFAULT_LIST, 46986414, None);
+        lists.add("Binder; trades", 38033121, Some("MFC-000"));
+        lists.add(DEFAULT_LIST, 46986414, None);
+        lists.add(DEFAULT_LIST, 46986414, Some("LOB-005"));
+        assert_eq!(lists.names(), vec![DEFAULT_LIST, "Binder; trades"]);
+        assert_eq!(lists.entries(DEFAULT_LIST).map(|e| e.quantity).collect_vec(), vec![2, 1]);
+
+        let serialized = lists.to_string();
+        assert!(!serialized.contains([';', ',', ' ', '"']), "{serialized} is not a valid cookie value");
+        assert_eq!(serialized.parse::<CardLists>().unwrap(), lists);
+
+        lists.remove(DEFAULT_LIST, 46986414, None);
+        assert_eq!(lists.entries(DEFAULT_LIST).map(|e| e.printing.as_deref()).collect_vec(), vec![Some("LOB-005")]);
+        assert!("Wishlist=abc".parse::<CardLists>().is_err());
+    }
+
+    #[test]
+    fn csv_field_test() {
+        assert_eq!(csv_field("Dark Magician"), "Dark Magician");
+        assert_eq!(csv_field("Ash Blossom & Joyous Spring"), "Ash Blossom & Joyous Spring");
+        assert_eq!(csv_field(r#"Gem-Knight "Master", Diamond"#), r#""Gem-Knight ""Master"", Diamond""#);
+    }
+}
@@ -0,0 +1,210 @@
+//! Reading the card data and swapping it in, at startup and whenever it’s reloaded.
+use askama::Template;
+use std::{
+    fmt::{self, Display},
+    path::Path,
+    sync::{atomic::Ordering, Arc},
+    time::Instant,
+};
+use time::OffsetDateTime;
+
+use crate::{
+    cardpage::{card_page, CardQuery, Visitor},
+    changes::CardState,
+    data::{Dump, LoadError},
+    dataversion::DataVersion,
+    i18n::Locale,
+    memory::Sizes,
+    resultcache::ResultCache,
+    searchpage::matching_cards,
+    selftest,
+    selftest::SelfTest,
+    state::{AppState, SharedState},
+    CARD_SOURCES, CHANGES, COLLECTIONS, DATA_READY, PRICES, READ_ONLY, SHARED, SITEMAP, WEBHOOKS,
+};
+
+// Every complete dump has results for these, see `self_test`.
+const SELF_TEST_QUERIES: &[&str] = &["c:effect", "c:spell", "c:trap", "a:dark l:4", "atk>=2000 sort:-atk", "o:destroy", "year:..2100"];
+
+/// Reads the dumps and swaps the new card data in. Requests that are already running finish with the old data.
+/// This is for the first load, so the data is swapped in even if the self-test fails, unless `SELF_TEST_STRICT` is set.
+pub fn load_data(shared: &SharedState) -> Result<(), LoadDataError> {
+    let now = Instant::now();
+    let state = read_data()?;
+    let test = self_test(&state);
+    log_self_test(&test);
+    if !test.failures().is_empty() && self_test_strict() {
+        return Err(LoadDataError::SelfTest(test.summary()));
+    }
+    let num_cards = state.cards.len();
+    swap_in(shared, state);
+    println!("Read {num_cards} cards in {:?}", now.elapsed());
+    Ok(())
+}
+
+#[derive(Debug)]
+pub enum LoadDataError {
+    Load(LoadError),
+    /// Only with `SELF_TEST_STRICT`.
+    SelfTest(String),
+}
+
+impl LoadDataError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Load(e) => e.exit_code(),
+            Self::SelfTest(_) => 5,
+        }
+    }
+}
+
+impl From<LoadError> for LoadDataError {
+    fn from(e: LoadError) -> Self {
+        Self::Load(e)
+    }
+}
+
+impl Display for LoadDataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Load(e) => e.fmt(f),
+            Self::SelfTest(summary) => write!(f, "{summary}, not using the data because SELF_TEST_STRICT is set"),
+        }
+    }
+}
+
+/// Like `load_data`, but for when there already is data, which stays if the new data fails the self-test.
+pub fn reload_data(shared: &SharedState) -> Result<usize, String> {
+    let state = read_data().map_err(|e| e.to_string())?;
+    let test = self_test(&state);
+    log_self_test(&test);
+    if let Some((check, error)) = test.failures().first() {
+        return Err(format!("{}, kept the old data. {check}: {error}", test.summary()));
+    }
+    let num_cards = state.cards.len();
+    swap_in(shared, state);
+    Ok(num_cards)
+}
+
+fn read_data() -> Result<AppState, LoadError> {
+    let mut sizes = Sizes::default();
+    let (dump, files) = sizes.of("Cards and sets", read_dump)?;
+    let mut state = AppState::new(dump.cards, dump.sets, sizes);
+    let without_misc_info = state.cards.iter().filter(|c| c.misc_info().is_none()).count();
+    if without_misc_info > 0 {
+        println!("Warning: {without_misc_info} cards have no misc_info, some information will be missing for them");
+    }
+    match DataVersion::new(&files, &CARD_SOURCES, state.cards.len(), OffsetDateTime::now_utc()) {
+        Ok(version) => state.version = Some(version),
+        Err(e) => eprintln!("Could not hash the card data: {e}"),
+    }
+    state.results = ResultCache::new(SHARED.as_ref(), state.version.as_ref().map(|v| v.hash.as_str()));
+    Ok(state)
+}
+
+/// Records what changed since the last data and makes `state` the one that requests use.
+fn swap_in(shared: &SharedState, state: AppState) {
+    if let Some(version) = state.version.as_ref().filter(|_| !READ_ONLY.load(Ordering::Relaxed)) {
+        match CHANGES.record(&version.hash, state.cards.iter().map(|c| (c.id, CardState::of(c)))) {
+            Ok(changes) => {
+                println!("{} changes since the last data version", changes.len());
+                if let Some(webhooks) = WEBHOOKS.as_ref() {
+                    webhooks.notify(&version.hash, &changes, |id| state.cards_by_id.get(&id).map(|c| c.name.as_str()));
+                }
+            }
+            Err(e) => eprintln!("Could not record data changes: {e}"),
+        }
+    }
+    *PRICES.write().unwrap() = state.cards.iter().map(|c| (c.id, c.prices())).collect();
+    shared.store(Arc::new(state));
+    // Their owned cards were resolved against the old set codes.
+    COLLECTIONS.clear_cache();
+    SITEMAP.clear();
+    DATA_READY.store(true, Ordering::Release);
+}
+
+fn log_self_test(test: &SelfTest) {
+    for (check, error) in test.failures() {
+        eprintln!("Self-test: {check}: {error}");
+    }
+    println!("{}", test.summary());
+}
+
+/// All card sources and the files they came from, for `AppState::version`.
+fn read_dump() -> Result<(Dump, Vec<String>), LoadError> {
+    let mut dump = Dump::load(&CARD_SOURCES)?;
+    let mut files: Vec<String> = CARD_SOURCES.iter().cloned().chain(["sets.json".to_owned()]).collect();
+    #[cfg(feature = "cdb")]
+    if let Ok(path) = std::env::var("CDB_FILE") {
+        let added = dump.add_cdb(&path)?;
+        println!("Added {added} cards from {path}");
+        files.push(path);
+    }
+    #[cfg(not(feature = "cdb"))]
+    if std::env::var_os("CDB_FILE").is_some() {
+        eprintln!("Ignoring CDB_FILE because this build can’t read card databases, see the cdb feature");
+    }
+    if let Ok(path) = std::env::var("YUGIPEDIA_FILE") {
+        let json = std::fs::read_to_string(&path).map_err(|e| LoadError::Open(path.clone(), e))?;
+        let added = dump.add_yugipedia(&json)?;
+        println!("Added Yugipedia data for {added} cards from {path}");
+        files.push(path);
+    }
+    if let Ok(path) = std::env::var("RULINGS_FILE") {
+        let json = std::fs::read_to_string(&path).map_err(|e| LoadError::Open(path.clone(), e))?;
+        let added = dump.add_rulings(&json)?;
+        println!("Added rulings for {added} cards from {path}");
+        files.push(path);
+    }
+    // The default file is optional, but one that was set explicitly has to be there.
+    let custom_path = std::env::var("CUSTOM_CARDS_FILE");
+    match std::fs::read_to_string(custom_path.as_deref().unwrap_or("custom_cards.json")) {
+        Ok(json) => {
+            files.push(custom_path.clone().unwrap_or_else(|_| "custom_cards.json".to_owned()));
+            let (added, renamed) = dump.add_custom(&json)?;
+            for (old, new) in renamed {
+                println!("Custom card {old} is now {new} because the ID was already taken");
+            }
+            println!("Added {added} custom cards");
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound && custom_path.is_err() => (),
+        Err(e) => return Err(LoadError::Open(custom_path.unwrap_or_else(|_| "custom_cards.json".to_owned()), e)),
+    }
+    Ok((dump, files))
+}
+
+/// If set, the server doesn’t start when the self-test fails.
+fn self_test_strict() -> bool {
+    std::env::var("SELF_TEST_STRICT").is_ok_and(|v| v == "1" || v == "true")
+}
+
+/// Searches, renders a card page, and checks the files and sets the pages need, see `selftest.rs`.
+fn self_test(state: &AppState) -> SelfTest {
+    let mut test = SelfTest::default();
+    test.check("Cards", || if state.cards.is_empty() { Err("No cards".to_owned()) } else { Ok(()) });
+    for &query in SELF_TEST_QUERIES {
+        test.check(format!("Query {query}"), || match matching_cards(state, query, None, Locale::En)?.cards.len() {
+            0 => Err("No results".to_owned()),
+            _ => Ok(()),
+        });
+    }
+    // The card with the most printings fills the most of its page.
+    if let Some(card) = state.cards.iter().max_by_key(|c| c.card_sets.len()).and_then(|c| state.cards_by_id.get(&c.id)) {
+        test.check(format!("Card page of {}", card.name), || {
+            let page = card_page(&Visitor::anonymous(), state, card, &CardQuery::default());
+            page.and_then(|page| Ok(page.render()?)).map(drop).map_err(|e| e.to_string())
+        });
+        test.check(format!("Name search for {}", card.name), || {
+            let matches = matching_cards(state, &format!("\"{}\"", card.name), None, Locale::En)?;
+            match matches.cards.iter().any(|c| c.name == card.name) {
+                true => Ok(()),
+                false => Err("Not found".to_owned()),
+            }
+        });
+    }
+    test.check("Set names", || selftest::check_set_names(&state.cards, &state.sets_by_name));
+    if let Ok(dir) = std::env::var("CARD_IMAGE_DIR") {
+        test.check("Static files", || selftest::check_static_files(Path::new(&dir)));
+    }
+    test
+}
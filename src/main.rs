@@ -1,276 +1,505 @@
-#![feature(try_blocks)]
-use actix_web::{http::header, route, web, App, Either, HttpResponse, HttpServer};
-use data::{Card, CardInfo, Set};
-use filter::SearchCard;
-use itertools::Itertools;
-use regex::{Captures, Regex};
-use serde::Deserialize;
+use actix_web::{
+    cookie::{time::Duration as CookieDuration, Cookie},
+    http::{header, KeepAlive},
+    middleware::from_fn,
+    web, App, HttpRequest, HttpResponse, HttpServer,
+};
+use admin::{
+    admin_clear_cache, admin_login, admin_logout, admin_memory, admin_page, admin_read_only, admin_reload, admin_reload_data, admin_stats,
+};
+use api::{
+    batch_search_api, bulk_prices, changes_api, combos, data_version_api, fields_api, on_this_day_api, price_alerts_api, quicksearch,
+    search_api,
+};
+use aro_core::{data, filter, parser};
+use askama::Template;
+use auth::Tokens;
+use browse::{help, on_this_day, rulings_page, set_overview, sitemap_xml, trending};
+use cardpage::{card_info, card_og_image, card_qr};
+use changes::ChangeLog;
+use collection::{Collection, CollectionStore};
+use collectionpages::{
+    add_to_list, collection_page, export_list, forget_collection, lists_page, price_alerts_feed, remove_from_list, set_completion,
+    upload_collection, watch_price,
+};
+use data::{Prices, DEFAULT_SOURCES};
+use deck::DeckSessions;
+use deckpages::{add_to_deck, close_deck, deck_page, export_deck, export_lflist, genesys_check, new_deck, remove_from_deck};
+use i18n::Locale;
+use jobs::{
+    data_refresh_schedule, data_retry_schedule, env_secs, flush_card_views, price_refresh_schedule, refresh_prices, views_flush_schedule,
+    warm_result_cache,
+};
+use lflist::Banlist;
+use lists::CardLists;
+use loading::{load_data, reload_data, LoadDataError};
+use ogimage::OgImages;
+use pages::{MessagePage, Meta, Theme, NAME};
+use pricehistory::PriceHistory;
+use pricewatch::PriceWatches;
+use proxy::{normalize_base_path, normalize_public_url};
+use scheduler::{Next, Schedule, Scheduler};
+use searchpage::{expand, search, shorten};
+use settings::{set_history, set_locale, set_theme};
+use shared::SharedStore;
+use shortlinks::Shortlinks;
+use sitemap::Sitemap;
+use state::{AppState, SharedState};
+use stats::{PageViews, QueryStats};
 use std::{
     collections::HashMap,
-    fmt::Write,
-    fs::File,
-    io::BufReader,
     net::Ipv4Addr,
     sync::{
-        atomic::{AtomicUsize, Ordering},
-        LazyLock,
+        atomic::{AtomicBool, Ordering},
+        Arc, LazyLock, RwLock,
     },
-    time::Instant,
+    time::{Duration, Instant},
 };
-use time::Date;
+use time::OffsetDateTime;
+use vendors::Referrals;
+use views::CardViews;
+use webhooks::Webhooks;
 
-mod data;
-mod filter;
-mod parser;
+mod accept;
+mod admin;
+mod api;
+mod apiformat;
+mod auth;
+mod browse;
+mod cardpage;
+mod changes;
+mod collection;
+mod collectionpages;
+mod cursor;
+mod dataversion;
+mod deck;
+mod deckpages;
+mod errors;
+mod fields;
+mod history;
+mod i18n;
+mod jobs;
+mod lflist;
+mod lists;
+mod loading;
+mod memory;
+mod mentions;
+mod ogimage;
+mod pages;
+mod plain;
+mod pricehistory;
+mod pricewatch;
+mod proxy;
+mod qr;
+mod render;
+mod resultcache;
+mod rulings;
+mod scheduler;
+mod searchpage;
+mod selftest;
+mod settings;
+mod shared;
+mod shortlinks;
+mod similar;
+mod sitemap;
+mod snippet;
+mod state;
+mod stats;
+#[cfg(test)]
+mod tempdir;
+mod vendors;
+mod views;
+mod webhooks;
 
 type AnyResult<T> = Result<T, Box<dyn std::error::Error>>;
 
+#[cfg(feature = "count-allocations")]
+#[global_allocator]
+static ALLOCATOR: memory::CountingAllocator = memory::CountingAllocator;
+
 // The yearly tins have ~250 cards in them.
 // I want to be higher than that so the page is usable as a set list.
-const RESULT_LIMIT: usize = 300;
+const PAGE_SIZE: usize = 300;
+// Users can request smaller pages via `n=`, which is then remembered in a cookie.
+const MIN_PAGE_SIZE: usize = 20;
+const PAGE_SIZE_COOKIE: &str = "page_size";
+const VIEW_COOKIE: &str = "view";
+const THEME_COOKIE: &str = "theme";
+const LANG_COOKIE: &str = "lang";
+const HISTORY_COOKIE: &str = "history";
+// Set if the user doesn’t want their searches remembered.
+const NO_HISTORY_COOKIE: &str = "no_history";
+const COLLECTION_COOKIE: &str = "collection";
+const LISTS_COOKIE: &str = "lists";
+const DECK_COOKIE: &str = "deck";
+// Browsers ignore cookies larger than 4 KB, including the name and attributes.
+const MAX_COOKIE_LEN: usize = 3900;
+const ARCHETYPE_CARDS: usize = 12;
+const TOP_QUERIES: usize = 20;
+// Other instances see this instance’s page views this much later.
+const PAGE_VIEWS_FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+// Results are cached for 10 minutes, so popular queries are searched again before most of them expire.
+const CACHE_WARM_INTERVAL: Duration = Duration::from_secs(300);
+// Only cards that came out since the last one are missing in between.
+const SITEMAP_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
 
-static CARDS: LazyLock<Vec<Card>> = LazyLock::new(|| {
-    let mut cards = serde_json::from_reader::<_, CardInfo>(BufReader::new(File::open("cards.json").expect("cards.json not found")))
-        .expect("Could not deserialize cards")
-        .data;
-    cards.iter_mut().for_each(|c| {
-        c.card_sets.sort_unstable_by_key(|s| SETS_BY_NAME.get(&s.set_name.to_lowercase()).and_then(|s| s.tcg_date).unwrap_or(Date::MAX))
-    });
-    cards
+/// Set by the first successful `load_data`. Until then, the state is empty and pages show the maintenance page instead.
+static DATA_READY: AtomicBool = AtomicBool::new(false);
+/// Everything that stores data is disabled while this is set, e.g. during migrations. Searching still works.
+pub static READ_ONLY: AtomicBool = AtomicBool::new(false);
+/// Files to read cards from, lowest precedence first. See `CARD_SOURCES` in the readme.
+static CARD_SOURCES: LazyLock<Vec<String>> = LazyLock::new(|| match std::env::var("CARD_SOURCES") {
+    Ok(sources) => sources.split(',').map(str::trim).filter(|s| !s.is_empty()).map(str::to_owned).collect(),
+    Err(_) => DEFAULT_SOURCES.iter().map(|s| s.to_string()).collect(),
 });
-static CARDS_BY_ID: LazyLock<HashMap<usize, Card>> = LazyLock::new(|| {
-    CARDS
-        .iter()
-        .map(|c| {
-            let text = PENDULUM_SEPARATOR
-                .replacen(&c.text.replace('\r', ""), 1, |caps: &Captures| {
-                    format!("</p><hr/>[ {} ]<p>", caps.iter().flatten().last().map_or_else(|| "Monster Effect", |g| g.as_str()))
-                })
-                .replace('\n', "<br/>");
-            (c.id, Card { text, ..c.clone() })
+static SHARED: LazyLock<Option<SharedStore>> = LazyLock::new(SharedStore::from_env);
+static SHORTLINKS: LazyLock<Shortlinks> = LazyLock::new(|| {
+    let path = std::env::var("SHORTLINKS_FILE").unwrap_or_else(|_| "shortlinks.tsv".to_owned());
+    Shortlinks::load(&path)
+        .unwrap_or_else(|e| {
+            eprintln!("Could not read shortlinks from {path}: {e}. Starting without them");
+            Shortlinks::empty(path)
         })
-        .collect()
+        .with_shared(SHARED.as_ref())
+});
+static COLLECTIONS: LazyLock<CollectionStore> =
+    LazyLock::new(|| CollectionStore::new(std::env::var("COLLECTIONS_DIR").unwrap_or_else(|_| "collections".to_owned())));
+static PRICE_WATCHES: LazyLock<PriceWatches> = LazyLock::new(|| {
+    let path = std::env::var("PRICE_WATCHES_FILE").unwrap_or_else(|_| "pricewatches.tsv".to_owned());
+    PriceWatches::load(&path).unwrap_or_else(|e| {
+        eprintln!("Could not read price watches from {path}: {e}. Starting without them");
+        PriceWatches::empty(path)
+    })
+});
+static PRICE_HISTORY: LazyLock<PriceHistory> = LazyLock::new(|| {
+    let path = std::env::var("PRICE_HISTORY_FILE").unwrap_or_else(|_| "pricehistory.tsv".to_owned());
+    PriceHistory::load(&path).unwrap_or_else(|e| {
+        eprintln!("Could not read price history from {path}: {e}. Starting without it");
+        PriceHistory::empty(path)
+    })
+});
+/// Starts out with the prices from the dump and is updated by the price refresher.
+static PRICES: LazyLock<RwLock<HashMap<usize, Prices>>> = LazyLock::new(RwLock::default);
+/// Extra banlists from an EDOPro `lflist.conf`, shown on card pages next to the ones from the dump.
+static BANLISTS: LazyLock<Vec<Banlist>> = LazyLock::new(|| {
+    let Ok(path) = std::env::var("LFLIST_FILE") else { return Vec::new() };
+    match std::fs::read_to_string(&path) {
+        Ok(conf) => lflist::parse(&conf),
+        Err(e) => {
+            eprintln!("Could not read banlists from {path}: {e}. Starting without them");
+            Vec::new()
+        }
+    }
+});
+/// `None` uses the plain card images for embeds, see `ogimage.rs`.
+static OG_IMAGES: LazyLock<Option<OgImages>> = LazyLock::new(OgImages::from_env);
+static CARD_VIEWS: LazyLock<CardViews> = LazyLock::new(|| {
+    let path = std::env::var("CARD_VIEWS_FILE").unwrap_or_else(|_| "cardviews.tsv".to_owned());
+    CardViews::load(&path, OffsetDateTime::now_utc()).unwrap_or_else(|e| {
+        eprintln!("Could not read card views from {path}: {e}. Starting without them");
+        CardViews::empty(path)
+    })
+});
+static CHANGES: LazyLock<ChangeLog> = LazyLock::new(|| {
+    let path = std::env::var("CHANGES_FILE").unwrap_or_else(|_| "changes.tsv".to_owned());
+    ChangeLog::load(&path).unwrap_or_else(|e| {
+        eprintln!("Could not read data changes from {path}: {e}. Starting without them");
+        ChangeLog::empty(path)
+    })
 });
-static SEARCH_CARDS: LazyLock<Vec<SearchCard>> = LazyLock::new(|| CARDS.iter().map(SearchCard::from).collect());
-static SETS_BY_NAME: LazyLock<HashMap<String, Set>> = LazyLock::new(|| {
-    serde_json::from_reader::<_, Vec<Set>>(BufReader::new(File::open("sets.json").expect("sets.json not found")))
-        .expect("Could not deserialize sets")
-        .into_iter()
-        .map(|s| (s.set_name.to_lowercase(), s))
-        .collect()
+static WEBHOOKS: LazyLock<Option<Webhooks>> = LazyLock::new(Webhooks::from_env);
+static SCHEDULER: LazyLock<Scheduler> = LazyLock::new(Scheduler::default);
+static SITEMAP: LazyLock<Sitemap> = LazyLock::new(Sitemap::default);
+static DECKS: LazyLock<DeckSessions> = LazyLock::new(DeckSessions::default);
+static REFERRALS: LazyLock<Referrals> = LazyLock::new(Referrals::from_env);
+/// Where aro is served, e.g. `/ygo` if the proxy serves it at `https://example.com/ygo/`. Empty for the root.
+static BASE_PATH: LazyLock<String> = LazyLock::new(|| normalize_base_path(&std::env::var("BASE_PATH").unwrap_or_default()));
+/// Scheme and host that users reach aro at, for absolute links in QR codes, embeds, and structured data. Without `BASE_PATH`.
+static PUBLIC_URL: LazyLock<String> = LazyLock::new(|| match std::env::var("PUBLIC_URL") {
+    Ok(url) => normalize_public_url(&url),
+    Err(_) => {
+        eprintln!("PUBLIC_URL is not set, so absolute links will point to http://localhost:1961");
+        "http://localhost:1961".to_owned()
+    }
 });
-static PENDULUM_SEPARATOR: LazyLock<Regex> =
-    LazyLock::new(|| Regex::new("(\\n-+)?\\n\\[\\s?(Monster Effect|Flavor Text)\\s?\\]\\n?").unwrap());
+// Static files are usually served by the proxy next to aro.
+static IMG_HOST: LazyLock<String> = LazyLock::new(|| std::env::var("IMG_HOST").unwrap_or_else(|_| BASE_PATH.clone()));
+static STARTED: LazyLock<Instant> = LazyLock::new(Instant::now);
+static QUERY_STATS: LazyLock<QueryStats> = LazyLock::new(QueryStats::default);
+static PAGE_VIEWS: LazyLock<PageViews> = LazyLock::new(PageViews::default);
+/// The admin page is disabled unless there is at least one admin token.
+static TOKENS: LazyLock<Tokens> = LazyLock::new(Tokens::from_env);
+/// If set, everything that stores data needs a token too, see `may_write`.
+static PROTECT_WRITES: LazyLock<bool> = LazyLock::new(|| std::env::var("PROTECT_WRITES").is_ok_and(|v| v == "1" || v == "true"));
+/// How many points a Genesys deck may have. This changes every now and then, so it’s not hardcoded.
+static GENESYS_CAP: LazyLock<i32> = LazyLock::new(|| std::env::var("GENESYS_CAP").ok().and_then(|s| s.parse().ok()).unwrap_or(100));
 
-static IMG_HOST: LazyLock<String> = LazyLock::new(|| std::env::var("IMG_HOST").unwrap_or_else(|_| String::new()));
+/// Without TLS, HTTP/2 only works with prior knowledge (h2c), which is what proxies use to talk to their backends.
+fn http2_enabled() -> bool {
+    std::env::var("HTTP2").is_ok_and(|v| v == "1" || v == "true")
+}
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    let now = Instant::now();
     println!("Starting server");
-    // tap these so they’re initialized
-    let num_cards = (CARDS_BY_ID.len() + SEARCH_CARDS.len()) / 2;
-    println!("Read {num_cards} cards in {:?}", now.elapsed());
-    HttpServer::new(|| App::new().service(search).service(card_info).service(help))
-        .bind((Ipv4Addr::from([127, 0, 0, 1]), 1961))?
-        .run()
-        .await
+    LazyLock::force(&STARTED);
+    LazyLock::force(&PUBLIC_URL);
+    READ_ONLY.store(std::env::var("READ_ONLY").is_ok_and(|v| v == "1" || v == "true"), Ordering::Relaxed);
+    if let Some(years) = std::env::var("UNREPRINTED_YEARS").ok().and_then(|s| s.parse().ok()) {
+        filter::UNREPRINTED_YEARS.store(years, Ordering::Relaxed);
+    }
+    if let Ok(path) = std::env::var("BANLIST_HISTORY_FILE") {
+        match std::fs::read_to_string(&path) {
+            Ok(conf) => {
+                let history = lflist::history(lflist::parse(&conf));
+                println!("Read {} old banlists from {path}", history.len());
+                let _ = filter::BANLIST_HISTORY.set(history);
+            }
+            Err(e) => eprintln!("Could not read old banlists from {path}: {e}. asof: won’t work"),
+        }
+    }
+    let state = web::Data::new(SharedState::from_pointee(AppState::default()));
+    if let Err(e) = load_data(&state) {
+        match data_retry_schedule().filter(|_| !matches!(e, LoadDataError::SelfTest(_))) {
+            Some(schedule) => {
+                eprintln!("{e}. Serving maintenance page and retrying every {:?}", schedule.interval);
+                let state = state.clone();
+                SCHEDULER.add("Retry data load", schedule, move || match DATA_READY.load(Ordering::Acquire) {
+                    true => Ok(Next::Stop),
+                    false => load_data(&state).map(|()| Next::Stop).map_err(|e| e.to_string()),
+                });
+            }
+            None => {
+                eprintln!("{e}");
+                std::process::exit(e.exit_code());
+            }
+        }
+    }
+    LazyLock::force(&SHORTLINKS);
+    LazyLock::force(&PRICE_WATCHES);
+    LazyLock::force(&PRICE_HISTORY);
+    LazyLock::force(&BANLISTS);
+    LazyLock::force(&CARD_VIEWS);
+    if let Some(schedule) = data_refresh_schedule() {
+        let state = state.clone();
+        SCHEDULER.add("Refresh data", schedule, move || match DATA_READY.load(Ordering::Acquire) {
+            true => reload_data(&state).map(|_| Next::Again),
+            false => Ok(Next::Again),
+        });
+    }
+    if let Some(schedule) = views_flush_schedule() {
+        SCHEDULER.add("Save card views", schedule, || flush_card_views().map(|()| Next::Again));
+    }
+    if let (Some(shared), Some(schedule)) = (SHARED.as_ref(), Schedule::from_env("PAGE_VIEWS_FLUSH", Some(PAGE_VIEWS_FLUSH_INTERVAL))) {
+        SCHEDULER.add("Share page views", schedule, || {
+            PAGE_VIEWS.flush(shared).map(|()| Next::Again).map_err(|e| format!("Could not share page views: {e}"))
+        });
+    }
+    if let Some(schedule) = price_refresh_schedule() {
+        let state = state.clone();
+        SCHEDULER.add("Refresh prices", schedule, move || match DATA_READY.load(Ordering::Acquire) {
+            true => refresh_prices(&state.load()).map(|()| Next::Again),
+            false => Ok(Next::Again),
+        });
+    }
+    if let Some(schedule) = Schedule::from_env("CACHE_WARM", Some(CACHE_WARM_INTERVAL)) {
+        let state = state.clone();
+        SCHEDULER.add("Warm result cache", schedule, move || match DATA_READY.load(Ordering::Acquire) {
+            true => warm_result_cache(&state.load()).map(|()| Next::Again),
+            false => Ok(Next::Again),
+        });
+    }
+    if let Some(schedule) = Schedule::from_env("SITEMAP", Some(SITEMAP_INTERVAL)) {
+        let state = state.clone();
+        SCHEDULER.add("Rebuild sitemap", schedule, move || {
+            if DATA_READY.load(Ordering::Acquire) {
+                SITEMAP.rebuild(&state.load(), OffsetDateTime::now_utc().date());
+            }
+            Ok(Next::Again)
+        });
+    }
+    let mut server = HttpServer::new(move || {
+        App::new()
+            .app_data(state.clone())
+            .wrap(from_fn(errors::with_request_id))
+            .wrap(from_fn(i18n::vary_by_language))
+            .wrap(from_fn(proxy::strip_base_path))
+            .service(search)
+            .service(card_info)
+            .service(card_qr)
+            .service(card_og_image)
+            .service(help)
+            .service(set_theme)
+            .service(set_locale)
+            .service(quicksearch)
+            .service(shorten)
+            .service(expand)
+            .service(set_history)
+            .service(on_this_day)
+            .service(rulings_page)
+            .service(sitemap_xml)
+            .service(search_api)
+            .service(batch_search_api)
+            .service(on_this_day_api)
+            .service(fields_api)
+            .service(data_version_api)
+            .service(changes_api)
+            .service(trending)
+            .service(combos)
+            .service(set_overview)
+            .service(set_completion)
+            .service(collection_page)
+            .service(upload_collection)
+            .service(forget_collection)
+            .service(add_to_list)
+            .service(remove_from_list)
+            .service(lists_page)
+            .service(export_list)
+            .service(new_deck)
+            .service(add_to_deck)
+            .service(remove_from_deck)
+            .service(close_deck)
+            .service(deck_page)
+            .service(export_deck)
+            .service(genesys_check)
+            .service(export_lflist)
+            .service(watch_price)
+            .service(price_alerts_feed)
+            .service(price_alerts_api)
+            .service(bulk_prices)
+            .service(admin_page)
+            .service(admin_memory)
+            .service(admin_stats)
+            .service(admin_login)
+            .service(admin_logout)
+            .service(admin_reload)
+            .service(admin_reload_data)
+            .service(admin_clear_cache)
+            .service(admin_read_only)
+    });
+    // Anything that isn’t set keeps actix’ defaults.
+    if let Some(keep_alive) = env_secs("KEEP_ALIVE_SECS") {
+        server = server.keep_alive(if keep_alive.is_zero() { KeepAlive::Disabled } else { KeepAlive::Timeout(keep_alive) });
+    }
+    if let Some(timeout) = env_secs("CLIENT_REQUEST_TIMEOUT_SECS") {
+        server = server.client_request_timeout(timeout);
+    }
+    if let Some(timeout) = env_secs("CLIENT_DISCONNECT_TIMEOUT_SECS") {
+        server = server.client_disconnect_timeout(timeout);
+    }
+    let address = (Ipv4Addr::from([127, 0, 0, 1]), 1961);
+    let server = if http2_enabled() { server.bind_auto_h2c(address)? } else { server.bind(address)? };
+    server.run().await?;
+    if let Err(e) = flush_card_views() {
+        eprintln!("{e}");
+    }
+    Ok(())
 }
 
-#[derive(Debug, Deserialize)]
-struct Query {
-    q: String,
+fn html(page: &impl Template) -> AnyResult<HttpResponse> {
+    Ok(HttpResponse::Ok().insert_header(header::ContentType::html()).body(page.render()?))
 }
 
-#[derive(Debug)]
-enum TargetPage {
-    Data(PageData),
-    Redirect(String),
+fn collection(req: &HttpRequest) -> Option<Arc<Collection>> {
+    COLLECTIONS.get(req.cookie(COLLECTION_COOKIE)?.value())
 }
 
-#[derive(Debug)]
-struct PageData {
-    description: String,
-    title:       String,
-    query:       Option<String>,
-    body:        String,
+fn card_lists(req: &HttpRequest) -> CardLists {
+    req.cookie(LISTS_COOKIE).and_then(|c| c.value().parse().ok()).unwrap_or_default()
 }
 
-const NAME: &str = "Unofficial YGO Card Search";
-const HEADER: &str = include_str!("../static/header.html");
-const HELP_CONTENT: &str = include_str!("../static/help.html");
-static VIEW_COUNT: AtomicUsize = AtomicUsize::new(0);
-fn footer() -> String {
-    format!(
-        r#"<div id="bottom">
-<span style="color: #bbb">{}</span>
-&nbsp;&nbsp;&nbsp;&nbsp;&nbsp;
-<a href="/">Home</a>
-&nbsp;&nbsp;&nbsp;&nbsp;&nbsp;
-<a href="/help">Query Syntax</a>
-</div>
-</body></html>"#,
-        VIEW_COUNT.fetch_add(1, Ordering::Relaxed)
-    )
+fn deck_token(req: &HttpRequest) -> Option<String> {
+    Some(req.cookie(DECK_COOKIE)?.value().to_owned())
 }
 
-#[route("/", method = "GET", method = "HEAD")]
-async fn search(q: Option<Either<web::Query<Query>, web::Form<Query>>>) -> AnyResult<HttpResponse> {
-    let q = match q {
-        Some(Either::Left(web::Query(Query { q }))) => Some(q),
-        Some(Either::Right(web::Form(Query { q }))) => Some(q),
-        None => None,
-    }
-    .filter(|s| !s.is_empty());
-    let mut res = String::with_capacity(10_000);
-    let data = match q {
-        Some(q) => compute_results(q)?,
-        None => TargetPage::Data(PageData {
-            title:       NAME.to_owned(),
-            description: "Enter a query above to search".to_owned(),
-            query:       None,
-            body:        "<p>Welcome to my cheap Scryfall clone for Yugioh.</p>\
-                          <p>Enter a query above to search or read the <a href=\"/help\">query syntax</a> for more information.</p>\
-                          <p>The source code is available <a href=\"https://github.com/kageru/aro\">on Github</a>.</p>\
-                          <p>If you have any feedback, feel free to add @kageru on Discord or send an email to &lt;that name&gt;@encode.moe.</p>"
-                .to_owned(),
-        }),
-    };
-    match data {
-        TargetPage::Data(data) => {
-            add_data(&mut res, &data, None)?;
-            Ok(HttpResponse::Ok().insert_header(header::ContentType::html()).body(res))
-        }
-        TargetPage::Redirect(target) => Ok(HttpResponse::Found().insert_header((header::LOCATION, target)).finish()),
-    }
+fn is_building_deck(req: &HttpRequest) -> bool {
+    deck_token(req).is_some_and(|token| DECKS.get(&token).is_some())
+}
+
+/// What the user last chose, or the default.
+fn page_size(req: &HttpRequest) -> usize {
+    req.cookie(PAGE_SIZE_COOKIE).and_then(|c| c.value().parse().ok()).map_or(PAGE_SIZE, |n: usize| n.clamp(MIN_PAGE_SIZE, PAGE_SIZE))
+}
+
+fn theme(req: &HttpRequest) -> Theme {
+    req.cookie(THEME_COOKIE).and_then(|c| c.value().parse().ok()).unwrap_or_default()
+}
+
+/// The language someone picked, or the browser’s.
+fn locale(req: &HttpRequest) -> Locale {
+    req.cookie(LANG_COOKIE)
+        .and_then(|c| c.value().parse().ok())
+        .or_else(|| req.headers().get(header::ACCEPT_LANGUAGE)?.to_str().ok().and_then(Locale::from_accept_language))
+        .unwrap_or_default()
 }
 
-#[route("/card/{id}", method = "GET", method = "HEAD")]
-async fn card_info(card_id: web::Path<usize>) -> AnyResult<HttpResponse> {
-    let mut res = String::with_capacity(2_000);
-    let data = match CARDS_BY_ID.get(&card_id) {
-        Some(card) => PageData {
-            title:       format!("{} - {NAME}", card.name),
-            description: card.short_info()?,
-            query:       None,
-            body:        format!(
-                r#"<div> <img alt="Card Image: {}" class="fullimage" src="{}/static/full/{}.jpg"/>{card} <hr/> {} </div>"#,
-                card.name,
-                IMG_HOST.as_str(),
-                card.id,
-                card.extended_info().unwrap_or_else(|_| String::new()),
-            ),
-        },
-        None => PageData {
-            description: format!("Card not found - {NAME}"),
-            title:       format!("Card not found - {NAME}"),
-            query:       None,
-            body:        "Card not found".to_owned(),
-        },
+/// What mutating endpoints return in read-only mode.
+fn read_only_page(req: &HttpRequest) -> AnyResult<HttpResponse> {
+    let locale = locale(req);
+    let page = MessagePage {
+        meta:    Meta::new(format!("{} - {NAME}", locale.tr("Read-only")), locale.tr("This feature is temporarily unavailable").to_owned())
+            .with_theme(theme(req))
+            .with_locale(locale),
+        message: locale.tr("This is temporarily disabled for maintenance. Searching still works, please try again later.").to_owned(),
     };
-    add_data(&mut res, &data, Some(*card_id))?;
-    Ok(HttpResponse::Ok().insert_header(header::ContentType::html()).body(res))
+    Ok(HttpResponse::ServiceUnavailable().insert_header(header::ContentType::html()).body(page.render()?))
 }
 
-#[route("/help", method = "GET", method = "HEAD")]
-async fn help() -> AnyResult<HttpResponse> {
-    let mut res = String::with_capacity(HEADER.len() + HELP_CONTENT.len() + 500);
-    let data = PageData {
-        query:       None,
-        title:       format!("Query Syntax - {NAME}"),
-        body:        HELP_CONTENT.to_owned(),
-        description: String::new(),
+fn maintenance_page(req: &HttpRequest) -> AnyResult<HttpResponse> {
+    let locale = locale(req);
+    let page = MessagePage {
+        meta:    Meta::new(
+            format!("{} - {NAME}", locale.tr("Maintenance")),
+            locale.tr("The card data is currently unavailable").to_owned(),
+        )
+        .with_theme(theme(req))
+        .with_locale(locale),
+        message: locale.tr("The card data is currently being updated. Please try again in a few minutes.").to_owned(),
     };
-    add_data(&mut res, &data, None)?;
-    Ok(HttpResponse::Ok().insert_header(header::ContentType::html()).body(res))
+    Ok(HttpResponse::ServiceUnavailable().insert_header(header::ContentType::html()).body(page.render()?))
 }
 
-fn add_searchbox(res: &mut String, query: &Option<String>) -> std::fmt::Result {
-    write!(
-        res,
-        r#"
-<form action="/">
-  <input type="text" name="q" autofocus id="searchbox" placeholder="Enter query (e.g. l:5 c:synchro atk>2000)" value="{}"><input type="submit" id="submit" value="🔍">
-</form>
-"#,
-        match &query {
-            Some(q) => q.replace('"', "&quot;"),
-            None => String::new(),
-        }
-    )
+/// `/ygo/` wouldn’t match requests for `/ygo`.
+fn cookie_path() -> &'static str {
+    Some(BASE_PATH.as_str()).filter(|p| !p.is_empty()).unwrap_or("/")
 }
 
-fn compute_results(raw_query: String) -> AnyResult<TargetPage> {
-    let mut body = String::with_capacity(10_000);
-    let (raw_filters, query) = match parser::parse_filters(raw_query.trim()) {
-        Ok(q) => q,
-        Err(e) => {
-            let s = format!("Could not parse query: {e:?}");
-            return Ok(TargetPage::Data(PageData {
-                description: s.clone(),
-                query:       Some(raw_query),
-                body:        s,
-                title:       NAME.to_owned(),
-            }));
-        }
-    };
-    let now = Instant::now();
-    let matches: Vec<&Card> = SEARCH_CARDS
-        .iter()
-        .filter(|card| query.iter().all(|q| q(card)))
-        .map(|c| CARDS_BY_ID.get(&c.id).unwrap())
-        .take(RESULT_LIMIT)
-        .collect();
-    let readable_query = format!("Showing {} results where {}", matches.len(), raw_filters.iter().map(|f| f.to_string()).join(" and "),);
-    write!(body, "<span class=\"meta\">{readable_query} (took {:?})</span>", now.elapsed())?;
-    match matches[..] {
-        [] => Ok(TargetPage::Data(PageData {
-            description: readable_query,
-            query: Some(raw_query),
-            body,
-            title: format!("No results - {NAME}"),
-        })),
-        [card] => Ok(TargetPage::Redirect(format!("/card/{}", card.id))),
-        ref cards => {
-            body.push_str("<div style=\"display: flex; flex-wrap: wrap;\">");
-            for card in cards {
-                write!(
-                    body,
-                    r#"<a class="cardresult" href="/card/{}"><img alt="Card Image: {}" src="{}/static/thumb/{}.jpg" class="thumb"/>{card}</a>"#,
-                    card.id,
-                    card.name,
-                    IMG_HOST.as_str(),
-                    card.id
-                )?;
-            }
-            body.push_str("</div>");
-            Ok(TargetPage::Data(PageData {
-                description: readable_query,
-                query: Some(raw_query),
-                body,
-                title: format!("{} results - {NAME}", cards.len()),
-            }))
+fn persistent_cookie(name: &str, value: String) -> Cookie<'_> {
+    Cookie::build(name, value).path(cookie_path()).max_age(CookieDuration::days(365)).finish()
+}
+
+/// 32 hex digits from the OS’s random number generator, for things like collection and deck tokens.
+pub fn random_token() -> String {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).expect("the OS should have random numbers");
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Escapes everything that could break out of an HTML text node or a quoted attribute.
+/// Anything derived from user input must go through this before being written into a page.
+pub fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
         }
     }
+    escaped
 }
 
-fn add_data(res: &mut String, pd: &PageData, card_id: Option<usize>) -> AnyResult<()> {
-    res.push_str(
-        &HEADER
-            .replacen("{DESCRIPTION}", &pd.description.replace('"', r#"\""#), 2)
-            .replacen("{IMG_HOST}", &IMG_HOST, 2)
-            .replacen("{TITLE}", &pd.title, 2)
-            .replacen(
-                "{OG_IMAGE}",
-                &match card_id {
-                    Some(id) => format!(r#"<meta property="og:image" content="{}/static/full/{id}.jpg" />"#, IMG_HOST.as_str()),
-                    None => String::new(),
-                },
-                1,
-            ),
-    );
-    add_searchbox(res, &pd.query)?;
-    res.push_str(&pd.body);
-    res.push_str(&footer());
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_html_test() {
+        assert_eq!(escape_html(r#"<script>alert("hi")</script>"#), "&lt;script&gt;alert(&quot;hi&quot;)&lt;/script&gt;");
+        assert_eq!(escape_html("Ally & Justice's"), "Ally &amp; Justice&#39;s");
+        assert_eq!(escape_html("harmless"), "harmless");
+    }
 }
@@ -0,0 +1,194 @@
+//! How much memory we use and for what, on the admin page and `/admin/memory`, to tell how small a server can be.
+//! The heap numbers and the sizes of the card data come from `CountingAllocator`, which is only the global allocator
+//! with the `count-allocations` feature, because it makes every allocation a bit slower. Resident memory is read from
+//! `/proc`, so it’s only there on Linux.
+use serde::Serialize;
+#[cfg(feature = "count-allocations")]
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+};
+use std::{collections::HashMap, mem::size_of};
+
+#[cfg(feature = "count-allocations")]
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+#[cfg(feature = "count-allocations")]
+static PEAK_ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+#[cfg(feature = "count-allocations")]
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+/// The system allocator, but it keeps count.
+#[cfg(feature = "count-allocations")]
+pub struct CountingAllocator;
+
+#[cfg(feature = "count-allocations")]
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            allocated(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            allocated(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new = System.realloc(ptr, layout, new_size);
+        if !new.is_null() {
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+            allocated(new_size);
+        }
+        new
+    }
+}
+
+#[cfg(feature = "count-allocations")]
+fn allocated(size: usize) {
+    let now = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK_ALLOCATED.fetch_max(now, Ordering::Relaxed);
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Runs `f` and returns how much more is allocated afterwards, i.e. roughly what the result holds on to.
+/// Whatever other threads allocate or free in the meantime counts too, so this is only an estimate.
+#[cfg(feature = "count-allocations")]
+pub fn measure<T>(f: impl FnOnce() -> T) -> (T, usize) {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let result = f();
+    (result, ALLOCATED.load(Ordering::Relaxed).saturating_sub(before))
+}
+
+/// Sizes of the parts of something as they’re built, with `measure`. Stays empty without `count-allocations`.
+#[derive(Debug, Default)]
+pub struct Sizes(pub Vec<StructureSize>);
+
+impl Sizes {
+    #[cfg(feature = "count-allocations")]
+    pub fn of<T>(&mut self, name: &'static str, f: impl FnOnce() -> T) -> T {
+        let (result, bytes) = measure(f);
+        self.0.push(StructureSize { name, bytes });
+        result
+    }
+
+    #[cfg(not(feature = "count-allocations"))]
+    pub fn of<T>(&mut self, _name: &'static str, f: impl FnOnce() -> T) -> T {
+        f()
+    }
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq, Clone, Copy)]
+pub struct StructureSize {
+    pub name:  &'static str,
+    /// An estimate
+    pub bytes: usize,
+}
+
+/// Heap usage of a map, not counting what the keys and values point to. That’s what `extra` is for.
+pub fn map_size<K, V>(map: &HashMap<K, V>, extra: impl Fn(&K, &V) -> usize) -> usize {
+    // hashbrown keeps one control byte per bucket.
+    map.capacity() * (size_of::<(K, V)>() + 1) + map.iter().map(|(k, v)| extra(k, v)).sum::<usize>()
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// From `/proc/self/status`
+    pub resident:       Option<u64>,
+    pub peak_resident:  Option<u64>,
+    /// Heap only, so this is less than the resident memory. Only with `count-allocations`.
+    pub allocated:      Option<u64>,
+    pub peak_allocated: Option<u64>,
+    /// Since the start
+    pub allocations:    Option<u64>,
+    /// Largest first
+    pub structures:     Vec<StructureSize>,
+}
+
+impl MemoryStats {
+    pub fn now(mut structures: Vec<StructureSize>) -> Self {
+        let (resident, peak_resident) = match std::fs::read_to_string("/proc/self/status") {
+            Ok(status) => (proc_status_bytes(&status, "VmRSS"), proc_status_bytes(&status, "VmHWM")),
+            Err(_) => (None, None),
+        };
+        structures.sort_by_key(|s| std::cmp::Reverse(s.bytes));
+        #[cfg(feature = "count-allocations")]
+        let (allocated, peak_allocated, allocations) = (
+            Some(ALLOCATED.load(Ordering::Relaxed) as u64),
+            Some(PEAK_ALLOCATED.load(Ordering::Relaxed) as u64),
+            Some(ALLOCATIONS.load(Ordering::Relaxed)),
+        );
+        #[cfg(not(feature = "count-allocations"))]
+        let (allocated, peak_allocated, allocations) = (None, None, None);
+        Self { resident, peak_resident, allocated, peak_allocated, allocations, structures }
+    }
+}
+
+/// Lines look like `VmRSS:     12345 kB`.
+fn proc_status_bytes(status: &str, key: &str) -> Option<u64> {
+    let line = status.lines().find_map(|l| l.strip_prefix(key)?.strip_prefix(':'))?;
+    let kb: u64 = line.trim().strip_suffix("kB")?.trim().parse().ok()?;
+    Some(kb * 1024)
+}
+
+/// e.g. `12.3 MiB`
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proc_status_test() {
+        let status = "Name:\taro\nVmHWM:\t   20480 kB\nVmRSS:\t   10240 kB\nThreads:\t9\n";
+        assert_eq!(proc_status_bytes(status, "VmRSS"), Some(10 * 1024 * 1024));
+        assert_eq!(proc_status_bytes(status, "VmHWM"), Some(20 * 1024 * 1024));
+        assert_eq!(proc_status_bytes(status, "VmSwap"), None);
+        assert_eq!(proc_status_bytes(status, "Threads"), None);
+    }
+
+    #[test]
+    fn format_bytes_test() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(300 * 1024 * 1024), "300.0 MiB");
+        assert_eq!(format_bytes(u64::MAX), "16777216.0 TiB");
+    }
+
+    #[cfg(feature = "count-allocations")]
+    #[test]
+    fn measure_test() {
+        let mut sizes = Sizes::default();
+        let v = sizes.of("Zeros", || vec![0u8; 1 << 20]);
+        // Other tests allocate at the same time, so only roughly.
+        assert!(sizes.0[0].bytes >= v.len() / 2, "{:?}", sizes);
+    }
+
+    #[test]
+    fn map_size_test() {
+        let map = HashMap::from([("a".to_owned(), 1u32)]);
+        assert!(map_size(&map, |k, _| k.capacity()) > size_of::<(String, u32)>());
+    }
+}
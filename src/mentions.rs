@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+/// Which cards mention which other cards in their text. All IDs are canonical IDs.
+#[derive(Debug, Default)]
+pub struct MentionsGraph {
+    mentions:      HashMap<usize, Vec<usize>>,
+    referenced_by: HashMap<usize, Vec<usize>>,
+}
+
+impl MentionsGraph {
+    pub fn new(mentions: impl IntoIterator<Item = (usize, Vec<usize>)>) -> Self {
+        let mentions: HashMap<_, _> = mentions.into_iter().filter(|(_, m)| !m.is_empty()).collect();
+        let mut referenced_by = HashMap::<_, Vec<_>>::new();
+        for (&id, mentioned) in &mentions {
+            for &other in mentioned {
+                referenced_by.entry(other).or_default().push(id);
+            }
+        }
+        referenced_by.values_mut().for_each(|ids| ids.sort_unstable());
+        Self { mentions, referenced_by }
+    }
+
+    pub fn referenced_by(&self, id: usize) -> &[usize] {
+        self.referenced_by.get(&id).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// All chains of up to `depth` mentions that start or end at `id`, e.g. searcher → target → payoff.
+    /// Chains are always in the direction of the mentions and never visit a card twice.
+    /// Only the longest chains are returned, i.e. if `a → b → c` is in there, `a → b` isn’t.
+    pub fn chains(&self, id: usize, depth: usize) -> Vec<Vec<usize>> {
+        let forward = walk(&self.mentions, vec![id], depth);
+        let backward = walk(&self.referenced_by, vec![id], depth).into_iter().map(|mut chain| {
+            chain.reverse();
+            chain
+        });
+        forward.into_iter().chain(backward).filter(|chain| chain.len() > 1).collect()
+    }
+}
+
+fn walk(edges: &HashMap<usize, Vec<usize>>, chain: Vec<usize>, depth: usize) -> Vec<Vec<usize>> {
+    let last = *chain.last().unwrap();
+    let next: Vec<_> = edges.get(&last).into_iter().flatten().filter(|id| !chain.contains(id)).collect();
+    if depth == 0 || next.is_empty() {
+        return vec![chain];
+    }
+    next.into_iter()
+        .flat_map(|&id| {
+            let mut longer = chain.clone();
+            longer.push(id);
+            walk(edges, longer, depth - 1)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chains_test() {
+        // 1 searches 2, which mentions 3 and 4. 3 mentions 2 back. 5 also searches 2.
+        let graph = MentionsGraph::new([(1, vec![2]), (2, vec![3, 4]), (3, vec![2]), (5, vec![2]), (6, vec![])]);
+        assert_eq!(graph.referenced_by(2), &[1, 3, 5]);
+        assert_eq!(graph.referenced_by(6), &[] as &[usize]);
+        assert_eq!(graph.chains(1, 2), vec![vec![1, 2, 3], vec![1, 2, 4]]);
+        assert_eq!(graph.chains(4, 2), vec![vec![1, 2, 4], vec![3, 2, 4], vec![5, 2, 4]]);
+        assert_eq!(graph.chains(1, 1), vec![vec![1, 2]]);
+        assert_eq!(graph.chains(6, 2), Vec::<Vec<usize>>::new());
+    }
+}
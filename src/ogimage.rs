@@ -0,0 +1,218 @@
+//! Wide preview images for link embeds (Discord, Twitter, etc.) that show the art next to the name, stats, and banlist status.
+//! The upright scans alone get cropped or shrunk to a tiny thumbnail by most of them.
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use ab_glyph::{point, Font, FontArc, PxScale, ScaleFont};
+use image::{
+    codecs::jpeg::JpegEncoder,
+    imageops::{self, FilterType},
+    ImageResult, Rgb, RgbImage,
+};
+
+use crate::{data::Card, pages::NAME};
+
+pub const WIDTH: u32 = 1200;
+pub const HEIGHT: u32 = 630;
+const MARGIN: u32 = 30;
+const BACKGROUND: Rgb<u8> = Rgb([24, 24, 27]);
+const TEXT: Rgb<u8> = Rgb([240, 240, 240]);
+const DIM_TEXT: Rgb<u8> = Rgb([170, 170, 170]);
+
+#[derive(Debug)]
+pub struct OgImages {
+    /// Where `full/{id}.jpg` are, i.e. the directory served as `/static/`
+    images: PathBuf,
+    cache:  PathBuf,
+    font:   FontArc,
+}
+
+impl OgImages {
+    /// `None` unless `CARD_IMAGE_DIR` and `OG_FONT` are set and the font can be read.
+    pub fn from_env() -> Option<Self> {
+        let images = std::env::var("CARD_IMAGE_DIR").ok()?;
+        let font_path = std::env::var("OG_FONT").ok()?;
+        let font = match fs::read(&font_path).map_err(|e| e.to_string()).and_then(|f| FontArc::try_from_vec(f).map_err(|e| e.to_string())) {
+            Ok(font) => font,
+            Err(e) => {
+                eprintln!("Could not load {font_path}, using the plain card images for embeds: {e}");
+                return None;
+            }
+        };
+        let cache = std::env::var("OG_IMAGE_CACHE").unwrap_or_else(|_| "ogimages".to_owned());
+        Some(Self { images: images.into(), cache: cache.into(), font })
+    }
+
+    /// A JPEG, rendered on the first request and then read from the cache directory.
+    /// The banlist status is part of the file name, so a new banlist doesn’t need the cache to be cleared.
+    pub fn get(&self, card: &Card) -> ImageResult<Vec<u8>> {
+        let copies = card.banlist_info.map_or(3, |bi| bi.ban_tcg.copies());
+        let cached = self.cache.join(format!("{}-{copies}.jpg", card.id));
+        match fs::read(&cached) {
+            Ok(jpeg) => return Ok(jpeg),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+            Err(e) => return Err(e.into()),
+        }
+        let art = image::open(self.images.join("full").join(format!("{}.jpg", card.id)))?.into_rgb8();
+        let mut jpeg = Vec::new();
+        JpegEncoder::new_with_quality(&mut jpeg, 85).encode_image(&compose(card, &art, Some(&self.font)))?;
+        write_atomically(&cached, &jpeg)?;
+        Ok(jpeg)
+    }
+}
+
+/// Several requests for the same card can arrive at once when a link is posted, and none of them should read half a file.
+fn write_atomically(path: &Path, contents: &[u8]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+    fs::write(&tmp, contents)?;
+    fs::rename(tmp, path)
+}
+
+/// The art on the left, text on the right. Without a font, that’s just the art.
+pub fn compose(card: &Card, art: &RgbImage, font: Option<&FontArc>) -> RgbImage {
+    let mut img = RgbImage::from_pixel(WIDTH, HEIGHT, BACKGROUND);
+    let art_height = HEIGHT - 2 * MARGIN;
+    let art_width = art.width() * art_height / art.height().max(1);
+    imageops::overlay(&mut img, &imageops::resize(art, art_width, art_height, FilterType::Triangle), MARGIN.into(), MARGIN.into());
+    let Some(font) = font else {
+        return img;
+    };
+    if let Some(copies) = card.banlist_info.and_then(|bi| bi.ban_tcg.icon().map(|_| bi.ban_tcg.copies())) {
+        // Where the icon is on the card pages, just bigger.
+        draw_banlist_icon(&mut img, font, MARGIN + art_width - 20, MARGIN + 20, copies);
+    }
+    let left = MARGIN + art_width + 40;
+    let max_width = (WIDTH - left - MARGIN) as f32;
+    let mut y = MARGIN as f32 + 20.0;
+    for (text, size, color, max_lines) in
+        [(card.name.as_str(), 64.0, TEXT, 3), (&card.type_line(), 36.0, DIM_TEXT, 2), (&card.stats(), 44.0, TEXT, 1)]
+    {
+        let scaled = font.as_scaled(PxScale::from(size));
+        for line in wrap(text, max_width, |s| text_width(font, size, s)).into_iter().take(max_lines) {
+            draw_text(&mut img, font, size, left as f32, y, &line, color);
+            y += scaled.height() + scaled.line_gap();
+        }
+        y += 24.0;
+    }
+    let footer = 26.0;
+    draw_text(
+        &mut img,
+        font,
+        footer,
+        (WIDTH - MARGIN) as f32 - text_width(font, footer, NAME),
+        (HEIGHT - MARGIN) as f32 - footer,
+        NAME,
+        DIM_TEXT,
+    );
+    img
+}
+
+/// Splits at spaces so that each line fits into `max_width`. Single words that are too long get a line of their own.
+fn wrap(text: &str, max_width: f32, width: impl Fn(&str) -> f32) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if width(&format!("{line} {word}")) <= max_width => {
+                line.push(' ');
+                line.push_str(word);
+            }
+            _ => lines.push(word.to_owned()),
+        }
+    }
+    lines
+}
+
+fn text_width(font: &FontArc, size: f32, text: &str) -> f32 {
+    let scaled = font.as_scaled(PxScale::from(size));
+    let mut previous = None;
+    text.chars()
+        .map(|c| {
+            let id = scaled.glyph_id(c);
+            let kern = previous.map_or(0.0, |p| scaled.kern(p, id));
+            previous = Some(id);
+            kern + scaled.h_advance(id)
+        })
+        .sum()
+}
+
+/// `y` is the top of the line.
+fn draw_text(img: &mut RgbImage, font: &FontArc, size: f32, x: f32, y: f32, text: &str, color: Rgb<u8>) {
+    let scaled = font.as_scaled(PxScale::from(size));
+    let mut caret = point(x, y + scaled.ascent());
+    let mut previous = None;
+    for c in text.chars() {
+        let id = scaled.glyph_id(c);
+        if let Some(p) = previous {
+            caret.x += scaled.kern(p, id);
+        }
+        previous = Some(id);
+        if let Some(outline) = font.outline_glyph(id.with_scale_and_position(size, caret)) {
+            let bounds = outline.px_bounds();
+            outline.draw(|gx, gy, coverage| {
+                let (px, py) = (bounds.min.x as i64 + gx as i64, bounds.min.y as i64 + gy as i64);
+                if let Some(pixel) =
+                    u32::try_from(px).ok().zip(u32::try_from(py).ok()).and_then(|(px, py)| img.get_pixel_mut_checked(px, py))
+                {
+                    blend(pixel, color, coverage);
+                }
+            });
+        }
+        caret.x += scaled.h_advance(id);
+    }
+}
+
+fn blend(pixel: &mut Rgb<u8>, color: Rgb<u8>, alpha: f32) {
+    let alpha = alpha.clamp(0.0, 1.0);
+    for (p, c) in pixel.0.iter_mut().zip(color.0) {
+        *p = (*p as f32 * (1.0 - alpha) + c as f32 * alpha).round() as u8;
+    }
+}
+
+/// Like the SVG icons: a red disc with a black border and the number of allowed copies.
+fn draw_banlist_icon(img: &mut RgbImage, font: &FontArc, cx: u32, cy: u32, copies: u8) {
+    let radius = 32.0;
+    for y in cy.saturating_sub(radius as u32 + 1)..=cy + radius as u32 + 1 {
+        for x in cx.saturating_sub(radius as u32 + 1)..=cx + radius as u32 + 1 {
+            let distance = ((x as f32 - cx as f32).powi(2) + (y as f32 - cy as f32).powi(2)).sqrt();
+            if let Some(pixel) = img.get_pixel_mut_checked(x, y) {
+                // Coverage of the edge pixels, so the circles aren’t jagged.
+                blend(pixel, Rgb([0, 0, 0]), radius + 0.5 - distance);
+                blend(pixel, Rgb([220, 0, 0]), radius - 5.5 - distance);
+            }
+        }
+    }
+    let size = 44.0;
+    let digit = copies.to_string();
+    let scaled = font.as_scaled(PxScale::from(size));
+    // Centered on the visible part of the digit, not the whole line height.
+    let height = font.outline_glyph(scaled.scaled_glyph(char::from(b'0' + copies))).map_or(size, |g| g.px_bounds().height());
+    let baseline = cy as f32 + height / 2.0;
+    draw_text(img, font, size, cx as f32 - text_width(font, size, &digit) / 2.0, baseline - scaled.ascent(), &digit, Rgb([255, 255, 255]));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap_test() {
+        let width = |s: &str| s.len() as f32;
+        assert_eq!(wrap("Blue-Eyes White Dragon", 15.0, width), vec!["Blue-Eyes White", "Dragon"]);
+        assert_eq!(wrap("Superdreadnought Rail Cannon", 10.0, width), vec!["Superdreadnought", "Rail", "Cannon"]);
+        assert_eq!(wrap("", 10.0, width), Vec::<String>::new());
+    }
+
+    #[test]
+    fn compose_test() {
+        let art = RgbImage::from_pixel(421, 614, Rgb([255, 0, 0]));
+        let img = compose(&Card::default(), &art, None);
+        assert_eq!(img.dimensions(), (WIDTH, HEIGHT));
+        assert_eq!(*img.get_pixel(MARGIN + 10, HEIGHT / 2), Rgb([255, 0, 0]));
+        assert_eq!(*img.get_pixel(WIDTH - MARGIN - 10, HEIGHT / 2), BACKGROUND);
+    }
+}
@@ -0,0 +1,992 @@
+use askama::Template;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    fmt::{self, Display},
+    str::FromStr,
+    sync::{atomic::Ordering, Arc},
+};
+use time::{format_description::well_known::Rfc2822, Date};
+
+use crate::{
+    collection::Collection,
+    data::{rarity_rank, BanlistStatus, Card, CardSet, Region, Set, SetEntry},
+    deck::{GenesysPoints, Section},
+    filter::BANLIST_HISTORY,
+    history::History,
+    i18n::Locale,
+    lists::ListEntry,
+    memory::{format_bytes, MemoryStats},
+    ogimage,
+    parser::{Sort, SortKey},
+    render,
+    rulings::RulingMatch,
+    scheduler::JobStatus,
+    vendors::VendorLinks,
+    BASE_PATH, IMG_HOST, OG_IMAGES, PAGE_VIEWS, READ_ONLY, REFERRALS, SHARED,
+};
+
+pub const NAME: &str = "Unofficial YGO Card Search";
+
+/// Everything the shared layout in `base.html` needs. Every page has one of these.
+#[derive(Debug, Default)]
+pub struct Meta {
+    pub title:       String,
+    pub description: String,
+    /// Prefilled in the search box.
+    pub query:       String,
+    /// Absolute URL of the embed image, see `og_image_url`
+    pub og_image:    Option<String>,
+    /// JSON-LD for search engines, see `render::product_json_ld`
+    pub json_ld:     Option<String>,
+    pub theme:       Theme,
+    pub locale:      Locale,
+}
+
+impl Meta {
+    pub fn new(title: String, description: String) -> Self {
+        Self { title, description, ..Default::default() }
+    }
+
+    pub fn with_query(self, query: String) -> Self {
+        Self { query, ..self }
+    }
+
+    pub fn with_theme(self, theme: Theme) -> Self {
+        Self { theme, ..self }
+    }
+
+    pub fn with_locale(self, locale: Locale) -> Self {
+        Self { locale, ..self }
+    }
+
+    /// See `Locale::tr`.
+    pub fn tr<'a>(&self, en: &'a str) -> &'a str {
+        self.locale.tr(en)
+    }
+
+    /// For translations with links in them, so only use this with `|safe`.
+    pub fn tr_html(&self, en: &str) -> String {
+        self.tr(en).replace("{base}", self.base_path())
+    }
+
+    /// For strings with a single number, like “{n} results”.
+    pub fn tr_n(&self, en: &str, n: impl Display) -> String {
+        self.locale.trf(en, &[("n", &n)])
+    }
+
+    /// For strings with a single named placeholder.
+    pub fn tr1(&self, en: &str, name: &str, value: impl Display) -> String {
+        self.locale.trf(en, &[(name, &value)])
+    }
+
+    /// For enums whose `Display` is the English name, like banlist statuses.
+    pub fn tr_display(&self, value: impl Display) -> String {
+        self.tr(&value.to_string()).to_owned()
+    }
+
+    /// Embeds show wide images big and everything else as a thumbnail.
+    pub fn og_image_is_wide(&self) -> bool {
+        OG_IMAGES.is_some()
+    }
+
+    pub fn img_host(&self) -> &str {
+        IMG_HOST.as_str()
+    }
+
+    /// Shows a banner on every page.
+    pub fn read_only(&self) -> bool {
+        READ_ONLY.load(Ordering::Relaxed)
+    }
+
+    /// Prefix for all links, see `BASE_PATH`.
+    pub fn base_path(&self) -> &str {
+        BASE_PATH.as_str()
+    }
+
+    /// Views so far, without counting one. Like `next_view_count`, this is across all instances if possible.
+    pub fn view_count() -> usize {
+        PAGE_VIEWS.total(SHARED.as_ref())
+    }
+
+    /// Counted across all instances if there’s a shared store, as of its last flush, see `PageViews`.
+    pub fn next_view_count(&self) -> usize {
+        PAGE_VIEWS.record(SHARED.as_ref())
+    }
+}
+
+#[derive(Template)]
+#[template(path = "index.html")]
+pub struct IndexPage {
+    pub meta:    Meta,
+    /// `None` if the user opted out.
+    pub history: Option<History>,
+}
+
+#[derive(Template)]
+#[template(path = "help.html")]
+pub struct HelpPage {
+    pub meta:              Meta,
+    pub unreprinted_years: i32,
+}
+
+/// Hit rates of the in-memory caches
+#[derive(Debug, Serialize)]
+pub struct CacheRow {
+    pub name:     &'static str,
+    pub entries:  usize,
+    pub lookups:  usize,
+    pub hit_rate: Option<f64>,
+}
+
+#[derive(Template)]
+#[template(path = "admin.html")]
+pub struct AdminPage {
+    pub meta:        Meta,
+    /// `false` shows the login form instead of everything else.
+    pub logged_in:   bool,
+    /// Feedback for the last action
+    pub message:     Option<String>,
+    pub uptime:      String,
+    pub read_only:   bool,
+    pub data_ready:  bool,
+    /// Card sources and when they were last modified
+    pub data_files:  Vec<(String, Option<String>)>,
+    pub card_count:  usize,
+    pub set_count:   usize,
+    pub views:       usize,
+    pub top_queries: Vec<(String, usize)>,
+    pub caches:      Vec<CacheRow>,
+    /// See `scheduler.rs`
+    pub jobs:        Vec<JobStatus>,
+    pub memory:      MemoryStats,
+}
+
+impl AdminPage {
+    fn bytes(bytes: &usize) -> String {
+        format_bytes(*bytes as u64)
+    }
+
+    /// Resident memory is only known on Linux, the heap only with `count-allocations`.
+    fn maybe_bytes(bytes: &Option<u64>) -> String {
+        bytes.map(format_bytes).unwrap_or_else(|| "-".to_owned())
+    }
+
+    fn last_run(job: &JobStatus) -> String {
+        job.last_run.and_then(|t| t.format(&Rfc2822).ok()).unwrap_or_else(|| "Not yet".to_owned())
+    }
+}
+
+/// Rendered by the request ID middleware in `errors.rs` for all errors that don’t have a page of their own.
+#[derive(Template)]
+#[template(path = "error.html")]
+pub struct ErrorPage {
+    pub meta:       Meta,
+    pub message:    String,
+    pub request_id: String,
+}
+
+/// A page that only shows some text, e.g. for errors.
+#[derive(Template)]
+#[template(path = "message.html")]
+pub struct MessagePage {
+    pub meta:    Meta,
+    pub message: String,
+}
+
+#[derive(Template)]
+#[template(path = "onthisday.html")]
+pub struct OnThisDayPage<'a> {
+    pub meta:       Meta,
+    /// Copies owned per card ID, see `ResultsPage::owned`
+    pub owned:      HashMap<usize, u32>,
+    pub deck_mode:  bool,
+    /// e.g. “October 16”
+    pub day:        String,
+    /// Newest first
+    pub years:      Vec<(i32, Vec<&'a Card>)>,
+    /// IDs of the cards that aren’t out yet, for the badges
+    pub unreleased: HashSet<usize>,
+}
+
+impl OnThisDayPage<'_> {
+    /// There are no results to flip through here.
+    fn card_link(&self, card: &Card) -> String {
+        format!("{}/card/{}", BASE_PATH.as_str(), card.id)
+    }
+
+    fn card_html(&self, card: &Card) -> String {
+        render::CardHtml(card, !self.unreleased.contains(&card.id)).to_string()
+    }
+}
+
+#[derive(Template)]
+#[template(path = "rulings.html")]
+pub struct RulingsPage<'a> {
+    pub meta:    Meta,
+    pub query:   String,
+    /// Number of cards with matching rulings, even if not all of them are shown
+    pub total:   usize,
+    pub results: Vec<(&'a Card, Vec<RulingMatch>)>,
+}
+
+impl RulingsPage<'_> {
+    fn summary(&self) -> String {
+        let args: [(&str, &dyn Display); 2] = [("shown", &self.results.len()), ("total", &self.total)];
+        match self.total > self.results.len() {
+            true => {
+                self.meta.locale.trf("Showing {shown} of {total} cards with matching rulings. Add more words to narrow it down.", &args)
+            }
+            false => self.meta.tr_n("{n} cards with matching rulings", self.total),
+        }
+    }
+}
+
+#[derive(Template)]
+#[template(path = "trending.html")]
+pub struct TrendingPage<'a> {
+    pub meta:  Meta,
+    pub days:  i64,
+    /// Most viewed first
+    pub cards: Vec<(&'a Card, u32)>,
+}
+
+#[derive(Template)]
+#[template(path = "results.html")]
+pub struct ResultsPage<'a> {
+    pub meta:           Meta,
+    pub readable_query: String,
+    /// What we translated from Scryfall syntax
+    pub notes:          Vec<String>,
+    /// Likely mistakes in the query, see `query::warnings`
+    pub warnings:       Vec<String>,
+    pub took:           String,
+    pub cards:          Vec<&'a Card>,
+    /// The same cards split up by `group:`. Empty without it.
+    pub groups:         Vec<ResultGroup<'a>>,
+    pub pagination:     Pagination,
+    pub sort_links:     Vec<SortLink>,
+    pub page_size:      usize,
+    pub view:           View,
+    /// Copies of each card in the user’s collection. Empty if they don’t have one.
+    pub owned:          HashMap<usize, u32>,
+    /// The sentence that matched `o:`, by card, see `snippet.rs`. Empty without `o:`.
+    pub snippets:       HashMap<usize, String>,
+    /// Whether the user is building a deck and needs “add to deck” buttons
+    pub deck_mode:      bool,
+    /// From `asof:`, for the banlist icons
+    pub as_of:          Option<Date>,
+    /// IDs of the cards on this page that aren’t out yet, for the badges
+    pub unreleased:     HashSet<usize>,
+}
+
+/// A section of the results with `group:`
+pub struct ResultGroup<'a> {
+    pub label:       String,
+    /// In all results, not just on this page
+    pub total:       usize,
+    pub cards:       Vec<&'a Card>,
+    /// Position of the first card on the page, for `ResultsPage::card_link`
+    pub first_index: usize,
+}
+
+impl ResultsPage<'_> {
+    pub const PAGE_SIZES: [usize; 4] = [20, 60, 120, 300];
+
+    /// `index` is the position on the current page.
+    fn card_link(&self, card: &Card, index: &usize) -> String {
+        let offset = (self.pagination.current - 1) * self.page_size;
+        ResultNavigation::card_link(card.id, &self.meta.query, offset + index, self.page_size)
+    }
+
+    /// With the banlist icon from back then if there’s an `asof:`.
+    fn card_html(&self, card: &Card) -> String {
+        match self.as_of.zip(BANLIST_HISTORY.get()) {
+            Some((date, history)) => {
+                render::CardHtmlWithStatus(card, history.status_on(card.id, date), !self.unreleased.contains(&card.id)).to_string()
+            }
+            None => render::CardHtml(card, !self.unreleased.contains(&card.id)).to_string(),
+        }
+    }
+
+    fn snippet(&self, card: &Card) -> Option<&str> {
+        self.snippets.get(&card.id).map(String::as_str)
+    }
+
+    /// Set code of the first printing, used in the list view.
+    pub fn first_printing(card: &Card) -> &str {
+        card.card_sets.first().map(|s| s.set_code.as_str()).unwrap_or_default()
+    }
+
+    pub fn format_price(card: &Card) -> String {
+        format_cents(card.price())
+    }
+}
+
+pub fn format_cents(cents: Option<i32>) -> String {
+    cents.map(|p| format!("{}.{:02}", p / 100, p % 100)).unwrap_or_default()
+}
+
+#[derive(Template)]
+#[template(path = "collection.html")]
+pub struct CollectionPage {
+    pub meta:       Meta,
+    pub collection: Option<Arc<Collection>>,
+    pub error:      Option<String>,
+    /// Value in cents per day, newest first
+    pub values:     Vec<(Date, i32)>,
+}
+
+impl CollectionPage {
+    fn format_cents(cents: &i32) -> String {
+        format_cents(Some(*cents))
+    }
+}
+
+#[derive(Template)]
+#[template(path = "setcomplete.html")]
+pub struct SetCompletionPage<'a> {
+    pub meta:       Meta,
+    /// The set prefix, e.g. `LOB`
+    pub code:       String,
+    /// What the user entered, so they can fix it if there was an error
+    pub collection: String,
+    pub error:      Option<String>,
+    pub completion: Option<SetCompletion<'a>>,
+}
+
+impl SetCompletionPage<'_> {
+    fn format_cents(cents: &Option<i32>) -> String {
+        format_cents(*cents)
+    }
+
+    fn summary(&self, completion: &SetCompletion) -> String {
+        let en = match completion.unpriced {
+            0 => "You own {owned} of {total} cards. Completing the set costs about {cost}.",
+            _ => "You own {owned} of {total} cards. Completing the set costs about {cost} (plus {unpriced} cards without a price).",
+        };
+        let args: [(&str, &dyn Display); 4] = [
+            ("owned", &completion.owned),
+            ("total", &completion.total),
+            ("cost", &completion.formatted_cost()),
+            ("unpriced", &completion.unpriced),
+        ];
+        self.meta.locale.trf(en, &args)
+    }
+}
+
+#[derive(Debug)]
+pub struct SetCompletion<'a> {
+    pub owned:    usize,
+    pub total:    usize,
+    pub missing:  Vec<SetEntry<'a>>,
+    /// In cents, using the cheapest rarity of every missing card
+    pub cost:     i32,
+    /// Missing cards we don’t have a price for
+    pub unpriced: usize,
+}
+
+impl<'a> SetCompletion<'a> {
+    pub fn new(entries: Vec<SetEntry<'a>>, collection: &Collection) -> Self {
+        let total = entries.len();
+        let missing: Vec<_> = entries.into_iter().filter(|e| !collection.owns_printing(e.card.id, e.code)).collect();
+        Self {
+            owned: total - missing.len(),
+            total,
+            cost: missing.iter().filter_map(|e| e.price).sum(),
+            unpriced: missing.iter().filter(|e| e.price.is_none()).count(),
+            missing,
+        }
+    }
+    pub fn formatted_cost(&self) -> String {
+        format_cents(Some(self.cost))
+    }
+}
+
+#[derive(Template)]
+#[template(path = "set.html")]
+pub struct SetPage<'a> {
+    pub meta:     Meta,
+    /// The set prefix, e.g. `LOB`
+    pub code:     String,
+    pub name:     &'a str,
+    pub overview: SetOverview<'a>,
+}
+
+impl SetPage<'_> {
+    fn format_cents(cents: &i32) -> String {
+        format_cents(Some(*cents))
+    }
+
+    fn summary(&self) -> String {
+        let overview = &self.overview;
+        let en = match overview.unpriced {
+            0 => "{cards} cards. One of each costs about {one}, a playset of each about {playset}, always using the cheapest rarity.",
+            _ => "{cards} cards. One of each costs about {one}, a playset of each about {playset} (plus {unpriced} cards without a price), always using the cheapest rarity.",
+        };
+        let args: [(&str, &dyn Display); 4] = [
+            ("cards", &overview.cards),
+            ("one", &format_cents(Some(overview.one_of_each))),
+            ("playset", &format_cents(Some(overview.playset))),
+            ("unpriced", &overview.unpriced),
+        ];
+        self.meta.locale.trf(en, &args)
+    }
+}
+
+#[derive(Debug)]
+pub struct SetOverview<'a> {
+    pub cards:          usize,
+    /// In cents, using the cheapest rarity of every card
+    pub one_of_each:    i32,
+    /// Three copies of every card, again with the cheapest rarity
+    pub playset:        i32,
+    /// Cards we don’t have a price for
+    pub unpriced:       usize,
+    /// Priciest first
+    pub most_expensive: Vec<(&'a Card, &'a CardSet, i32)>,
+    /// Rarest first
+    pub rarities:       Vec<RarityBreakdown<'a>>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct RarityBreakdown<'a> {
+    pub rarity:    &'a str,
+    pub printings: usize,
+    /// Sum of all prices in cents
+    pub value:     i32,
+    /// Share of the value of all printings in the set, in percent
+    pub share:     i32,
+}
+
+impl<'a> SetOverview<'a> {
+    const MOST_EXPENSIVE: usize = 10;
+
+    pub fn new(entries: &[SetEntry<'a>]) -> Self {
+        // Every rarity of every code, e.g. both the Ultra and the Ultimate Rare of a card
+        let printings: Vec<(&Card, &CardSet)> = entries
+            .iter()
+            .flat_map(|e| e.card.card_sets.iter().filter(|s| s.set_code == e.code).unique_by(|s| &s.set_rarity).map(|s| (e.card, s)))
+            .collect();
+        let priced: Vec<_> = printings.iter().filter_map(|&(card, set)| Some((card, set, set.price()?))).collect();
+        let total = priced.iter().map(|(_, _, p)| p).sum::<i32>().max(1);
+        let rarities = priced
+            .iter()
+            .into_group_map_by(|(_, set, _)| set.set_rarity.as_str())
+            .into_iter()
+            .map(|(rarity, printings)| {
+                let value = printings.iter().map(|(_, _, p)| p).sum();
+                RarityBreakdown { rarity, printings: printings.len(), value, share: (value as i64 * 100 / total as i64) as i32 }
+            })
+            .sorted_by_key(|r| (Reverse(rarity_rank(r.rarity)), r.rarity))
+            .collect();
+        let one_of_each = entries.iter().filter_map(|e| e.price).sum();
+        Self {
+            cards: entries.len(),
+            one_of_each,
+            playset: one_of_each * 3,
+            unpriced: entries.iter().filter(|e| e.price.is_none()).count(),
+            most_expensive: priced
+                .into_iter()
+                .sorted_by_key(|&(_, set, p)| (Reverse(p), &set.set_code))
+                .take(Self::MOST_EXPENSIVE)
+                .collect(),
+            rarities,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum View {
+    #[default]
+    Grid,
+    /// A dense table without images
+    List,
+}
+
+impl FromStr for View {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "grid" => Ok(Self::Grid),
+            "list" => Ok(Self::List),
+            _ => Err(format!("Unknown view: {s}")),
+        }
+    }
+}
+
+impl Display for View {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Grid => "grid",
+            Self::List => "list",
+        })
+    }
+}
+
+/// Applied as a class on `<body>`, so switching doesn’t need any JS.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum Theme {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl Theme {
+    pub fn other(self) -> Self {
+        match self {
+            Self::Dark => Self::Light,
+            Self::Light => Self::Dark,
+        }
+    }
+
+    /// Untranslated, see `Meta::tr`.
+    pub fn switch_label(self) -> &'static str {
+        match self {
+            Self::Dark => "Switch to dark theme",
+            Self::Light => "Switch to light theme",
+        }
+    }
+}
+
+impl FromStr for Theme {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dark" => Ok(Self::Dark),
+            "light" => Ok(Self::Light),
+            _ => Err(format!("Unknown theme: {s}")),
+        }
+    }
+}
+
+impl Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Dark => "dark",
+            Self::Light => "light",
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct SortLink {
+    pub label:     String,
+    pub query:     String,
+    /// ▲ or ▼ if the results are currently sorted by this
+    pub indicator: &'static str,
+}
+
+/// Replaces any `sort:` directive in the query. Clicking the current sort order again reverses it.
+pub fn sort_links(query: &str, current: Option<Sort>) -> Vec<SortLink> {
+    let base = query.split(' ').filter(|t| !t.to_lowercase().starts_with("sort:")).join(" ");
+    SortKey::ALL
+        .into_iter()
+        .map(|key| {
+            let (indicator, descending) = match current {
+                Some(Sort { key: k, descending: false }) if k == key => ("▲", true),
+                Some(Sort { key: k, descending: true }) if k == key => ("▼", false),
+                _ => ("", false),
+            };
+            let sort = format!("sort:{}{}", if descending { "-" } else { "" }, key.keyword());
+            SortLink { label: key.to_string(), query: format!("{} {sort}", base.trim_end()), indicator }
+        })
+        .collect()
+}
+
+/// How many pages are shown on either side of the current one.
+const PAGINATION_WINDOW: usize = 2;
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Pagination {
+    pub current: usize,
+    pub last:    usize,
+    /// Page numbers to link to, `None` is a gap (…).
+    pub links:   Vec<Option<usize>>,
+}
+
+impl Pagination {
+    pub fn new(current: usize, last: usize) -> Self {
+        let start = current.saturating_sub(PAGINATION_WINDOW).max(1);
+        let end = (current + PAGINATION_WINDOW).min(last);
+        let mut links = Vec::new();
+        if start > 1 {
+            links.push(Some(1));
+        }
+        if start > 2 {
+            links.push(None);
+        }
+        links.extend((start..=end).map(Some));
+        if end + 1 < last {
+            links.push(None);
+        }
+        if end < last {
+            links.push(Some(last));
+        }
+        Self { current, last, links }
+    }
+
+    pub fn is_current(&self, page: &usize) -> bool {
+        *page == self.current
+    }
+
+    pub fn is_needed(&self) -> bool {
+        self.last > 1
+    }
+}
+
+#[derive(Template)]
+#[template(path = "card.html")]
+pub struct CardPage<'a> {
+    pub meta:              Meta,
+    pub card:              &'a Card,
+    /// With links to the cards it mentions, see `AppState::linked_texts`
+    pub text:              &'a str,
+    pub extended_info:     String,
+    pub other_versions:    Vec<usize>,
+    /// Other cards that mention this one by name
+    pub referenced_by:     Vec<&'a Card>,
+    pub similar:           Vec<&'a Card>,
+    /// The other cards of its archetype, see `ARCHETYPE_CARDS`
+    pub archetype_members: Vec<&'a Card>,
+    pub printings:         Vec<(Region, Vec<Printing<'a>>)>,
+    /// Inline SVG, if we have seen the price change
+    pub price_chart:       Option<String>,
+    pub price_range:       PriceRange,
+    pub legalities:        Vec<Legality>,
+    /// If the card was opened from search results
+    pub navigation:        Option<ResultNavigation>,
+    /// See `aro_core::tags`
+    pub tags:              Vec<&'static str>,
+    /// How many other cards mention this one or its archetype
+    pub support_count:     i32,
+    /// For the “add to list” form
+    pub list_names:        Vec<String>,
+    /// For the badge
+    pub released:          bool,
+    /// The query parameters this page was opened with, so links that change one of them keep the rest
+    pub params:            Vec<(&'static str, String)>,
+}
+
+impl CardPage<'_> {
+    /// This page with `key` set to `value`
+    fn link_with(&self, key: &str, value: impl Display) -> String {
+        let params = self.params.iter().filter(|(k, _)| *k != key).map(|(k, v)| (*k, v.to_string())).chain([(key, value.to_string())]);
+        format!("?{}", serde_urlencoded::to_string(params.collect_vec()).unwrap_or_default())
+    }
+
+    fn card_html(&self) -> String {
+        render::CardHtmlWithText(self.card, self.text, self.released).to_string()
+    }
+
+    fn position(&self, nav: &ResultNavigation) -> String {
+        self.meta.locale.trf("Result {position} of {total}", &[("position", &nav.position), ("total", &nav.total)])
+    }
+
+    fn support_line(&self) -> String {
+        let en = match (self.support_count, &self.card.archetype) {
+            (1, None) => "Supported by 1 card that mentions it",
+            (_, None) => "Supported by {n} cards that mention it",
+            (1, Some(_)) => "Supported by 1 card that mentions it or the {archetype} archetype",
+            (_, Some(_)) => "Supported by {n} cards that mention it or the {archetype} archetype",
+        };
+        let archetype = self.card.archetype.as_deref().unwrap_or_default();
+        self.meta.locale.trf(en, &[("n", &self.support_count), ("archetype", &archetype)])
+    }
+
+    fn archetype_heading(&self) -> String {
+        self.meta.locale.trf("Other {archetype} cards:", &[("archetype", &self.card.archetype.as_deref().unwrap_or_default())])
+    }
+
+    fn see_all_archetype(&self) -> String {
+        let archetype = self.card.archetype.as_deref().unwrap_or_default();
+        self.meta.locale.trf("See all {n} {archetype} cards", &[("n", &(self.archetype_members.len() + 1)), ("archetype", &archetype)])
+    }
+
+    fn archetype_preview(&self) -> &[&Card] {
+        &self.archetype_members[..self.archetype_members.len().min(crate::ARCHETYPE_CARDS)]
+    }
+
+    /// Finds the whole archetype, including this card.
+    fn archetype_query(&self) -> String {
+        format!("archetype:\"{}\"", self.card.archetype.as_deref().unwrap_or_default())
+    }
+
+    fn printing_codes(&self) -> Vec<&str> {
+        self.card.card_sets.iter().map(|s| s.set_code.as_str()).unique().collect()
+    }
+}
+
+#[derive(Template)]
+#[template(path = "lists.html")]
+pub struct ListsPage<'a> {
+    pub meta:  Meta,
+    pub lists: Vec<(String, Vec<ListRow<'a>>)>,
+}
+
+impl ListsPage<'_> {
+    fn format_cents(cents: &Option<i32>) -> String {
+        format_cents(*cents)
+    }
+}
+
+#[derive(Template)]
+#[template(path = "deck.html")]
+pub struct DeckPage<'a> {
+    pub meta:     Meta,
+    /// `None` if the user isn’t building a deck.
+    pub sections: Option<Vec<DeckSection<'a>>>,
+    pub problems: Vec<String>,
+}
+
+#[derive(Template)]
+#[template(path = "genesys.html")]
+pub struct GenesysPage<'a> {
+    pub meta:   Meta,
+    /// The most points a deck may have
+    pub cap:    i32,
+    /// What the user entered, so they can fix it if there was an error
+    pub ydk:    String,
+    pub error:  Option<String>,
+    pub points: Option<GenesysPoints<'a>>,
+}
+
+impl GenesysPage<'_> {
+    fn points_of(copies: &u32, each: &i32) -> i32 {
+        *copies as i32 * each
+    }
+
+    fn summary(&self, points: &GenesysPoints) -> String {
+        let args: [(&str, &dyn Display); 3] = [("total", &points.total), ("cap", &self.cap), ("over", &(points.total - self.cap))];
+        match points.total > self.cap {
+            true => self.meta.locale.trf("This deck uses {total} points, {over} more than the cap of {cap}.", &args),
+            false => self.meta.locale.trf("This deck uses {total} of {cap} points.", &args),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct DeckSection<'a> {
+    pub section: Section,
+    /// Number of cards, including duplicates
+    pub size:    u32,
+    /// Cards and their number of copies
+    pub cards:   Vec<(&'a Card, u32)>,
+}
+
+#[derive(Debug)]
+pub struct ListRow<'a> {
+    pub entry:  ListEntry,
+    pub card:   &'a Card,
+    /// Of the preferred printing
+    pub rarity: Option<&'a str>,
+    /// Of the preferred printing if we know it, otherwise the lowest price of the card
+    pub price:  Option<i32>,
+}
+
+impl<'a> ListRow<'a> {
+    pub fn new(entry: ListEntry, card: &'a Card) -> Self {
+        let printing = entry.printing.as_ref().and_then(|code| card.card_sets.iter().find(|s| &s.set_code == code));
+        Self {
+            rarity: printing.map(|p| p.set_rarity.as_str()),
+            price: printing.and_then(CardSet::price).or_else(|| card.price()),
+            entry,
+            card,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PrintingOrder {
+    #[default]
+    Date,
+    Rarity,
+}
+
+impl Display for PrintingOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Date => "date",
+            Self::Rarity => "rarity",
+        })
+    }
+}
+
+/// Flipping through search results from a card page
+#[derive(Debug)]
+pub struct ResultNavigation {
+    pub previous: Option<String>,
+    pub next:     Option<String>,
+    /// The results page the card is on
+    pub back:     String,
+    /// Starting at 1
+    pub position: usize,
+    pub total:    usize,
+}
+
+impl ResultNavigation {
+    /// A card page that remembers where in the results of `query` it was, and how many of them were on a page.
+    pub fn card_link(id: usize, query: &str, index: usize, page_size: usize) -> String {
+        let params =
+            serde_urlencoded::to_string([("from", query), ("i", &index.to_string()), ("n", &page_size.to_string())]).unwrap_or_default();
+        format!("{}/card/{id}?{params}", BASE_PATH.as_str())
+    }
+}
+
+/// A row of the legality table on card pages
+#[derive(Debug)]
+pub struct Legality {
+    pub format: String,
+    /// `None` if the card can’t be played in the format at all
+    pub status: Option<BanlistStatus>,
+}
+
+/// How far back the price chart on the card page goes.
+#[derive(Debug, Deserialize, PartialEq, Eq, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PriceRange {
+    Month,
+    #[default]
+    Year,
+    All,
+}
+
+impl PriceRange {
+    pub const ALL: [Self; 3] = [Self::Month, Self::Year, Self::All];
+
+    /// Unix timestamp
+    pub fn start(self, now: i64) -> i64 {
+        const DAY: i64 = 24 * 60 * 60;
+        match self {
+            Self::Month => now - 30 * DAY,
+            Self::Year => now - 365 * DAY,
+            Self::All => i64::MIN,
+        }
+    }
+}
+
+impl Display for PriceRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Month => "month",
+            Self::Year => "year",
+            Self::All => "all",
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Printing<'a> {
+    pub set:   &'a CardSet,
+    pub date:  Option<Date>,
+    pub links: VendorLinks,
+}
+
+impl<'a> Printing<'a> {
+    pub fn grouped(card: &'a Card, order: PrintingOrder, sets_by_name: &HashMap<String, Set>) -> Vec<(Region, Vec<Self>)> {
+        card.printings_by_region()
+            .into_iter()
+            .map(|(region, sets)| {
+                let mut printings: Vec<_> = sets
+                    .into_iter()
+                    .map(|set| Printing {
+                        date: sets_by_name.get(&set.set_name.to_lowercase()).and_then(|s| s.tcg_date),
+                        links: VendorLinks::for_printing(card, set, &REFERRALS),
+                        set,
+                    })
+                    .collect();
+                // Printings are already sorted by date, so a stable sort keeps that as a tie-breaker.
+                if order == PrintingOrder::Rarity {
+                    printings.sort_by_key(|p| Reverse(rarity_rank(&p.set.set_rarity)));
+                }
+                (region, printings)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::set_entries;
+
+    #[test]
+    fn user_input_is_escaped() {
+        let page = MessagePage {
+            meta:    Meta::new("title".to_owned(), r#""><script>"#.to_owned()).with_query(r#""><img src=x>"#.to_owned()),
+            message: "<b>hi</b>".to_owned(),
+        };
+        let html = page.render().unwrap();
+        assert!(!html.contains("<script>"));
+        assert!(!html.contains("<img src=x>"));
+        assert!(html.contains("&lt;b&gt;hi&lt;/b&gt;"));
+    }
+
+    #[test]
+    fn sort_links_test() {
+        let links = sort_links("c:spell sort:-price", Some(Sort { key: SortKey::Price, descending: true }));
+        assert_eq!(links[0], SortLink { label: "Name".to_owned(), query: "c:spell sort:name".to_owned(), indicator: "" });
+        assert_eq!(links[4], SortLink { label: "Price".to_owned(), query: "c:spell sort:price".to_owned(), indicator: "▼" });
+        let links = sort_links("c:spell", None);
+        assert_eq!(links[1].query, "c:spell sort:atk");
+        let links = sort_links("c:spell sort:atk", Some(Sort { key: SortKey::Atk, descending: false }));
+        assert_eq!(links[1], SortLink { label: "ATK".to_owned(), query: "c:spell sort:-atk".to_owned(), indicator: "▲" });
+    }
+
+    #[test]
+    fn result_link_test() {
+        assert_eq!(ResultNavigation::card_link(1, "o:\"draw 1\" & more", 20, 60), "/card/1?from=o%3A%22draw+1%22+%26+more&i=20&n=60");
+    }
+
+    #[test]
+    fn pagination_test() {
+        assert_eq!(Pagination::new(1, 1).links, vec![Some(1)]);
+        assert_eq!(Pagination::new(1, 3).links, vec![Some(1), Some(2), Some(3)]);
+        assert_eq!(Pagination::new(7, 42).links, vec![Some(1), None, Some(5), Some(6), Some(7), Some(8), Some(9), None, Some(42)]);
+        assert_eq!(Pagination::new(4, 7).links, vec![Some(1), Some(2), Some(3), Some(4), Some(5), Some(6), Some(7)]);
+        assert_eq!(Pagination::new(42, 42).links, vec![Some(1), None, Some(40), Some(41), Some(42)]);
+    }
+
+    #[test]
+    fn set_overview_test() {
+        let printing = |code: &str, rarity: &str, price: &str| CardSet {
+            set_code: code.to_owned(),
+            set_rarity: rarity.to_owned(),
+            set_price: price.to_owned(),
+            ..Default::default()
+        };
+        let cards = [
+            Card {
+                id: 1,
+                card_sets: vec![printing("LOB-EN001", "Ultra Rare", "10.00"), printing("LOB-001", "Ultra Rare", "8.00")],
+                ..Default::default()
+            },
+            Card {
+                id: 2,
+                card_sets: vec![printing("LOB-EN002", "Common", "1.00"), printing("LOB-EN002", "Secret Rare", "29.00")],
+                ..Default::default()
+            },
+            Card { id: 3, card_sets: vec![printing("LOB-EN003", "Common", "0")], ..Default::default() },
+        ];
+        let overview = SetOverview::new(&set_entries(&cards, "LOB"));
+        assert_eq!(overview.cards, 4);
+        assert_eq!(overview.one_of_each, 800 + 1000 + 100);
+        assert_eq!(overview.playset, 3 * 1900);
+        assert_eq!(overview.unpriced, 1);
+        assert_eq!(
+            overview.most_expensive.iter().map(|&(c, s, p)| (c.id, s.set_code.as_str(), p)).collect_vec(),
+            vec![(2, "LOB-EN002", 2900), (1, "LOB-EN001", 1000), (1, "LOB-001", 800), (2, "LOB-EN002", 100)]
+        );
+        assert_eq!(
+            overview.rarities,
+            vec![
+                RarityBreakdown { rarity: "Secret Rare", printings: 1, value: 2900, share: 60 },
+                RarityBreakdown { rarity: "Ultra Rare", printings: 2, value: 1800, share: 37 },
+                RarityBreakdown { rarity: "Common", printings: 1, value: 100, share: 2 },
+            ]
+        );
+    }
+}
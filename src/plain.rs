@@ -0,0 +1,116 @@
+//! Text-only search results and card pages for screen readers, braille displays, and `curl`.
+//! No images, tables, or links that only make sense when clicked, just one card after another.
+use std::fmt::{self, Write};
+
+use crate::{accept, data::Card, pages::Legality};
+
+/// `?plain=1`, or `Accept: text/plain` preferred over (or without) `text/html`.
+pub fn wanted(plain_param: Option<&str>, accept: Option<&str>) -> bool {
+    if let Some(plain) = plain_param {
+        return plain == "1" || plain == "true";
+    }
+    accept
+        .map(accept::by_preference)
+        .unwrap_or_default()
+        .into_iter()
+        .find(|&mime| mime == "text/plain" || mime == "text/html")
+        .is_some_and(|mime| mime == "text/plain")
+}
+
+/// Name, type, stats, and text, i.e. everything the grid shows.
+pub fn write_card(f: &mut impl Write, card: &Card) -> fmt::Result {
+    writeln!(f, "{}", card.name)?;
+    card.basic_info(f, "\n")?;
+    f.write_char('\n')?;
+    match card.pendulum_texts() {
+        Some((pendulum, monster)) => {
+            if let Some(scale) = card.scale {
+                writeln!(f, "Pendulum Scale: {scale}")?;
+            }
+            writeln!(f, "Pendulum Effect: {pendulum}")?;
+            let label = if card.card_type.contains("Normal") { "Flavor Text" } else { "Monster Effect" };
+            writeln!(f, "{label}: {monster}")
+        }
+        None => writeln!(f, "{}", card.text.replace('\r', "")),
+    }
+}
+
+/// The card, then where it’s legal, where it was printed, and what it costs.
+pub fn card_page(card: &Card, legalities: &[Legality]) -> Result<String, fmt::Error> {
+    let mut s = String::new();
+    write_card(&mut s, card)?;
+    if !legalities.is_empty() {
+        s.push_str("\nBanlists:\n");
+        for Legality { format, status } in legalities {
+            match status {
+                Some(status) => writeln!(s, "{format}: {status}")?,
+                None => writeln!(s, "{format}: Not legal")?,
+            }
+        }
+    }
+    if !card.card_sets.is_empty() {
+        s.push_str("\nPrintings:\n");
+        for set in &card.card_sets {
+            writeln!(s, "{} ({}), {}", set.set_code, set.set_rarity, set.set_name)?;
+        }
+    }
+    if let Some(prices) = card.card_prices.first() {
+        writeln!(s, "\nPrices:\nCardmarket: {} €\nTCGplayer: ${}", prices.cardmarket_price, prices.tcgplayer_price)?;
+    }
+    Ok(s)
+}
+
+/// `summary` is the “Showing … results” line, `next_page` where to get more.
+pub fn results(summary: &str, cards: &[&Card], next_page: Option<&str>) -> Result<String, fmt::Error> {
+    let mut s = String::new();
+    writeln!(s, "{summary}")?;
+    for card in cards {
+        s.push('\n');
+        write_card(&mut s, card)?;
+    }
+    if let Some(next) = next_page {
+        writeln!(s, "\nNext page: {next}")?;
+    }
+    Ok(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wanted_test() {
+        assert!(wanted(Some("1"), Some("text/html")));
+        assert!(!wanted(Some("0"), Some("text/plain")));
+        assert!(wanted(None, Some("text/plain")));
+        assert!(wanted(None, Some("text/plain;q=0.9, text/html;q=0.8")));
+        assert!(!wanted(None, Some("text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8")));
+        assert!(!wanted(None, Some("*/*")));
+        assert!(wanted(None, Some("text/html;q=0.5, text/plain")));
+        assert!(!wanted(None, Some("text/plain;q=0, text/html;q=0.1")));
+        assert!(!wanted(None, None));
+    }
+
+    #[test]
+    fn write_card_test() {
+        let card = Card {
+            name: "Odd-Eyes Pendulum Dragon".to_owned(),
+            card_type: "Pendulum Effect Monster".to_owned(),
+            r#type: "Dragon".to_owned(),
+            attribute: Some("DARK".to_owned()),
+            level: Some(7),
+            atk: Some(2500),
+            def: Some(2000),
+            scale: Some(4),
+            pend_desc: Some("Once per turn.".to_owned()),
+            monster_desc: Some("Double damage.".to_owned()),
+            ..Default::default()
+        };
+        let mut s = String::new();
+        write_card(&mut s, &card).unwrap();
+        assert_eq!(
+            s,
+            "Odd-Eyes Pendulum Dragon\nLevel 7 DARK/Dragon Pendulum Effect Monster\n2500 ATK / 2000 DEF\nPendulum Scale: 4\nPendulum Effect: Once per turn.\nMonster Effect: Double damage.\n"
+        );
+    }
+}
@@ -0,0 +1,96 @@
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    mem::size_of,
+    path::PathBuf,
+    sync::RwLock,
+};
+
+use crate::memory::map_size;
+
+/// The lowest price of every card over time. Snapshots are appended to a file as `time\tid\tcents`,
+/// but only when the price changed since the last one, so refreshing often doesn’t make the file grow much.
+#[derive(Debug)]
+pub struct PriceHistory {
+    path:   PathBuf,
+    /// Unix timestamps and prices in cents, oldest first
+    points: RwLock<HashMap<usize, Vec<(i64, i32)>>>,
+}
+
+impl PriceHistory {
+    pub fn empty(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), points: RwLock::default() }
+    }
+
+    /// A missing file just means no prices were recorded yet.
+    pub fn load(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let mut points: HashMap<usize, Vec<(i64, i32)>> = HashMap::new();
+        match File::open(&path) {
+            Ok(f) => {
+                for line in BufReader::new(f).lines() {
+                    let line = line?;
+                    let mut parts = line.split('\t').map(str::parse::<i64>);
+                    if let (Some(Ok(time)), Some(Ok(id)), Some(Ok(cents))) = (parts.next(), parts.next(), parts.next()) {
+                        points.entry(id as usize).or_default().push((time, cents as i32));
+                    }
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+            Err(e) => return Err(e),
+        }
+        Ok(Self { path, points: RwLock::new(points) })
+    }
+
+    /// Records every price that changed and returns how many did.
+    /// The file is written before the lock is taken, so card pages don’t wait for the disk.
+    /// Only the price refresh calls this, so nothing else can change the history in between.
+    pub fn record(&self, prices: impl IntoIterator<Item = (usize, i32)>, time: i64) -> io::Result<usize> {
+        let changed: Vec<_> = {
+            let points = self.points.read().unwrap();
+            let last = |id| points.get(&id).and_then(|history| history.last()).map(|&(_, cents)| cents);
+            prices.into_iter().filter(|&(id, cents)| last(id) != Some(cents)).collect()
+        };
+        let mut file = BufWriter::new(OpenOptions::new().create(true).append(true).open(&self.path)?);
+        for (id, cents) in &changed {
+            writeln!(file, "{time}\t{id}\t{cents}")?;
+        }
+        file.flush()?;
+        let mut points = self.points.write().unwrap();
+        for &(id, cents) in &changed {
+            points.entry(id).or_default().push((time, cents));
+        }
+        Ok(changed.len())
+    }
+
+    /// Oldest first. Each price holds until the next one.
+    pub fn get(&self, id: usize) -> Vec<(i64, i32)> {
+        self.points.read().unwrap().get(&id).cloned().unwrap_or_default()
+    }
+
+    /// For the admin page, see `memory.rs`.
+    pub fn estimated_size(&self) -> usize {
+        map_size(&self.points.read().unwrap(), |_, points| points.capacity() * size_of::<(i64, i32)>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+
+    #[test]
+    fn price_history_test() {
+        let dir = TempDir::new("pricehistory");
+        let path = dir.join("pricehistory.tsv");
+        let history = PriceHistory::empty(&path);
+        assert_eq!(history.record([(1, 500), (2, 100)], 10).unwrap(), 2);
+        assert_eq!(history.record([(1, 500), (2, 90)], 20).unwrap(), 1);
+        assert_eq!(history.record([(1, 450)], 30).unwrap(), 1);
+        assert_eq!(history.get(1), vec![(10, 500), (30, 450)]);
+        assert_eq!(history.get(3), vec![]);
+
+        assert_eq!(PriceHistory::load(&path).unwrap().get(2), vec![(10, 100), (20, 90)]);
+    }
+}
@@ -0,0 +1,174 @@
+use serde::Serialize;
+use std::{
+    collections::VecDeque,
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::PathBuf,
+    sync::Mutex,
+};
+use time::OffsetDateTime;
+
+// Anyone can add watches, and the file is rewritten whenever one fires, so this keeps it small.
+const MAX_WATCHES: usize = 1_000;
+const MAX_ALERTS: usize = 200;
+
+/// Price thresholds for cards. Watches are appended to a file as `id\tthreshold` and read back on startup.
+/// Whenever new prices come in, every watch whose card dropped to or below its threshold raises an alert.
+/// Watches that fired are marked with a third `1` column, so a restart doesn’t raise the same alerts again.
+#[derive(Debug)]
+pub struct PriceWatches {
+    path:  PathBuf,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    watches: Vec<Watch>,
+    /// Newest first
+    alerts:  VecDeque<Alert>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Watch {
+    id:        usize,
+    /// In cents
+    threshold: i32,
+    /// So a watch only fires once until the price goes back up
+    triggered: bool,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq, Clone)]
+pub struct Alert {
+    pub id:        usize,
+    pub threshold: i32,
+    pub price:     i32,
+    /// Unix timestamp
+    pub time:      i64,
+}
+
+impl PriceWatches {
+    pub fn empty(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), state: Mutex::default() }
+    }
+
+    /// A missing file just means nobody has registered a watch yet.
+    pub fn load(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let watches = match File::open(&path) {
+            Ok(f) => BufReader::new(f)
+                .lines()
+                .map(|line| {
+                    let line = line?;
+                    let mut columns = line.split('\t');
+                    let (id, threshold) = (columns.next().unwrap_or_default(), columns.next().unwrap_or_default());
+                    let triggered = columns.next() == Some("1");
+                    Ok(id.parse().ok().zip(threshold.parse().ok()).map(|(id, threshold)| Watch { id, threshold, triggered }))
+                })
+                .filter_map(Result::transpose)
+                .collect::<io::Result<_>>()?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(Self { path, state: Mutex::new(State { watches, alerts: VecDeque::new() }) })
+    }
+
+    /// Adding the same watch twice does nothing.
+    pub fn add(&self, id: usize, threshold: i32) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        if state.watches.iter().any(|w| w.id == id && w.threshold == threshold) {
+            return Ok(());
+        }
+        if state.watches.len() >= MAX_WATCHES {
+            return Err(io::Error::other("Too many price watches"));
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{id}\t{threshold}")?;
+        state.watches.push(Watch { id, threshold, triggered: false });
+        Ok(())
+    }
+
+    /// Compares new prices (in cents) against all thresholds and returns how many alerts were raised.
+    /// If any watch fired or reset, the file is rewritten to remember that.
+    pub fn check(&self, prices: impl Fn(usize) -> Option<i32>, now: OffsetDateTime) -> io::Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        let State { watches, alerts } = &mut *state;
+        let mut raised = 0;
+        let mut changed = false;
+        for watch in watches.iter_mut() {
+            let Some(price) = prices(watch.id) else { continue };
+            match (price <= watch.threshold, watch.triggered) {
+                (true, false) => {
+                    alerts.push_front(Alert { id: watch.id, threshold: watch.threshold, price, time: now.unix_timestamp() });
+                    raised += 1;
+                }
+                (false, true) => (),
+                _ => continue,
+            }
+            watch.triggered = !watch.triggered;
+            changed = true;
+        }
+        alerts.truncate(MAX_ALERTS);
+        if changed {
+            // Still under the lock, or a watch added in the meantime could be lost.
+            self.save(watches)?;
+        }
+        Ok(raised)
+    }
+
+    fn save(&self, watches: &[Watch]) -> io::Result<()> {
+        let tmp = self.path.with_extension(format!("{}.tmp", std::process::id()));
+        let mut file = BufWriter::new(File::create(&tmp)?);
+        for Watch { id, threshold, triggered } in watches {
+            match triggered {
+                true => writeln!(file, "{id}\t{threshold}\t1")?,
+                false => writeln!(file, "{id}\t{threshold}")?,
+            }
+        }
+        file.into_inner()?.sync_all()?;
+        fs::rename(tmp, &self.path)
+    }
+
+    /// Newest first, optionally only those raised after the given unix timestamp.
+    pub fn alerts(&self, since: Option<i64>) -> Vec<Alert> {
+        let state = self.state.lock().unwrap();
+        state.alerts.iter().take_while(|a| since.map_or(true, |since| a.time > since)).cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+    use std::collections::HashMap;
+
+    #[test]
+    fn price_watch_test() {
+        let dir = TempDir::new("pricewatches");
+        let path = dir.join("pricewatches.tsv");
+        let watches = PriceWatches::empty(&path);
+        watches.add(1, 500).unwrap();
+        watches.add(1, 500).unwrap();
+        watches.add(2, 100).unwrap();
+        let check = |prices: &[(usize, i32)], time| {
+            watches.check(|id| HashMap::<_, _>::from_iter(prices.iter().copied()).get(&id).copied(), time).unwrap()
+        };
+
+        assert_eq!(check(&[(1, 600), (2, 50)], OffsetDateTime::from_unix_timestamp(1).unwrap()), 1);
+        assert_eq!(check(&[(1, 450), (2, 40)], OffsetDateTime::from_unix_timestamp(2).unwrap()), 1, "2 is still below its threshold");
+        assert_eq!(check(&[(1, 700), (2, 90)], OffsetDateTime::from_unix_timestamp(3).unwrap()), 0);
+        assert_eq!(check(&[(1, 500)], OffsetDateTime::from_unix_timestamp(4).unwrap()), 1);
+        assert_eq!(
+            watches.alerts(None).iter().map(|a| (a.id, a.price, a.time)).collect::<Vec<_>>(),
+            vec![(1, 500, 4), (1, 450, 2), (2, 50, 1)]
+        );
+        assert_eq!(watches.alerts(Some(2)).len(), 1);
+
+        let loaded = PriceWatches::load(&path).unwrap();
+        assert_eq!(loaded.state.lock().unwrap().watches.len(), 2);
+        assert_eq!(
+            loaded.check(|_| Some(40), OffsetDateTime::from_unix_timestamp(5).unwrap()).unwrap(),
+            0,
+            "both already fired before the restart"
+        );
+    }
+}
@@ -0,0 +1,101 @@
+//! Running behind a reverse proxy, possibly under a prefix like `/ygo`.
+//! The server only listens on localhost, so the `X-Forwarded-*` headers can only come from the proxy and are trusted.
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::Uri,
+    middleware::Next,
+    HttpRequest,
+};
+
+use crate::{BASE_PATH, PUBLIC_URL};
+
+/// `""` for the root, otherwise with a leading and without a trailing slash, e.g. `/ygo`.
+pub fn normalize_base_path(path: &str) -> String {
+    match path.trim().trim_matches('/') {
+        "" => String::new(),
+        path => format!("/{path}"),
+    }
+}
+
+/// Absolute path of a page, e.g. `url("/card/123")` is `/ygo/card/123` under `/ygo`.
+pub fn url(path: &str) -> String {
+    format!("{}{path}", BASE_PATH.as_str())
+}
+
+/// Without a trailing slash, so paths can be appended.
+pub fn normalize_public_url(url: &str) -> String {
+    url.trim().trim_end_matches('/').to_owned()
+}
+
+/// Full URL of a page, for links that leave the site, e.g. in QR codes, embeds, or feeds.
+/// This is always built from `PUBLIC_URL` and never from the request, because some of these responses are cached for everyone.
+pub fn public_url(path: &str) -> String {
+    format!("{}{}", PUBLIC_URL.as_str(), url(path))
+}
+
+/// The client’s address from `X-Forwarded-For` (or `Forwarded`), falling back to the proxy’s own address.
+pub fn client_ip(req: &HttpRequest) -> String {
+    req.connection_info().realip_remote_addr().unwrap_or("unknown").to_owned()
+}
+
+/// Some proxies pass the prefix on and some strip it. This removes it if it’s there, so routing works either way.
+pub async fn strip_base_path(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    if let Some(stripped) = strip_prefix(req.uri(), &BASE_PATH) {
+        req.match_info_mut().get_mut().update(&stripped);
+        req.head_mut().uri = stripped;
+    }
+    next.call(req).await
+}
+
+fn strip_prefix(uri: &Uri, prefix: &str) -> Option<Uri> {
+    if prefix.is_empty() {
+        return None;
+    }
+    let path = uri.path().strip_prefix(prefix)?;
+    let path = match path {
+        "" => "/",
+        path if path.starts_with('/') => path,
+        // Only whole segments, `/ygofoo` isn’t under `/ygo`.
+        _ => return None,
+    };
+    let path_and_query = match uri.query() {
+        Some(query) => format!("{path}?{query}"),
+        None => path.to_owned(),
+    };
+    path_and_query.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_base_path_test() {
+        assert_eq!(normalize_base_path(""), "");
+        assert_eq!(normalize_base_path("/"), "");
+        assert_eq!(normalize_base_path("ygo"), "/ygo");
+        assert_eq!(normalize_base_path("/ygo/"), "/ygo");
+        assert_eq!(normalize_base_path("/tools/ygo/"), "/tools/ygo");
+    }
+
+    #[test]
+    fn normalize_public_url_test() {
+        assert_eq!(normalize_public_url("https://example.com/"), "https://example.com");
+        assert_eq!(normalize_public_url(" http://localhost:1961 "), "http://localhost:1961");
+    }
+
+    #[test]
+    fn strip_prefix_test() {
+        let strip = |uri: &str, prefix| strip_prefix(&uri.parse().unwrap(), prefix).map(|u| u.to_string());
+        assert_eq!(strip("/ygo/card/1", "/ygo"), Some("/card/1".to_owned()));
+        assert_eq!(strip("/ygo?q=c%3Aspell", "/ygo"), Some("/?q=c%3Aspell".to_owned()));
+        assert_eq!(strip("/ygo/", "/ygo"), Some("/".to_owned()));
+        assert_eq!(strip("/card/1", "/ygo"), None);
+        assert_eq!(strip("/ygofoo", "/ygo"), None);
+        assert_eq!(strip("/card/1", ""), None);
+    }
+}
@@ -0,0 +1,356 @@
+use std::fmt::{self, Display, Write};
+
+use serde_json::json;
+
+use crate::{
+    data::{BanlistStatus, Card, CardPrice},
+    escape_html,
+    pages::format_cents,
+    vendors::VendorLinks,
+    IMG_HOST, REFERRALS,
+};
+
+/// The HTML for a card’s name, type, stats and text, as shown in the grid and on its page.
+/// The text is written as is, so it has to be HTML already (as in `AppState::cards_by_id`, see `text_html`).
+/// The flag is whether the card is out yet, see `AppState::is_unreleased`.
+pub struct CardHtml<'a>(pub &'a Card, pub bool);
+
+impl Display for CardHtml<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        CardHtmlWithText(self.0, &self.0.text, self.1).fmt(f)
+    }
+}
+
+/// Like `CardHtml`, but with different (still HTML) text, e.g. with links from `link_mentions`.
+pub struct CardHtmlWithText<'a>(pub &'a Card, pub &'a str, pub bool);
+
+impl Display for CardHtmlWithText<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let CardHtmlWithText(card, text, released) = *self;
+        write_card_html(f, card, text, card.banlist_info.map(|bi| bi.ban_tcg).unwrap_or_default(), released)
+    }
+}
+
+/// Like `CardHtml`, but with the icon for another banlist status, e.g. from an old banlist for `asof:`.
+pub struct CardHtmlWithStatus<'a>(pub &'a Card, pub BanlistStatus, pub bool);
+
+impl Display for CardHtmlWithStatus<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let CardHtmlWithStatus(card, status, released) = *self;
+        write_card_html(f, card, &card.text, status, released)
+    }
+}
+
+fn write_card_html(f: &mut fmt::Formatter<'_>, card: &Card, text: &str, status: BanlistStatus, released: bool) -> fmt::Result {
+    write!(
+        f,
+        r#"<h2 class="cardname">{} {}{}{}{}</h2><em>"#,
+        escape_html(&card.name),
+        match status.icon() {
+            Some(icon) => format!(r#"<img class="banlist-icon" src="{}/static/{icon}"/>"#, IMG_HOST.as_str()),
+            None => String::new(),
+        },
+        match card.exclusive_to() {
+            Some(format) => format!(r#"<span class="badge">{format} only</span>"#),
+            None => String::new(),
+        },
+        match released {
+            true => "",
+            false => r#"<span class="badge">Not yet released</span>"#,
+        },
+        match card.custom {
+            true => r#"<span class="badge">Custom</span>"#,
+            false => "",
+        }
+    )?;
+    let mut info = String::new();
+    card.basic_info(&mut info, "\n")?;
+    f.write_str(&escape_html(&info).replace('\n', "<br/>"))?;
+    f.write_str("</em>")?;
+    write_link_arrows(card, f)?;
+    write!(f, "<hr/>{text}")?;
+    Ok(())
+}
+
+/// The escaped card text as HTML. Pendulums get separate boxes for both effects, with the scales on either side.
+pub fn text_html(card: &Card) -> String {
+    let paragraph = |text: &str| format!("<p>{}</p>", escape_html(text).replace('\n', "<br/>"));
+    let Some((pendulum, monster)) = card.pendulum_texts() else {
+        return paragraph(&card.text.replace('\r', ""));
+    };
+    let scale = card.scale.map_or_else(|| "?".to_owned(), |s| s.to_string());
+    let label = if card.card_type.contains("Normal") { "Flavor Text" } else { "Monster Effect" };
+    format!(
+        r#"<div class="pendulum"><span class="scale" title="Left Pendulum Scale">◀<br/>{scale}</span><div class="pendulumeffect">{}</div><span class="scale" title="Right Pendulum Scale">▶<br/>{scale}</span></div><div class="monstereffect"><span class="meta">[ {label} ]</span>{}</div>"#,
+        paragraph(&pendulum),
+        paragraph(&monster),
+    )
+}
+
+/// After a quoted name, these mean the archetype rather than the card, as in `1 "Dark Magician" monster`.
+const ARCHETYPE_CONTEXT: [&str; 4] = [" monster", " card", " Spell", " Trap"];
+
+/// Turns quoted names in escaped card text into links. `href` gets the escaped name and whether it’s used like an archetype,
+/// and returns where it should link to, if anywhere.
+pub fn link_mentions(text: &str, href: impl Fn(&str, bool) -> Option<String>) -> String {
+    const QUOTE: &str = "&quot;";
+    let parts: Vec<&str> = text.split(QUOTE).collect();
+    let mut linked = String::with_capacity(text.len());
+    for (i, part) in parts.iter().enumerate() {
+        if i > 0 {
+            linked.push_str(QUOTE);
+        }
+        // Every other part is quoted, as long as the quote is closed again.
+        let link = match parts.get(i + 1) {
+            Some(next) if i % 2 == 1 => href(part, ARCHETYPE_CONTEXT.iter().any(|c| next.starts_with(c))),
+            _ => None,
+        };
+        match link {
+            Some(href) => {
+                let _ = write!(linked, r#"<a href="{href}">{part}</a>"#);
+            }
+            None => linked.push_str(part),
+        }
+    }
+    linked
+}
+
+/// Rulings, Yugipedia, and prices for the card page.
+pub fn extended_info(card: &Card) -> Result<String, fmt::Error> {
+    let mut s = String::with_capacity(1000);
+    // the ygorg search breaks for I:P and similar criminals.
+    let url_name = escape_html(&card.name.replace(':', " "));
+    write!(s, "<p><a href=\"https://db.ygorganization.com/search#card:{url_name}\">Rulings</a> – <a href=\"https://yugipedia.com/wiki/{:08}\">Yugipedia</a></p>", card.id)?;
+    if let Some(CardPrice { cardmarket_price, tcgplayer_price }) = card.card_prices.first() {
+        let (cardmarket_price, tcgplayer_price) = (escape_html(cardmarket_price), escape_html(tcgplayer_price));
+        s.push_str("<h3>Prices:</h3>");
+        let links = VendorLinks::search(card, &REFERRALS);
+        write!(s, "Cardmarket: <a href=\"{}\">{cardmarket_price}&ThinSpace;€</a><br/>", escape_html(&links.cardmarket))?;
+        write!(s, "TCGplayer: <a href=\"{}\">$&ThinSpace;{tcgplayer_price}</a><br/>", escape_html(&links.tcgplayer))?;
+    }
+    Ok(s)
+}
+
+/// schema.org `Product` data for the card page, so search engines can show the prices.
+/// Each vendor gets its own `Offer` because they use different currencies.
+pub fn product_json_ld(card: &Card, description: &str, page_url: &str, image_url: &str) -> String {
+    let VendorLinks { cardmarket, tcgplayer } = VendorLinks::search(card, &REFERRALS);
+    let offers: Vec<_> = card
+        .card_prices
+        .first()
+        .map(|p| vec![("Cardmarket", &p.cardmarket_price, "EUR", cardmarket), ("TCGplayer", &p.tcgplayer_price, "USD", tcgplayer)])
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(_, price, ..)| price.parse::<f32>().is_ok_and(|p| p > 0.0))
+        .map(|(seller, price, currency, url)| {
+            json!({
+                "@type": "Offer",
+                "price": price,
+                "priceCurrency": currency,
+                "url": url,
+                "seller": { "@type": "Organization", "name": seller },
+            })
+        })
+        .collect();
+    let mut product = json!({
+        "@context": "https://schema.org",
+        "@type": "Product",
+        "name": card.name,
+        "image": image_url,
+        "description": description,
+        "url": page_url,
+        "sku": card.id.to_string(),
+    });
+    if !offers.is_empty() {
+        product["offers"] = offers.into();
+    }
+    // A `</script>` in a card name would end the script tag early.
+    product.to_string().replace("</", "<\\/")
+}
+
+const CHART_WIDTH: i64 = 600;
+const CHART_HEIGHT: i64 = 150;
+/// Room for the price labels on the left and the dates below
+const CHART_MARGIN: i64 = 50;
+
+/// A step chart of the lowest price from `from` until `now`, as inline SVG so it works without JavaScript.
+/// `points` are unix timestamps and prices in cents, oldest first. `None` if the price never changed.
+pub fn price_chart(points: &[(i64, i32)], from: i64, now: i64) -> Option<String> {
+    if points.len() < 2 {
+        return None;
+    }
+    // The price at the start of the range is whatever was recorded last before it.
+    let start = points.iter().rposition(|&(time, _)| time <= from).unwrap_or(0);
+    let visible: Vec<_> = points[start..].iter().map(|&(time, cents)| (time.max(from), cents)).filter(|&(time, _)| time <= now).collect();
+    let first = visible.first()?.0;
+    let (min, max) = visible.iter().map(|&(_, cents)| cents).fold((i32::MAX, i32::MIN), |(min, max), c| (min.min(c), max.max(c)));
+    let x = |time: i64| CHART_MARGIN + (time - first) * (CHART_WIDTH - CHART_MARGIN) / (now - first).max(1);
+    // Leave some space above and below so a flat line isn’t drawn on the border.
+    let y = |cents: i32| 10 + (i64::from(max) - i64::from(cents)) * (CHART_HEIGHT - 40) / i64::from(max - min).max(1);
+    let mut path = format!("M{} {}", x(first), y(visible[0].1));
+    for &(time, cents) in &visible[1..] {
+        write!(path, " H{} V{}", x(time), y(cents)).ok()?;
+    }
+    write!(path, " H{}", x(now)).ok()?;
+    let date = |time: i64| time::OffsetDateTime::from_unix_timestamp(time).map(|t| t.date().to_string()).unwrap_or_default();
+    let label_y = CHART_HEIGHT - 5;
+    Some(format!(
+        r#"<svg class="pricechart" viewBox="0 0 {CHART_WIDTH} {CHART_HEIGHT}" role="img" aria-label="Lowest price from {} to {}"><path d="{path}" fill="none" stroke="currentColor" stroke-width="2"/><text x="0" y="{}">{}</text><text x="0" y="{}">{}</text><text x="{CHART_MARGIN}" y="{label_y}">{}</text><text x="{CHART_WIDTH}" y="{label_y}" text-anchor="end">{}</text></svg>"#,
+        format_cents(Some(min)),
+        format_cents(Some(max)),
+        y(max) + 5,
+        format_cents(Some(max)),
+        y(min) + 5,
+        format_cents(Some(min)),
+        date(first),
+        date(now),
+    ))
+}
+
+/// Link arrows in the order they appear in a 3×3 grid, with the symbol to draw for each.
+/// The center has no arrow.
+const LINK_ARROW_GRID: [(&str, char); 9] = [
+    ("Top-Left", '◤'),
+    ("Top", '▲'),
+    ("Top-Right", '◥'),
+    ("Left", '◀'),
+    ("", ' '),
+    ("Right", '▶'),
+    ("Bottom-Left", '◣'),
+    ("Bottom", '▼'),
+    ("Bottom-Right", '◢'),
+];
+
+fn write_link_arrows<W: Write>(card: &Card, f: &mut W) -> fmt::Result {
+    let Some(arrows) = &card.link_arrows else {
+        return Ok(());
+    };
+    write!(f, r#"<div class="linkarrows" title="{}">"#, escape_html(&arrows.join(", ")))?;
+    for (name, symbol) in LINK_ARROW_GRID {
+        let class = if arrows.iter().any(|a| a == name) { "arrow active" } else { "arrow" };
+        write!(f, r#"<span class="{class}">{symbol}</span>"#)?;
+    }
+    f.write_str("</div>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::Date;
+
+    use crate::data::MiscInfo;
+
+    #[test]
+    fn display_escapes_card_fields() {
+        let card = Card { name: "<b>Ally & Justice</b>".to_owned(), r#type: "<i>".to_owned(), ..Default::default() };
+        let html = CardHtml(&card, true).to_string();
+        assert!(html.contains("&lt;b&gt;Ally &amp; Justice&lt;/b&gt;"));
+        assert!(html.contains("&lt;i&gt;"));
+        assert!(!html.contains("<b>"));
+    }
+
+    #[test]
+    fn exclusive_badge_test() {
+        let misc_info = |formats: &[&str]| {
+            vec![MiscInfo { formats: formats.iter().map(|f| f.to_string()).collect(), tcg_date: Some(Date::MIN), ..Default::default() }]
+        };
+        let ocg_only = Card { misc_info: misc_info(&["OCG"]), ..Default::default() };
+        assert!(CardHtml(&ocg_only, true).to_string().contains(r#"<span class="badge">OCG only</span>"#));
+        let everywhere = Card { misc_info: misc_info(&["TCG", "OCG", "Master Duel"]), ..Default::default() };
+        assert!(!CardHtml(&everywhere, true).to_string().contains("badge"));
+    }
+
+    #[test]
+    fn unreleased_badge_test() {
+        let badge = r#"<span class="badge">Not yet released</span>"#;
+        assert!(CardHtml(&Card::default(), false).to_string().contains(badge));
+        assert!(!CardHtml(&Card::default(), true).to_string().contains(badge));
+    }
+
+    #[test]
+    fn product_json_ld_test() {
+        let card = Card {
+            id: 123,
+            name: "</script> Dragon".to_owned(),
+            card_prices: vec![CardPrice { cardmarket_price: "0.50".to_owned(), tcgplayer_price: "0.00".to_owned() }],
+            ..Default::default()
+        };
+        let json_ld = product_json_ld(&card, "A dragon", "https://example.com/card/123", "https://example.com/123.jpg");
+        assert!(!json_ld.contains("</script>"));
+        let product: serde_json::Value = serde_json::from_str(&json_ld).unwrap();
+        assert_eq!(product["@type"], "Product");
+        assert_eq!(product["name"], "</script> Dragon");
+        assert_eq!(product["image"], "https://example.com/123.jpg");
+        // TCGplayer has no price for it
+        assert_eq!(product["offers"].as_array().unwrap().len(), 1);
+        assert_eq!(product["offers"][0]["price"], "0.50");
+        assert_eq!(product["offers"][0]["priceCurrency"], "EUR");
+        assert_eq!(product["offers"][0]["url"], "https://www.cardmarket.com/en/YuGiOh/Products/Search?searchString=%3C%2Fscript%3E+Dragon");
+
+        let unpriced: serde_json::Value = serde_json::from_str(&product_json_ld(&Card::default(), "", "", "")).unwrap();
+        assert!(unpriced.get("offers").is_none());
+    }
+
+    #[test]
+    fn text_html_test() {
+        let normal = Card { text: "Draw 1 card.\r\nThen \"discard\" 1.".to_owned(), ..Default::default() };
+        assert_eq!(text_html(&normal), "<p>Draw 1 card.<br/>Then &quot;discard&quot; 1.</p>");
+        let pendulum = Card {
+            card_type: "Pendulum Effect Monster".to_owned(),
+            scale: Some(4),
+            pend_desc: Some("Once per turn: <stuff>".to_owned()),
+            monster_desc: Some("Destroy it.".to_owned()),
+            ..Default::default()
+        };
+        let html = text_html(&pendulum);
+        assert!(html.contains(r#"<div class="pendulumeffect"><p>Once per turn: &lt;stuff&gt;</p></div>"#), "{html}");
+        assert!(html.contains(r#"[ Monster Effect ]</span><p>Destroy it.</p>"#), "{html}");
+        assert_eq!(html.matches("<br/>4</span>").count(), 2);
+    }
+
+    #[test]
+    fn link_mentions_test() {
+        let href = |name: &str, archetype: bool| match (name, archetype) {
+            ("Dark Magician", false) => Some("/card/1".to_owned()),
+            ("Dark Magician", true) => Some("/archetype".to_owned()),
+            _ => None,
+        };
+        assert_eq!(
+            link_mentions(
+                "Add 1 &quot;Dark Magician&quot; monster or &quot;Dark Magician&quot;. &quot;Nope&quot; &quot;Dark Magician",
+                href
+            ),
+            r#"Add 1 &quot;<a href="/archetype">Dark Magician</a>&quot; monster or &quot;<a href="/card/1">Dark Magician</a>&quot;. &quot;Nope&quot; &quot;Dark Magician"#
+        );
+        assert_eq!(link_mentions("No quotes", href), "No quotes");
+    }
+
+    #[test]
+    fn link_arrow_grid_test() {
+        let arrows = ["Top", "Bottom-Left", "Bottom-Right"].map(str::to_owned).to_vec();
+        let bls = Card { link_rating: Some(3), link_arrows: Some(arrows), ..Default::default() };
+        let mut grid = String::new();
+        write_link_arrows(&bls, &mut grid).unwrap();
+        assert_eq!(grid.matches("arrow active").count(), 3);
+        assert_eq!(grid.matches("<span").count(), 9);
+        assert!(grid.contains(r#"<span class="arrow active">▲</span>"#));
+        assert!(grid.contains(r#"<span class="arrow">▼</span>"#));
+        let mut no_grid = String::new();
+        write_link_arrows(&Card::default(), &mut no_grid).unwrap();
+        assert!(no_grid.is_empty());
+    }
+
+    #[test]
+    fn price_chart_test() {
+        assert_eq!(price_chart(&[(0, 100)], 0, 100), None, "nothing changed");
+        let points = [(0, 100), (50, 300), (80, 200)];
+        let chart = price_chart(&points, 0, 100).unwrap();
+        assert!(chart.contains(r#"d="M50 120 H325 V10 H490 V65 H600""#), "{chart}");
+        assert!(chart.contains(">1.00<") && chart.contains(">3.00<"));
+        assert!(chart.contains(">1970-01-01<"));
+
+        let recent = price_chart(&points, 60, 100).unwrap();
+        assert!(recent.contains(r#"d="M50 10 H325 V120 H600""#), "{recent}");
+        assert!(!recent.contains(">1.00<"), "only prices in the range");
+    }
+}
@@ -0,0 +1,152 @@
+//! The results of recent queries, so popular searches (and flipping through their pages) don’t search every time.
+//! Each `AppState` has its own, so loading new data starts with an empty cache. With a shared store, results are also
+//! stored there under the data version, so instances don’t all run the same searches.
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use itertools::Itertools;
+
+use crate::{shared::SharedStore, stats::CacheStats};
+
+/// Cards are released and `is:unreprinted` moves on over time, so results can’t be kept forever even if the data stays.
+const MAX_AGE: Duration = Duration::from_secs(600);
+/// In card IDs over all entries, so a few queries that match everything don’t push the memory up. About 8 MB.
+const MAX_CACHED_IDS: usize = 1_000_000;
+
+#[derive(Debug)]
+struct Entries {
+    results: HashMap<String, (Instant, Arc<[usize]>)>,
+    /// Oldest first, for evicting
+    order:   VecDeque<String>,
+    ids:     usize,
+    max_ids: usize,
+}
+
+impl Default for Entries {
+    fn default() -> Self {
+        Self { results: HashMap::new(), order: VecDeque::new(), ids: 0, max_ids: MAX_CACHED_IDS }
+    }
+}
+
+impl Entries {
+    fn insert(&mut self, query: String, ids: Arc<[usize]>, now: Instant) {
+        if ids.len() > self.max_ids {
+            return;
+        }
+        if let Some((_, old)) = self.results.remove(&query) {
+            self.ids -= old.len();
+            self.order.retain(|q| q != &query);
+        }
+        while self.ids + ids.len() > self.max_ids {
+            let Some(oldest) = self.order.pop_front() else { break };
+            if let Some((_, old)) = self.results.remove(&oldest) {
+                self.ids -= old.len();
+            }
+        }
+        self.ids += ids.len();
+        self.order.push_back(query.clone());
+        self.results.insert(query, (now, ids));
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ResultCache {
+    entries:   Mutex<Entries>,
+    /// The store and the Redis hash for this data version
+    shared:    Option<(&'static SharedStore, String)>,
+    pub stats: CacheStats,
+}
+
+impl ResultCache {
+    /// Without a version, the shared store isn’t used because other instances could have different data.
+    pub fn new(shared: Option<&'static SharedStore>, version: Option<&str>) -> Self {
+        let shared = shared.zip(version).map(|(store, version)| (store, format!("results:{version}")));
+        Self { shared, ..Default::default() }
+    }
+
+    /// The cached IDs for `query`, if any, without searching.
+    /// The lock isn’t held while talking to the shared store.
+    pub fn get(&self, query: &str) -> Option<Arc<[usize]>> {
+        let now = Instant::now();
+        if let Some((cached_at, ids)) = self.entries.lock().unwrap().results.get(query) {
+            if now.duration_since(*cached_at) < MAX_AGE {
+                self.stats.hit();
+                return Some(Arc::clone(ids));
+            }
+        }
+        self.stats.miss();
+        let ids = self.shared.as_ref().and_then(|(store, key)| match store.get(key, query) {
+            Ok(ids) => ids.map(|ids| ids.split(',').filter_map(|id| id.parse().ok()).collect::<Arc<[usize]>>()),
+            Err(e) => {
+                eprintln!("Could not read cached results: {e}");
+                None
+            }
+        })?;
+        self.entries.lock().unwrap().insert(query.to_owned(), Arc::clone(&ids), now);
+        Some(ids)
+    }
+
+    /// The cached IDs for `query`, or the result of `search`, which is then cached.
+    /// The lock isn’t held while searching.
+    pub fn get_or_search(&self, query: &str, search: impl FnOnce() -> Vec<usize>) -> Arc<[usize]> {
+        if let Some(ids) = self.get(query) {
+            return ids;
+        }
+        let ids: Arc<[usize]> = search().into();
+        if let Some((store, key)) = &self.shared {
+            if let Err(e) = store.set_expiring(key, query, &ids.iter().join(","), MAX_AGE) {
+                eprintln!("Could not share cached results: {e}");
+            }
+        }
+        self.entries.lock().unwrap().insert(query.to_owned(), Arc::clone(&ids), Instant::now());
+        ids
+    }
+
+    /// Only this instance’s entries, the shared ones expire on their own. Returns how many there were.
+    pub fn clear(&self) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let cleared = entries.results.len();
+        *entries = Entries { max_ids: entries.max_ids, ..Default::default() };
+        cleared
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().results.len()
+    }
+
+    /// For the admin page, see `memory.rs`.
+    pub fn estimated_size(&self) -> usize {
+        let entries = self.entries.lock().unwrap();
+        entries.ids * size_of::<usize>() + entries.results.keys().map(|q| 2 * q.capacity()).sum::<usize>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn result_cache_test() {
+        let cache = ResultCache::default();
+        assert!(cache.get("c:spell").is_none());
+        assert_eq!(&*cache.get_or_search("c:spell", || vec![1, 2, 3]), &[1, 2, 3]);
+        assert_eq!(&*cache.get_or_search("c:spell", || unreachable!()), &[1, 2, 3]);
+        assert_eq!(cache.get("c:spell").as_deref(), Some(&[1, 2, 3][..]));
+        assert_eq!(cache.stats.hits(), 2);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.clear(), 1);
+        assert!(cache.get("c:spell").is_none());
+
+        let mut entries = Entries { max_ids: 10, ..Default::default() };
+        let now = Instant::now();
+        entries.insert("big".to_owned(), vec![0; 9].into(), now);
+        entries.insert("small".to_owned(), vec![0; 2].into(), now);
+        assert!(!entries.results.contains_key("big"), "the oldest entry makes room");
+        assert_eq!(entries.ids, 2);
+        entries.insert("huge".to_owned(), vec![0; 11].into(), now);
+        assert!(!entries.results.contains_key("huge"));
+    }
+}
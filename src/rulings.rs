@@ -0,0 +1,84 @@
+//! The rulings page, which searches ruling text instead of card text, e.g. for judges at events.
+use crate::{data::Card, filter::SearchCard, snippet::marked};
+
+/// How much of a ruling to show around the first match, in bytes on either side
+const CONTEXT: usize = 150;
+
+#[derive(Debug)]
+pub struct RulingMatch {
+    /// HTML, with the matches marked
+    pub snippet: String,
+    pub url:     Option<String>,
+}
+
+/// Every ruling that contains all words of the query (case-insensitive), grouped by card in the order of `cards`.
+/// Only cards whose lowercased rulings contain every word are looked at ruling by ruling.
+pub fn search<'a>(cards: impl IntoIterator<Item = (&'a SearchCard, &'a Card)>, query: &str) -> Vec<(&'a Card, Vec<RulingMatch>)> {
+    let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    if words.is_empty() {
+        return Vec::new();
+    }
+    cards
+        .into_iter()
+        .filter(|(search_card, _)| words.iter().all(|w| search_card.rulings().contains(w.as_str())))
+        .filter_map(|(_, card)| {
+            let matches: Vec<_> = card
+                .rulings
+                .iter()
+                .filter(|r| {
+                    let text = r.text.to_lowercase();
+                    words.iter().all(|w| text.contains(w.as_str()))
+                })
+                .map(|r| RulingMatch { snippet: snippet(&r.text, &words), url: r.url.clone() })
+                .collect();
+            (!matches.is_empty()).then_some((card, matches))
+        })
+        .collect()
+}
+
+/// The part of `text` around the first match, escaped, with all matches in `<mark>`.
+/// `words` have to be lowercase. Only ASCII is compared case-insensitively here, so byte positions are the same in both cases.
+pub fn snippet(text: &str, words: &[String]) -> String {
+    let lower = text.to_ascii_lowercase();
+    let mut matches: Vec<(usize, usize)> =
+        words.iter().filter(|w| !w.is_empty()).flat_map(|w| lower.match_indices(w.as_str()).map(|(i, m)| (i, i + m.len()))).collect();
+    matches.sort_unstable();
+    marked(text, &matches, CONTEXT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aro_core::rulings::Ruling;
+    use std::collections::HashMap;
+
+    #[test]
+    fn snippet_test() {
+        let words = ["summon".to_owned(), "dark".to_owned()];
+        assert_eq!(
+            snippet("Dark Magician can be Normal Summoned <here>.", &words),
+            "<mark>Dark</mark> Magician can be Normal <mark>Summon</mark>ed &lt;here&gt;."
+        );
+        let long = format!("{}target{}", "a".repeat(200), "é".repeat(200));
+        let cut = snippet(&long, &["target".to_owned()]);
+        assert!(cut.starts_with('…') && cut.ends_with('…'));
+        assert!(cut.contains("<mark>target</mark>"));
+        assert_eq!(snippet("nothing", &words), "nothing");
+    }
+
+    #[test]
+    fn search_test() {
+        let ruling = |text: &str| Ruling { text: text.to_owned(), url: None };
+        let card = Card { rulings: vec![ruling("Negates the activation."), ruling("Negates the effect.")], ..Default::default() };
+        let unruled = Card::default();
+        let umlaut = Card { rulings: vec![ruling("ÜBER alles")], ..Default::default() };
+        let search_cards = [&card, &unruled, &umlaut].map(|c| SearchCard::new(c, &HashMap::new()));
+        let cards = || search_cards.iter().zip([&card, &unruled, &umlaut]);
+        let results = search(cards(), "NEGATES effect");
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].1.len(), 1);
+        assert_eq!(results[0].1[0].snippet, "<mark>Negates</mark> the <mark>effect</mark>.");
+        assert_eq!(search(cards(), "über").len(), 1, "not just ASCII");
+        assert!(search(cards(), " ").is_empty());
+    }
+}
@@ -0,0 +1,169 @@
+//! Runs all periodic background work on the runtime, so there’s one place that knows what runs when and how it went.
+//! Jobs are blocking functions and run on the blocking thread pool, so a slow one doesn’t hold up the server.
+use actix_web::rt::{self, task};
+use std::{
+    hash::{BuildHasher, Hasher, RandomState},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use time::OffsetDateTime;
+
+/// How far each wait may be off from the interval unless configured, so jobs of several instances don’t all run at the same moment
+const DEFAULT_JITTER_PERCENT: u64 = 10;
+
+/// How often a job runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Schedule {
+    pub interval:       Duration,
+    /// How far each wait may be off from the interval, in percent of it
+    pub jitter_percent: u64,
+}
+
+impl Schedule {
+    /// `{prefix}_SECS` (or `default`) and `{prefix}_JITTER` in percent from the environment.
+    /// `None` if the job is off, i.e. there’s neither a default nor an interval in the environment.
+    pub fn from_env(prefix: &str, default: Option<Duration>) -> Option<Self> {
+        let var = |suffix: &str| std::env::var(format!("{prefix}_{suffix}")).ok().and_then(|s| s.parse().ok());
+        // 0 would run the job in a busy loop.
+        let secs = var("SECS").filter(|&secs| {
+            if secs == 0 {
+                eprintln!("{prefix}_SECS has to be more than 0, using the default");
+            }
+            secs > 0
+        });
+        let interval = secs.map(Duration::from_secs).or(default)?;
+        Some(Self { interval, jitter_percent: var("JITTER").unwrap_or(DEFAULT_JITTER_PERCENT).min(100) })
+    }
+}
+
+/// What a job wants after a run. Failed runs are always repeated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Next {
+    Again,
+    /// e.g. once the data it was waiting for is there
+    Stop,
+}
+
+/// For the admin page
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobStatus {
+    pub name:     &'static str,
+    pub schedule: Schedule,
+    pub runs:     u64,
+    pub last_run: Option<OffsetDateTime>,
+    pub took:     Option<Duration>,
+    /// From the last run
+    pub error:    Option<String>,
+    /// The job said it’s done and won’t run again.
+    pub stopped:  bool,
+}
+
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    jobs: Mutex<Vec<JobStatus>>,
+}
+
+impl Scheduler {
+    /// Runs `job` every interval of `schedule` (give or take the jitter), starting one interval from now, until it returns `Next::Stop`.
+    /// This has to be called from within the runtime.
+    pub fn add(&'static self, name: &'static str, schedule: Schedule, job: impl Fn() -> Result<Next, String> + Send + Sync + 'static) {
+        let status = JobStatus { name, schedule, runs: 0, last_run: None, took: None, error: None, stopped: false };
+        self.jobs.lock().unwrap().push(status);
+        let job = Arc::new(job);
+        rt::spawn(async move {
+            loop {
+                rt::time::sleep(jittered(schedule, random())).await;
+                let started = Instant::now();
+                let job = Arc::clone(&job);
+                let result = task::spawn_blocking(move || job()).await.unwrap_or_else(|e| Err(format!("Job panicked: {e}")));
+                if let Err(e) = &result {
+                    eprintln!("Job {name} failed: {e}");
+                }
+                if self.finished(name, started.elapsed(), result) == Next::Stop {
+                    break;
+                }
+            }
+        });
+    }
+
+    fn finished(&self, name: &str, took: Duration, result: Result<Next, String>) -> Next {
+        let next = *result.as_ref().unwrap_or(&Next::Again);
+        if let Some(status) = self.jobs.lock().unwrap().iter_mut().find(|j| j.name == name) {
+            status.runs += 1;
+            status.last_run = Some(OffsetDateTime::now_utc());
+            status.took = Some(took);
+            status.error = result.err();
+            status.stopped = next == Next::Stop;
+        }
+        next
+    }
+
+    /// In the order they were added
+    pub fn statuses(&self) -> Vec<JobStatus> {
+        self.jobs.lock().unwrap().clone()
+    }
+}
+
+/// The interval moved by up to its jitter in either direction, depending on `random`.
+fn jittered(Schedule { interval, jitter_percent }: Schedule, random: u64) -> Duration {
+    let max = interval.as_millis() as u64 * jitter_percent / 100;
+    if max == 0 {
+        return interval;
+    }
+    let offset = random % (2 * max + 1);
+    (interval + Duration::from_millis(offset)).saturating_sub(Duration::from_millis(max))
+}
+
+fn random() -> u64 {
+    RandomState::new().build_hasher().finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jitter_test() {
+        let minute = Schedule { interval: Duration::from_secs(60), jitter_percent: DEFAULT_JITTER_PERCENT };
+        assert_eq!(jittered(minute, 0), Duration::from_secs(54));
+        assert_eq!(jittered(minute, 6000), minute.interval);
+        assert_eq!(jittered(minute, 12000), Duration::from_secs(66));
+        assert!((0..1000).map(|_| jittered(minute, random())).all(|d| d >= Duration::from_secs(54) && d <= Duration::from_secs(66)));
+        assert_eq!(jittered(Schedule { interval: Duration::from_millis(5), ..minute }, 3), Duration::from_millis(5));
+        let exact = Schedule { jitter_percent: 0, ..minute };
+        assert_eq!(jittered(exact, 12000), minute.interval);
+        let wide = Schedule { jitter_percent: 50, ..minute };
+        assert_eq!(jittered(wide, 0), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn from_env_test() {
+        let default = Duration::from_secs(60);
+        std::env::set_var("ARO_SCHEDULE_TEST_SECS", "0");
+        assert_eq!(Schedule::from_env("ARO_SCHEDULE_TEST", Some(default)).map(|s| s.interval), Some(default));
+        assert!(Schedule::from_env("ARO_SCHEDULE_TEST", None).is_none());
+        std::env::set_var("ARO_SCHEDULE_TEST_SECS", "5");
+        assert_eq!(Schedule::from_env("ARO_SCHEDULE_TEST", Some(default)).map(|s| s.interval), Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn status_test() {
+        let scheduler = Scheduler::default();
+        scheduler.jobs.lock().unwrap().push(JobStatus {
+            name:     "prices",
+            schedule: Schedule { interval: Duration::from_secs(1), jitter_percent: 0 },
+            runs:     0,
+            last_run: None,
+            took:     None,
+            error:    None,
+            stopped:  false,
+        });
+        assert_eq!(scheduler.finished("prices", Duration::from_millis(3), Err("No prices".to_owned())), Next::Again);
+        scheduler.finished("unknown", Duration::ZERO, Ok(Next::Again));
+        let [status] = &scheduler.statuses()[..] else { panic!("one job") };
+        assert_eq!((status.runs, status.took, status.error.as_deref()), (1, Some(Duration::from_millis(3)), Some("No prices")));
+        assert!(status.last_run.is_some());
+        assert_eq!(scheduler.finished("prices", Duration::ZERO, Ok(Next::Stop)), Next::Stop);
+        assert!(scheduler.statuses()[0].stopped);
+    }
+}
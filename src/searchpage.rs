@@ -0,0 +1,402 @@
+//! The search page, and the shortlinks that lead to it.
+use actix_web::{http::header, route, web, Either, HttpRequest, HttpResponse};
+use aro_core::scryfall;
+use askama::Template;
+use itertools::Itertools;
+use serde::Deserialize;
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, HashSet},
+    ops::Range,
+    sync::atomic::Ordering,
+    time::Instant,
+};
+use time::OffsetDateTime;
+
+use crate::{
+    admin::{may_write, unauthorized},
+    api::QuickQuery,
+    collection,
+    collection::Collection,
+    data::Card,
+    history::History,
+    i18n::Locale,
+    is_building_deck, locale, maintenance_page, page_size,
+    pages::{sort_links, IndexPage, MessagePage, Meta, Pagination, ResultGroup, ResultsPage, View, NAME},
+    parser,
+    parser::{SearchOptions, Sort, SortKey},
+    persistent_cookie, plain,
+    proxy::{public_url, url},
+    read_only_page, snippet,
+    state::{AppState, State},
+    theme, AnyResult, CARD_VIEWS, DATA_READY, HISTORY_COOKIE, MIN_PAGE_SIZE, NO_HISTORY_COOKIE, PAGE_SIZE, PAGE_SIZE_COOKIE, QUERY_STATS,
+    READ_ONLY, SHORTLINKS, VIEW_COOKIE,
+};
+
+// Long enough for anything reasonable, short enough that the file can’t be filled with garbage too quickly.
+const SHORTLINK_MAX_LEN: usize = 2000;
+
+#[derive(Debug, Deserialize)]
+struct Query {
+    q:     String,
+    /// 1-indexed page of the results
+    p:     Option<usize>,
+    /// results per page
+    n:     Option<usize>,
+    view:  Option<String>,
+    /// `1` for text only, see `plain.rs`
+    plain: Option<String>,
+}
+
+#[derive(Debug)]
+enum TargetPage {
+    Html(String),
+    Text(String),
+    Redirect(String),
+}
+
+#[route("/", method = "GET", method = "HEAD")]
+pub async fn search(req: HttpRequest, state: State, q: Option<Either<web::Query<Query>, web::Form<Query>>>) -> AnyResult<HttpResponse> {
+    let theme = theme(&req);
+    if !DATA_READY.load(Ordering::Acquire) {
+        return maintenance_page(&req);
+    }
+    let (q, p, n, view, plain) = match q {
+        Some(Either::Left(web::Query(Query { q, p, n, view, plain }))) => (Some(q), p, n, view, plain),
+        Some(Either::Right(web::Form(Query { q, p, n, view, plain }))) => (Some(q), p, n, view, plain),
+        None => (None, None, None, None, None),
+    };
+    let requested_page_size = n.map(|n| n.clamp(MIN_PAGE_SIZE, PAGE_SIZE));
+    let page_size = requested_page_size.unwrap_or_else(|| page_size(&req));
+    let requested_view = view.and_then(|v| v.parse::<View>().ok());
+    let view = requested_view.or_else(|| req.cookie(VIEW_COOKIE).and_then(|c| c.value().parse().ok())).unwrap_or_default();
+    let history = (req.cookie(NO_HISTORY_COOKIE).is_none())
+        .then(|| req.cookie(HISTORY_COOKIE).and_then(|c| c.value().parse::<History>().ok()).unwrap_or_default());
+    let mut updated_history = None;
+    let page = match q.filter(|s| !s.is_empty()) {
+        Some(q) => {
+            QUERY_STATS.record(&q);
+            let (page, total) = if wants_plain_text(&req, plain.as_deref()) {
+                plain_results(&req, &state, &q, p.unwrap_or(1), page_size)?
+            } else {
+                compute_results(&req, &state, q.clone(), p.unwrap_or(1), page_size, view)?
+            };
+            if let (Some(mut history), Some(total)) = (history, total) {
+                history.push(q.trim(), total);
+                updated_history = Some(history);
+            }
+            page
+        }
+        None => TargetPage::Html(
+            IndexPage {
+                meta: Meta::new(NAME.to_owned(), locale(&req).tr("Enter a query above to search").to_owned())
+                    .with_theme(theme)
+                    .with_locale(locale(&req)),
+                history,
+            }
+            .render()?,
+        ),
+    };
+    let mut res = match page {
+        TargetPage::Html(body) => HttpResponse::Ok().insert_header(header::ContentType::html()).insert_header(vary_accept()).body(body),
+        TargetPage::Text(body) => plain_text(body),
+        TargetPage::Redirect(target) => HttpResponse::Found().insert_header((header::LOCATION, url(&target))).finish(),
+    };
+    if let Some(n) = requested_page_size {
+        res.add_cookie(&persistent_cookie(PAGE_SIZE_COOKIE, n.to_string()))?;
+    }
+    if let Some(view) = requested_view {
+        res.add_cookie(&persistent_cookie(VIEW_COOKIE, view.to_string()))?;
+    }
+    if let Some(history) = updated_history {
+        res.add_cookie(&persistent_cookie(HISTORY_COOKIE, history.to_string()))?;
+    }
+    Ok(res)
+}
+
+/// Everything that matched a query, in the order they’re shown.
+pub struct Matches<'a> {
+    pub query:    aro_core::query::Query,
+    pub options:  parser::SearchOptions,
+    pub warnings: Vec<String>,
+    /// What we translated from Scryfall syntax
+    pub notes:    Vec<String>,
+    pub cards:    Vec<&'a Card>,
+}
+
+/// The error is the message to show instead of results.
+pub fn matching_cards<'a>(
+    state: &'a AppState,
+    raw_query: &str,
+    collection: Option<&Collection>,
+    locale: Locale,
+) -> Result<Matches<'a>, String> {
+    find_matching_cards(state, raw_query, collection, locale, true)
+}
+
+/// Without `search_if_uncached`, queries that aren’t in the result cache are an (empty) error.
+pub fn find_matching_cards<'a>(
+    state: &'a AppState,
+    raw_query: &str,
+    collection: Option<&Collection>,
+    locale: Locale,
+    search_if_uncached: bool,
+) -> Result<Matches<'a>, String> {
+    let parse_error = |e: String| locale.trf("Could not parse query: {error}", &[("error", &e)]);
+    let translation = scryfall::translate(raw_query.trim()).map_err(parse_error)?;
+    let (query, filter, options, warnings) = parser::parse_filters(&translation.query).map_err(parse_error)?;
+    let owned_filter = match (options.owned, collection) {
+        (Some(_), None) => return Err(locale.tr("You need to upload your collection to use owned:").to_owned()),
+        (Some(owned), Some(collection)) => Some((owned, collection)),
+        (None, _) => None,
+    };
+    // Unreleased cards are filtered out afterwards, so we can tell when they’re the only matches without searching again.
+    let with_spoilers = SearchOptions { include_spoilers: true, ..options.clone() };
+    let run_search = || aro_core::search(&state.search_cards, &filter, &with_spoilers).into_iter().map(|c| c.id).collect();
+    let all_matches = match search_if_uncached {
+        true => state.results.get_or_search(translation.query.trim(), run_search),
+        false => state.results.get(translation.query.trim()).ok_or_else(String::new)?,
+    };
+    let today = OffsetDateTime::now_utc().date();
+    let mut matches: Vec<usize> =
+        all_matches.iter().copied().filter(|&id| options.include_spoilers || !state.is_unreleased(id, today)).collect();
+    let mut notes = translation.notes;
+    if matches.is_empty() && !all_matches.is_empty() {
+        notes.push(locale.tr("Only unreleased cards match. Add include:spoilers to see them.").to_owned());
+    }
+    if let Some(Sort { key: SortKey::Views, descending }) = options.sort {
+        let views = CARD_VIEWS.recent(OffsetDateTime::now_utc());
+        let views_of =
+            |id: &usize| state.cards_by_id.get(id).and_then(|c| views.get(&state.canonical_version(c).id)).copied().unwrap_or_default();
+        match descending {
+            true => matches.sort_by_cached_key(|id| Reverse(views_of(id))),
+            false => matches.sort_by_cached_key(views_of),
+        }
+    }
+    let mut cards: Vec<&Card> = matches
+        .into_iter()
+        .map(|id| state.cards_by_id.get(&id).unwrap())
+        .map(|c| if options.include_duplicates { c } else { state.canonical_version(c) })
+        .unique_by(|c| c.id)
+        .filter(|c| match owned_filter {
+            Some((owned, collection)) => (state.owned_count(collection, c) > 0) == owned,
+            None => true,
+        })
+        .collect();
+    if let Some(key) = options.group {
+        key.sort(&mut cards);
+    }
+    Ok(Matches { query, options, warnings, notes, cards })
+}
+
+/// Also returns the total number of results, or `None` if the query was invalid.
+fn compute_results(
+    req: &HttpRequest,
+    state: &AppState,
+    raw_query: String,
+    page: usize,
+    page_size: usize,
+    view: View,
+) -> AnyResult<(TargetPage, Option<usize>)> {
+    let (theme, locale) = (theme(req), locale(req));
+    let error_page = |message: String| -> AnyResult<_> {
+        let page = MessagePage {
+            meta: Meta::new(NAME.to_owned(), message.clone()).with_query(raw_query.clone()).with_theme(theme).with_locale(locale),
+            message,
+        };
+        Ok((TargetPage::Html(page.render()?), None))
+    };
+    let now = Instant::now();
+    let collection = collection(req);
+    let collection = collection.as_deref();
+    let Matches { query, options, warnings, notes, cards: matches } = match matching_cards(state, &raw_query, collection, locale) {
+        Ok(m) => m,
+        Err(e) => return error_page(e),
+    };
+    let total = matches.len();
+    let pages = total.div_ceil(page_size).max(1);
+    let page = page.clamp(1, pages);
+    let offset = (page - 1) * page_size;
+    // Counted before paging so a group that continues on the next page still shows its full size.
+    let group_totals = options.group.map(|key| matches.iter().map(|c| key.value(c)).counts()).unwrap_or_default();
+    let cards: Vec<&Card> = matches.into_iter().skip(offset).take(page_size).collect();
+    let groups = match options.group {
+        Some(key) => cards
+            .chunk_by(|a, b| key.value(a) == key.value(b))
+            .scan(0, |first_index, cards| {
+                let value = key.value(cards[0]);
+                let group = ResultGroup {
+                    label:       value.unwrap_or(locale.tr("Other")).to_owned(),
+                    total:       group_totals[&value],
+                    cards:       cards.to_vec(),
+                    first_index: *first_index,
+                };
+                *first_index += cards.len();
+                Some(group)
+            })
+            .collect(),
+        None => Vec::new(),
+    };
+    let readable_query = result_summary(locale, &query, &options, total, offset..offset + cards.len());
+    let took = format!("{:?}", now.elapsed());
+    let title = match cards[..] {
+        [] => format!("{} - {NAME}", locale.tr("No results")),
+        [card] if total == 1 => return Ok((TargetPage::Redirect(format!("/card/{}", card.id)), Some(total))),
+        _ => format!("{} - {NAME}", locale.trf("{n} results", &[("n", &total)])),
+    };
+    let text_values = snippet::text_values(&query);
+    let snippets = match text_values.is_empty() {
+        true => HashMap::new(),
+        false => cards.iter().filter_map(|c| Some((c.id, snippet::card_text(&state.plain_card(c.id)?.text, &text_values)?))).collect(),
+    };
+    let page = ResultsPage {
+        owned: owned_counts(state, collection, &cards),
+        unreleased: unreleased(state, &cards),
+        snippets,
+        sort_links: sort_links(&raw_query, options.sort),
+        meta: Meta::new(title, readable_query.clone()).with_query(raw_query).with_theme(theme).with_locale(locale),
+        readable_query,
+        notes,
+        warnings,
+        took,
+        cards,
+        groups,
+        pagination: Pagination::new(page, pages),
+        page_size,
+        view,
+        deck_mode: is_building_deck(req),
+        as_of: options.as_of,
+    };
+    Ok((TargetPage::Html(page.render()?), Some(total)))
+}
+
+/// e.g. “Showing 1-60 of 200 results where …, sorted by name”. `shown` is the range of results on this page.
+fn result_summary(
+    locale: Locale,
+    query: &aro_core::query::Query,
+    options: &parser::SearchOptions,
+    total: usize,
+    shown: Range<usize>,
+) -> String {
+    let mut details = Vec::new();
+    if query.matches_nothing() {
+        details.push(locale.tr("because the filters contradict each other").to_owned());
+    } else if !query.matches_everything() {
+        details.push(locale.trf("where {query}", &[("query", &query)]));
+    }
+    match options.owned {
+        Some(true) => details.push(locale.tr("only cards you own").to_owned()),
+        Some(false) => details.push(locale.tr("only cards you don’t own").to_owned()),
+        None => (),
+    }
+    if let Some(date) = options.as_of {
+        details.push(locale.trf("with the banlist from {date}", &[("date", &date)]));
+    }
+    if let Some(sort) = options.sort {
+        details.push(locale.trf("sorted by {sort}", &[("sort", &sort)]));
+    }
+    if let Some(group) = options.group {
+        details.push(locale.trf("grouped by {group}", &[("group", &group)]));
+    }
+    let details = details.join(", ");
+    if shown.len() < total {
+        let (start, end) = (shown.start + 1, shown.end);
+        locale.trf(
+            "Showing {start}-{end} of {total} results {details}",
+            &[("start", &start), ("end", &end), ("total", &total), ("details", &details)],
+        )
+    } else {
+        locale.trf("Showing {total} results {details}", &[("total", &total), ("details", &details)])
+    }
+}
+
+/// Like `compute_results`, but as text and without the redirect for single results, since the text has the whole card anyway.
+fn plain_results(
+    req: &HttpRequest,
+    state: &AppState,
+    raw_query: &str,
+    page: usize,
+    page_size: usize,
+) -> AnyResult<(TargetPage, Option<usize>)> {
+    let Matches { query, options, warnings, notes, cards } = match matching_cards(state, raw_query, collection(req).as_deref(), locale(req))
+    {
+        Ok(m) => m,
+        Err(e) => return Ok((TargetPage::Text(e), None)),
+    };
+    let total = cards.len();
+    let pages = total.div_ceil(page_size).max(1);
+    let page = page.clamp(1, pages);
+    let shown = (page - 1) * page_size..(page * page_size).min(total);
+    let summary = notes.iter().chain(&warnings).chain([&result_summary(locale(req), &query, &options, total, shown.clone())]).join("\n");
+    let next_page = if page < pages {
+        let query = serde_urlencoded::to_string([("q", raw_query), ("p", &(page + 1).to_string()), ("plain", "1")])?;
+        Some(public_url(&format!("/?{query}")))
+    } else {
+        None
+    };
+    let cards: Vec<&Card> = cards[shown].iter().map(|c| state.plain_card(c.id).unwrap_or(c)).collect();
+    Ok((TargetPage::Text(plain::results(&summary, &cards, next_page.as_deref())?), Some(total)))
+}
+
+/// See `plain::wanted`.
+pub fn wants_plain_text(req: &HttpRequest, plain_param: Option<&str>) -> bool {
+    plain::wanted(plain_param, req.headers().get(header::ACCEPT).and_then(|a| a.to_str().ok()))
+}
+
+pub fn plain_text(body: String) -> HttpResponse {
+    HttpResponse::Ok().insert_header((header::CONTENT_TYPE, "text/plain; charset=utf-8")).insert_header(vary_accept()).body(body)
+}
+
+/// For pages that can also be text, so caches don’t hand the text to browsers or the other way round.
+pub fn vary_accept() -> (header::HeaderName, &'static str) {
+    (header::VARY, "Accept")
+}
+
+/// Stores the query and returns the path of its shortlink, e.g. `/s/abc1234`.
+#[route("/s", method = "POST")]
+pub async fn shorten(req: HttpRequest, q: web::Form<QuickQuery>) -> AnyResult<HttpResponse> {
+    if READ_ONLY.load(Ordering::Relaxed) {
+        return read_only_page(&req);
+    }
+    if !may_write(&req, "created a shortlink") {
+        return Ok(unauthorized());
+    }
+    let q = q.q.trim();
+    if q.is_empty() || q.len() > SHORTLINK_MAX_LEN {
+        return Ok(HttpResponse::BadRequest().body(format!("Queries must be between 1 and {SHORTLINK_MAX_LEN} bytes long")));
+    }
+    let path = url(&format!("/s/{}", SHORTLINKS.shorten(q)?));
+    Ok(HttpResponse::Created().insert_header((header::LOCATION, path.as_str())).body(path))
+}
+
+#[route("/s/{token}", method = "GET", method = "HEAD")]
+pub async fn expand(req: HttpRequest, token: web::Path<String>) -> AnyResult<HttpResponse> {
+    match SHORTLINKS.resolve(&token) {
+        Some(q) => Ok(HttpResponse::Found()
+            .insert_header((header::LOCATION, url(&format!("/?{}", serde_urlencoded::to_string([("q", q)])?))))
+            .finish()),
+        None => Ok(HttpResponse::NotFound().insert_header(header::ContentType::html()).body(
+            MessagePage {
+                meta:    Meta::new(format!("{} - {NAME}", locale(&req).tr("Link not found")), locale(&req).tr("Link not found").to_owned())
+                    .with_theme(theme(&req))
+                    .with_locale(locale(&req)),
+                message: locale(&req).tr("This shortlink doesn’t exist.").to_owned(),
+            }
+            .render()?,
+        )),
+    }
+}
+
+/// For the badges on search results. Cards that aren’t owned are left out.
+pub fn owned_counts(state: &AppState, collection: Option<&Collection>, cards: &[&Card]) -> HashMap<usize, u32> {
+    let Some(collection) = collection else {
+        return HashMap::new();
+    };
+    cards.iter().map(|c| (c.id, state.owned_count(collection, c))).filter(|&(_, n)| n > 0).collect()
+}
+
+/// For the “Not yet released” badges.
+pub fn unreleased(state: &AppState, cards: &[&Card]) -> HashSet<usize> {
+    let today = OffsetDateTime::now_utc().date();
+    cards.iter().map(|c| c.id).filter(|&id| state.is_unreleased(id, today)).collect()
+}
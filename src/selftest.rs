@@ -0,0 +1,107 @@
+//! Checks that run once the data is loaded at startup, so a dump that loads fine but breaks the site shows up in the logs
+//! (or stops the server with `SELF_TEST_STRICT=1`) before anyone visits. The checks that need the rest of the server are
+//! `self_test` in `loading.rs`, this has the bookkeeping and the ones that don’t.
+use std::{
+    collections::HashMap,
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::Path,
+};
+
+use crate::data::{BanlistStatus, Card, Set};
+
+/// Printings whose set isn’t in `sets.json` have no release date. A few of those are normal (e.g. promos),
+/// but more than this means the card and set files don’t fit together.
+const MAX_UNKNOWN_SETS_PERCENT: usize = 5;
+
+#[derive(Debug, Default)]
+pub struct SelfTest {
+    passed:   usize,
+    /// Name and error of each check
+    failures: Vec<(String, String)>,
+}
+
+impl SelfTest {
+    /// A check that panics fails like any other.
+    pub fn check(&mut self, name: impl Into<String>, check: impl FnOnce() -> Result<(), String>) {
+        match catch_unwind(AssertUnwindSafe(check)) {
+            Ok(Ok(())) => self.passed += 1,
+            Ok(Err(e)) => self.failures.push((name.into(), e)),
+            Err(_) => self.failures.push((name.into(), "Panicked".to_owned())),
+        }
+    }
+
+    pub fn failures(&self) -> &[(String, String)] {
+        &self.failures
+    }
+
+    pub fn summary(&self) -> String {
+        let checks = |n: usize| if n == 1 { "1 check".to_owned() } else { format!("{n} checks") };
+        match self.failures.len() {
+            0 => format!("Self-test passed ({})", checks(self.passed)),
+            failed => format!("Self-test failed: {failed} of {}", checks(self.passed + failed)),
+        }
+    }
+}
+
+/// Everything under `/static/` that pages link to, apart from the card images.
+pub fn static_files() -> impl Iterator<Item = &'static str> {
+    let icons = [BanlistStatus::Forbidden, BanlistStatus::Limited, BanlistStatus::SemiLimited].into_iter().filter_map(BanlistStatus::icon);
+    ["style.css"].into_iter().chain(icons)
+}
+
+pub fn check_static_files(dir: &Path) -> Result<(), String> {
+    match static_files().filter(|f| !dir.join(f).is_file()).collect::<Vec<_>>() {
+        missing if missing.is_empty() => Ok(()),
+        missing => Err(format!("Missing from {}: {}", dir.display(), missing.join(", "))),
+    }
+}
+
+/// Whether the printings find their sets, which is where release dates and eras come from.
+pub fn check_set_names(cards: &[Card], sets_by_name: &HashMap<String, Set>) -> Result<(), String> {
+    let printings = cards.iter().flat_map(|c| &c.card_sets);
+    let (total, unknown) = printings
+        .fold((0, 0), |(total, unknown), p| (total + 1, unknown + usize::from(!sets_by_name.contains_key(&p.set_name.to_lowercase()))));
+    if unknown * 100 > total * MAX_UNKNOWN_SETS_PERCENT {
+        return Err(format!("{unknown} of {total} printings are from sets that aren’t in sets.json"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::CardSet;
+
+    #[test]
+    fn self_test_test() {
+        let mut test = SelfTest::default();
+        test.check("ok", || Ok(()));
+        assert_eq!(test.summary(), "Self-test passed (1 check)");
+        test.check("error", || Err("Broken".to_owned()));
+        test.check("panic", || panic!("at the disco"));
+        assert_eq!(test.failures(), &[("error".to_owned(), "Broken".to_owned()), ("panic".to_owned(), "Panicked".to_owned())]);
+        assert_eq!(test.summary(), "Self-test failed: 2 of 3 checks");
+    }
+
+    #[test]
+    fn set_names_test() {
+        let printing = |name: &str| CardSet { set_name: name.to_owned(), ..Default::default() };
+        let card = Card { card_sets: vec![printing("Legend of Blue Eyes White Dragon"), printing("Unknown Promo")], ..Default::default() };
+        let set = Set { set_name: "Legend of Blue Eyes White Dragon".to_owned(), tcg_date: None };
+        let sets = HashMap::from([("legend of blue eyes white dragon".to_owned(), set)]);
+        let mut cards = vec![card];
+        assert!(check_set_names(&cards, &sets).unwrap_err().starts_with("1 of 2 printings"));
+        cards.push(Card { card_sets: vec![printing("Legend of Blue Eyes White Dragon"); 19], ..Default::default() });
+        assert_eq!(check_set_names(&cards, &sets), Ok(()));
+        assert_eq!(check_set_names(&[], &sets), Ok(()));
+    }
+
+    #[test]
+    fn static_files_test() {
+        assert!(check_static_files(Path::new("static")).is_ok());
+        assert_eq!(
+            check_static_files(Path::new("templates")).unwrap_err(),
+            "Missing from templates: style.css, forbidden.svg, limited.svg, semi_limited.svg"
+        );
+    }
+}
@@ -0,0 +1,53 @@
+//! The links that change a setting and send the user back.
+use actix_web::{
+    http::{header, Uri},
+    route, web, HttpRequest, HttpResponse,
+};
+
+use crate::{
+    i18n::Locale, pages::Theme, persistent_cookie, proxy::url, AnyResult, HISTORY_COOKIE, LANG_COOKIE, NO_HISTORY_COOKIE, THEME_COOKIE,
+};
+
+/// Remembers the theme and sends the user back to where they clicked the link.
+#[route("/theme/{theme}", method = "GET")]
+pub async fn set_theme(req: HttpRequest, theme: web::Path<String>) -> AnyResult<HttpResponse> {
+    let theme: Theme = theme.parse()?;
+    let mut res = redirect_back(&req);
+    res.add_cookie(&persistent_cookie(THEME_COOKIE, theme.to_string()))?;
+    Ok(res)
+}
+
+#[route("/lang/{locale}", method = "GET")]
+pub async fn set_locale(req: HttpRequest, locale: web::Path<String>) -> AnyResult<HttpResponse> {
+    let locale: Locale = locale.parse()?;
+    let mut res = redirect_back(&req);
+    res.add_cookie(&persistent_cookie(LANG_COOKIE, locale.to_string()))?;
+    Ok(res)
+}
+
+/// Opts in or out of the search history. Opting out also forgets everything that was already stored.
+#[route("/history/{setting}", method = "GET")]
+pub async fn set_history(req: HttpRequest, setting: web::Path<String>) -> AnyResult<HttpResponse> {
+    let mut res = redirect_back(&req);
+    match setting.as_str() {
+        "on" => res.add_removal_cookie(&persistent_cookie(NO_HISTORY_COOKIE, String::new()))?,
+        "off" => {
+            res.add_cookie(&persistent_cookie(NO_HISTORY_COOKIE, "1".to_owned()))?;
+            res.add_removal_cookie(&persistent_cookie(HISTORY_COOKIE, String::new()))?;
+        }
+        _ => return Ok(HttpResponse::NotFound().finish()),
+    }
+    Ok(res)
+}
+
+pub fn redirect_back(req: &HttpRequest) -> HttpResponse {
+    // Only keep the path of the referer so this can’t be used to redirect to other sites.
+    let back = req
+        .headers()
+        .get(header::REFERER)
+        .and_then(|r| r.to_str().ok()?.parse::<Uri>().ok())
+        .and_then(|uri| Some(uri.path_and_query()?.to_string()))
+        .filter(|p| p.starts_with('/') && !p.starts_with("//"))
+        .unwrap_or_else(|| url("/"));
+    HttpResponse::Found().insert_header((header::LOCATION, back)).finish()
+}
@@ -0,0 +1,123 @@
+//! State shared between instances behind a load balancer, stored in Redis.
+//! This needs the `redis` feature and `REDIS_URL`. Without them, everything stays local to the instance like before.
+use std::io;
+
+/// All keys are prefixed with this so the Redis instance can be shared with other things.
+#[cfg(feature = "redis")]
+const KEY_PREFIX: &str = "aro:";
+
+#[cfg(feature = "redis")]
+pub struct SharedStore {
+    client:     redis::Client,
+    /// Dropped after errors, so the next call reconnects.
+    connection: std::sync::Mutex<Option<redis::Connection>>,
+}
+
+/// Built without the `redis` feature, so there can’t be one.
+#[cfg(not(feature = "redis"))]
+#[derive(Debug)]
+pub enum SharedStore {}
+
+// The connection isn’t `Debug`.
+#[cfg(feature = "redis")]
+impl std::fmt::Debug for SharedStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SharedStore").field("client", &self.client).finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "redis")]
+impl SharedStore {
+    /// Requests wait for Redis, so this should be short.
+    const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(1);
+
+    pub fn from_env() -> Option<Self> {
+        let url = std::env::var("REDIS_URL").ok()?;
+        match redis::Client::open(url.as_str()) {
+            Ok(client) => {
+                println!("Sharing state through Redis at {url}");
+                Some(Self { client, connection: Default::default() })
+            }
+            Err(e) => {
+                eprintln!("Invalid REDIS_URL {url}: {e}. Keeping all state local");
+                None
+            }
+        }
+    }
+
+    fn with_connection<T>(&self, f: impl FnOnce(&mut redis::Connection) -> redis::RedisResult<T>) -> io::Result<T> {
+        let mut connection = self.connection.lock().unwrap();
+        let conn = match connection.as_mut() {
+            Some(conn) => conn,
+            None => {
+                let conn = self.client.get_connection_with_timeout(Self::TIMEOUT).map_err(io::Error::other)?;
+                conn.set_read_timeout(Some(Self::TIMEOUT)).map_err(io::Error::other)?;
+                conn.set_write_timeout(Some(Self::TIMEOUT)).map_err(io::Error::other)?;
+                connection.insert(conn)
+            }
+        };
+        let result = f(conn);
+        if result.is_err() {
+            *connection = None;
+        }
+        result.map_err(io::Error::other)
+    }
+
+    /// Adds to a counter and returns the new value.
+    pub fn incr_by(&self, key: &str, n: usize) -> io::Result<i64> {
+        self.with_connection(|conn| redis::cmd("INCRBY").arg(format!("{KEY_PREFIX}{key}")).arg(n).query(conn))
+    }
+
+    pub fn get(&self, hash: &str, field: &str) -> io::Result<Option<String>> {
+        self.with_connection(|conn| redis::cmd("HGET").arg(format!("{KEY_PREFIX}{hash}")).arg(field).query(conn))
+    }
+
+    /// Only sets the field if it doesn’t exist yet. Returns whether it was set.
+    pub fn set_new(&self, hash: &str, field: &str, value: &str) -> io::Result<bool> {
+        self.with_connection(|conn| redis::cmd("HSETNX").arg(format!("{KEY_PREFIX}{hash}")).arg(field).arg(value).query(conn))
+    }
+
+    /// Sets the field and lets the whole hash expire after `ttl`, counted from the last write.
+    pub fn set_expiring(&self, hash: &str, field: &str, value: &str, ttl: std::time::Duration) -> io::Result<()> {
+        let key = format!("{KEY_PREFIX}{hash}");
+        self.with_connection(|conn| {
+            redis::pipe()
+                .cmd("HSET")
+                .arg(&key)
+                .arg(field)
+                .arg(value)
+                .ignore()
+                .cmd("EXPIRE")
+                .arg(&key)
+                .arg(ttl.as_secs())
+                .ignore()
+                .query(conn)
+        })
+    }
+}
+
+#[cfg(not(feature = "redis"))]
+impl SharedStore {
+    pub fn from_env() -> Option<Self> {
+        if std::env::var("REDIS_URL").is_ok() {
+            eprintln!("REDIS_URL is set, but aro was built without the redis feature. Keeping all state local");
+        }
+        None
+    }
+
+    pub fn incr_by(&self, _: &str, _: usize) -> io::Result<i64> {
+        match *self {}
+    }
+
+    pub fn get(&self, _: &str, _: &str) -> io::Result<Option<String>> {
+        match *self {}
+    }
+
+    pub fn set_new(&self, _: &str, _: &str, _: &str) -> io::Result<bool> {
+        match *self {}
+    }
+
+    pub fn set_expiring(&self, _: &str, _: &str, _: &str, _: std::time::Duration) -> io::Result<()> {
+        match *self {}
+    }
+}
@@ -0,0 +1,159 @@
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::PathBuf,
+    sync::Mutex,
+};
+
+use crate::{memory::map_size, shared::SharedStore, stats::CacheStats};
+
+const TOKEN_ALPHABET: &[u8] = b"0123456789abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const TOKEN_LENGTH: usize = 7;
+
+/// Short tokens for long queries so they can be shared in chat.
+/// Every new mapping is appended to a file as `token\tquery` and read back on startup.
+/// With a shared store, mappings go there instead so all instances know them, and `links` is only a cache.
+#[derive(Debug)]
+pub struct Shortlinks {
+    path:      PathBuf,
+    links:     Mutex<HashMap<String, String>>,
+    shared:    Option<&'static SharedStore>,
+    /// Only interesting with a shared store, otherwise every known link is a hit.
+    pub stats: CacheStats,
+}
+
+/// The Redis hash for shortlinks
+const SHARED_KEY: &str = "shortlinks";
+
+impl Shortlinks {
+    pub fn empty(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), links: Mutex::default(), shared: None, stats: CacheStats::default() }
+    }
+
+    pub fn with_shared(self, shared: Option<&'static SharedStore>) -> Self {
+        Self { shared, ..self }
+    }
+
+    /// A missing file just means nothing has been shortened yet.
+    pub fn load(path: impl Into<PathBuf>) -> io::Result<Self> {
+        let path = path.into();
+        let links = match File::open(&path) {
+            Ok(f) => BufReader::new(f)
+                .lines()
+                .map(|line| Ok(line?.split_once('\t').map(|(token, query)| (token.to_owned(), query.to_owned()))))
+                .filter_map(Result::transpose)
+                .collect::<io::Result<_>>()?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(Self { path, links: Mutex::new(links), shared: None, stats: CacheStats::default() })
+    }
+
+    /// Shortening the same query twice returns the same token.
+    pub fn shorten(&self, query: &str) -> io::Result<String> {
+        // Tabs and newlines would break the file format, and they mean nothing to the parser anyway.
+        let query = query.replace(['\t', '\n', '\r'], " ");
+        // Not locked while talking to the shared store, which decides about collisions on its own.
+        if let Some(shared) = self.shared {
+            let mut attempt = 0;
+            let token = loop {
+                let token = token(&query, attempt);
+                if shared.set_new(SHARED_KEY, &token, &query)? || shared.get(SHARED_KEY, &token)?.as_ref() == Some(&query) {
+                    break token;
+                }
+                attempt += 1;
+            };
+            self.links.lock().unwrap().insert(token.clone(), query);
+            return Ok(token);
+        }
+        // The token is taken while locked, but written to the file after, so other requests don’t wait for the disk.
+        let mut attempt = 0;
+        let token = {
+            let mut links = self.links.lock().unwrap();
+            loop {
+                let token = token(&query, attempt);
+                match links.get(&token) {
+                    Some(existing) if existing == &query => return Ok(token),
+                    Some(_) => attempt += 1,
+                    None => {
+                        links.insert(token.clone(), query.clone());
+                        break token;
+                    }
+                }
+            }
+        };
+        // One write per line, so lines from concurrent appends don’t end up interleaved.
+        let appended = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(format!("{token}\t{query}\n").as_bytes()));
+        if let Err(e) = appended {
+            self.links.lock().unwrap().remove(&token);
+            return Err(e);
+        }
+        Ok(token)
+    }
+
+    pub fn len(&self) -> usize {
+        self.links.lock().unwrap().len()
+    }
+
+    /// For the admin page, see `memory.rs`.
+    pub fn estimated_size(&self) -> usize {
+        map_size(&self.links.lock().unwrap(), |token, query| token.capacity() + query.capacity())
+    }
+
+    pub fn resolve(&self, token: &str) -> Option<String> {
+        if let Some(query) = self.links.lock().unwrap().get(token) {
+            self.stats.hit();
+            return Some(query.clone());
+        }
+        self.stats.miss();
+        // Another instance might have created it.
+        let query = self.shared?.get(SHARED_KEY, token).unwrap_or_else(|e| {
+            eprintln!("Could not resolve shortlink {token}: {e}");
+            None
+        })?;
+        self.links.lock().unwrap().insert(token.to_owned(), query.clone());
+        Some(query)
+    }
+}
+
+/// FNV-1a of the query, base62 encoded. `attempt` is only there to get a different token on collisions.
+fn token(query: &str, attempt: u32) -> String {
+    let mut hash = 0xcbf29ce484222325u64;
+    for b in query.bytes().chain(attempt.to_le_bytes()) {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    (0..TOKEN_LENGTH)
+        .map(|_| {
+            let c = TOKEN_ALPHABET[(hash % TOKEN_ALPHABET.len() as u64) as usize];
+            hash /= TOKEN_ALPHABET.len() as u64;
+            c as char
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+
+    #[test]
+    fn shortlinks_test() {
+        let dir = TempDir::new("shortlinks");
+        let path = dir.join("shortlinks.tsv");
+        let links = Shortlinks::load(&path).unwrap();
+        let token = links.shorten("c:synchro o:/destroy.*monster/\nsort:-atk").unwrap();
+        assert_eq!(token.len(), TOKEN_LENGTH);
+        assert_eq!(links.shorten("c:synchro o:/destroy.*monster/ sort:-atk").unwrap(), token);
+        assert_ne!(links.shorten("c:xyz").unwrap(), token);
+        assert_eq!(links.resolve("nope"), None);
+
+        let reloaded = Shortlinks::load(&path).unwrap();
+        assert_eq!(reloaded.resolve(&token).as_deref(), Some("c:synchro o:/destroy.*monster/ sort:-atk"));
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+
+use itertools::Itertools;
+
+/// Terms that appear in more than this fraction of all texts don’t say anything about a card,
+/// and skipping them keeps the postings we have to walk short.
+const MAX_DOCUMENT_FREQUENCY: f32 = 0.5;
+
+/// TF-IDF vectors of all card texts with an inverted index, so the most similar texts to a card
+/// can be found without comparing it to every other card.
+#[derive(Debug, Default)]
+pub struct SimilarityIndex {
+    ids:       Vec<usize>,
+    doc_by_id: HashMap<usize, usize>,
+    /// Normalized TF-IDF weights per document
+    vectors:   Vec<Vec<(usize, f32)>>,
+    /// For every term, all documents that contain it and its weight in them
+    postings:  Vec<Vec<(usize, f32)>>,
+}
+
+impl SimilarityIndex {
+    pub fn new<'a>(texts: impl IntoIterator<Item = (usize, &'a str)>) -> Self {
+        let mut terms = HashMap::new();
+        let (ids, counts): (Vec<_>, Vec<_>) = texts
+            .into_iter()
+            .map(|(id, text)| {
+                let counts = tokenize(text)
+                    .map(|t| {
+                        let next_term = terms.len();
+                        *terms.entry(t).or_insert(next_term)
+                    })
+                    .counts();
+                (id, counts)
+            })
+            .unzip();
+        let mut document_frequency = vec![0usize; terms.len()];
+        counts.iter().flat_map(|c| c.keys()).for_each(|&t| document_frequency[t] += 1);
+        let num_docs = ids.len() as f32;
+        let idf: Vec<_> = document_frequency
+            .into_iter()
+            .map(|df| if df as f32 > num_docs * MAX_DOCUMENT_FREQUENCY { 0.0 } else { (num_docs / df as f32).ln() })
+            .collect();
+        let vectors: Vec<Vec<_>> = counts
+            .into_iter()
+            .map(|counts| {
+                let weights: Vec<_> = counts
+                    .into_iter()
+                    .map(|(t, count)| (t, count as f32 * idf[t]))
+                    .filter(|&(_, w)| w > 0.0)
+                    .sorted_unstable_by_key(|&(t, _)| t)
+                    .collect();
+                let norm = weights.iter().map(|(_, w)| w * w).sum::<f32>().sqrt();
+                weights.into_iter().map(|(t, w)| (t, w / norm)).collect()
+            })
+            .collect();
+        let mut postings = vec![Vec::new(); terms.len()];
+        for (doc, vector) in vectors.iter().enumerate() {
+            for &(t, w) in vector {
+                postings[t].push((doc, w));
+            }
+        }
+        let doc_by_id = ids.iter().enumerate().map(|(doc, &id)| (id, doc)).collect();
+        Self { ids, doc_by_id, vectors, postings }
+    }
+
+    /// IDs of the `n` cards with the highest cosine similarity to `id`, best match first.
+    pub fn similar(&self, id: usize, n: usize) -> Vec<usize> {
+        let Some(&doc) = self.doc_by_id.get(&id) else {
+            return Vec::new();
+        };
+        let mut scores = HashMap::<usize, f32>::new();
+        for &(t, w) in &self.vectors[doc] {
+            for &(other, other_w) in &self.postings[t] {
+                *scores.entry(other).or_default() += w * other_w;
+            }
+        }
+        scores.remove(&doc);
+        scores
+            .into_iter()
+            .sorted_unstable_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)))
+            .take(n)
+            .map(|(doc, _)| self.ids[doc])
+            .collect()
+    }
+}
+
+/// Lowercase words of the text. Anything in quotes is skipped because those are names of other cards,
+/// and we want cards that *do* similar things, not cards of the same archetype.
+fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split('"').step_by(2).flat_map(|s| s.split(|c: char| !c.is_alphanumeric())).filter(|w| w.len() > 1).map(str::to_lowercase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn similar_test() {
+        let index = SimilarityIndex::new([
+            (1, "Draw 2 cards."),
+            (2, "Draw 2 cards, then discard 1 card."),
+            (3, r#"Destroy all monsters your opponent controls. You can only activate 1 "Draw" per turn."#),
+            (4, "Destroy all monsters on the field."),
+            (5, "Special Summon 1 monster from your GY."),
+            (6, "Add 1 monster from your Deck to your hand."),
+        ]);
+        assert_eq!(index.similar(1, 1), vec![2]);
+        assert_eq!(index.similar(4, 1), vec![3]);
+        assert_eq!(index.similar(42, 3), Vec::<usize>::new());
+        assert!(!index.similar(3, 10).contains(&3));
+    }
+}
@@ -0,0 +1,93 @@
+//! `/sitemap.xml` for search engines: the main pages, every set, and every card that’s out.
+//! Building it goes through all cards, so it’s kept until the data changes or the job in `main.rs` rebuilds it,
+//! which picks up cards that came out since.
+use std::{
+    fmt::Write,
+    sync::{Arc, RwLock},
+};
+
+use itertools::Itertools;
+use time::Date;
+
+use crate::{escape_html, proxy::public_url, state::AppState};
+
+/// Search engines don’t read more than this from one sitemap. All cards fit easily.
+const MAX_URLS: usize = 50_000;
+const PAGES: &[&str] = &["/", "/help", "/trending", "/onthisday", "/rulings"];
+
+#[derive(Debug, Default)]
+pub struct Sitemap {
+    xml: RwLock<Option<Arc<str>>>,
+}
+
+impl Sitemap {
+    /// The last sitemap, or a new one if there is none yet.
+    pub fn get(&self, state: &AppState, today: Date) -> Arc<str> {
+        let cached = self.xml.read().unwrap().clone();
+        cached.unwrap_or_else(|| self.rebuild(state, today))
+    }
+
+    pub fn rebuild(&self, state: &AppState, today: Date) -> Arc<str> {
+        let xml: Arc<str> = build(paths(state, today).map(|path| public_url(&path))).into();
+        *self.xml.write().unwrap() = Some(Arc::clone(&xml));
+        xml
+    }
+
+    /// After loading new data, so the next request builds it from that.
+    pub fn clear(&self) {
+        *self.xml.write().unwrap() = None;
+    }
+}
+
+/// Only the canonical version of each card, and no custom cards, same as in search results.
+fn paths(state: &AppState, today: Date) -> impl Iterator<Item = String> + '_ {
+    let pages = PAGES.iter().map(|&p| p.to_owned());
+    let sets = state
+        .cards
+        .iter()
+        .filter(|c| !c.custom)
+        .flat_map(|c| c.card_sets.iter().map(|s| s.set_prefix()))
+        .filter(|prefix| !prefix.is_empty())
+        .unique()
+        .sorted()
+        .map(|prefix| format!("/set/{prefix}"));
+    let cards = state
+        .search_cards
+        .iter()
+        .filter(move |c| !c.is_custom() && !c.is_unreleased(today))
+        .filter_map(|c| state.cards_by_id.get(&c.id))
+        .filter(|c| state.canonical_version(c).id == c.id)
+        .map(|c| format!("/card/{}", c.id));
+    pages.chain(sets).chain(cards)
+}
+
+fn build(urls: impl IntoIterator<Item = String>) -> String {
+    let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?><urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#);
+    for url in urls.into_iter().take(MAX_URLS) {
+        let _ = write!(xml, "<url><loc>{}</loc></url>", escape_html(&url));
+    }
+    xml.push_str("</urlset>");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::OffsetDateTime;
+
+    #[test]
+    fn sitemap_test() {
+        let xml = build(["https://example.com/?q=a&b".to_owned()]);
+        assert!(xml.ends_with("<url><loc>https://example.com/?q=a&amp;b</loc></url></urlset>"));
+        assert_eq!(build((0..MAX_URLS + 1).map(|i| i.to_string())).matches("<url>").count(), MAX_URLS);
+
+        let sitemap = Sitemap::default();
+        let state = AppState::default();
+        let today = OffsetDateTime::UNIX_EPOCH.date();
+        let xml = sitemap.get(&state, today);
+        assert!(xml.contains("/help</loc>"));
+        assert!(Arc::ptr_eq(&xml, &sitemap.get(&state, today)), "cached");
+        sitemap.clear();
+        assert!(!Arc::ptr_eq(&xml, &sitemap.get(&state, today)));
+    }
+}
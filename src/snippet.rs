@@ -0,0 +1,122 @@
+//! Short excerpts of text with the search matches highlighted, for rulings and for `o:` searches in the results.
+use aro_core::{
+    filter::text_matches,
+    parser::{Field, Operator, RawCardFilter, Value},
+    query::Query,
+};
+
+use crate::escape_html;
+
+/// How much of a long sentence to show around the first match in the results, in bytes on either side
+const SENTENCE_CONTEXT: usize = 100;
+
+/// The part of `text` around the first match, escaped, with all matches in `<mark>`.
+/// `matches` are sorted start and end positions, `context` is how much to show on either side of the first one, in bytes.
+pub fn marked(text: &str, matches: &[(usize, usize)], context: usize) -> String {
+    let Some(&(first, first_end)) = matches.first() else {
+        return escape_html(text);
+    };
+    let start = floor_char_boundary(text, first.saturating_sub(context));
+    let end = floor_char_boundary(text, (first + context).max(first_end).min(text.len()));
+    let mut html = String::new();
+    if start > 0 {
+        html.push('…');
+    }
+    let mut pos = start;
+    for &(from, to) in matches.iter().filter(|&&(from, to)| from >= start && to <= end) {
+        // Overlapping matches are already marked.
+        if from < pos {
+            continue;
+        }
+        html.push_str(&escape_html(&text[pos..from]));
+        html.push_str(&format!("<mark>{}</mark>", escape_html(&text[from..to])));
+        pos = to;
+    }
+    html.push_str(&escape_html(&text[pos..end]));
+    if end < text.len() {
+        html.push('…');
+    }
+    html
+}
+
+/// The `o:` values in `query` that a result can have matched.
+pub fn text_values(query: &Query) -> Vec<&Value> {
+    query
+        .positive_filters()
+        .into_iter()
+        .filter(|RawCardFilter(field, op, _)| *field == Field::Text && *op == Operator::Equal)
+        .map(|RawCardFilter(_, _, value)| value)
+        .collect()
+}
+
+/// The first sentence of the card text that matches any of `values`, marked like `marked`.
+pub fn card_text(text: &str, values: &[&Value]) -> Option<String> {
+    let lower = text.to_ascii_lowercase();
+    let mut matches: Vec<_> = values.iter().flat_map(|v| text_matches(v, &lower)).collect();
+    matches.sort_unstable();
+    let &(first, first_end) = matches.first()?;
+    let (start, end) = sentence_around(text, first, first_end);
+    let sentence = text[start..end].trim_start();
+    let start = end - sentence.len();
+    let sentence = sentence.trim_end();
+    let in_sentence: Vec<_> = matches
+        .into_iter()
+        .filter(|&(from, to)| from >= start && to <= start + sentence.len())
+        .map(|(from, to)| (from - start, to - start))
+        .collect();
+    Some(marked(sentence, &in_sentence, SENTENCE_CONTEXT))
+}
+
+/// Start and end of the sentence (or line) that contains `from..to`.
+fn sentence_around(text: &str, from: usize, to: usize) -> (usize, usize) {
+    let bytes = text.as_bytes();
+    let ends_sentence = |i: usize| bytes[i] == b'\n' || (bytes[i] == b'.' && bytes.get(i + 1).map_or(true, u8::is_ascii_whitespace));
+    let start = (0..from).rev().find(|&i| ends_sentence(i)).map_or(0, |i| i + 1);
+    let end = (to.saturating_sub(1)..text.len()).find(|&i| ends_sentence(i)).map_or(text.len(), |i| i + 1);
+    (start, end)
+}
+
+/// `str::floor_char_boundary` isn’t stable yet.
+fn floor_char_boundary(s: &str, mut i: usize) -> usize {
+    while !s.is_char_boundary(i) {
+        i -= 1;
+    }
+    i
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aro_core::parser::parse_filters;
+
+    #[test]
+    fn marked_test() {
+        let text = "Dark Magician can be Normal Summoned <here>.";
+        assert_eq!(
+            marked(text, &[(0, 4), (2, 6), (28, 34)], 100),
+            "<mark>Dark</mark> Magician can be Normal <mark>Summon</mark>ed &lt;here&gt;."
+        );
+        let long = format!("{}target{}", "a".repeat(200), "é".repeat(200));
+        let cut = marked(&long, &[(200, 206)], 150);
+        assert!(cut.starts_with('…') && cut.ends_with('…'));
+        assert!(cut.contains("<mark>target</mark>"));
+        assert_eq!(marked("nothing", &[], 100), "nothing");
+    }
+
+    #[test]
+    fn card_text_test() {
+        let text =
+            "Cannot be Normal Summoned/Set.\r\nIf this card is sent to the GY: Draw 1 card. You can only use this effect once per turn.";
+        let snippet = |query: &str| card_text(text, &text_values(&parse_filters(query).unwrap().0));
+        assert_eq!(snippet("o:draw").as_deref(), Some("If this card is sent to the GY: <mark>Draw</mark> 1 card."));
+        assert_eq!(snippet("o:graveyard").as_deref(), Some("If this card is sent to the <mark>GY</mark>: Draw 1 card."));
+        assert_eq!(snippet("o:summoned|once").as_deref(), Some("Cannot be Normal <mark>Summoned</mark>/Set."));
+        assert_eq!(
+            snippet(r#"o:"1 card. you""#).as_deref(),
+            Some("If this card is sent to the GY: Draw <mark>1 card. You</mark> can only use this effect once per turn.")
+        );
+        assert_eq!(snippet("o!=draw"), None);
+        assert_eq!(snippet("c:spell"), None);
+        assert_eq!(snippet("draw"), None, "names aren’t card text");
+    }
+}
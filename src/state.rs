@@ -0,0 +1,264 @@
+//! All card data and everything derived from it, built in one go by `load_data`.
+//! Handlers get the current one with the `State` extractor. Loading new data builds a whole new `AppState` and swaps it in,
+//! so requests that are already running keep the one they started with and nobody sees half of each.
+use actix_web::{dev::Payload, error::ErrorServiceUnavailable, web, FromRequest, HttpRequest};
+use arc_swap::ArcSwap;
+use itertools::Itertools;
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    future::{ready, Ready},
+    ops::Deref,
+    sync::Arc,
+};
+use time::Date;
+
+use crate::{
+    collection::Collection,
+    data::{support_counts, Card, Set},
+    dataversion::DataVersion,
+    escape_html,
+    fields::{field_info, FieldInfo},
+    filter::SearchCard,
+    memory::{Sizes, StructureSize},
+    mentions::MentionsGraph,
+    proxy::url,
+    render,
+    resultcache::ResultCache,
+    similar::SimilarityIndex,
+};
+
+/// What the server holds on to. `load_data` replaces the contents, everything else only reads them.
+pub type SharedState = ArcSwap<AppState>;
+
+#[derive(Debug)]
+pub struct AppState {
+    /// With the printings sorted by release date, and the text as it is in the dump, for `plain.rs` and the API.
+    pub cards:           Vec<Card>,
+    // The text of these cards is already rendered as HTML, see `render::text_html`.
+    pub cards_by_id:     HashMap<usize, Card>,
+    /// Positions in `cards`
+    positions:           HashMap<usize, usize>,
+    /// Card text from `cards_by_id` with links to the cards and archetypes it mentions, for the card pages.
+    /// The grid can’t use this because each card there is a link already. Cards that don’t mention anything aren’t in here.
+    pub linked_texts:    HashMap<usize, String>,
+    /// All IDs that share a name, e.g. because of alternate artworks.
+    /// The first one is the canonical printing, i.e. the one with the most printings (or the lowest ID if that’s a tie).
+    pub ids_by_name:     HashMap<String, Vec<usize>>,
+    /// Uppercase set codes of all printings, for valuing collections that list printings instead of cards.
+    pub ids_by_set_code: HashMap<String, usize>,
+    /// For every card name, the canonical IDs of other cards that mention it in their text.
+    pub mentions:        MentionsGraph,
+    pub similar:         SimilarityIndex,
+    /// Lowercased names and the canonical ID of every card, sorted by name so we can binary search for prefixes.
+    pub name_index:      Vec<(String, usize)>,
+    // Filtering preserves order, so sorting once here gives every query a stable result order.
+    pub search_cards:    Vec<SearchCard>,
+    /// Positions in `search_cards`
+    search_positions:    HashMap<usize, usize>,
+    /// Canonical IDs of all cards in each archetype, in the default search order.
+    pub archetypes:      HashMap<String, Vec<usize>>,
+    pub field_info:      Vec<FieldInfo>,
+    pub support_counts:  HashMap<usize, i32>,
+    /// Effect tags for the card pages. They’re already computed for searching, so this reuses them.
+    pub effect_tags:     HashMap<usize, Vec<&'static str>>,
+    pub sets_by_name:    HashMap<String, Set>,
+    /// `None` until the data is loaded, or if hashing it failed
+    pub version:         Option<DataVersion>,
+    /// IDs of the `search_cards` that matched recent queries
+    pub results:         ResultCache,
+    /// Of everything above, measured while building it. See `memory.rs`.
+    pub sizes:           Vec<StructureSize>,
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self::new(Vec::new(), Vec::new(), Sizes::default())
+    }
+}
+
+impl AppState {
+    /// `sizes` has what the cards and sets took to load, if that was measured.
+    pub fn new(mut cards: Vec<Card>, sets: Vec<Set>, mut sizes: Sizes) -> Self {
+        let sets_by_name = aro_core::sets_by_name(sets);
+        cards.iter_mut().for_each(|c| {
+            c.card_sets.sort_unstable_by_key(|s| sets_by_name.get(&s.set_name.to_lowercase()).and_then(|s| s.tcg_date).unwrap_or(Date::MAX))
+        });
+        let cards_by_id: HashMap<usize, Card> =
+            sizes.of("Cards with HTML text", || cards.iter().map(|c| (c.id, Card { text: render::text_html(c), ..c.clone() })).collect());
+        let positions = cards.iter().enumerate().map(|(i, c)| (c.id, i)).collect();
+        let ids_by_name: HashMap<String, Vec<usize>> = sizes.of("IDs by name", || {
+            let mut ids = cards.iter().sorted_by_key(|c| (Reverse(c.card_sets.len()), c.id)).into_group_map_by(|c| c.name.clone());
+            ids.values_mut().for_each(|cards| cards.dedup_by_key(|c| c.id));
+            ids.into_iter().map(|(name, cards)| (name, cards.into_iter().map(|c| c.id).collect())).collect()
+        });
+        let ids_by_set_code = sizes
+            .of("IDs by set code", || cards.iter().flat_map(|c| c.card_sets.iter().map(|s| (s.set_code.to_uppercase(), c.id))).collect());
+        let is_canonical = |c: &Card| ids_by_name.get(&c.name).and_then(|ids| ids.first()) == Some(&c.id);
+        // Custom cards are only for `is:custom`, so they don’t show up as mentions, similar cards, archetype members, or support.
+        let official = || cards.iter().filter(|c| !c.custom);
+        let canonical = || official().filter(|c| is_canonical(c)).unique_by(|c| c.id);
+        let mentions = sizes.of("Mentions", || {
+            MentionsGraph::new(canonical().map(|c| {
+                let mentioned =
+                    c.quoted_names().filter(|&name| name != c.name).filter_map(|name| ids_by_name.get(name).map(|ids| ids[0])).unique();
+                (c.id, mentioned.collect())
+            }))
+        });
+        // Uses the raw text because the quotes are already escaped in `cards_by_id`.
+        let similar = sizes.of("Similar cards", || SimilarityIndex::new(canonical().map(|c| (c.id, c.text.as_str()))));
+        let name_index = sizes.of("Name index", || canonical().map(|c| (c.name.to_lowercase(), c.id)).sorted_unstable().collect());
+        let support_counts = support_counts(official());
+        let search_cards = sizes.of("Search cards", || {
+            let mut search_cards: Vec<_> = cards
+                .iter()
+                .map(|c| SearchCard::new(c, &sets_by_name).with_support_count(support_counts.get(&c.id).copied().unwrap_or_default()))
+                .collect();
+            search_cards.sort_unstable_by(|a, b| a.sort_key().cmp(&b.sort_key()));
+            search_cards
+        });
+        let search_positions = search_cards.iter().enumerate().map(|(i, c)| (c.id, i)).collect();
+        let archetypes = search_cards
+            .iter()
+            .filter_map(|c| cards_by_id.get(&c.id))
+            .filter(|c| !c.custom && is_canonical(c))
+            .filter_map(|c| Some((c.archetype.clone()?, c.id)))
+            .into_group_map();
+        let effect_tags = search_cards.iter().map(|c| (c.id, c.tags().to_vec())).collect();
+        let linked_texts = sizes.of("Card texts with links", || linked_texts(&cards, &cards_by_id, &ids_by_name));
+        Self {
+            sizes: sizes.0,
+            field_info: field_info(&cards),
+            cards,
+            cards_by_id,
+            positions,
+            linked_texts,
+            ids_by_name,
+            ids_by_set_code,
+            mentions,
+            similar,
+            name_index,
+            search_cards,
+            search_positions,
+            archetypes,
+            support_counts,
+            effect_tags,
+            sets_by_name,
+            version: None,
+            results: ResultCache::default(),
+        }
+    }
+
+    /// Like `cards_by_id`, but with the text as it is in the dump.
+    pub fn plain_card(&self, id: usize) -> Option<&Card> {
+        self.cards.get(*self.positions.get(&id)?)
+    }
+
+    /// See `SearchCard::is_unreleased`. Unknown cards count as released, there’s nothing to badge.
+    pub fn is_unreleased(&self, id: usize, today: Date) -> bool {
+        self.search_positions.get(&id).is_some_and(|&i| self.search_cards[i].is_unreleased(today))
+    }
+
+    pub fn canonical_version<'a>(&'a self, card: &'a Card) -> &'a Card {
+        self.ids_by_name.get(&card.name).and_then(|ids| self.cards_by_id.get(ids.first()?)).unwrap_or(card)
+    }
+
+    pub fn other_versions(&self, card: &Card) -> Vec<usize> {
+        self.ids_by_name.get(&card.name).map(|ids| ids.iter().copied().filter(|&id| id != card.id).collect()).unwrap_or_default()
+    }
+
+    /// How many copies of the card (including alternate artworks) the user owns.
+    pub fn owned_count(&self, collection: &Collection, card: &Card) -> u32 {
+        let ids = self.ids_by_name.get(&card.name).map_or(std::slice::from_ref(&card.id), Vec::as_slice);
+        collection.count(ids, |code| self.ids_by_set_code.get(code).copied())
+    }
+}
+
+fn linked_texts(cards: &[Card], cards_by_id: &HashMap<usize, Card>, ids_by_name: &HashMap<String, Vec<usize>>) -> HashMap<usize, String> {
+    let official = || cards.iter().filter(|c| !c.custom);
+    let names: HashMap<String, usize> = official().map(|c| (escape_html(&c.name), ids_by_name[&c.name][0])).collect();
+    let archetypes: HashMap<String, String> =
+        official().filter_map(|c| c.archetype.as_deref()).map(|a| (escape_html(a), format!("archetype:\"{a}\""))).collect();
+    let archetype_href = |name: &str| Some(url(&format!("/?{}", serde_urlencoded::to_string([("q", archetypes.get(name)?)]).ok()?)));
+    cards_by_id
+        .values()
+        .filter_map(|card| {
+            let own_name = escape_html(&card.name);
+            let linked = render::link_mentions(&card.text, |name, used_as_archetype| match names.get(name) {
+                _ if name == own_name => None,
+                Some(_) if used_as_archetype && archetypes.contains_key(name) => archetype_href(name),
+                Some(id) => Some(url(&format!("/card/{id}"))),
+                None => archetype_href(name),
+            });
+            (linked != card.text).then_some((card.id, linked))
+        })
+        .collect()
+}
+
+/// The state as of when the request came in.
+pub struct State(Arc<AppState>);
+
+impl Deref for State {
+    type Target = AppState;
+
+    fn deref(&self) -> &AppState {
+        &self.0
+    }
+}
+
+impl State {
+    /// For things that outlive the request, e.g. work on the blocking thread pool.
+    pub fn to_arc(&self) -> Arc<AppState> {
+        Arc::clone(&self.0)
+    }
+}
+
+impl FromRequest for State {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let shared = req.app_data::<web::Data<SharedState>>();
+        ready(shared.map(|s| Self(s.load_full())).ok_or_else(|| ErrorServiceUnavailable("The card data is not available")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::CardSet;
+
+    #[test]
+    fn app_state_test() {
+        let printing = |code: &str| CardSet { set_code: code.to_owned(), set_name: "Legend of Blue Eyes".to_owned(), ..Default::default() };
+        let card = |id, name: &str, text: &str, sets: Vec<CardSet>| Card {
+            id,
+            name: name.to_owned(),
+            text: text.to_owned(),
+            card_sets: sets,
+            ..Default::default()
+        };
+        let state = AppState::new(
+            vec![
+                card(46986414, "Dark Magician", "The ultimate wizard.", vec![printing("LOB-005"), printing("SDY-006")]),
+                card(36996508, "Dark Magician", "The ultimate wizard.", vec![]),
+                card(38033121, "Dark Magician Girl", "Gains 300 ATK for every \"Dark Magician\" in the GY.", vec![]),
+            ],
+            vec![],
+            Sizes::default(),
+        );
+        let alternate = &state.cards_by_id[&36996508];
+        assert_eq!(state.canonical_version(alternate).id, 46986414);
+        assert_eq!(state.other_versions(alternate), vec![46986414]);
+        assert_eq!(state.ids_by_set_code["SDY-006"], 46986414);
+        assert_eq!(state.mentions.referenced_by(46986414), &[38033121]);
+        assert!(state.linked_texts[&38033121].contains("/card/46986414"));
+        assert!(state.plain_card(38033121).unwrap().text.contains('"'));
+        assert!(!state.cards_by_id[&38033121].text.contains('"'));
+        assert_eq!(state.name_index.len(), 2);
+        assert_eq!(state.search_cards.len(), 3);
+
+        // Tests don’t share anything with each other or the server.
+        assert!(AppState::default().cards_by_id.is_empty());
+    }
+}
@@ -0,0 +1,167 @@
+//! Numbers for the admin page. Everything here is per instance and reset on restart.
+use std::{
+    collections::HashMap,
+    io,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use crate::shared::SharedStore;
+
+// Anything beyond this is almost certainly a one-off, and we don’t want the map to grow forever.
+const MAX_TRACKED_QUERIES: usize = 10_000;
+const MAX_QUERY_LEN: usize = 200;
+
+#[derive(Debug, Default)]
+pub struct CacheStats {
+    hits:   AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl CacheStats {
+    pub fn hit(&self) {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn miss(&self) {
+        self.misses.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    pub fn lookups(&self) -> usize {
+        self.hits() + self.misses.load(Ordering::Relaxed)
+    }
+
+    /// In percent, `None` if nothing has been looked up yet.
+    pub fn hit_rate(&self) -> Option<f64> {
+        match self.lookups() {
+            0 => None,
+            lookups => Some(self.hits() as f64 * 100.0 / lookups as f64),
+        }
+    }
+}
+
+/// Page views for the footer and the admin page. With a shared store, they’re counted locally and added to the shared count
+/// by `flush`, so rendering a page never waits for Redis.
+#[derive(Debug, Default)]
+pub struct PageViews {
+    local:   AtomicUsize,
+    /// Counted here but not yet shared
+    pending: AtomicUsize,
+    /// The shared count as of the last flush, including this instance’s views up to then
+    shared:  AtomicUsize,
+}
+
+impl PageViews {
+    /// Counts a view and returns the total including it.
+    pub fn record(&self, shared: Option<&SharedStore>) -> usize {
+        let local = self.local.fetch_add(1, Ordering::Relaxed) + 1;
+        let pending = self.pending.fetch_add(1, Ordering::Relaxed) + 1;
+        match shared {
+            Some(_) => self.shared.load(Ordering::Relaxed) + pending,
+            None => local,
+        }
+    }
+
+    pub fn total(&self, shared: Option<&SharedStore>) -> usize {
+        match shared {
+            Some(_) => self.shared.load(Ordering::Relaxed) + self.pending.load(Ordering::Relaxed),
+            None => self.local.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Adds what was counted since the last flush to the shared count. Views that couldn’t be added are kept for the next one.
+    pub fn flush(&self, shared: &SharedStore) -> io::Result<()> {
+        let pending = self.pending.swap(0, Ordering::Relaxed);
+        match shared.incr_by("views", pending) {
+            Ok(total) => {
+                self.shared.store(total as usize, Ordering::Relaxed);
+                Ok(())
+            }
+            Err(e) => {
+                self.pending.fetch_add(pending, Ordering::Relaxed);
+                Err(e)
+            }
+        }
+    }
+}
+
+/// How often each query was searched, normalized so `C:Spell` and `c:spell ` count as the same.
+#[derive(Debug, Default)]
+pub struct QueryStats(Mutex<HashMap<String, usize>>);
+
+impl QueryStats {
+    pub fn record(&self, query: &str) {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() || query.len() > MAX_QUERY_LEN {
+            return;
+        }
+        let mut counts = self.0.lock().unwrap();
+        if counts.len() >= MAX_TRACKED_QUERIES && !counts.contains_key(&query) {
+            // Make room by forgetting everything that was only searched once.
+            counts.retain(|_, count| *count > 1);
+            if counts.len() >= MAX_TRACKED_QUERIES {
+                return;
+            }
+        }
+        *counts.entry(query).or_default() += 1;
+    }
+
+    /// Most searched first, ties in alphabetical order.
+    pub fn top(&self, n: usize) -> Vec<(String, usize)> {
+        let counts = self.0.lock().unwrap();
+        let mut top: Vec<_> = counts.iter().map(|(q, c)| (q.clone(), *c)).collect();
+        top.sort_unstable_by(|(q1, c1), (q2, c2)| c2.cmp(c1).then_with(|| q1.cmp(q2)));
+        top.truncate(n);
+        top
+    }
+}
+
+/// Like `3d 4h 5m`, leaving out leading zeroes.
+pub fn format_uptime(uptime: Duration) -> String {
+    let minutes = uptime.as_secs() / 60;
+    match (minutes / 1440, minutes / 60 % 24, minutes % 60) {
+        (0, 0, m) => format!("{m}m"),
+        (0, h, m) => format!("{h}h {m}m"),
+        (d, h, m) => format!("{d}d {h}h {m}m"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_stats_test() {
+        let stats = QueryStats::default();
+        for q in ["c:spell", "l:4", "C:Spell ", "", "a:dark", "l:4", "c:spell"] {
+            stats.record(q);
+        }
+        assert_eq!(stats.top(2), vec![("c:spell".to_owned(), 3), ("l:4".to_owned(), 2)]);
+        assert_eq!(stats.top(10).len(), 3);
+    }
+
+    #[test]
+    fn format_uptime_test() {
+        assert_eq!(format_uptime(Duration::from_secs(59)), "0m");
+        assert_eq!(format_uptime(Duration::from_secs(3 * 3600 + 120)), "3h 2m");
+        assert_eq!(format_uptime(Duration::from_secs(2 * 86400 + 60)), "2d 0h 1m");
+    }
+
+    #[test]
+    fn cache_stats_test() {
+        let stats = CacheStats::default();
+        assert_eq!(stats.hit_rate(), None);
+        stats.hit();
+        stats.hit();
+        stats.hit();
+        stats.miss();
+        assert_eq!(stats.hit_rate(), Some(75.0));
+    }
+}
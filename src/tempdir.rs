@@ -0,0 +1,40 @@
+//! A directory for tests that write files.
+use std::{
+    fs,
+    ops::Deref,
+    path::{Path, PathBuf},
+};
+
+/// Unique to the test (by name) and the test run, and deleted with everything in it when dropped,
+/// so files don’t pile up in the temp directory when an assertion fails.
+#[derive(Debug)]
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("aro-{name}-{}", std::process::id()));
+        // Left over from a run that was killed before it could clean up
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).expect("the temp directory should be writable");
+        Self(path)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
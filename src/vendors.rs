@@ -0,0 +1,122 @@
+use itertools::Itertools;
+
+use crate::data::{Card, CardSet};
+
+/// Extra query parameters that instance operators can append to all vendor links, e.g. for affiliate or referral programs.
+/// Read from `CARDMARKET_REFERRAL` and `TCGPLAYER_REFERRAL`, e.g. `TCGPLAYER_REFERRAL=utm_source=aro&partner=123`.
+#[derive(Debug, Default)]
+pub struct Referrals {
+    pub cardmarket: Option<String>,
+    pub tcgplayer:  Option<String>,
+}
+
+impl Referrals {
+    pub fn from_env() -> Self {
+        let var = |name| std::env::var(name).ok().map(|v: String| v.trim_start_matches(['?', '&']).to_owned()).filter(|v| !v.is_empty());
+        Self { cardmarket: var("CARDMARKET_REFERRAL"), tcgplayer: var("TCGPLAYER_REFERRAL") }
+    }
+}
+
+/// Where to buy one specific printing of a card.
+#[derive(Debug, PartialEq, Eq)]
+pub struct VendorLinks {
+    pub cardmarket: String,
+    pub tcgplayer:  String,
+}
+
+impl VendorLinks {
+    pub fn for_printing(card: &Card, printing: &CardSet, referrals: &Referrals) -> Self {
+        // Cardmarket has one product per rarity and numbers them if a card has several in the same set.
+        // Their order isn’t in the dump, so we go by the order of `card_sets`, which is usually right.
+        // Printings with different codes (e.g. LOB-005 and LOB-EN005) but the same rarity are the same product.
+        let rarities: Vec<_> = card.card_sets.iter().filter(|s| s.set_name == printing.set_name).map(|s| &s.set_rarity).unique().collect();
+        let version = match rarities.len() {
+            1 => String::new(),
+            _ => format!("-V{}", rarities.iter().position(|&r| *r == printing.set_rarity).unwrap_or_default() + 1),
+        };
+        let cardmarket =
+            format!("https://www.cardmarket.com/en/YuGiOh/Products/Singles/{}/{}{version}", slug(&printing.set_name), slug(&card.name));
+        let query = serde_urlencoded::to_string([
+            ("productLineName", "yugioh"),
+            ("q", &format!("{} {}", card.name, printing.set_code)),
+            ("setName", &slug(&printing.set_name).to_lowercase()),
+            ("RarityName", &printing.set_rarity),
+        ])
+        .unwrap_or_default();
+        let tcgplayer = format!("https://www.tcgplayer.com/search/yugioh/product?{query}");
+        Self { cardmarket: with_referral(cardmarket, &referrals.cardmarket), tcgplayer: with_referral(tcgplayer, &referrals.tcgplayer) }
+    }
+
+    /// A search for the card’s name, for when it’s not about one printing.
+    pub fn search(card: &Card, referrals: &Referrals) -> Self {
+        let query = |params: &[(&str, &str)]| serde_urlencoded::to_string(params).unwrap_or_default();
+        let cardmarket = format!("https://www.cardmarket.com/en/YuGiOh/Products/Search?{}", query(&[("searchString", &card.name)]));
+        let tcgplayer =
+            format!("https://www.tcgplayer.com/search/yugioh/product?{}", query(&[("productLineName", "yugioh"), ("q", &card.name)]));
+        Self { cardmarket: with_referral(cardmarket, &referrals.cardmarket), tcgplayer: with_referral(tcgplayer, &referrals.tcgplayer) }
+    }
+}
+
+/// Appends the operator’s referral parameters to any link, with or without a query string.
+pub fn with_referral(url: String, referral: &Option<String>) -> String {
+    match referral {
+        Some(params) if url.contains('?') => format!("{url}&{params}"),
+        Some(params) => format!("{url}?{params}"),
+        None => url,
+    }
+}
+
+/// How Cardmarket turns names into URLs, e.g. `Harpie's Feather Duster` → `Harpies-Feather-Duster`.
+fn slug(name: &str) -> String {
+    name.replace('\'', "").split(|c: char| !c.is_ascii_alphanumeric()).filter(|s| !s.is_empty()).collect::<Vec<_>>().join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vendor_links_test() {
+        let printing = |code: &str, rarity: &str| CardSet {
+            set_name: "Legend of Blue Eyes White Dragon".to_owned(),
+            set_code: code.to_owned(),
+            set_rarity: rarity.to_owned(),
+            ..Default::default()
+        };
+        let card =
+            Card { name: "Harpie's Feather Duster".to_owned(), card_sets: vec![printing("LOB-EN081", "Ultra Rare")], ..Default::default() };
+        let links = VendorLinks::for_printing(&card, &card.card_sets[0], &Referrals::default());
+        assert_eq!(
+            links.cardmarket,
+            "https://www.cardmarket.com/en/YuGiOh/Products/Singles/Legend-of-Blue-Eyes-White-Dragon/Harpies-Feather-Duster"
+        );
+        assert_eq!(
+            links.tcgplayer,
+            "https://www.tcgplayer.com/search/yugioh/product?productLineName=yugioh&q=Harpie%27s+Feather+Duster+LOB-EN081&setName=legend-of-blue-eyes-white-dragon&RarityName=Ultra+Rare"
+        );
+
+        let card = Card {
+            card_sets: vec![printing("LOB-EN081", "Ultra Rare"), printing("LOB-081", "Ultra Rare"), printing("LOB-EN081", "Secret Rare")],
+            ..card
+        };
+        assert!(VendorLinks::for_printing(&card, &card.card_sets[1], &Referrals::default()).cardmarket.ends_with("-V1"));
+        let referrals = Referrals { cardmarket: Some("referrer=aro".to_owned()), tcgplayer: Some("partner=aro".to_owned()) };
+        let links = VendorLinks::for_printing(&card, &card.card_sets[2], &referrals);
+        assert!(links.cardmarket.ends_with("/Harpies-Feather-Duster-V2?referrer=aro"), "{}", links.cardmarket);
+        assert!(links.tcgplayer.ends_with("&RarityName=Secret+Rare&partner=aro"), "{}", links.tcgplayer);
+    }
+
+    #[test]
+    fn vendor_search_test() {
+        let card = Card { name: "Harpie's Feather Duster & Friends".to_owned(), ..Default::default() };
+        let links = VendorLinks::search(&card, &Referrals::default());
+        assert_eq!(
+            links.cardmarket,
+            "https://www.cardmarket.com/en/YuGiOh/Products/Search?searchString=Harpie%27s+Feather+Duster+%26+Friends"
+        );
+        assert_eq!(
+            links.tcgplayer,
+            "https://www.tcgplayer.com/search/yugioh/product?productLineName=yugioh&q=Harpie%27s+Feather+Duster+%26+Friends"
+        );
+    }
+}
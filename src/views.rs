@@ -0,0 +1,193 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fs::{self, File, OpenOptions},
+    hash::{BuildHasher, RandomState},
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::PathBuf,
+    sync::Mutex,
+};
+use time::{Date, Duration, OffsetDateTime};
+
+use crate::memory::map_size;
+
+/// Only views this recent count for trending and `sort:views`.
+pub const TRENDING_DAYS: i64 = 7;
+/// Clients and cards seen today, for counting each view only once. Past that, today’s new views aren’t counted at all,
+/// because that many is a scraper anyway.
+const MAX_SEEN: usize = 1_000_000;
+
+/// How often each card page was viewed, per day. Each client counts at most once per card and day.
+/// Views are counted in memory and periodically appended to a file as `timestamp\tid\tviews`, which is read back on startup.
+/// Once a day has dropped out of the window, the file is rewritten with only the remaining ones.
+#[derive(Debug)]
+pub struct CardViews {
+    path:  PathBuf,
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    /// Only the last `TRENDING_DAYS`, older days are dropped on flush.
+    days:      BTreeMap<Date, HashMap<usize, u32>>,
+    /// Everything that isn’t in the file yet
+    unflushed: HashMap<usize, u32>,
+    /// Whether the file still has days that were dropped
+    stale:     bool,
+    /// Hashes of client and card for `seen_on`, so no addresses are kept
+    seen:      HashSet<u64>,
+    seen_on:   Option<Date>,
+    hasher:    RandomState,
+}
+
+impl CardViews {
+    pub fn empty(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into(), state: Mutex::default() }
+    }
+
+    /// A missing file just means nothing has been viewed yet.
+    pub fn load(path: impl Into<PathBuf>, now: OffsetDateTime) -> io::Result<Self> {
+        let path = path.into();
+        let mut state = State::default();
+        match File::open(&path) {
+            Ok(f) => {
+                for line in BufReader::new(f).lines() {
+                    let line = line?;
+                    let mut fields = line.split('\t').map(str::parse::<i64>);
+                    if let (Some(Ok(time)), Some(Ok(id)), Some(Ok(views))) = (fields.next(), fields.next(), fields.next()) {
+                        if let Ok(time) = OffsetDateTime::from_unix_timestamp(time) {
+                            *state.days.entry(time.date()).or_default().entry(id as usize).or_default() += views as u32;
+                        }
+                    }
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => (),
+            Err(e) => return Err(e),
+        }
+        state.forget_before(now.date());
+        Ok(Self { path, state: Mutex::new(state) })
+    }
+
+    /// `client` is anything that tells visitors apart, e.g. their address.
+    pub fn record(&self, id: usize, client: &str, now: OffsetDateTime) {
+        let mut state = self.state.lock().unwrap();
+        if state.seen_on != Some(now.date()) {
+            state.seen.clear();
+            state.seen_on = Some(now.date());
+        }
+        let key = state.hasher.hash_one((client, id));
+        if state.seen.len() >= MAX_SEEN || !state.seen.insert(key) {
+            return;
+        }
+        *state.days.entry(now.date()).or_default().entry(id).or_default() += 1;
+        *state.unflushed.entry(id).or_default() += 1;
+    }
+
+    /// Writes everything since the last flush to the file. The views are attributed to `now`.
+    /// If days were dropped since the last flush, the whole file is rewritten instead, with each day’s views at its start.
+    pub fn flush(&self, now: OffsetDateTime) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        state.forget_before(now.date());
+        if state.stale {
+            self.rewrite(&state.days)?;
+            state.stale = false;
+            state.unflushed.clear();
+            return Ok(());
+        }
+        if state.unflushed.is_empty() {
+            return Ok(());
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        let timestamp = now.unix_timestamp();
+        for (id, views) in &state.unflushed {
+            writeln!(file, "{timestamp}\t{id}\t{views}")?;
+        }
+        state.unflushed.clear();
+        Ok(())
+    }
+
+    fn rewrite(&self, days: &BTreeMap<Date, HashMap<usize, u32>>) -> io::Result<()> {
+        let tmp = self.path.with_extension(format!("{}.tmp", std::process::id()));
+        let mut file = BufWriter::new(File::create(&tmp)?);
+        for (day, views) in days {
+            let timestamp = day.midnight().assume_utc().unix_timestamp();
+            for (id, views) in views {
+                writeln!(file, "{timestamp}\t{id}\t{views}")?;
+            }
+        }
+        file.into_inner()?.sync_all()?;
+        fs::rename(tmp, &self.path)
+    }
+
+    /// For the admin page, see `memory.rs`. The days are small next to their maps, so they’re not counted.
+    pub fn estimated_size(&self) -> usize {
+        let state = self.state.lock().unwrap();
+        let seen = state.seen.capacity() * (size_of::<u64>() + 1);
+        seen + state.days.values().chain([&state.unflushed]).map(|views| map_size(views, |_, _| 0)).sum::<usize>()
+    }
+
+    /// Views of each card in the last `TRENDING_DAYS`.
+    pub fn recent(&self, now: OffsetDateTime) -> HashMap<usize, u32> {
+        let state = self.state.lock().unwrap();
+        let mut views = HashMap::new();
+        for (_, day) in state.days.range(first_day(now.date())..) {
+            for (&id, &count) in day {
+                *views.entry(id).or_default() += count;
+            }
+        }
+        views
+    }
+
+    /// The `n` most viewed cards in the last `TRENDING_DAYS`, most viewed first.
+    pub fn trending(&self, now: OffsetDateTime, n: usize) -> Vec<(usize, u32)> {
+        let mut views: Vec<_> = self.recent(now).into_iter().collect();
+        views.sort_unstable_by(|(id1, v1), (id2, v2)| v2.cmp(v1).then(id1.cmp(id2)));
+        views.truncate(n);
+        views
+    }
+}
+
+impl State {
+    fn forget_before(&mut self, today: Date) {
+        let kept = self.days.split_off(&first_day(today));
+        self.stale |= !self.days.is_empty();
+        self.days = kept;
+    }
+}
+
+fn first_day(today: Date) -> Date {
+    today - Duration::days(TRENDING_DAYS - 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tempdir::TempDir;
+
+    #[test]
+    fn trending_test() {
+        let dir = TempDir::new("views");
+        let path = dir.join("cardviews.tsv");
+        let now = OffsetDateTime::from_unix_timestamp(1_700_000_000).unwrap();
+        let views = CardViews::empty(&path);
+        views.record(1, "a", now - Duration::days(TRENDING_DAYS));
+        views.record(3, "a", now - Duration::days(1));
+        views.record(2, "a", now);
+        views.record(3, "a", now);
+        assert_eq!(views.trending(now, 10), vec![(3, 2), (2, 1)]);
+        assert_eq!(views.trending(now, 1), vec![(3, 2)]);
+
+        views.flush(now).unwrap();
+        views.record(2, "a", now);
+        views.record(2, "b", now);
+        views.flush(now).unwrap();
+        let reloaded = CardViews::load(&path, now).unwrap();
+        assert_eq!(reloaded.trending(now, 10), vec![(2, 2), (3, 2)], "a already viewed 2 today");
+
+        // A week later, the file only keeps what’s still recent.
+        let later = now + Duration::days(TRENDING_DAYS);
+        reloaded.record(3, "a", later);
+        reloaded.flush(later).unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap().lines().count(), 1);
+        assert_eq!(CardViews::load(&path, later).unwrap().recent(later), HashMap::from([(3, 1)]));
+    }
+}
@@ -0,0 +1,160 @@
+//! POSTs to `WEBHOOK_URLS` when a new data version has new cards or banlist changes, e.g. for announcement bots.
+//! Sending needs the `webhooks` feature. The body is signed with `WEBHOOK_SECRET` so receivers can check it came from us.
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::io;
+use time::OffsetDateTime;
+
+use crate::changes::{Change, ChangeKind};
+
+#[cfg(feature = "webhooks")]
+const SIGNATURE_HEADER: &str = "X-Aro-Signature";
+#[cfg(feature = "webhooks")]
+const TIMESTAMP_HEADER: &str = "X-Aro-Timestamp";
+
+#[derive(Debug)]
+pub struct Webhooks {
+    urls:   Vec<String>,
+    secret: String,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+struct Payload<'a> {
+    /// See `dataversion.rs`
+    version:         &'a str,
+    new_cards:       Vec<NewCard<'a>>,
+    banlist_changes: Vec<BanlistChange<'a>>,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+struct NewCard<'a> {
+    id:   usize,
+    name: &'a str,
+}
+
+#[derive(Debug, Serialize, PartialEq, Eq)]
+struct BanlistChange<'a> {
+    id:     usize,
+    name:   &'a str,
+    format: &'static str,
+    old:    &'a str,
+    new:    &'a str,
+}
+
+impl Webhooks {
+    pub fn from_env() -> Option<Self> {
+        let urls: Vec<String> =
+            std::env::var("WEBHOOK_URLS").ok()?.split(',').map(|u| u.trim().to_owned()).filter(|u| !u.is_empty()).collect();
+        if cfg!(not(feature = "webhooks")) {
+            eprintln!("WEBHOOK_URLS is set, but this was built without the webhooks feature. No webhooks will be sent");
+            return None;
+        }
+        match std::env::var("WEBHOOK_SECRET") {
+            Ok(secret) if !secret.is_empty() => Some(Self { urls, secret }),
+            _ => {
+                eprintln!("WEBHOOK_URLS needs a WEBHOOK_SECRET to sign the requests with. No webhooks will be sent");
+                None
+            }
+        }
+    }
+
+    /// Sends the changes that are worth announcing to every URL in the background. Failures are only logged.
+    pub fn notify<'a>(&self, version: &str, changes: &'a [Change], name_of: impl Fn(usize) -> Option<&'a str>) {
+        let Some(body) = payload(version, changes, name_of) else { return };
+        let timestamp = OffsetDateTime::now_utc().unix_timestamp();
+        let signature = signature(self.secret.as_bytes(), timestamp, &body);
+        let urls = self.urls.clone();
+        std::thread::spawn(move || {
+            for url in urls {
+                if let Err(e) = post(&url, &body, timestamp, &signature) {
+                    eprintln!("Could not send webhook to {url}: {e}");
+                }
+            }
+        });
+    }
+}
+
+/// JSON with the new cards and banlist changes, `None` if there are neither.
+fn payload<'a>(version: &str, changes: &'a [Change], name_of: impl Fn(usize) -> Option<&'a str>) -> Option<String> {
+    let new_cards: Vec<_> =
+        changes.iter().filter(|c| c.kind == ChangeKind::Added).map(|c| NewCard { id: c.id, name: c.new.as_str() }).collect();
+    let banlist_changes: Vec<_> = changes
+        .iter()
+        .filter_map(|c| {
+            let format = match c.kind {
+                ChangeKind::TcgBanlist => "tcg",
+                ChangeKind::OcgBanlist => "ocg",
+                _ => return None,
+            };
+            Some(BanlistChange { id: c.id, name: name_of(c.id).unwrap_or_default(), format, old: &c.old, new: &c.new })
+        })
+        .collect();
+    if new_cards.is_empty() && banlist_changes.is_empty() {
+        return None;
+    }
+    serde_json::to_string(&Payload { version, new_cards, banlist_changes }).ok()
+}
+
+#[cfg(feature = "webhooks")]
+fn post(url: &str, body: &str, timestamp: i64, signature: &str) -> io::Result<()> {
+    let agent = ureq::AgentBuilder::new().timeout(std::time::Duration::from_secs(10)).build();
+    agent
+        .post(url)
+        .set("Content-Type", "application/json")
+        .set(TIMESTAMP_HEADER, &timestamp.to_string())
+        .set(SIGNATURE_HEADER, signature)
+        .send_string(body)
+        .map(drop)
+        .map_err(io::Error::other)
+}
+
+// `from_env` doesn’t return any webhooks without the feature, so this is never called.
+#[cfg(not(feature = "webhooks"))]
+fn post(_url: &str, _body: &str, _timestamp: i64, _signature: &str) -> io::Result<()> {
+    Err(io::Error::other("built without the webhooks feature"))
+}
+
+/// `sha256=` and the hex HMAC-SHA256 of `<timestamp>.<body>`.
+/// The timestamp is signed too, so receivers can reject old deliveries that someone captured and sends again.
+fn signature(secret: &[u8], timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC takes keys of any length");
+    mac.update(format!("{timestamp}.").as_bytes());
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex(&mac.finalize().into_bytes()))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_test() {
+        // Python’s `hmac.new(b"Jefe", b"1700000000.what do ya want for nothing?", "sha256")`
+        assert_eq!(
+            signature(b"Jefe", 1700000000, "what do ya want for nothing?"),
+            "sha256=1cdd0650c8be1cb0974b1788d458b1e781206cfef59b85faafc582d2e182c57e"
+        );
+    }
+
+    #[test]
+    fn payload_test() {
+        let change =
+            |id, kind, old: &str, new: &str| Change { version: "b".to_owned(), id, kind, old: old.to_owned(), new: new.to_owned() };
+        let name_of = |id| (id == 55144522).then_some("Pot of Greed");
+        let changes = [
+            change(12580477, ChangeKind::Added, "", "Raigeki"),
+            change(46986414, ChangeKind::Price, "5", "1"),
+            change(55144522, ChangeKind::TcgBanlist, "Unlimited", "Forbidden"),
+        ];
+        assert_eq!(
+            payload("b", &changes, name_of).unwrap(),
+            r#"{"version":"b","new_cards":[{"id":12580477,"name":"Raigeki"}],"banlist_changes":[{"id":55144522,"name":"Pot of Greed","format":"tcg","old":"Unlimited","new":"Forbidden"}]}"#
+        );
+        assert_eq!(payload("b", &changes[1..2], name_of), None);
+    }
+}